@@ -14,10 +14,11 @@ use atuin_common::{
 };
 use atuin_common::{
     api::{
-        AddHistoryRequest, ChangePasswordRequest, CountResponse, DeleteHistoryRequest,
-        ErrorResponse, LoginRequest, LoginResponse, MeResponse, RegisterResponse,
-        SendVerificationResponse, StatusResponse, SyncHistoryResponse, VerificationTokenRequest,
-        VerificationTokenResponse,
+        AddHistoryRequest, AddTeamMemberRequest, ChangePasswordRequest, CountResponse,
+        CreateTeamRequest, DeleteHistoryRequest, ErrorResponse, KeyEscrowRequest,
+        KeyEscrowResponse, ListTeamsResponse, LoginRequest, LoginResponse, MeResponse,
+        RegisterResponse, SendVerificationResponse, StatusResponse, SyncHistoryResponse,
+        TeamResponse, VerificationTokenRequest, VerificationTokenResponse,
     },
     record::RecordStatus,
 };
@@ -33,6 +34,7 @@ static APP_USER_AGENT: &str = concat!("atuin/", env!("CARGO_PKG_VERSION"),);
 pub struct Client<'a> {
     sync_addr: &'a str,
     client: reqwest::Client,
+    team: Option<String>,
 }
 
 fn make_url(address: &str, path: &str) -> Result<String> {
@@ -212,9 +214,27 @@ impl<'a> Client<'a> {
                 .connect_timeout(Duration::new(connect_timeout, 0))
                 .timeout(Duration::new(timeout, 0))
                 .build()?,
+            team: None,
         })
     }
 
+    /// Use the record store shared by `team`, rather than the user's own store
+    pub fn with_team(mut self, team: &str) -> Self {
+        self.team = Some(team.to_string());
+        self
+    }
+
+    fn record_url(&self, path: &str) -> Result<Url> {
+        let url = make_url(self.sync_addr, path)?;
+        let mut url = Url::parse(url.as_str())?;
+
+        if let Some(team) = &self.team {
+            url.query_pairs_mut().append_pair("team", team);
+        }
+
+        Ok(url)
+    }
+
     pub async fn count(&self) -> Result<i64> {
         let url = make_url(self.sync_addr, "/sync/count")?;
         let url = Url::parse(url.as_str())?;
@@ -327,10 +347,114 @@ impl<'a> Client<'a> {
         Ok(())
     }
 
-    pub async fn post_records(&self, records: &[Record<EncryptedData>]) -> Result<()> {
-        let url = make_url(self.sync_addr, "/api/v0/record")?;
+    /// Fetch the escrowed key for this account, if one has been uploaded
+    pub async fn get_key_escrow(&self) -> Result<Option<String>> {
+        let url = make_url(self.sync_addr, "/api/v0/key")?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.get(url).send().await?;
+
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let resp = handle_resp_error(resp).await?;
+        let escrow = resp.json::<KeyEscrowResponse>().await?;
+
+        Ok(Some(escrow.escrow))
+    }
+
+    pub async fn put_key_escrow(&self, escrow: String) -> Result<()> {
+        let url = make_url(self.sync_addr, "/api/v0/key")?;
         let url = Url::parse(url.as_str())?;
 
+        let resp = self
+            .client
+            .put(url)
+            .json(&KeyEscrowRequest { escrow })
+            .send()
+            .await?;
+        handle_resp_error(resp).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_key_escrow(&self) -> Result<()> {
+        let url = make_url(self.sync_addr, "/api/v0/key")?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.delete(url).send().await?;
+        handle_resp_error(resp).await?;
+
+        Ok(())
+    }
+
+    pub async fn list_teams(&self) -> Result<Vec<TeamResponse>> {
+        let url = make_url(self.sync_addr, "/api/v0/team")?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.get(url).send().await?;
+        let resp = handle_resp_error(resp).await?;
+
+        Ok(resp.json::<ListTeamsResponse>().await?.teams)
+    }
+
+    pub async fn create_team(&self, name: &str) -> Result<TeamResponse> {
+        let url = make_url(self.sync_addr, "/api/v0/team")?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self
+            .client
+            .post(url)
+            .json(&CreateTeamRequest {
+                name: name.to_string(),
+            })
+            .send()
+            .await?;
+        let resp = handle_resp_error(resp).await?;
+
+        Ok(resp.json().await?)
+    }
+
+    pub async fn add_team_member(
+        &self,
+        team: &str,
+        username: &str,
+        admin: bool,
+    ) -> Result<TeamResponse> {
+        let url = make_url(self.sync_addr, &format!("/api/v0/team/{team}/member"))?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self
+            .client
+            .post(url)
+            .json(&AddTeamMemberRequest {
+                username: username.to_string(),
+                admin,
+            })
+            .send()
+            .await?;
+        let resp = handle_resp_error(resp).await?;
+
+        Ok(resp.json().await?)
+    }
+
+    pub async fn remove_team_member(&self, team: &str, username: &str) -> Result<TeamResponse> {
+        let url = make_url(
+            self.sync_addr,
+            &format!("/api/v0/team/{team}/member/{username}"),
+        )?;
+        let url = Url::parse(url.as_str())?;
+
+        let resp = self.client.delete(url).send().await?;
+        let resp = handle_resp_error(resp).await?;
+
+        Ok(resp.json().await?)
+    }
+
+    pub async fn post_records(&self, records: &[Record<EncryptedData>]) -> Result<()> {
+        let url = self.record_url("/api/v0/record")?;
+
         debug!("uploading {} records to {url}", records.len());
 
         let resp = self.client.post(url).json(records).send().await?;
@@ -348,15 +472,10 @@ impl<'a> Client<'a> {
     ) -> Result<Vec<Record<EncryptedData>>> {
         debug!("fetching record/s from host {}/{}/{}", host.0, tag, start);
 
-        let url = make_url(
-            self.sync_addr,
-            &format!(
-                "/api/v0/record/next?host={}&tag={}&count={}&start={}",
-                host.0, tag, count, start
-            ),
-        )?;
-
-        let url = Url::parse(url.as_str())?;
+        let url = self.record_url(&format!(
+            "/api/v0/record/next?host={}&tag={}&count={}&start={}",
+            host.0, tag, count, start
+        ))?;
 
         let resp = self.client.get(url).send().await?;
         let resp = handle_resp_error(resp).await?;
@@ -367,8 +486,7 @@ impl<'a> Client<'a> {
     }
 
     pub async fn record_status(&self) -> Result<RecordStatus> {
-        let url = make_url(self.sync_addr, "/api/v0/record")?;
-        let url = Url::parse(url.as_str())?;
+        let url = self.record_url("/api/v0/record")?;
 
         let resp = self.client.get(url).send().await?;
         let resp = handle_resp_error(resp).await?;
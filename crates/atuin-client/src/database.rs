@@ -19,11 +19,11 @@ use sqlx::{
         SqliteSynchronous,
     },
 };
-use time::OffsetDateTime;
+use time::{OffsetDateTime, macros::format_description};
 use uuid::Uuid;
 
 use crate::{
-    history::{HistoryId, HistoryStats},
+    history::{self, HistoryId, HistoryStats},
     utils::get_host_user,
 };
 
@@ -41,6 +41,14 @@ pub struct Context {
     pub git_root: Option<PathBuf>,
 }
 
+/// How many commands ran in a directory, and when the last of them did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryVisits {
+    pub path: String,
+    pub visits: i64,
+    pub last_visit: OffsetDateTime,
+}
+
 #[derive(Default, Clone)]
 pub struct OptFilters {
     pub exit: Option<i64>,
@@ -131,7 +139,22 @@ pub trait Database: Send + Sync + 'static {
 
     async fn stats(&self, h: &History) -> Result<HistoryStats>;
 
+    /// The commands run around this one in its session, up to `count` before and after it.
+    /// Oldest first, including this one.
+    async fn context(&self, h: &History, count: usize) -> Result<Vec<History>>;
+
+    /// The latest `count` runs of a command before a time, newest first
+    async fn runs(
+        &self,
+        command: &str,
+        before: OffsetDateTime,
+        count: usize,
+    ) -> Result<Vec<History>>;
+
     async fn get_dups(&self, before: i64, dupkeep: u32) -> Result<Vec<History>>;
+
+    /// Every directory commands ran in, on any host
+    async fn directories(&self) -> Result<Vec<DirectoryVisits>>;
 }
 
 // Intended for use on a developer machine and not a sync server.
@@ -735,6 +758,31 @@ impl Database for Sqlite {
             .group_by("month_year")
             .having("duration > 0");
 
+        // Percentiles and failure streaks are worked out here, from every run in order
+        let mut runs = SqlBuilder::select_from("history");
+        runs.fields(&["timestamp", "duration", "exit"])
+            .and_where("command = ?1")
+            .order_by("timestamp", false);
+
+        let mut hosts = SqlBuilder::select_from("history");
+        hosts
+            .fields(&[
+                "substr(hostname, 1, instr(hostname || ':', ':') - 1) as host",
+                "count(1) as count",
+            ])
+            .and_where("command = ?1")
+            .group_by("host")
+            .order_by("count", true)
+            .limit(STATS_PLACES);
+
+        let mut directories = SqlBuilder::select_from("history");
+        directories
+            .fields(&["cwd", "count(1) as count"])
+            .and_where("command = ?1")
+            .group_by("cwd")
+            .order_by("count", true)
+            .limit(STATS_PLACES);
+
         let prev = prev.sql().expect("issue in stats previous query");
         let next = next.sql().expect("issue in stats next query");
         let total = total.sql().expect("issue in stats average query");
@@ -744,6 +792,9 @@ impl Database for Sqlite {
         let duration_over_time = duration_over_time
             .sql()
             .expect("issue in stats duration over time query");
+        let runs = runs.sql().expect("issue in stats runs query");
+        let hosts = hosts.sql().expect("issue in stats hosts query");
+        let directories = directories.sql().expect("issue in stats directories query");
 
         let prev = sqlx::query(&prev)
             .bind(h.timestamp.unix_timestamp_nanos() as i64)
@@ -789,6 +840,62 @@ impl Database for Sqlite {
             .map(|f| (f.0.clone(), f.1.round() as i64))
             .collect();
 
+        let runs: Vec<(i64, i64, i64)> = sqlx::query_as(&runs)
+            .bind(&h.command)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let hosts: Vec<(String, i64)> = sqlx::query_as(&hosts)
+            .bind(&h.command)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let directories: Vec<(String, i64)> = sqlx::query_as(&directories)
+            .bind(&h.command)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let (current_failure_streak, longest_failure_streak) =
+            failure_streaks(runs.iter().map(|(_, _, exit)| *exit));
+
+        // durations of -1 are unknown, and 0 likely too
+        let mut durations: Vec<i64> = runs
+            .iter()
+            .map(|(_, duration, _)| *duration)
+            .filter(|duration| *duration > 0)
+            .collect();
+        durations.sort_unstable();
+
+        let month_format = format_description!("01-[month]-[year]");
+        let mut by_month: Vec<(String, Vec<i64>)> = Vec::new();
+        for (timestamp, duration, _) in &runs {
+            if *duration <= 0 {
+                continue;
+            }
+
+            let month = OffsetDateTime::from_unix_timestamp_nanos(*timestamp as i128)
+                .ok()
+                .and_then(|t| t.format(month_format).ok())
+                .unwrap_or_default();
+
+            match by_month.last_mut() {
+                Some((last, durations)) if *last == month => durations.push(*duration),
+                _ => by_month.push((month, vec![*duration])),
+            }
+        }
+
+        let duration_percentiles = by_month
+            .into_iter()
+            .map(|(month, mut durations)| {
+                durations.sort_unstable();
+                (
+                    month,
+                    percentile(&durations, 50),
+                    percentile(&durations, 95),
+                )
+            })
+            .collect();
+
         Ok(HistoryStats {
             next,
             previous: prev,
@@ -797,9 +904,79 @@ impl Database for Sqlite {
             exits,
             day_of_week,
             duration_over_time,
+            duration_percentiles,
+            median_duration: percentile(&durations, 50) as u64,
+            p95_duration: percentile(&durations, 95) as u64,
+            current_failure_streak,
+            longest_failure_streak,
+            hosts,
+            directories,
         })
     }
 
+    async fn context(&self, h: &History, count: usize) -> Result<Vec<History>> {
+        let mut before = SqlBuilder::select_from("history");
+        before
+            .field("*")
+            .and_where("timestamp < ?1")
+            .and_where("session = ?2")
+            .and_where_is_null("deleted_at")
+            .order_by("timestamp", true)
+            .limit(count);
+
+        let mut after = SqlBuilder::select_from("history");
+        after
+            .field("*")
+            .and_where("timestamp > ?1")
+            .and_where("session = ?2")
+            .and_where_is_null("deleted_at")
+            .order_by("timestamp", false)
+            .limit(count);
+
+        let before = before.sql().expect("issue in context before query");
+        let after = after.sql().expect("issue in context after query");
+
+        let mut context = sqlx::query(&before)
+            .bind(h.timestamp.unix_timestamp_nanos() as i64)
+            .bind(&h.session)
+            .map(Self::query_history)
+            .fetch_all(&self.pool)
+            .await?;
+        context.reverse();
+        context.push(h.clone());
+
+        let after = sqlx::query(&after)
+            .bind(h.timestamp.unix_timestamp_nanos() as i64)
+            .bind(&h.session)
+            .map(Self::query_history)
+            .fetch_all(&self.pool)
+            .await?;
+        context.extend(after);
+
+        Ok(context)
+    }
+
+    async fn runs(
+        &self,
+        command: &str,
+        before: OffsetDateTime,
+        count: usize,
+    ) -> Result<Vec<History>> {
+        let res = sqlx::query(
+            "select * from history
+            where command = ?1 and timestamp < ?2 and deleted_at is null
+            order by timestamp desc limit ?3",
+        )
+        .bind(command)
+        .bind(before.unix_timestamp_nanos() as i64)
+        .bind(count as i64)
+        .map(Self::query_history)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(res)
+    }
+
     async fn get_dups(&self, before: i64, dupkeep: u32) -> Result<Vec<History>> {
         let res = sqlx::query(
             "SELECT * FROM (
@@ -819,6 +996,57 @@ impl Database for Sqlite {
 
         Ok(res)
     }
+
+    async fn directories(&self) -> Result<Vec<DirectoryVisits>> {
+        let res = sqlx::query(
+            "select cwd, count(1) as visits, max(timestamp) as last_visit from history
+            where deleted_at is null and cwd != ''
+            group by cwd",
+        )
+        .map(|row: SqliteRow| DirectoryVisits {
+            path: row.get("cwd"),
+            visits: row.get("visits"),
+            last_visit: OffsetDateTime::from_unix_timestamp_nanos(
+                row.get::<i64, _>("last_visit") as i128
+            )
+            .unwrap(),
+        })
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(res)
+    }
+}
+
+/// How many of the hosts and directories a command runs in are kept in its stats
+const STATS_PLACES: usize = 5;
+
+/// The current and longest runs of failures, from exit codes in order. Runs still going (an
+/// exit of -1) don't break a streak.
+fn failure_streaks(exits: impl Iterator<Item = i64>) -> (u64, u64) {
+    let mut current = 0;
+    let mut longest = 0;
+
+    for exit in exits.filter(|exit| *exit != -1) {
+        current = if history::failed(exit) {
+            current + 1
+        } else {
+            0
+        };
+        longest = longest.max(current);
+    }
+
+    (current, longest)
+}
+
+/// The nearest-rank percentile of sorted values, or 0 without any
+fn percentile(sorted: &[i64], percent: usize) -> i64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
 }
 
 trait SqlBuilderExt {
@@ -1170,6 +1398,189 @@ mod test {
             .unwrap();
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_context() {
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+
+        let start = OffsetDateTime::now_utc();
+        let mut session = Vec::new();
+        for i in 0..6 {
+            let mut h: History = History::capture()
+                .timestamp(start + time::Duration::seconds(i))
+                .command(format!("step {i}"))
+                .cwd("/home/ellie")
+                .build()
+                .into();
+            h.session = "beep boop".to_string();
+
+            db.save(&h).await.unwrap();
+            session.push(h);
+        }
+
+        let mut other: History = History::capture()
+            .timestamp(start + time::Duration::seconds(2))
+            .command("elsewhere")
+            .cwd("/home/ellie")
+            .build()
+            .into();
+        other.session = "another session".to_string();
+        db.save(&other).await.unwrap();
+
+        db.delete(session[1].clone()).await.unwrap();
+
+        let context = db.context(&session[3], 2).await.unwrap();
+        let commands: Vec<_> = context.iter().map(|h| h.command.as_str()).collect();
+        assert_eq!(
+            commands,
+            vec!["step 0", "step 2", "step 3", "step 4", "step 5"]
+        );
+
+        let context = db.context(&session[5], 1).await.unwrap();
+        let commands: Vec<_> = context.iter().map(|h| h.command.as_str()).collect();
+        assert_eq!(commands, vec!["step 4", "step 5"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_runs() {
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+
+        let start = OffsetDateTime::now_utc();
+        for (i, command) in ["make", "ls", "make", "make", "make"].iter().enumerate() {
+            let h: History = History::capture()
+                .timestamp(start + time::Duration::seconds(i as i64))
+                .command(*command)
+                .cwd("/home/ellie")
+                .build()
+                .into();
+            db.save(&h).await.unwrap();
+        }
+
+        let runs = db
+            .runs("make", start + time::Duration::seconds(4), 2)
+            .await
+            .unwrap();
+        let timestamps: Vec<_> = runs.iter().map(|h| h.timestamp).collect();
+        assert_eq!(
+            timestamps,
+            vec![
+                start + time::Duration::seconds(3),
+                start + time::Duration::seconds(2)
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stats() {
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+
+        let start = time::macros::datetime!(2025-01-01 00:00 UTC);
+        let runs = [
+            (0, 10, "a:ellie", "/code"),
+            (1, 20, "a:ellie", "/code"),
+            (1, 30, "b:ellie", "/code"),
+            (0, 40, "a:ellie", "/tmp"),
+            (2, 1000, "a:ellie", "/code"),
+            (1, 50, "b:ellie", "/code"),
+        ];
+
+        let mut saved = Vec::new();
+        for (i, (exit, duration, hostname, cwd)) in runs.into_iter().enumerate() {
+            let h: History = History::import()
+                .timestamp(start + time::Duration::days(20 * i as i64))
+                .command("make")
+                .exit(exit)
+                .duration(duration)
+                .hostname(hostname)
+                .cwd(cwd)
+                .build()
+                .into();
+            db.save(&h).await.unwrap();
+            saved.push(h);
+        }
+
+        let stats = db.stats(&saved[0]).await.unwrap();
+
+        assert_eq!(stats.total, 6);
+        assert_eq!(stats.current_failure_streak, 2);
+        assert_eq!(stats.longest_failure_streak, 2);
+        assert_eq!(stats.median_duration, 30);
+        assert_eq!(stats.p95_duration, 1000);
+        assert_eq!(
+            stats.duration_percentiles,
+            [
+                ("01-01-2025".to_string(), 10, 20),
+                ("01-02-2025".to_string(), 30, 30),
+                ("01-03-2025".to_string(), 40, 1000),
+                ("01-04-2025".to_string(), 50, 50),
+            ]
+        );
+        assert_eq!(stats.hosts, [("a".to_string(), 4), ("b".to_string(), 2)]);
+        assert_eq!(
+            stats.directories,
+            [("/code".to_string(), 5), ("/tmp".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_failure_streaks() {
+        assert_eq!(failure_streaks([0, 1, 2, 0, 1].into_iter()), (1, 2));
+        // still running
+        assert_eq!(failure_streaks([1, -1, 1, -1].into_iter()), (2, 2));
+        // interrupted
+        assert_eq!(failure_streaks([1, 1, 130, 1].into_iter()), (1, 2));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_directories() {
+        let db = Sqlite::new("sqlite::memory:", test_local_timeout())
+            .await
+            .unwrap();
+
+        let start = OffsetDateTime::now_utc().replace_nanosecond(0).unwrap();
+        let mut saved = Vec::new();
+        for (i, cwd) in ["/code/atuin", "/tmp", "/code/atuin", "/code/atuin"]
+            .into_iter()
+            .enumerate()
+        {
+            let h: History = History::capture()
+                .timestamp(start + time::Duration::seconds(i as i64))
+                .command("ls")
+                .cwd(cwd)
+                .build()
+                .into();
+            db.save(&h).await.unwrap();
+            saved.push(h);
+        }
+
+        // deleted commands don't count as visits
+        db.delete(saved.pop().unwrap()).await.unwrap();
+
+        let mut dirs = db.directories().await.unwrap();
+        dirs.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(
+            dirs,
+            [
+                DirectoryVisits {
+                    path: "/code/atuin".to_string(),
+                    visits: 2,
+                    last_visit: start + time::Duration::seconds(2),
+                },
+                DirectoryVisits {
+                    path: "/tmp".to_string(),
+                    visits: 1,
+                    last_visit: start + time::Duration::seconds(1),
+                },
+            ]
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_search_bench_dupes() {
         let context = Context {
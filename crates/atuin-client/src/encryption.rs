@@ -118,6 +118,137 @@ pub fn decode_key(key: String) -> Result<Key> {
     }
 }
 
+/// The encryption key, wrapped with a key derived from a passphrase with Argon2id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WrappedKey {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    pub salt: Vec<u8>,
+    pub nonce: Nonce<XSalsa20Poly1305>,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Everything needed to recover the encryption key on a new machine.
+///
+/// The key is wrapped twice - once with the user's passphrase, and once with a generated recovery
+/// code - so that either can unlock it. This is stored on the sync server, which cannot read it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyEscrow {
+    pub passphrase: WrappedKey,
+    pub recovery: WrappedKey,
+}
+
+fn derive_wrapping_key(secret: &str, salt: &[u8], params: argon2::Params) -> Result<Key> {
+    let mut key = Key::default();
+
+    argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+        .hash_password_into(secret.as_bytes(), salt, &mut key)
+        .map_err(|e| eyre!("could not derive key from passphrase: {e}"))?;
+
+    Ok(key)
+}
+
+fn wrap_key_with_params(key: &Key, secret: &str, params: argon2::Params) -> Result<WrappedKey> {
+    let mut salt = vec![0; 16];
+    rand::RngCore::fill_bytes(&mut OsRng, &mut salt);
+
+    let wrapping_key = derive_wrapping_key(secret, &salt, params.clone())?;
+
+    let mut ciphertext = key.to_vec();
+    let nonce = XSalsa20Poly1305::generate_nonce(&mut OsRng);
+    XSalsa20Poly1305::new(&wrapping_key)
+        .encrypt_in_place(&nonce, &[], &mut ciphertext)
+        .map_err(|_| eyre!("could not wrap key"))?;
+
+    Ok(WrappedKey {
+        m_cost: params.m_cost(),
+        t_cost: params.t_cost(),
+        p_cost: params.p_cost(),
+        salt,
+        nonce,
+        ciphertext,
+    })
+}
+
+/// Wrap the key with a passphrase, using the recommended Argon2id parameters
+pub fn wrap_key(key: &Key, secret: &str) -> Result<WrappedKey> {
+    wrap_key_with_params(key, secret, argon2::Params::default())
+}
+
+pub fn unwrap_key(wrapped: &WrappedKey, secret: &str) -> Result<Key> {
+    let params = argon2::Params::new(wrapped.m_cost, wrapped.t_cost, wrapped.p_cost, None)
+        .map_err(|e| eyre!("invalid key derivation parameters: {e}"))?;
+    let wrapping_key = derive_wrapping_key(secret, &wrapped.salt, params)?;
+
+    let mut key = wrapped.ciphertext.clone();
+    XSalsa20Poly1305::new(&wrapping_key)
+        .decrypt_in_place(&wrapped.nonce, &[], &mut key)
+        .map_err(|_| eyre!("incorrect passphrase or recovery code"))?;
+
+    let key = <[u8; 32]>::try_from(key.as_slice()).context("wrapped key is the wrong size")?;
+
+    Ok(key.into())
+}
+
+/// Generate a random recovery code, formatted for humans to copy
+///
+/// eg. 3F9A-01CC-7E42-B1D0-9A6E-55F2-0C81-D4A7
+pub fn generate_recovery_code() -> String {
+    let mut bytes = [0; 16];
+    rand::RngCore::fill_bytes(&mut OsRng, &mut bytes);
+
+    bytes
+        .chunks(2)
+        .map(|c| format!("{:02X}{:02X}", c[0], c[1]))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// Recovery codes are typed in by hand, so be lenient with case and separators
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+impl KeyEscrow {
+    /// Escrow the key with a passphrase, returning the escrow and a newly generated recovery code
+    pub fn new(key: &Key, passphrase: &str) -> Result<(Self, String)> {
+        Self::with_params(key, passphrase, argon2::Params::default())
+    }
+
+    fn with_params(key: &Key, passphrase: &str, params: argon2::Params) -> Result<(Self, String)> {
+        let code = generate_recovery_code();
+
+        let escrow = KeyEscrow {
+            passphrase: wrap_key_with_params(key, passphrase, params.clone())?,
+            recovery: wrap_key_with_params(key, &normalize_recovery_code(&code), params)?,
+        };
+
+        Ok((escrow, code))
+    }
+
+    /// Unwrap the key with either the passphrase or the recovery code
+    pub fn unwrap(&self, secret: &str) -> Result<Key> {
+        unwrap_key(&self.passphrase, secret)
+            .or_else(|_| unwrap_key(&self.recovery, &normalize_recovery_code(secret)))
+    }
+
+    pub fn encode(&self) -> Result<String> {
+        Ok(BASE64_STANDARD.encode(serde_json::to_vec(self)?))
+    }
+
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let buf = BASE64_STANDARD
+            .decode(encoded.trim())
+            .wrap_err("key escrow is not a valid base64 encoding")?;
+
+        serde_json::from_slice(&buf).wrap_err("could not decode key escrow")
+    }
+}
+
 pub fn encrypt(history: &History, key: &Key) -> Result<EncryptedHistory> {
     // serialize with msgpack
     let mut buf = encode(history)?;
@@ -271,7 +402,32 @@ mod test {
 
     use crate::history::History;
 
-    use super::{decode, decrypt, encode, encrypt};
+    use super::{KeyEscrow, decode, decrypt, encode, encrypt};
+
+    fn fast_params() -> argon2::Params {
+        argon2::Params::new(256, 1, 1, None).unwrap()
+    }
+
+    #[test]
+    fn key_escrow_round_trip() {
+        let key = XSalsa20Poly1305::generate_key(&mut OsRng);
+
+        let (escrow, code) = KeyEscrow::with_params(&key, "correct horse", fast_params()).unwrap();
+        let escrow = KeyEscrow::decode(&escrow.encode().unwrap()).unwrap();
+
+        assert_eq!(escrow.unwrap("correct horse").unwrap(), key);
+        assert_eq!(escrow.unwrap(&code).unwrap(), key);
+        // recovery codes are forgiving of how they were typed
+        assert_eq!(
+            escrow
+                .unwrap(&code.to_lowercase().replace('-', " "))
+                .unwrap(),
+            key
+        );
+
+        assert!(escrow.unwrap("battery staple").is_err());
+        assert_eq!(code.len(), 39);
+    }
 
     #[test]
     fn test_encrypt_decrypt() {
@@ -20,6 +20,15 @@ pub mod store;
 const HISTORY_VERSION: &str = "v0";
 pub const HISTORY_TAG: &str = "history";
 
+/// The exit code of a command interrupted with Ctrl-C
+const INTERRUPTED: i64 = 130;
+
+/// Whether a command that exited with `exit` failed. Interrupting it with Ctrl-C isn't it
+/// failing, and a command that's still running (an exit of -1) hasn't failed yet.
+pub fn failed(exit: i64) -> bool {
+    exit != 0 && exit != INTERRUPTED && exit != -1
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct HistoryId(pub String);
 
@@ -90,6 +99,25 @@ pub struct HistoryStats {
     pub day_of_week: Vec<(String, i64)>,
 
     pub duration_over_time: Vec<(String, i64)>,
+
+    /// The median and 95th percentile of the duration, by month, in the same format as
+    /// `duration_over_time`
+    pub duration_percentiles: Vec<(String, i64, i64)>,
+
+    /// The median and 95th percentile of the duration, over every run
+    pub median_duration: u64,
+    pub p95_duration: u64,
+
+    /// How many runs in a row have failed, up to the latest one
+    pub current_failure_streak: u64,
+    /// The most runs in a row that ever failed
+    pub longest_failure_streak: u64,
+
+    /// The hosts the command runs on, most runs first
+    pub hosts: Vec<(String, i64)>,
+
+    /// The directories the command runs in, most runs first
+    pub directories: Vec<(String, i64)>,
 }
 
 impl History {
@@ -369,6 +397,11 @@ impl History {
         builder::HistoryFromDb::builder()
     }
 
+    /// Whether the command failed, by [`failed`]
+    pub fn failed(&self) -> bool {
+        failed(self.exit)
+    }
+
     pub fn success(&self) -> bool {
         self.exit == 0 || self.duration == -1
     }
@@ -6,7 +6,7 @@ use rmp::decode::Bytes;
 
 use crate::{
     database::{Database, current_context},
-    record::{encryption::PASETO_V4, sqlite_store::SqliteStore, store::Store},
+    record::{keyring::Keyring, sqlite_store::SqliteStore, store::Store},
 };
 use atuin_common::record::{DecryptedData, Host, HostId, Record, RecordId, RecordIdx};
 
@@ -16,7 +16,7 @@ use super::{HISTORY_TAG, HISTORY_VERSION, History, HistoryId};
 pub struct HistoryStore {
     pub store: SqliteStore,
     pub host_id: HostId,
-    pub encryption_key: [u8; 32],
+    pub keyring: Keyring,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -109,11 +109,11 @@ impl HistoryRecord {
 }
 
 impl HistoryStore {
-    pub fn new(store: SqliteStore, host_id: HostId, encryption_key: [u8; 32]) -> Self {
+    pub fn new(store: SqliteStore, host_id: HostId, keyring: impl Into<Keyring>) -> Self {
         HistoryStore {
             store,
             host_id,
-            encryption_key,
+            keyring: keyring.into(),
         }
     }
 
@@ -135,9 +135,7 @@ impl HistoryStore {
 
         let id = record.id;
 
-        self.store
-            .push(&record.encrypt::<PASETO_V4>(&self.encryption_key))
-            .await?;
+        self.store.push(&self.keyring.encrypt(record)).await?;
 
         Ok((id, idx))
     }
@@ -164,7 +162,7 @@ impl HistoryStore {
                 .data(bytes)
                 .build();
 
-            let record = record.encrypt::<PASETO_V4>(&self.encryption_key);
+            let record = self.keyring.encrypt(record);
 
             ret.push(record);
         }
@@ -197,7 +195,7 @@ impl HistoryStore {
         for record in records.into_iter() {
             let hist = match record.version.as_str() {
                 HISTORY_VERSION => {
-                    let decrypted = record.decrypt::<PASETO_V4>(&self.encryption_key)?;
+                    let decrypted = self.keyring.decrypt(record)?;
 
                     HistoryRecord::deserialize(&decrypted.data, HISTORY_VERSION)
                 }
@@ -257,7 +255,7 @@ impl HistoryStore {
                 continue;
             }
 
-            let decrypted = record.decrypt::<PASETO_V4>(&self.encryption_key)?;
+            let decrypted = self.keyring.decrypt(record)?;
             let record = HistoryRecord::deserialize(&decrypted.data, HISTORY_VERSION)?;
 
             match record {
@@ -0,0 +1,123 @@
+//! Hooks are shell commands run after a command ends, with placeholders like `{command}` in them
+//! replaced by the details of the command. The daemon runs `[[hooks]]` and alert hooks, and alert
+//! hooks also run without it.
+
+use std::{borrow::Cow, process::Stdio, time::Duration};
+
+use tokio::process::Command;
+
+use crate::history::History;
+
+/// How long a shell hook can run for before it's killed
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A shell hook, run through `sh -c` with no input or output
+pub fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    shell
+}
+
+/// A value quoted for the shell
+pub fn quote(value: &str) -> Cow<'_, str> {
+    shlex::try_quote(value).unwrap_or(Cow::Borrowed("''"))
+}
+
+/// The template with the placeholders replaced by the details of the command, and any `extra`
+/// placeholders by their values, escaped. The values aren't searched for placeholders themselves.
+#[allow(clippy::cast_precision_loss)]
+pub fn render<'a>(
+    template: &str,
+    h: &'a History,
+    extra: &[(&str, &'a str)],
+    escape: impl Fn(&'a str) -> Cow<'a, str>,
+) -> String {
+    let exit = h.exit.to_string();
+    let duration = format!("{:.1}", h.duration as f64 / 1_000_000_000.0);
+
+    let mut values = vec![
+        ("{command}", escape(&h.command)),
+        ("{cwd}", escape(&h.cwd)),
+        ("{hostname}", escape(&h.hostname)),
+        ("{session}", escape(&h.session)),
+        ("{id}", escape(&h.id.0)),
+        ("{exit}", Cow::Borrowed(exit.as_str())),
+        ("{duration}", Cow::Borrowed(duration.as_str())),
+    ];
+    let extra: Vec<(String, Cow<str>)> = extra
+        .iter()
+        .map(|(name, value)| (format!("{{{name}}}"), escape(value)))
+        .collect();
+    values.extend(
+        extra
+            .iter()
+            .map(|(p, v)| (p.as_str(), Cow::Borrowed(v.as_ref()))),
+    );
+
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match values.iter().find(|(p, _)| rest.starts_with(p)) {
+            Some((placeholder, value)) => {
+                rendered.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::{quote, render};
+    use crate::history::History;
+
+    #[test]
+    fn templates() {
+        let h: History = History::import()
+            .timestamp(datetime!(2025-01-06 09:00 UTC))
+            .command("make 'my target' {cwd}")
+            .cwd("/home/ellie/my app")
+            .exit(1)
+            .duration(3_000_000_000)
+            .build()
+            .into();
+
+        assert_eq!(
+            render(
+                "notify {command} in {cwd}: {exit} after {duration}s",
+                &h,
+                &[],
+                quote
+            ),
+            r#"notify "make 'my target' {cwd}" in '/home/ellie/my app': 1 after 3.0s"#
+        );
+        assert_eq!(
+            render(
+                "{kind}: {message}",
+                &h,
+                &[("kind", "slow"), ("message", "it's {slow}")],
+                quote
+            ),
+            r#"slow: "it's {slow}""#
+        );
+    }
+}
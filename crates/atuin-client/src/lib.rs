@@ -11,6 +11,7 @@ pub mod sync;
 pub mod database;
 pub mod encryption;
 pub mod history;
+pub mod hooks;
 pub mod import;
 pub mod login;
 pub mod logout;
@@ -20,6 +21,7 @@ pub mod record;
 pub mod register;
 pub mod secrets;
 pub mod settings;
+pub mod team;
 pub mod theme;
 
 mod utils;
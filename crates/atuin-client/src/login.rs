@@ -7,8 +7,8 @@ use tokio::io::AsyncWriteExt;
 
 use crate::{
     api_client,
-    encryption::{Key, decode_key, encode_key, load_key},
-    record::{sqlite_store::SqliteStore, store::Store},
+    encryption::{Key, decode_key, encode_key},
+    record::{keyring::Keyring, sqlite_store::SqliteStore, store::Store},
     settings::Settings,
 };
 
@@ -62,21 +62,20 @@ pub async fn login(
 
         // 1. check if the saved key and the provided key match. if so, nothing to do.
         // 2. if not, re-encrypt the local history and overwrite the key
-        let current_key: [u8; 32] = load_key(settings)?.into();
+        let keyring = Keyring::load(settings)?;
 
-        let encoded = key.clone(); // gonna want to save it in a bit
         let new_key: [u8; 32] = decode_key(key)
             .context("could not decode provided key - is not valid base64")?
             .into();
 
-        if new_key != current_key {
+        if new_key != keyring.current() {
             println!("\nRe-encrypting local store with new key");
 
-            store.re_encrypt(&current_key, &new_key).await?;
+            store.re_encrypt(&keyring, &new_key).await?;
 
             println!("Writing new key");
-            let mut file = File::create(key_path).await?;
-            file.write_all(encoded.as_bytes()).await?;
+            keyring.set_current(new_key);
+            keyring.save(settings)?;
         }
     }
 
@@ -1,4 +1,204 @@
-use std::collections::HashMap;
+//! Plugins are executables named `atuin-<name>`, run for `atuin <name>`.
+//!
+//! A plugin registers itself with a manifest in the plugins directory, which `atuin plugins
+//! list` reads. Plugins whose manifest declares a protocol version are sent a [`Handshake`] as
+//! JSON in the `ATUIN_PLUGIN_HANDSHAKE` environment variable, with everything they need to find
+//! atuin's data, and to query history through the daemon. Their stdin is left to them.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use config::{Config, File as ConfigFile, FileFormat};
+use eyre::{Result, WrapErr, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+/// The version of the plugin protocol this version of atuin speaks
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The environment variable a plugin's [`Handshake`] is sent in
+pub const HANDSHAKE_VAR: &str = "ATUIN_PLUGIN_HANDSHAKE";
+
+/// A plugin's registration with atuin, a TOML file in the plugins directory named after it
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Manifest {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub version: Option<String>,
+
+    /// The executable to run, if it isn't `atuin-<name>` on the PATH
+    #[serde(default)]
+    pub bin: Option<PathBuf>,
+
+    /// The version of the plugin protocol the plugin speaks. Plugins without one aren't sent a
+    /// handshake.
+    #[serde(default)]
+    pub protocol: Option<u32>,
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> Result<Self> {
+        let manifest: Self = Config::builder()
+            .add_source(ConfigFile::from_str(text, FileFormat::Toml))
+            .build()?
+            .try_deserialize()
+            .wrap_err("invalid plugin manifest")?;
+
+        if manifest.name.is_empty()
+            || !manifest
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!(
+                "invalid plugin name '{}': use letters, numbers, - and _",
+                manifest.name
+            );
+        }
+
+        Ok(manifest)
+    }
+
+    /// The executable that runs the plugin
+    pub fn bin(&self) -> PathBuf {
+        self.bin
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("atuin-{}", self.name)))
+    }
+}
+
+/// The directory plugin manifests are kept in
+pub fn plugins_dir() -> PathBuf {
+    atuin_common::utils::data_dir().join("plugins")
+}
+
+/// The manifests of every registered plugin, by name. Manifests that can't be read are skipped.
+pub fn manifests(dir: &Path) -> Result<BTreeMap<String, Manifest>> {
+    let mut manifests = BTreeMap::new();
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(manifests),
+        Err(e) => return Err(e).wrap_err_with(|| format!("could not read {dir:?}")),
+    };
+
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "toml") {
+            continue;
+        }
+
+        match fs::read_to_string(&path)
+            .map_err(eyre::Report::from)
+            .and_then(|text| Manifest::parse(&text))
+        {
+            Ok(manifest) => {
+                manifests.insert(manifest.name.clone(), manifest);
+            }
+            Err(e) => log::warn!("skipping plugin manifest {path:?}: {e}"),
+        }
+    }
+
+    Ok(manifests)
+}
+
+/// Register a plugin with its manifest, replacing any manifest it had before
+pub fn register(dir: &Path, manifest: &str) -> Result<Manifest> {
+    let parsed = Manifest::parse(manifest)?;
+
+    fs::create_dir_all(dir).wrap_err_with(|| format!("could not create {dir:?}"))?;
+    fs::write(dir.join(format!("{}.toml", parsed.name)), manifest)
+        .wrap_err("could not write plugin manifest")?;
+
+    Ok(parsed)
+}
+
+/// The `atuin-*` executables on the PATH, by the name of the plugin they'd provide. The first
+/// one on the PATH wins, as it's the one that would run.
+pub fn discover() -> BTreeMap<String, PathBuf> {
+    let mut plugins = BTreeMap::new();
+    let Some(path) = env::var_os("PATH") else {
+        return plugins;
+    };
+
+    for dir in env::split_paths(&path) {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let Some(name) = file_name.to_str().and_then(|f| f.strip_prefix("atuin-")) else {
+                continue;
+            };
+            let name = name.strip_suffix(".exe").unwrap_or(name);
+
+            if !name.is_empty() && entry.path().is_file() && !plugins.contains_key(name) {
+                plugins.insert(name.to_string(), entry.path());
+            }
+        }
+    }
+
+    plugins
+}
+
+/// What a plugin that speaks the protocol is sent when it starts. It doesn't include the
+/// encryption key's path, as plugins read history through the daemon.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Handshake {
+    pub protocol: u32,
+    pub atuin_version: String,
+
+    pub config_file: PathBuf,
+    pub data_dir: PathBuf,
+    pub db_path: String,
+    pub record_store_path: String,
+
+    /// The shell session atuin was run from, if it was run from one
+    pub session: Option<String>,
+
+    pub daemon: HandshakeDaemon,
+}
+
+/// How to reach the daemon, which serves the history API in `atuin-daemon`'s `history.proto`
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct HandshakeDaemon {
+    pub enabled: bool,
+    /// The unix socket the daemon listens on
+    pub socket_path: Option<String>,
+    /// The local TCP port the daemon listens on, where there are no unix sockets
+    pub tcp_port: Option<u64>,
+}
+
+impl Handshake {
+    pub fn new(settings: &Settings) -> Self {
+        let config_dir = env::var("ATUIN_CONFIG_DIR")
+            .map_or_else(|_| atuin_common::utils::config_dir(), PathBuf::from);
+
+        Self {
+            protocol: PROTOCOL_VERSION,
+            atuin_version: env!("CARGO_PKG_VERSION").to_string(),
+            config_file: config_dir.join("config.toml"),
+            data_dir: atuin_common::utils::data_dir(),
+            db_path: settings.db_path.clone(),
+            record_store_path: settings.record_store_path.clone(),
+            session: env::var("ATUIN_SESSION").ok(),
+            daemon: HandshakeDaemon {
+                enabled: settings.daemon.enabled,
+                socket_path: cfg!(unix).then(|| settings.daemon.socket_path.clone()),
+                tcp_port: cfg!(not(unix)).then_some(settings.daemon.tcp_port),
+            },
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct OfficialPlugin {
@@ -47,6 +247,13 @@ impl OfficialPluginRegistry {
         );
     }
 
+    /// Every official plugin, by name
+    pub fn plugins(&self) -> impl Iterator<Item = &OfficialPlugin> {
+        let mut plugins: Vec<&OfficialPlugin> = self.plugins.values().collect();
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+        plugins.into_iter()
+    }
+
     pub fn get_plugin(&self, name: &str) -> Option<&OfficialPlugin> {
         self.plugins.get(name)
     }
@@ -87,6 +294,41 @@ mod tests {
         assert_eq!(plugin.unwrap().name, "update");
     }
 
+    #[test]
+    fn test_manifest() {
+        let manifest = Manifest::parse(
+            r#"
+            name = "notes"
+            description = "Notes on your commands"
+            protocol = 1
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.name, "notes");
+        assert_eq!(manifest.protocol, Some(1));
+        assert_eq!(manifest.bin(), PathBuf::from("atuin-notes"));
+
+        assert!(Manifest::parse(r#"name = "../notes""#).is_err());
+        assert!(Manifest::parse(r#"description = "no name""#).is_err());
+    }
+
+    #[test]
+    fn test_register() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(manifests(dir.path()).unwrap().is_empty());
+
+        register(dir.path(), "name = \"notes\"\nbin = \"/opt/notes/bin\"").unwrap();
+        register(dir.path(), "name = \"notes\"\nversion = \"0.2.0\"").unwrap();
+        std::fs::write(dir.path().join("broken.toml"), "name = ").unwrap();
+
+        let manifests = manifests(dir.path()).unwrap();
+        assert_eq!(manifests.len(), 1);
+        assert_eq!(manifests["notes"].version.as_deref(), Some("0.2.0"));
+        assert_eq!(manifests["notes"].bin, None);
+    }
+
     #[test]
     fn test_get_install_message() {
         let registry = OfficialPluginRegistry::new();
@@ -0,0 +1,359 @@
+//! Local backups of the record store.
+//!
+//! A backup is a single gzip compressed file of newline delimited JSON. The first line holds
+//! metadata that does not depend on the encryption key, followed by every record exactly as it is
+//! stored - still encrypted - and a footer with the record count and a sha256 checksum of the
+//! record lines. The key is never written to a backup, so it must be kept separately.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use atuin_common::record::{EncryptedData, HostId, Record, RecordStatus};
+use eyre::{Result, bail, eyre};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::{OffsetDateTime, macros::format_description};
+
+use super::store::Store;
+
+/// Bumped whenever the backup format changes incompatibly
+pub const BACKUP_VERSION: u32 = 1;
+
+/// The file extension used for automatic backups
+pub const BACKUP_EXTENSION: &str = "atuin-backup";
+
+const PAGE_SIZE: u64 = 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupMetadata {
+    pub version: u32,
+    pub created_at: OffsetDateTime,
+    pub atuin_version: String,
+
+    /// The host the backup was taken on
+    pub host_id: HostId,
+
+    /// The tail index of every store at the time of the backup
+    pub status: RecordStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    Metadata(BackupMetadata),
+    Record(Record<EncryptedData>),
+    Footer { records: u64, checksum: String },
+}
+
+/// A backup that has been read and checked for integrity
+#[derive(Debug)]
+pub struct Backup {
+    pub metadata: BackupMetadata,
+    pub records: Vec<Record<EncryptedData>>,
+}
+
+fn write_entry(writer: &mut impl Write, entry: &Entry) -> Result<Vec<u8>> {
+    let line = serde_json::to_vec(entry)?;
+    writer.write_all(&line)?;
+    writer.write_all(b"\n")?;
+
+    Ok(line)
+}
+
+/// Write every record in the store to `writer`, returning the number of records written
+pub async fn write(store: &impl Store, host_id: HostId, writer: impl Write) -> Result<u64> {
+    let mut writer = GzEncoder::new(writer, Compression::default());
+    let metadata = BackupMetadata {
+        version: BACKUP_VERSION,
+        created_at: OffsetDateTime::now_utc(),
+        atuin_version: env!("CARGO_PKG_VERSION").to_string(),
+        host_id,
+        status: store.status().await?,
+    };
+
+    let stores = metadata
+        .status
+        .hosts
+        .iter()
+        .flat_map(|(host, tags)| tags.keys().map(|tag| (*host, tag.clone())))
+        .collect::<Vec<_>>();
+
+    write_entry(&mut writer, &Entry::Metadata(metadata))?;
+
+    let mut hasher = Sha256::new();
+    let mut count = 0;
+
+    for (host, tag) in stores {
+        let mut idx = 0;
+
+        loop {
+            let page = store.next(host, &tag, idx, PAGE_SIZE).await?;
+
+            let Some(last) = page.last() else {
+                break;
+            };
+            idx = last.idx + 1;
+
+            let full = page.len() as u64 == PAGE_SIZE;
+
+            for record in page {
+                let line = write_entry(&mut writer, &Entry::Record(record))?;
+                hasher.update(&line);
+                count += 1;
+            }
+
+            if !full {
+                break;
+            }
+        }
+    }
+
+    write_entry(
+        &mut writer,
+        &Entry::Footer {
+            records: count,
+            checksum: format!("{:x}", hasher.finalize()),
+        },
+    )?;
+
+    writer.finish()?.flush()?;
+
+    Ok(count)
+}
+
+/// Read a backup, checking that it is complete and that the checksum matches
+pub fn read(reader: impl Read) -> Result<Backup> {
+    let mut metadata = None;
+    let mut records = Vec::new();
+    let mut hasher = Sha256::new();
+    let mut finished = false;
+
+    for (n, line) in BufReader::new(GzDecoder::new(reader)).lines().enumerate() {
+        let line = line?;
+
+        if finished {
+            bail!("unexpected data after footer on line {}", n + 1);
+        }
+
+        let entry = serde_json::from_str(&line)
+            .map_err(|e| eyre!("not a valid atuin backup: line {}: {e}", n + 1))?;
+
+        match entry {
+            Entry::Record(record) => {
+                hasher.update(line.as_bytes());
+                records.push(record);
+            }
+            Entry::Metadata(m) => {
+                if m.version != BACKUP_VERSION {
+                    bail!(
+                        "unsupported backup version {}, expected {BACKUP_VERSION}",
+                        m.version
+                    );
+                }
+
+                metadata = Some(m);
+            }
+            Entry::Footer {
+                records: count,
+                checksum,
+            } => {
+                let actual = format!("{:x}", std::mem::take(&mut hasher).finalize());
+
+                if count != records.len() as u64 || checksum != actual {
+                    bail!(
+                        "backup failed integrity check: expected {count} records with checksum {checksum}, found {} with checksum {actual}",
+                        records.len()
+                    );
+                }
+
+                finished = true;
+            }
+        }
+    }
+
+    if !finished {
+        bail!("backup is truncated: missing footer");
+    }
+
+    let metadata = metadata.ok_or_else(|| eyre!("backup is missing its metadata"))?;
+
+    Ok(Backup { metadata, records })
+}
+
+/// Insert every record in the backup into the store, in a single transaction.
+///
+/// Records that already exist are left alone, so restoring is safe to repeat.
+pub async fn restore(store: &impl Store, backup: &Backup) -> Result<()> {
+    store.push_batch(backup.records.iter()).await
+}
+
+/// Write a new backup into `dir`, keeping at most `keep` backups there
+pub async fn write_scheduled(
+    store: &impl Store,
+    host_id: HostId,
+    dir: impl AsRef<Path>,
+    keep: usize,
+) -> Result<PathBuf> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    // Colons are not valid in windows filenames, and fixed width names sort chronologically
+    let name = OffsetDateTime::now_utc().format(format_description!(
+        "[year]-[month]-[day]T[hour]-[minute]-[second].[subsecond digits:9]Z"
+    ))?;
+    let path = dir.join(format!("records-{name}.{BACKUP_EXTENSION}"));
+    let partial = path.with_extension("partial");
+
+    let file = fs::File::create(&partial)?;
+    write(store, host_id, std::io::BufWriter::new(file)).await?;
+    fs::rename(&partial, &path)?;
+
+    let mut existing = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == BACKUP_EXTENSION))
+        .collect::<Vec<_>>();
+
+    existing.sort();
+
+    let excess = existing.len().saturating_sub(keep.max(1));
+    for old in &existing[..excess] {
+        debug!("removing old backup {old:?}");
+        fs::remove_file(old)?;
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use atuin_common::{
+        record::{EncryptedData, Host, HostId, Record},
+        utils::uuid_v7,
+    };
+
+    use crate::{
+        record::{sqlite_store::SqliteStore, store::Store},
+        settings::test_local_timeout,
+    };
+
+    fn test_record(host: HostId, tag: &str, idx: u64) -> Record<EncryptedData> {
+        Record::builder()
+            .host(Host::new(host))
+            .version("v1".into())
+            .tag(tag.into())
+            .data(EncryptedData {
+                data: uuid_v7().simple().to_string(),
+                content_encryption_key: "1234".into(),
+            })
+            .idx(idx)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn backup_round_trip() {
+        let source = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let host = HostId(uuid_v7());
+
+        let records: Vec<_> = (0..1200)
+            .map(|i| test_record(host, "history", i))
+            .chain((0..3).map(|i| test_record(host, "kv", i)))
+            .collect();
+        source.push_batch(records.iter()).await.unwrap();
+
+        let mut file = Vec::new();
+        let written = super::write(&source, host, &mut file).await.unwrap();
+        assert_eq!(written, 1203);
+
+        let backup = super::read(file.as_slice()).unwrap();
+        assert_eq!(backup.metadata.host_id, host);
+        assert_eq!(
+            backup.metadata.status.get(host, "history".into()),
+            Some(1199)
+        );
+
+        let target = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        super::restore(&target, &backup).await.unwrap();
+        // restoring twice is harmless
+        super::restore(&target, &backup).await.unwrap();
+
+        assert_eq!(target.len_all().await.unwrap(), 1203);
+        for record in records {
+            assert_eq!(target.get(record.id).await.unwrap(), record);
+        }
+    }
+
+    #[tokio::test]
+    async fn damaged_backup_is_rejected() {
+        let source = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let host = HostId(uuid_v7());
+        let records: Vec<_> = (0..5).map(|i| test_record(host, "history", i)).collect();
+        source.push_batch(records.iter()).await.unwrap();
+
+        let mut file = Vec::new();
+        super::write(&source, host, &mut file).await.unwrap();
+
+        let mut plain = String::new();
+        flate2::read::GzDecoder::new(file.as_slice())
+            .read_to_string(&mut plain)
+            .unwrap();
+
+        let recompress = |lines: Vec<&str>| {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(lines.join("\n").as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        let lines: Vec<&str> = plain.lines().collect();
+
+        let mut missing_record = lines.clone();
+        missing_record.remove(2);
+        let missing_record = recompress(missing_record);
+        let err = super::read(missing_record.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("integrity check"));
+
+        let truncated = recompress(lines[..lines.len() - 1].to_vec());
+        let err = super::read(truncated.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("truncated"));
+    }
+
+    #[tokio::test]
+    async fn scheduled_backups_are_pruned() {
+        let store = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let host = HostId(uuid_v7());
+        store.push(&test_record(host, "history", 0)).await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut written = Vec::new();
+        for _ in 0..3 {
+            written.push(
+                super::write_scheduled(&store, host, dir.path(), 2)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        let remaining: Vec<_> = fs_err::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+
+        assert_eq!(remaining.len(), 2);
+        assert!(!written[0].exists());
+        assert!(written[2].exists());
+    }
+}
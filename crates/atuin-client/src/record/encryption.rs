@@ -114,6 +114,20 @@ impl Encryption for PASETO_V4 {
 }
 
 impl PASETO_V4 {
+    /// The ID of the key that wrapped the content encryption key of this data.
+    /// This is stored in the clear, so can be read without any key.
+    pub fn key_id(data: &EncryptedData) -> Result<KeyId<V4, Local>> {
+        let AtuinFooter { kid, .. } = serde_json::from_str(&data.content_encryption_key)
+            .context("wrapped cek did not contain the correct contents")?;
+
+        Ok(kid)
+    }
+
+    /// The ID that records wrapped with this key will carry
+    pub fn id_for_key(key: &[u8; 32]) -> KeyId<V4, Local> {
+        Key::<V4, Local>::from_bytes(*key).to_id()
+    }
+
     fn decrypt_cek(wrapped_cek: String, key: &[u8; 32]) -> Result<Key<V4, Local>> {
         let wrapping_key = Key::<V4, Local>::from_bytes(*key);
 
@@ -123,10 +137,8 @@ impl PASETO_V4 {
             .context("wrapped cek did not contain the correct contents")?;
 
         // check that the wrapping key matches the required key to decrypt.
-        // In future, we could support multiple keys and use this key to
-        // look up the key rather than only allow one key.
-        // For now though we will only support the one key and key rotation will
-        // have to be a hard reset
+        // Callers holding several keys should pick the right one with `key_id` - see
+        // `record::keyring`
         let current_kid = wrapping_key.to_id();
 
         ensure!(
@@ -0,0 +1,530 @@
+//! Multiple encryption keys, and rotating between them.
+//!
+//! Every record carries the ID of the key that wrapped its content encryption key. A keyring
+//! holds the current key - used for everything new - alongside any retired keys, and picks the
+//! right one to decrypt each record. Records in the local store are re-encrypted with the current
+//! key by [`migrate`], after a rotation and after each sync, so retired keys are only needed for
+//! older records downloaded from the server.
+//!
+//! Rotations are recorded in the `keyring` store, and synced like any other record. Each rotation
+//! holds the new key along with every retired key, written twice: encrypted with the key it
+//! retires, so every other device switches to the new key when it next syncs, and with the new
+//! key, so a device given only the new key (`atuin key use`, or a new login) learns the retired
+//! ones. As anyone holding the old key learns the new one, rotating doesn't lock out a leaked key.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use atuin_common::record::{DecryptedData, EncryptedData, Host, HostId, Record};
+use eyre::{Result, bail, ensure, eyre};
+use fs_err as fs;
+use rusty_paserk::{KeyId, Local};
+use rusty_paseto::core::V4;
+
+use super::{encryption::PASETO_V4, sqlite_store::SqliteStore, store::Store};
+use crate::{
+    encryption::{Key, decode_key, encode_key, generate_encoded_key, load_key},
+    settings::Settings,
+};
+
+pub const KEYRING_VERSION: &str = "v0";
+pub const KEYRING_TAG: &str = "keyring";
+
+#[derive(Clone)]
+struct Inner {
+    current: [u8; 32],
+    keys: BTreeMap<KeyId<V4, Local>, [u8; 32]>,
+}
+
+/// A set of encryption keys, one of which is current.
+///
+/// Clones share the same keys, so a rotation learned through sync is picked up by every store
+/// holding the keyring.
+#[derive(Clone)]
+pub struct Keyring {
+    inner: Arc<RwLock<Inner>>,
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print the keys themselves
+        let inner = self.inner.read().expect("keyring lock poisoned");
+
+        f.debug_struct("Keyring")
+            .field("current", &PASETO_V4::id_for_key(&inner.current))
+            .field("keys", &inner.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl From<[u8; 32]> for Keyring {
+    fn from(key: [u8; 32]) -> Self {
+        Keyring::new(key)
+    }
+}
+
+impl Keyring {
+    pub fn new(current: [u8; 32]) -> Self {
+        let keys = BTreeMap::from([(PASETO_V4::id_for_key(&current), current)]);
+
+        Keyring {
+            inner: Arc::new(RwLock::new(Inner { current, keys })),
+        }
+    }
+
+    /// Load the current key, creating it if it does not exist, along with any retired keys
+    pub fn load(settings: &Settings) -> Result<Self> {
+        let keyring = Keyring::new(load_key(settings)?.into());
+        let path = PathBuf::from(&settings.keyring_path);
+
+        if path.exists() {
+            for line in fs::read_to_string(&path)?.lines() {
+                let line = line.trim();
+
+                if line.is_empty() {
+                    continue;
+                }
+
+                keyring.insert(decode_key(line.to_string())?.into());
+            }
+        }
+
+        Ok(keyring)
+    }
+
+    /// Write the retired keys, then the current key
+    pub fn save(&self, settings: &Settings) -> Result<()> {
+        let current = self.current();
+
+        let mut retired = String::new();
+        for key in self.retired() {
+            retired.push_str(&encode_key(&Key::from(key))?);
+            retired.push('\n');
+        }
+
+        write_atomic(settings.keyring_path.as_ref(), &retired)?;
+        write_atomic(settings.key_path.as_ref(), &encode_key(&current.into())?)?;
+
+        Ok(())
+    }
+
+    /// The key used to encrypt new records
+    pub fn current(&self) -> [u8; 32] {
+        self.inner.read().expect("keyring lock poisoned").current
+    }
+
+    /// Every key other than the current one
+    pub fn retired(&self) -> Vec<[u8; 32]> {
+        let inner = self.inner.read().expect("keyring lock poisoned");
+
+        inner
+            .keys
+            .values()
+            .filter(|k| **k != inner.current)
+            .copied()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().expect("keyring lock poisoned").keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add a key, without changing the current key. Returns true if the key was new.
+    pub fn insert(&self, key: [u8; 32]) -> bool {
+        self.inner
+            .write()
+            .expect("keyring lock poisoned")
+            .keys
+            .insert(PASETO_V4::id_for_key(&key), key)
+            .is_none()
+    }
+
+    /// Make `key` the current key. The previous key is kept to read older records.
+    pub fn set_current(&self, key: [u8; 32]) {
+        let mut inner = self.inner.write().expect("keyring lock poisoned");
+
+        inner.keys.insert(PASETO_V4::id_for_key(&key), key);
+        inner.current = key;
+    }
+
+    /// The key that the given data was encrypted with
+    pub fn key_for(&self, data: &EncryptedData) -> Result<[u8; 32]> {
+        let kid = PASETO_V4::key_id(data)?;
+
+        self.inner
+            .read()
+            .expect("keyring lock poisoned")
+            .keys
+            .get(&kid)
+            .copied()
+            .ok_or_else(|| eyre!("record was encrypted with an unknown key {kid}"))
+    }
+
+    /// Encrypt a record with the current key
+    pub fn encrypt(&self, record: Record<DecryptedData>) -> Record<EncryptedData> {
+        record.encrypt::<PASETO_V4>(&self.current())
+    }
+
+    /// Decrypt a record with whichever key it was encrypted with
+    pub fn decrypt(&self, record: Record<EncryptedData>) -> Result<Record<DecryptedData>> {
+        let key = self.key_for(&record.data)?;
+
+        record.decrypt::<PASETO_V4>(&key)
+    }
+}
+
+/// Replace a file without ever leaving it half written, as losing a key loses the records it
+/// encrypted
+fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let mut file = fs::File::create(&partial)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&partial, path)?;
+
+    Ok(())
+}
+
+/// The contents of a rotation record
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeyringRecord {
+    current: [u8; 32],
+    retired: Vec<[u8; 32]>,
+}
+
+impl KeyringRecord {
+    fn serialize(&self) -> Result<DecryptedData> {
+        use rmp::encode;
+
+        let mut output = vec![];
+
+        encode::write_array_len(&mut output, 2)?;
+        encode::write_bin(&mut output, &self.current)?;
+
+        encode::write_array_len(&mut output, self.retired.len() as u32)?;
+        for key in &self.retired {
+            encode::write_bin(&mut output, key)?;
+        }
+
+        Ok(DecryptedData(output))
+    }
+
+    fn deserialize(data: &DecryptedData, version: &str) -> Result<Self> {
+        use rmp::decode;
+
+        fn error_report<E: std::fmt::Debug>(err: E) -> eyre::Report {
+            eyre!("{err:?}")
+        }
+
+        fn read_key(bytes: &[u8]) -> Result<([u8; 32], &[u8])> {
+            let mut bytes = decode::Bytes::new(bytes);
+            let len = decode::read_bin_len(&mut bytes).map_err(error_report)?;
+            let bytes = bytes.remaining_slice();
+
+            ensure!(
+                len == 32 && bytes.len() >= 32,
+                "key in keyring record is not 32 bytes"
+            );
+
+            let (key, bytes) = bytes.split_at(32);
+
+            Ok((key.try_into().expect("length checked above"), bytes))
+        }
+
+        match version {
+            KEYRING_VERSION => {
+                let mut bytes = decode::Bytes::new(&data.0);
+
+                let nfields = decode::read_array_len(&mut bytes).map_err(error_report)?;
+                if nfields != 2 {
+                    bail!("incorrect number of fields in keyring record");
+                }
+
+                let (current, bytes) = read_key(bytes.remaining_slice())?;
+
+                let mut bytes = decode::Bytes::new(bytes);
+                let nkeys = decode::read_array_len(&mut bytes).map_err(error_report)?;
+                let mut bytes = bytes.remaining_slice();
+
+                let mut retired = Vec::with_capacity(nkeys as usize);
+                for _ in 0..nkeys {
+                    let (key, rest) = read_key(bytes)?;
+                    retired.push(key);
+                    bytes = rest;
+                }
+
+                if !bytes.is_empty() {
+                    bail!("trailing bytes in encoded keyring record. malformed")
+                }
+
+                Ok(KeyringRecord { current, retired })
+            }
+            _ => bail!("unknown version {version:?}"),
+        }
+    }
+}
+
+/// Generate a new key and make it current, recording the rotation so that devices holding the
+/// old key switch to it, and devices given the new key learn the retired ones. Returns the new
+/// key.
+///
+/// The keyring is updated in memory only - save it once the rotation has been pushed.
+pub async fn rotate(store: &SqliteStore, host_id: HostId, keyring: &Keyring) -> Result<Key> {
+    let old = keyring.current();
+    let (new, _) = generate_encoded_key()?;
+    let new_bytes: [u8; 32] = new.into();
+
+    let mut retired = keyring.retired();
+    retired.push(old);
+
+    let bytes = KeyringRecord {
+        current: new_bytes,
+        retired,
+    }
+    .serialize()?;
+
+    let idx = store
+        .last(host_id, KEYRING_TAG)
+        .await?
+        .map_or(0, |p| p.idx + 1);
+
+    for (idx, key) in [(idx, old), (idx + 1, new_bytes)] {
+        let record = Record::builder()
+            .host(Host::new(host_id))
+            .version(KEYRING_VERSION.to_string())
+            .tag(KEYRING_TAG.to_string())
+            .idx(idx)
+            .data(bytes.clone())
+            .build();
+
+        store.push(&record.encrypt::<PASETO_V4>(&key)).await?;
+    }
+
+    keyring.set_current(new_bytes);
+
+    Ok(new)
+}
+
+/// Learn any keys from rotation records that the keyring can read, and switch to the most recently
+/// rotated key. Returns true if the keyring changed.
+pub async fn apply(store: &SqliteStore, keyring: &Keyring) -> Result<bool> {
+    let mut pending = store.all_tagged(KEYRING_TAG).await?;
+    let mut latest: Option<(u64, [u8; 32])> = None;
+    let mut changed = false;
+
+    // Each key we learn may unlock more records, so keep going until nothing new can be read
+    loop {
+        let before = pending.len();
+        let mut remaining = Vec::new();
+
+        for record in pending {
+            let Ok(key) = keyring.key_for(&record.data) else {
+                remaining.push(record);
+                continue;
+            };
+
+            let timestamp = record.timestamp;
+            let decrypted = record.decrypt::<PASETO_V4>(&key)?;
+            let rotation = KeyringRecord::deserialize(&decrypted.data, &decrypted.version)?;
+
+            for key in rotation.retired.iter().chain([&rotation.current]) {
+                changed |= keyring.insert(*key);
+            }
+
+            if latest.is_none_or(|(ts, _)| timestamp > ts) {
+                latest = Some((timestamp, rotation.current));
+            }
+        }
+
+        pending = remaining;
+
+        if pending.is_empty() || pending.len() == before {
+            break;
+        }
+    }
+
+    if !pending.is_empty() {
+        log::warn!(
+            "could not read {} key rotations, as they were encrypted with an unknown key. Run 'atuin key use' with the current key from another machine",
+            pending.len()
+        );
+    }
+
+    if let Some((_, current)) = latest
+        && current != keyring.current()
+    {
+        keyring.set_current(current);
+        changed = true;
+    }
+
+    Ok(changed)
+}
+
+/// Whether `key` was rotated to, on any device
+pub async fn is_rotation(store: &SqliteStore, key: &[u8; 32]) -> Result<bool> {
+    let kid = PASETO_V4::id_for_key(key);
+
+    for record in store.all_tagged(KEYRING_TAG).await? {
+        // rotations are also encrypted with the key they retired, which wasn't rotated to
+        if PASETO_V4::key_id(&record.data).is_ok_and(|id| id == kid) {
+            let decrypted = record.decrypt::<PASETO_V4>(key)?;
+            let rotation = KeyringRecord::deserialize(&decrypted.data, &decrypted.version)?;
+
+            if rotation.current == *key {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+/// Re-encrypt every local record that isn't encrypted with the current key, so that retired keys
+/// are only needed for what's downloaded later. Returns how many were re-encrypted.
+pub async fn migrate(store: &SqliteStore, keyring: &Keyring) -> Result<u64> {
+    // Nothing can be stale without a retired key to have encrypted it
+    if keyring.len() < 2 {
+        return Ok(0);
+    }
+
+    store.re_encrypt(keyring, &keyring.current()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_common::{record::DecryptedData, utils::uuid_v7};
+
+    use super::*;
+    use crate::settings::test_local_timeout;
+
+    fn record(host_id: HostId, idx: u64) -> Record<DecryptedData> {
+        Record::builder()
+            .host(Host::new(host_id))
+            .version("v0".to_string())
+            .tag("kv".to_string())
+            .idx(idx)
+            .data(DecryptedData(vec![1, 2, 3, idx as u8]))
+            .build()
+    }
+
+    #[test]
+    fn keyring_record_round_trip() {
+        let record = KeyringRecord {
+            current: [1; 32],
+            retired: vec![[2; 32], [3; 32]],
+        };
+
+        let bytes = record.serialize().unwrap();
+        let decoded = KeyringRecord::deserialize(&bytes, KEYRING_VERSION).unwrap();
+
+        assert_eq!(record, decoded);
+    }
+
+    #[test]
+    fn decrypts_with_any_known_key() {
+        let host_id = HostId(uuid_v7());
+        let keyring = Keyring::new([1; 32]);
+
+        let old = keyring.encrypt(record(host_id, 0));
+
+        keyring.set_current([2; 32]);
+        let new = keyring.encrypt(record(host_id, 1));
+
+        assert_eq!(keyring.key_for(&old.data).unwrap(), [1; 32]);
+        assert_eq!(keyring.key_for(&new.data).unwrap(), [2; 32]);
+        assert_eq!(keyring.retired(), vec![[1; 32]]);
+
+        keyring.decrypt(old.clone()).unwrap();
+        keyring.decrypt(new).unwrap();
+
+        // a keyring without the old key cannot read the old record
+        let _ = Keyring::new([2; 32]).decrypt(old).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn rotation_is_shared_through_the_store() {
+        let store = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let host_id = HostId(uuid_v7());
+
+        let first = Keyring::new([1; 32]);
+        let old = first.encrypt(record(host_id, 0));
+
+        rotate(&store, host_id, &first).await.unwrap();
+        let new = first.encrypt(record(host_id, 1));
+        let new_key = first.current();
+
+        // a device that knows the original key switches to the new one
+        let other = Keyring::new([1; 32]);
+        assert!(apply(&store, &other).await.unwrap());
+        assert_eq!(other.current(), new_key);
+        other.decrypt(new.clone()).unwrap();
+
+        // and one given the new key learns the old ones
+        let fresh = Keyring::new(new_key);
+        assert!(apply(&store, &fresh).await.unwrap());
+        assert_eq!(fresh.current(), new_key);
+        fresh.decrypt(new).unwrap();
+        fresh.decrypt(old).unwrap();
+
+        // applying again changes nothing
+        assert!(!apply(&store, &fresh).await.unwrap());
+
+        assert!(is_rotation(&store, &new_key).await.unwrap());
+        assert!(!is_rotation(&store, &[1; 32]).await.unwrap());
+
+        // rotations chain, so a device that missed several still catches up
+        rotate(&store, host_id, &fresh).await.unwrap();
+        let behind = Keyring::new([1; 32]);
+        apply(&store, &behind).await.unwrap();
+        assert_eq!(behind.current(), fresh.current());
+        assert_eq!(behind.len(), 3);
+
+        // a key that was never rotated to unlocks nothing
+        let stranger = Keyring::new([9; 32]);
+        assert!(!apply(&store, &stranger).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn migrate_to_the_current_key() {
+        let store = SqliteStore::new(":memory:", test_local_timeout())
+            .await
+            .unwrap();
+        let host_id = HostId(uuid_v7());
+
+        let keyring = Keyring::new([1; 32]);
+        let old = keyring.encrypt(record(host_id, 0));
+        store.push(&old).await.unwrap();
+
+        // nothing to do without a retired key
+        assert_eq!(migrate(&store, &keyring).await.unwrap(), 0);
+
+        rotate(&store, host_id, &keyring).await.unwrap();
+        store
+            .push(&keyring.encrypt(record(host_id, 1)))
+            .await
+            .unwrap();
+
+        assert_eq!(migrate(&store, &keyring).await.unwrap(), 1);
+        assert_eq!(migrate(&store, &keyring).await.unwrap(), 0);
+
+        // everything can now be read without the retired key
+        let current = Keyring::new(keyring.current());
+        for record in store.all_tagged("kv").await.unwrap() {
+            current.decrypt(record).unwrap();
+        }
+    }
+}
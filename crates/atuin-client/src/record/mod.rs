@@ -1,4 +1,6 @@
+pub mod backup;
 pub mod encryption;
+pub mod keyring;
 pub mod sqlite_store;
 pub mod store;
 
@@ -21,6 +21,7 @@ use atuin_common::utils;
 use uuid::Uuid;
 
 use super::encryption::PASETO_V4;
+use super::keyring::{KEYRING_TAG, Keyring};
 use super::store::Store;
 
 #[derive(Debug, Clone)]
@@ -295,52 +296,61 @@ impl Store for SqliteStore {
         Ok(res)
     }
 
-    /// Reencrypt every single item in this store with a new key
+    /// Reencrypt every item in this store that isn't already encrypted with the new key
     /// Be careful - this may mess with sync.
-    async fn re_encrypt(&self, old_key: &[u8; 32], new_key: &[u8; 32]) -> Result<()> {
-        // Load all the records
-        // In memory like some of the other code here
-        // This will never be called in a hot loop, and only under the following circumstances
-        // 1. The user has logged into a new account, with a new key. They are unlikely to have a
-        //    lot of data
-        // 2. The user has encountered some sort of issue, and runs a maintenance command that
-        //    invokes this
-        let all = self.load_all().await?;
+    async fn re_encrypt(&self, keyring: &Keyring, new_key: &[u8; 32]) -> Result<u64> {
+        // The wrapped cek names the key it was wrapped with, so only load the records that
+        // need re-encrypting. After a rotation that's everything written before it, the first
+        // time round, and then just what other machines wrote with older keys. Key rotations
+        // stay encrypted with the key they were written for, so devices holding it can read them.
+        let kid = PASETO_V4::id_for_key(new_key).to_string();
+        let stale = sqlx::query("select * from store where instr(cek, ?1) = 0 and tag != ?2")
+            .bind(kid)
+            .bind(KEYRING_TAG)
+            .map(Self::query_row)
+            .fetch_all(&self.pool)
+            .await?;
 
-        let re_encrypted = all
-            .into_iter()
-            .map(|record| record.re_encrypt::<PASETO_V4>(old_key, new_key))
-            .collect::<Result<Vec<_>>>()?;
+        let mut re_encrypted = Vec::with_capacity(stale.len());
+        for record in stale {
+            let Ok(old_key) = keyring.key_for(&record.data) else {
+                // leave it for when we learn the key
+                debug!(
+                    "cannot re-encrypt record {}, its key is unknown",
+                    record.id.0
+                );
+                continue;
+            };
+
+            re_encrypted.push(record.re_encrypt::<PASETO_V4>(&old_key, new_key)?);
+        }
 
-        // next up, we delete all the old data and reinsert the new stuff
         // do it in one transaction, so if anything fails we rollback OK
-
         let mut tx = self.pool.begin().await?;
 
-        let res = sqlx::query("delete from store").execute(&mut *tx).await?;
-
-        let rows = res.rows_affected();
-        debug!("deleted {rows} rows");
-
-        // don't call push_batch, as it will start its own transaction
-        // call the underlying save_raw
-
-        for record in re_encrypted {
-            Self::save_raw(&mut tx, &record).await?;
+        for record in &re_encrypted {
+            sqlx::query("update store set data = ?1, cek = ?2 where id = ?3")
+                .bind(record.data.data.as_str())
+                .bind(record.data.content_encryption_key.as_str())
+                .bind(record.id.0.as_hyphenated().to_string())
+                .execute(&mut *tx)
+                .await?;
         }
 
         tx.commit().await?;
 
-        Ok(())
+        debug!("re-encrypted {} records", re_encrypted.len());
+
+        Ok(re_encrypted.len() as u64)
     }
 
-    /// Verify that every record in this store can be decrypted with the current key
+    /// Verify that every record in this store can be decrypted with a key in the keyring
     /// Someday maybe also check each tag/record can be deserialized, but not for now.
-    async fn verify(&self, key: &[u8; 32]) -> Result<()> {
+    async fn verify(&self, keyring: &Keyring) -> Result<()> {
         let all = self.load_all().await?;
 
         all.into_iter()
-            .map(|record| record.decrypt::<PASETO_V4>(key))
+            .map(|record| keyring.decrypt(record))
             .collect::<Result<Vec<_>>>()?;
 
         Ok(())
@@ -348,11 +358,11 @@ impl Store for SqliteStore {
 
     /// Verify that every record in this store can be decrypted with the current key
     /// Someday maybe also check each tag/record can be deserialized, but not for now.
-    async fn purge(&self, key: &[u8; 32]) -> Result<()> {
+    async fn purge(&self, keyring: &Keyring) -> Result<()> {
         let all = self.load_all().await?;
 
         for record in all.iter() {
-            match record.clone().decrypt::<PASETO_V4>(key) {
+            match keyring.decrypt(record.clone()) {
                 Ok(_) => continue,
                 Err(_) => {
                     println!(
@@ -378,7 +388,7 @@ mod tests {
 
     use crate::{
         encryption::generate_encoded_key,
-        record::{encryption::PASETO_V4, store::Store},
+        record::{encryption::PASETO_V4, keyring::Keyring, store::Store},
         settings::test_local_timeout,
     };
 
@@ -617,10 +627,19 @@ mod tests {
         // 2) it can be decrypted with the new key
 
         let (new_key, _) = generate_encoded_key().unwrap();
-        store
-            .re_encrypt(&key.into(), &new_key.into())
+        let keyring = Keyring::new(key.into());
+        let re_encrypted = store
+            .re_encrypt(&keyring, &new_key.into())
             .await
             .expect("failed to re-encrypt store");
+        assert_eq!(re_encrypted, 10);
+
+        // records already using the new key are left alone
+        keyring.insert(new_key.into());
+        assert_eq!(
+            store.re_encrypt(&keyring, &new_key.into()).await.unwrap(),
+            0
+        );
 
         let all = store.all_tagged("test").await.unwrap();
 
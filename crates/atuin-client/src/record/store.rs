@@ -3,6 +3,8 @@ use eyre::Result;
 
 use atuin_common::record::{EncryptedData, HostId, Record, RecordId, RecordIdx, RecordStatus};
 
+use super::keyring::Keyring;
+
 /// A record store stores records
 /// In more detail - we tend to need to process this into _another_ format to actually query it.
 /// As is, the record store is intended as the source of truth for arbitrary data, which could
@@ -32,9 +34,12 @@ pub trait Store {
     async fn last(&self, host: HostId, tag: &str) -> Result<Option<Record<EncryptedData>>>;
     async fn first(&self, host: HostId, tag: &str) -> Result<Option<Record<EncryptedData>>>;
 
-    async fn re_encrypt(&self, old_key: &[u8; 32], new_key: &[u8; 32]) -> Result<()>;
-    async fn verify(&self, key: &[u8; 32]) -> Result<()>;
-    async fn purge(&self, key: &[u8; 32]) -> Result<()>;
+    /// Re-encrypt every record that is not already encrypted with `new_key`, reading each with
+    /// whichever key in the keyring it was encrypted with. Key rotations are left as they are.
+    /// Returns how many were re-encrypted.
+    async fn re_encrypt(&self, keyring: &Keyring, new_key: &[u8; 32]) -> Result<u64>;
+    async fn verify(&self, keyring: &Keyring) -> Result<()>;
+    async fn purge(&self, keyring: &Keyring) -> Result<()>;
 
     /// Get the next `limit` records, after and including the given index
     async fn next(
@@ -49,10 +49,7 @@ pub enum Operation {
     },
 }
 
-pub async fn diff(
-    settings: &Settings,
-    store: &impl Store,
-) -> Result<(Vec<Diff>, RecordStatus), SyncError> {
+fn client<'a>(settings: &'a Settings, team: Option<&str>) -> Result<Client<'a>, SyncError> {
     let client = Client::new(
         &settings.sync_address,
         settings
@@ -64,6 +61,23 @@ pub async fn diff(
     )
     .map_err(|e| SyncError::OperationalError { msg: e.to_string() })?;
 
+    Ok(match team {
+        Some(team) => client.with_team(team),
+        None => client,
+    })
+}
+
+pub async fn diff(
+    settings: &Settings,
+    store: &impl Store,
+) -> Result<(Vec<Diff>, RecordStatus), SyncError> {
+    diff_with(&client(settings, None)?, store).await
+}
+
+async fn diff_with(
+    client: &Client<'_>,
+    store: &impl Store,
+) -> Result<(Vec<Diff>, RecordStatus), SyncError> {
     let local_index = store
         .status()
         .await
@@ -271,17 +285,14 @@ pub async fn sync_remote(
     local_store: &impl Store,
     settings: &Settings,
 ) -> Result<(i64, Vec<RecordId>), SyncError> {
-    let client = Client::new(
-        &settings.sync_address,
-        settings
-            .session_token()
-            .map_err(|e| SyncError::RemoteRequestError { msg: e.to_string() })?
-            .as_str(),
-        settings.network_connect_timeout,
-        settings.network_timeout,
-    )
-    .expect("failed to create client");
+    sync_remote_with(operations, local_store, &client(settings, None)?).await
+}
 
+async fn sync_remote_with(
+    operations: Vec<Operation>,
+    local_store: &impl Store,
+    client: &Client<'_>,
+) -> Result<(i64, Vec<RecordId>), SyncError> {
     let mut uploaded = 0;
     let mut downloaded = Vec::new();
 
@@ -293,7 +304,7 @@ pub async fn sync_remote(
                 tag,
                 local,
                 remote,
-            } => uploaded += sync_upload(local_store, &client, host, tag, local, remote).await?,
+            } => uploaded += sync_upload(local_store, client, host, tag, local, remote).await?,
 
             Operation::Download {
                 host,
@@ -301,7 +312,7 @@ pub async fn sync_remote(
                 local,
                 remote,
             } => {
-                let mut d = sync_download(local_store, &client, host, tag, local, remote).await?;
+                let mut d = sync_download(local_store, client, host, tag, local, remote).await?;
                 downloaded.append(&mut d)
             }
 
@@ -323,6 +334,20 @@ pub async fn sync(
     Ok((uploaded, downloaded))
 }
 
+/// Sync a local store with the store shared by `team` on the server
+pub async fn sync_team(
+    settings: &Settings,
+    store: &impl Store,
+    team: &str,
+) -> Result<(i64, Vec<RecordId>), SyncError> {
+    let client = client(settings, Some(team))?;
+
+    let (diff, _) = diff_with(&client, store).await?;
+    let operations = operations(diff, store).await?;
+
+    sync_remote_with(operations, store, &client).await
+}
+
 #[cfg(test)]
 mod tests {
     use atuin_common::record::{Diff, EncryptedData, HostId, Record};
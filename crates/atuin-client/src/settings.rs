@@ -11,7 +11,7 @@ use config::{
 use eyre::{Context, Error, Result, bail, eyre};
 use fs_err::{File, create_dir_all};
 use humantime::parse_duration;
-use regex::RegexSet;
+use regex::{Regex, RegexSet};
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use serde_with::DeserializeFromStr;
@@ -29,7 +29,9 @@ pub const LATEST_VERSION_FILENAME: &str = "latest_version";
 pub const HOST_ID_FILENAME: &str = "host_id";
 static EXAMPLE_CONFIG: &str = include_str!("../config.toml");
 
+pub mod columns;
 mod dotfiles;
+pub mod keymap;
 mod kv;
 mod scripts;
 
@@ -70,6 +72,42 @@ impl SearchMode {
     }
 }
 
+/// How the interactive search folds results into groups
+#[derive(Clone, Debug, Deserialize, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum GroupBy {
+    #[serde(rename = "none")]
+    None,
+
+    #[serde(rename = "session")]
+    Session,
+
+    #[serde(rename = "day")]
+    Day,
+
+    #[serde(rename = "directory")]
+    Directory,
+}
+
+impl GroupBy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GroupBy::None => "none",
+            GroupBy::Session => "session",
+            GroupBy::Day => "day",
+            GroupBy::Directory => "directory",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            GroupBy::None => GroupBy::Session,
+            GroupBy::Session => GroupBy::Day,
+            GroupBy::Day => GroupBy::Directory,
+            GroupBy::Directory => GroupBy::None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Copy, PartialEq, Eq, ValueEnum, Serialize)]
 pub enum FilterMode {
     #[serde(rename = "global")]
@@ -281,6 +319,21 @@ pub struct Stats {
     pub common_subcommands: Vec<String>, // kubectl, commands we should consider subcommands for
     #[serde(default = "Stats::ignored_commands_default")]
     pub ignored_commands: Vec<String>, // cd, ls, etc. commands we want to completely hide from stats
+    /// Count commands that only differ in their arguments as the same command
+    #[serde(default)]
+    pub normalize: bool,
+    /// Rules to normalise commands by, before arguments are replaced with placeholders
+    #[serde(default)]
+    pub normalize_rules: Vec<NormalizeRule>,
+}
+
+/// A rule to normalise commands by: every match of the pattern is replaced
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NormalizeRule {
+    #[serde(with = "serde_regex")]
+    pub pattern: Regex,
+    /// What to replace matches with, which can refer to the groups of the pattern, like `$1`
+    pub replacement: String,
 }
 
 impl Stats {
@@ -328,6 +381,8 @@ impl Default for Stats {
             common_prefix: Self::common_prefix_default(),
             common_subcommands: Self::common_subcommands_default(),
             ignored_commands: Self::ignored_commands_default(),
+            normalize: false,
+            normalize_rules: Vec::new(),
         }
     }
 }
@@ -384,6 +439,139 @@ pub struct Daemon {
     pub tcp_port: u64,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Report {
+    /// How long, in seconds, the gap between two commands in a session can be before it's taken
+    /// as time spent away rather than on the project
+    pub idle_gap: u64,
+
+    /// Projects by name, with the directories that belong to them. Commands run under one of the
+    /// directories count towards its project, and the longest matching directory wins. Commands
+    /// elsewhere count towards the git repo they ran in.
+    pub projects: HashMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Alerts {
+    /// Check each command as it ends against its previous runs, and alert when it was unusually
+    /// slow, or failed when it usually succeeds
+    pub enabled: bool,
+
+    /// How many times longer than its median a command must take to be slow
+    pub slowdown: f64,
+
+    /// Commands that took less than this, in seconds, are never slow
+    pub min_duration: u64,
+
+    /// How many previous runs a command needs before it's checked
+    pub min_runs: usize,
+
+    /// Alert when a command that usually succeeds fails
+    pub failures: bool,
+
+    /// A command to run for each alert, through `sh -c`, with placeholders like `{message}`
+    /// replaced by the details of the alert, quoted for the shell
+    #[serde(default)]
+    pub hook: Option<String>,
+
+    /// Settings for the commands that match a pattern. The first matching rule wins, and
+    /// anything it leaves out comes from the settings above.
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AlertRule {
+    #[serde(with = "serde_regex")]
+    pub pattern: Regex,
+    pub enabled: Option<bool>,
+    pub slowdown: Option<f64>,
+    pub min_duration: Option<u64>,
+    pub min_runs: Option<usize>,
+    pub failures: Option<bool>,
+    pub hook: Option<String>,
+}
+
+impl Alerts {
+    /// The alert settings for a command, with those of the first rule it matches applied
+    pub fn for_command(&self, command: &str) -> Self {
+        let mut alerts = self.clone();
+        alerts.rules.clear();
+
+        if let Some(rule) = self.rules.iter().find(|r| r.pattern.is_match(command)) {
+            alerts.enabled = rule.enabled.unwrap_or(self.enabled);
+            alerts.slowdown = rule.slowdown.unwrap_or(self.slowdown);
+            alerts.min_duration = rule.min_duration.unwrap_or(self.min_duration);
+            alerts.min_runs = rule.min_runs.unwrap_or(self.min_runs);
+            alerts.failures = rule.failures.unwrap_or(self.failures);
+            alerts.hook = rule.hook.clone().or_else(|| self.hook.clone());
+        }
+
+        alerts
+    }
+}
+
+/// A hook the daemon runs after each command that matches it. A hook runs a shell command, posts
+/// to a URL, or both.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Hook {
+    /// A name for the hook, for the logs
+    #[serde(default)]
+    pub name: String,
+
+    /// A shell command to run through `sh -c`, with placeholders like `{command}` replaced by
+    /// the details of the command that ended, quoted for the shell
+    #[serde(default)]
+    pub command: Option<String>,
+
+    /// A URL to post the command that ended to, as JSON, with placeholders replaced by its
+    /// details, URL-encoded
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Only run for commands that match this pattern
+    #[serde(default, with = "serde_regex")]
+    pub pattern: Option<Regex>,
+
+    /// Only run for commands that succeeded, or that failed
+    #[serde(default)]
+    pub on: HookOn,
+
+    /// Only run for commands that exited with one of these codes
+    #[serde(default)]
+    pub exit: Vec<i64>,
+
+    /// Only run for commands that took at least this long, in seconds
+    #[serde(default)]
+    pub min_duration: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookOn {
+    #[default]
+    Any,
+    Success,
+    Failure,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Backup {
+    /// Have the daemon back up the record store on a schedule
+    /// Requires a running daemon with `atuin daemon`
+    #[serde(alias = "enable")]
+    pub enabled: bool,
+
+    /// How often the daemon should take a backup, in seconds
+    pub frequency: u64,
+
+    /// The directory that scheduled backups are written to
+    pub dir: String,
+
+    /// How many scheduled backups to keep. The oldest are deleted first.
+    pub keep: usize,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Search {
     /// The list of enabled filter modes, in order of priority.
@@ -420,6 +608,42 @@ impl Default for Daemon {
     }
 }
 
+impl Default for Report {
+    fn default() -> Self {
+        Self {
+            idle_gap: 300,
+            projects: HashMap::new(),
+        }
+    }
+}
+
+impl Default for Alerts {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            slowdown: 3.0,
+            min_duration: 1,
+            min_runs: 5,
+            failures: true,
+            hook: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl Default for Backup {
+    fn default() -> Self {
+        let dir = atuin_common::utils::data_dir().join("backups");
+
+        Self {
+            enabled: false,
+            frequency: 60 * 60 * 24,
+            dir: dir.to_string_lossy().to_string(),
+            keep: 7,
+        }
+    }
+}
+
 impl Default for Search {
     fn default() -> Self {
         Self {
@@ -463,6 +687,8 @@ pub struct Settings {
     pub db_path: String,
     pub record_store_path: String,
     pub key_path: String,
+    pub keyring_path: String,
+    pub teams_path: String,
     pub session_path: String,
     pub search_mode: SearchMode,
     pub filter_mode: Option<FilterMode>,
@@ -476,7 +702,9 @@ pub struct Settings {
     pub max_preview_height: u16,
     pub show_help: bool,
     pub show_tabs: bool,
+    pub group_by: GroupBy,
     pub show_numeric_shortcuts: bool,
+    pub result_columns: Vec<columns::ResultColumn>,
     pub auto_hide_height: u16,
     pub exit_mode: ExitMode,
     pub keymap_mode: KeymapMode,
@@ -485,6 +713,7 @@ pub struct Settings {
     pub word_jump_mode: WordJumpMode,
     pub word_chars: String,
     pub scroll_context_lines: usize,
+    pub inspector_context_lines: usize,
     pub history_format: String,
     pub prefers_reduced_motion: bool,
     pub store_failed: bool,
@@ -515,6 +744,9 @@ pub struct Settings {
     #[serde(default)]
     pub keys: Keys,
 
+    #[serde(default)]
+    pub keymap: keymap::Settings,
+
     #[serde(default)]
     pub preview: Preview,
 
@@ -524,6 +756,18 @@ pub struct Settings {
     #[serde(default)]
     pub daemon: Daemon,
 
+    #[serde(default)]
+    pub backup: Backup,
+
+    #[serde(default)]
+    pub report: Report,
+
+    #[serde(default)]
+    pub alerts: Alerts,
+
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+
     #[serde(default)]
     pub search: Search,
 
@@ -754,8 +998,11 @@ impl Settings {
         let kv_path = data_dir.join("kv.db");
         let scripts_path = data_dir.join("scripts.db");
         let socket_path = atuin_common::utils::runtime_dir().join("atuin.sock");
+        let backup_dir = data_dir.join("backups");
 
         let key_path = data_dir.join("key");
+        let keyring_path = data_dir.join("keyring");
+        let teams_path = data_dir.join("teams");
         let session_path = data_dir.join("session");
 
         Ok(Config::builder()
@@ -763,6 +1010,8 @@ impl Settings {
             .set_default("db_path", db_path.to_str())?
             .set_default("record_store_path", record_store_path.to_str())?
             .set_default("key_path", key_path.to_str())?
+            .set_default("keyring_path", keyring_path.to_str())?
+            .set_default("teams_path", teams_path.to_str())?
             .set_default("session_path", session_path.to_str())?
             .set_default("dialect", "us")?
             .set_default("timezone", "local")?
@@ -779,7 +1028,9 @@ impl Settings {
             .set_default("max_preview_height", 4)?
             .set_default("show_help", true)?
             .set_default("show_tabs", true)?
+            .set_default("group_by", "none")?
             .set_default("show_numeric_shortcuts", true)?
+            .set_default("result_columns", vec!["duration", "time"])?
             .set_default("auto_hide_height", 8)?
             .set_default("invert", false)?
             .set_default("exit_mode", "return-original")?
@@ -789,6 +1040,7 @@ impl Settings {
                 "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789",
             )?
             .set_default("scroll_context_lines", 1)?
+            .set_default("inspector_context_lines", 5)?
             .set_default("shell_up_key_binding", false)?
             .set_default("workspaces", false)?
             .set_default("ctrl_n_shortcuts", false)?
@@ -820,6 +1072,17 @@ impl Settings {
             .set_default("daemon.socket_path", socket_path.to_str())?
             .set_default("daemon.systemd_socket", false)?
             .set_default("daemon.tcp_port", 8889)?
+            .set_default("backup.enabled", false)?
+            .set_default("backup.frequency", 60 * 60 * 24)?
+            .set_default("backup.dir", backup_dir.to_str())?
+            .set_default("backup.keep", 7)?
+            .set_default("report.idle_gap", 300)?
+            .set_default("report.projects", HashMap::<String, Vec<String>>::new())?
+            .set_default("alerts.enabled", false)?
+            .set_default("alerts.slowdown", 3.0)?
+            .set_default("alerts.min_duration", 1)?
+            .set_default("alerts.min_runs", 5)?
+            .set_default("alerts.failures", true)?
             .set_default("kv.db_path", kv_path.to_str())?
             .set_default("scripts.db_path", scripts_path.to_str())?
             .set_default(
@@ -892,8 +1155,23 @@ impl Settings {
         settings.db_path = Self::expand_path(settings.db_path)?;
         settings.record_store_path = Self::expand_path(settings.record_store_path)?;
         settings.key_path = Self::expand_path(settings.key_path)?;
+        settings.keyring_path = Self::expand_path(settings.keyring_path)?;
+        settings.teams_path = Self::expand_path(settings.teams_path)?;
         settings.session_path = Self::expand_path(settings.session_path)?;
         settings.daemon.socket_path = Self::expand_path(settings.daemon.socket_path)?;
+        settings.backup.dir = Self::expand_path(settings.backup.dir)?;
+
+        for dirs in settings.report.projects.values_mut() {
+            for dir in dirs.iter_mut() {
+                *dir = Self::expand_path(std::mem::take(dir))?;
+            }
+        }
+
+        settings.keymap.validate(&settings.keys.prefix)?;
+
+        if settings.backup.frequency == 0 {
+            bail!("backup.frequency must be at least 1 second");
+        }
 
         Ok(settings)
     }
@@ -0,0 +1,179 @@
+//! Columns shown before each command in the interactive search results.
+//!
+//! `result_columns` lists the columns in order. Each entry is either the name of a column, or a
+//! table that also sets its `width`, its `align`ment, and whether it is shown in the `compact`
+//! styles:
+//!
+//! ```toml
+//! result_columns = ["duration", { column = "directory", width = 30 }, "time"]
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Column {
+    /// How long the command ran for
+    Duration,
+    /// How long ago the command ran
+    Time,
+    /// When the command ran, as a date and time
+    Datetime,
+    /// The exit code of the command
+    Exit,
+    Host,
+    User,
+    /// The directory the command ran in
+    Directory,
+    /// The short id of the session the command ran in
+    Session,
+    /// The git branch currently checked out in the directory the command ran in
+    Branch,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    Left,
+    Right,
+}
+
+impl Column {
+    /// The width and alignment of a column, unless configured otherwise. The defaults of the
+    /// duration and time columns line commands up with the search input.
+    fn defaults(self) -> (u16, Align) {
+        match self {
+            Column::Duration => (5, Align::Left),
+            Column::Time => (7, Align::Right),
+            Column::Datetime => (16, Align::Left),
+            Column::Exit => (3, Align::Right),
+            Column::Host => (12, Align::Left),
+            Column::User => (10, Align::Left),
+            Column::Directory => (24, Align::Left),
+            Column::Session => (8, Align::Left),
+            Column::Branch => (16, Align::Left),
+        }
+    }
+
+    /// Whether values too long for the column are cut to its width. Values of the other columns
+    /// are short, so they push the rest of the row along instead.
+    pub fn truncates(self) -> bool {
+        matches!(
+            self,
+            Column::Host | Column::User | Column::Directory | Column::Session | Column::Branch
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "Entry")]
+pub struct ResultColumn {
+    pub column: Column,
+    pub width: u16,
+    pub align: Align,
+    /// Show the column in the compact styles too
+    pub compact: bool,
+}
+
+impl From<Column> for ResultColumn {
+    fn from(column: Column) -> Self {
+        let (width, align) = column.defaults();
+
+        Self {
+            column,
+            width,
+            align,
+            compact: true,
+        }
+    }
+}
+
+/// A column as written in the config, by name or as a table
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Name(Column),
+    Table {
+        column: Column,
+        width: Option<u16>,
+        align: Option<Align>,
+        compact: Option<bool>,
+    },
+}
+
+impl From<Entry> for ResultColumn {
+    fn from(entry: Entry) -> Self {
+        match entry {
+            Entry::Name(column) => column.into(),
+            Entry::Table {
+                column,
+                width,
+                align,
+                compact,
+            } => {
+                let defaults = Self::from(column);
+
+                Self {
+                    column,
+                    width: width.unwrap_or(defaults.width),
+                    align: align.unwrap_or(defaults.align),
+                    compact: compact.unwrap_or(defaults.compact),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, File, FileFormat};
+    use serde::Deserialize;
+
+    use super::{Align, Column, ResultColumn};
+
+    #[derive(Deserialize)]
+    struct Columns {
+        result_columns: Vec<ResultColumn>,
+    }
+
+    #[test]
+    fn parse_columns() {
+        let config = Config::builder()
+            .add_source(File::from_str(
+                r#"result_columns = [
+                    "exit",
+                    { column = "directory", width = 30, compact = false },
+                    { column = "time", align = "left" },
+                ]"#,
+                FileFormat::Toml,
+            ))
+            .build()
+            .unwrap();
+
+        let columns: Columns = config.try_deserialize().unwrap();
+
+        assert_eq!(
+            columns.result_columns,
+            [
+                ResultColumn {
+                    column: Column::Exit,
+                    width: 3,
+                    align: Align::Right,
+                    compact: true,
+                },
+                ResultColumn {
+                    column: Column::Directory,
+                    width: 30,
+                    align: Align::Left,
+                    compact: false,
+                },
+                ResultColumn {
+                    column: Column::Time,
+                    width: 7,
+                    align: Align::Left,
+                    compact: true,
+                },
+            ]
+        );
+    }
+}
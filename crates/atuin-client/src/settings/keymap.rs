@@ -0,0 +1,554 @@
+//! Keybindings for the interactive search.
+//!
+//! Every keymap mode starts from a set of default bindings, which can be overridden per mode in
+//! the `[keymap.emacs]`, `[keymap.vim-normal]` and `[keymap.vim-insert]` tables of the config.
+//! Each entry maps a key chord such as `"ctrl-r"` or `"alt-f"` to an [`Action`]. Binding a chord
+//! to `"none"` removes its default binding.
+
+use std::{collections::HashMap, fmt, str::FromStr, sync::LazyLock};
+
+use eyre::{Result, bail, eyre};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+
+use super::KeymapMode;
+
+/// Something the interactive search can do in response to a key
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Deserialize,
+    Serialize,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum Action {
+    /// Remove the default binding for a chord
+    None,
+
+    /// Accept the selected command, running it if `enter_accept` is set
+    Accept,
+    /// Accept the selected command, leaving it in the shell for editing
+    AcceptAndEdit,
+    /// Edit the selected command before accepting it
+    EditCommand,
+    /// Edit the selected command in `$EDITOR` before accepting it
+    OpenEditor,
+    /// Break the line while editing a command
+    NewLine,
+    /// Exit as configured by `exit_mode`
+    Exit,
+    /// Exit, returning the original command line
+    ReturnOriginal,
+    /// Exit, returning the current query
+    ReturnQuery,
+    /// Copy the selected command to the clipboard, or every marked command on its own line
+    Copy,
+    /// Delete the selected command from history, or every marked command if any are marked
+    Delete,
+    /// Mark or unmark the selected command, for the batch actions
+    ToggleMark,
+    /// Mark every command between the last one marked or unmarked and the selected one
+    MarkRange,
+    /// Unmark every command
+    ClearMarks,
+    /// Copy the marked commands to the clipboard, joined with `&&`
+    CopyChained,
+    /// Save the marked commands, or the selected one, as a new script
+    SaveScript,
+    /// Switch between the search and inspector tabs
+    ToggleInspector,
+    CycleFilterMode,
+    CycleSearchMode,
+    /// Switch between grouping results by nothing, session, day and directory
+    CycleGroupBy,
+    /// Fold or unfold the group of the selected entry
+    ToggleGroup,
+    Redraw,
+
+    /// Move the selection up one entry
+    ScrollUp,
+    /// Move the selection down one entry, exiting past the first entry if `keys.scroll_exits` is
+    /// set
+    ScrollDown,
+    /// Move the selection up one entry, never exiting
+    SelectPrevious,
+    /// Move the selection down one entry, never exiting
+    SelectNext,
+    PageUp,
+    PageDown,
+
+    CursorLeft,
+    CursorRight,
+    CursorWordLeft,
+    CursorWordRight,
+    CursorStart,
+    CursorEnd,
+    DeleteCharBefore,
+    DeleteCharAfter,
+    DeleteWordBefore,
+    DeleteWordAfter,
+    /// Delete back to the previous whitespace, like `unix-word-rubout`
+    DeleteToWhitespace,
+    /// Delete the next character, or return the original command line if the query is empty
+    DeleteOrReturnOriginal,
+    ClearInput,
+
+    /// Switch to vim normal mode
+    VimNormal,
+    /// Switch to vim insert mode
+    VimInsert,
+    /// Switch to vim insert mode at the start of the line
+    VimInsertStart,
+    /// Switch to vim insert mode after the cursor
+    VimAppend,
+    /// Switch to vim insert mode at the end of the line
+    VimAppendEnd,
+    /// Clear the query and switch to vim insert mode
+    VimSearch,
+}
+
+/// A key, without modifiers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Esc,
+    Tab,
+    Backspace,
+    Delete,
+    Insert,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    F(u8),
+}
+
+/// A key along with the modifiers held while pressing it.
+///
+/// Shift is not tracked for characters, as it is already reflected in the character itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyChord {
+    pub key: Key,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl KeyChord {
+    pub fn new(key: Key, ctrl: bool, alt: bool, shift: bool) -> Self {
+        KeyChord {
+            key,
+            ctrl,
+            alt,
+            shift: shift && !matches!(key, Key::Char(_)),
+        }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (mut ctrl, mut alt, mut shift) = (false, false, false);
+        let mut rest = s;
+
+        // a trailing "-" is the minus key itself, as in "ctrl--"
+        while let Some((modifier, tail)) = rest.split_once('-')
+            && !tail.is_empty()
+        {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => ctrl = true,
+                "alt" | "meta" => alt = true,
+                "shift" => shift = true,
+                _ => break,
+            }
+
+            rest = tail;
+        }
+
+        let mut chars = rest.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(c), None) => Key::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "space" => Key::Char(' '),
+                "enter" | "return" => Key::Enter,
+                "esc" | "escape" => Key::Esc,
+                "tab" => Key::Tab,
+                "backspace" => Key::Backspace,
+                "delete" | "del" => Key::Delete,
+                "insert" => Key::Insert,
+                "up" => Key::Up,
+                "down" => Key::Down,
+                "left" => Key::Left,
+                "right" => Key::Right,
+                "home" => Key::Home,
+                "end" => Key::End,
+                "pageup" | "page-up" => Key::PageUp,
+                "pagedown" | "page-down" => Key::PageDown,
+                f => match f.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=24) => Key::F(n),
+                    _ => bail!("unknown key '{rest}' in key chord '{s}'"),
+                },
+            },
+        };
+
+        let key = match key {
+            Key::Char(c) if shift => Key::Char(c.to_uppercase().next().unwrap_or(c)),
+            key => key,
+        };
+
+        Ok(KeyChord::new(key, ctrl, alt, shift))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            f.write_str("ctrl-")?;
+        }
+        if self.alt {
+            f.write_str("alt-")?;
+        }
+        if self.shift {
+            f.write_str("shift-")?;
+        }
+
+        match self.key {
+            Key::Char(' ') => f.write_str("space"),
+            Key::Char(c) => write!(f, "{c}"),
+            Key::Enter => f.write_str("enter"),
+            Key::Esc => f.write_str("esc"),
+            Key::Tab => f.write_str("tab"),
+            Key::Backspace => f.write_str("backspace"),
+            Key::Delete => f.write_str("delete"),
+            Key::Insert => f.write_str("insert"),
+            Key::Up => f.write_str("up"),
+            Key::Down => f.write_str("down"),
+            Key::Left => f.write_str("left"),
+            Key::Right => f.write_str("right"),
+            Key::Home => f.write_str("home"),
+            Key::End => f.write_str("end"),
+            Key::PageUp => f.write_str("pageup"),
+            Key::PageDown => f.write_str("pagedown"),
+            Key::F(n) => write!(f, "f{n}"),
+        }
+    }
+}
+
+/// The bindings configured for one keymap mode
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bindings(HashMap<KeyChord, Action>);
+
+impl Bindings {
+    /// Parse bindings, failing if a chord is invalid, or if two entries bind the same chord
+    /// spelled differently (such as `"ctrl-r"` and `"control-r"`)
+    pub fn parse<'a>(entries: impl IntoIterator<Item = (&'a str, Action)>) -> Result<Self> {
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        entries.sort_unstable_by_key(|(chord, _)| *chord);
+
+        let mut bindings = HashMap::with_capacity(entries.len());
+        let mut spelling = HashMap::with_capacity(entries.len());
+
+        for (chord, action) in entries {
+            let parsed: KeyChord = chord.parse()?;
+
+            if let Some(previous) = spelling.insert(parsed, chord) {
+                bail!("'{previous}' and '{chord}' both bind {parsed}");
+            }
+
+            bindings.insert(parsed, action);
+        }
+
+        Ok(Bindings(bindings))
+    }
+
+    pub fn get(&self, chord: &KeyChord) -> Option<Action> {
+        self.0.get(chord).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&KeyChord, &Action)> {
+        self.0.iter()
+    }
+}
+
+impl<'de> Deserialize<'de> for Bindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = HashMap::<String, Action>::deserialize(deserializer)?;
+
+        Bindings::parse(entries.iter().map(|(k, v)| (k.as_str(), *v))).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Bindings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(k, v)| (k.to_string(), v)))
+    }
+}
+
+/// Bindings available in every mode
+const COMMON: &[(&str, Action)] = &[
+    ("ctrl-c", Action::ReturnOriginal),
+    ("ctrl-g", Action::ReturnOriginal),
+    ("esc", Action::Exit),
+    ("ctrl-[", Action::Exit),
+    ("tab", Action::AcceptAndEdit),
+    ("ctrl-o", Action::ToggleInspector),
+    ("enter", Action::Accept),
+    ("ctrl-m", Action::Accept),
+    ("ctrl-y", Action::Copy),
+    ("left", Action::CursorLeft),
+    ("ctrl-b", Action::CursorLeft),
+    ("right", Action::CursorRight),
+    ("ctrl-f", Action::CursorRight),
+    ("ctrl-left", Action::CursorWordLeft),
+    ("alt-b", Action::CursorWordLeft),
+    ("ctrl-right", Action::CursorWordRight),
+    ("alt-f", Action::CursorWordRight),
+    ("home", Action::CursorStart),
+    ("ctrl-a", Action::CursorStart),
+    ("end", Action::CursorEnd),
+    ("ctrl-e", Action::CursorEnd),
+    ("backspace", Action::DeleteCharBefore),
+    // Depending on the terminal, backspace can be sent as ctrl-h or ctrl-?, so treat those the
+    // same as backspace
+    ("ctrl-h", Action::DeleteCharBefore),
+    ("ctrl-?", Action::DeleteCharBefore),
+    ("ctrl-backspace", Action::DeleteWordBefore),
+    ("delete", Action::DeleteCharAfter),
+    ("ctrl-delete", Action::DeleteWordAfter),
+    ("ctrl-d", Action::DeleteOrReturnOriginal),
+    ("ctrl-w", Action::DeleteToWhitespace),
+    ("ctrl-u", Action::ClearInput),
+    ("ctrl-r", Action::CycleFilterMode),
+    ("ctrl-s", Action::CycleSearchMode),
+    ("up", Action::ScrollUp),
+    ("down", Action::ScrollDown),
+    ("ctrl-p", Action::SelectPrevious),
+    ("ctrl-k", Action::SelectPrevious),
+    ("ctrl-n", Action::SelectNext),
+    ("ctrl-j", Action::SelectNext),
+    ("pageup", Action::PageUp),
+    ("pagedown", Action::PageDown),
+    ("ctrl-l", Action::Redraw),
+    ("ctrl-t", Action::ToggleMark),
+    ("alt-t", Action::MarkRange),
+    ("alt-u", Action::ClearMarks),
+    ("alt-y", Action::CopyChained),
+    ("alt-s", Action::SaveScript),
+    ("alt-g", Action::CycleGroupBy),
+    ("alt-z", Action::ToggleGroup),
+    ("alt-e", Action::EditCommand),
+    ("ctrl-x", Action::OpenEditor),
+    ("alt-enter", Action::NewLine),
+];
+
+const VIM_NORMAL: &[(&str, Action)] = &[
+    ("/", Action::VimSearch),
+    ("?", Action::VimSearch),
+    ("j", Action::ScrollDown),
+    ("k", Action::ScrollUp),
+    ("h", Action::CursorLeft),
+    ("l", Action::CursorRight),
+    ("i", Action::VimInsert),
+    ("I", Action::VimInsertStart),
+    ("a", Action::VimAppend),
+    ("A", Action::VimAppendEnd),
+    ("space", Action::ToggleMark),
+    ("V", Action::MarkRange),
+    ("v", Action::OpenEditor),
+];
+
+const VIM_INSERT: &[(&str, Action)] = &[("esc", Action::VimNormal), ("ctrl-[", Action::VimNormal)];
+
+fn defaults(extra: &[(&'static str, Action)]) -> Bindings {
+    let mut bindings = Bindings::parse(COMMON.iter().copied()).expect("invalid default bindings");
+    let extra = Bindings::parse(extra.iter().copied()).expect("invalid default bindings");
+    bindings.0.extend(extra.0);
+
+    bindings
+}
+
+static EMACS_DEFAULTS: LazyLock<Bindings> = LazyLock::new(|| defaults(&[]));
+static VIM_NORMAL_DEFAULTS: LazyLock<Bindings> = LazyLock::new(|| defaults(VIM_NORMAL));
+static VIM_INSERT_DEFAULTS: LazyLock<Bindings> = LazyLock::new(|| defaults(VIM_INSERT));
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub emacs: Bindings,
+
+    #[serde(default, rename = "vim-normal")]
+    pub vim_normal: Bindings,
+
+    #[serde(default, rename = "vim-insert")]
+    pub vim_insert: Bindings,
+}
+
+impl Settings {
+    fn custom(&self, mode: KeymapMode) -> &Bindings {
+        match mode {
+            KeymapMode::Emacs | KeymapMode::Auto => &self.emacs,
+            KeymapMode::VimNormal => &self.vim_normal,
+            KeymapMode::VimInsert => &self.vim_insert,
+        }
+    }
+
+    fn defaults(mode: KeymapMode) -> &'static Bindings {
+        match mode {
+            KeymapMode::Emacs | KeymapMode::Auto => &EMACS_DEFAULTS,
+            KeymapMode::VimNormal => &VIM_NORMAL_DEFAULTS,
+            KeymapMode::VimInsert => &VIM_INSERT_DEFAULTS,
+        }
+    }
+
+    /// The action bound to a chord in a mode, if any
+    pub fn action(&self, mode: KeymapMode, chord: &KeyChord) -> Option<Action> {
+        match self
+            .custom(mode)
+            .get(chord)
+            .or_else(|| Self::defaults(mode).get(chord))
+        {
+            Some(Action::None) | None => None,
+            action => action,
+        }
+    }
+
+    /// Every binding active in a mode, sorted by chord. The flag is set for custom bindings.
+    pub fn bindings(&self, mode: KeymapMode) -> Vec<(KeyChord, Action, bool)> {
+        let custom = self.custom(mode);
+
+        let mut bindings: Vec<_> = Self::defaults(mode)
+            .iter()
+            .filter(|(chord, _)| custom.get(chord).is_none())
+            .map(|(chord, action)| (*chord, *action, false))
+            .chain(custom.iter().map(|(chord, action)| (*chord, *action, true)))
+            .filter(|(_, action, _)| *action != Action::None)
+            .collect();
+
+        bindings.sort_unstable_by_key(|(chord, _, _)| *chord);
+
+        bindings
+    }
+
+    /// Check that no custom binding is shadowed by the ctrl prefix key from `keys.prefix`
+    pub fn validate(&self, prefix: &str) -> Result<()> {
+        let prefix = prefix
+            .chars()
+            .next()
+            .ok_or_else(|| eyre!("keys.prefix must not be empty"))?;
+        let prefix = KeyChord::new(Key::Char(prefix), true, false, false);
+
+        for (name, bindings) in [
+            ("emacs", &self.emacs),
+            ("vim-normal", &self.vim_normal),
+            ("vim-insert", &self.vim_insert),
+        ] {
+            if let Some(action) = bindings.get(&prefix)
+                && action != Action::None
+            {
+                bail!(
+                    "keymap.{name} binds {prefix} to {action}, but {prefix} is the prefix key set by keys.prefix"
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_chords() {
+        let chord = |s: &str| s.parse::<KeyChord>().unwrap();
+
+        assert_eq!(
+            chord("ctrl-r"),
+            KeyChord::new(Key::Char('r'), true, false, false)
+        );
+        assert_eq!(chord("Control-r"), chord("ctrl-r"));
+        assert_eq!(chord("meta-f"), chord("alt-f"));
+        assert_eq!(chord("shift-a"), chord("A"));
+        assert_eq!(chord("ctrl--").key, Key::Char('-'));
+        assert_eq!(chord("-").key, Key::Char('-'));
+        assert_eq!(chord("PageDown"), chord("page-down"));
+        assert_eq!(chord("shift-tab").to_string(), "shift-tab");
+        assert_eq!(chord("ctrl-alt-space").to_string(), "ctrl-alt-space");
+        assert_eq!(chord("f12").key, Key::F(12));
+
+        assert!("ctrl-".parse::<KeyChord>().is_err());
+        assert!("hyper-x".parse::<KeyChord>().is_err());
+        assert!("f99".parse::<KeyChord>().is_err());
+        assert!("".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn conflicting_bindings() {
+        let err = Bindings::parse([
+            ("ctrl-r", Action::CycleFilterMode),
+            ("control-r", Action::Copy),
+        ])
+        .unwrap_err();
+
+        assert_eq!(err.to_string(), "'control-r' and 'ctrl-r' both bind ctrl-r");
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        for bindings in [COMMON, VIM_NORMAL, VIM_INSERT] {
+            Bindings::parse(bindings.iter().copied()).unwrap();
+        }
+    }
+
+    #[test]
+    fn custom_bindings_override_defaults() {
+        let settings = Settings {
+            emacs: Bindings::parse([
+                ("ctrl-r", Action::CycleSearchMode),
+                ("ctrl-y", Action::None),
+            ])
+            .unwrap(),
+            ..Settings::default()
+        };
+
+        let ctrl = |c| KeyChord::new(Key::Char(c), true, false, false);
+
+        assert_eq!(
+            settings.action(KeymapMode::Emacs, &ctrl('r')),
+            Some(Action::CycleSearchMode)
+        );
+        assert_eq!(settings.action(KeymapMode::Emacs, &ctrl('y')), None);
+        assert_eq!(
+            settings.action(KeymapMode::VimInsert, &ctrl('r')),
+            Some(Action::CycleFilterMode)
+        );
+
+        let bindings = settings.bindings(KeymapMode::Emacs);
+        assert!(bindings.contains(&(ctrl('r'), Action::CycleSearchMode, true)));
+        assert!(!bindings.iter().any(|(chord, _, _)| *chord == ctrl('y')));
+    }
+
+    #[test]
+    fn prefix_conflicts() {
+        let settings = Settings {
+            vim_normal: Bindings::parse([("ctrl-a", Action::Copy)]).unwrap(),
+            ..Settings::default()
+        };
+
+        assert!(settings.validate("a").is_err());
+        assert!(settings.validate("b").is_ok());
+    }
+}
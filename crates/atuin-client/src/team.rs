@@ -0,0 +1,214 @@
+//! Local state for teams the user has joined.
+//!
+//! A team shares a record store on the server, encrypted with a key that every member holds. Each
+//! joined team gets its own directory under `teams_path`, holding the team key, a local copy of
+//! the shared record store, and the kv and script databases built from it. Nothing from a team
+//! store ever ends up in the user's own store, or the other way around.
+
+use std::path::{Path, PathBuf};
+
+use eyre::{Context, Result, bail};
+use fs_err as fs;
+
+use crate::{
+    encryption::{Key, decode_key, encode_key},
+    record::{keyring::Keyring, sqlite_store::SqliteStore},
+    settings::Settings,
+};
+
+const MAX_NAME_LENGTH: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct Team {
+    pub name: String,
+    dir: PathBuf,
+}
+
+/// Team names match the rules the server enforces, so they are also safe to use as a directory
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+    if !valid {
+        bail!(
+            "team names must be at most {MAX_NAME_LENGTH} characters, and only contain alphanumerics and hyphens (-)"
+        );
+    }
+
+    Ok(())
+}
+
+/// All teams joined on this machine, sorted by name
+pub fn list(settings: &Settings) -> Result<Vec<Team>> {
+    let root = Path::new(&settings.teams_path);
+
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut teams = Vec::new();
+
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+
+        if validate_name(&name).is_ok() && entry.path().join("key").exists() {
+            teams.push(Team {
+                name,
+                dir: entry.path(),
+            });
+        }
+    }
+
+    teams.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(teams)
+}
+
+impl Team {
+    fn dir(settings: &Settings, name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+
+        Ok(Path::new(&settings.teams_path).join(name))
+    }
+
+    /// Open a team that has already been joined on this machine
+    pub fn open(settings: &Settings, name: &str) -> Result<Self> {
+        let dir = Self::dir(settings, name)?;
+
+        if !dir.join("key").exists() {
+            bail!("not a member of team '{name}' on this machine - join it with 'atuin team join'");
+        }
+
+        Ok(Team {
+            name: name.to_string(),
+            dir,
+        })
+    }
+
+    /// Join a team locally, saving its key. Joining a team that has already been joined replaces
+    /// the key.
+    pub fn join(settings: &Settings, name: &str, key: &Key) -> Result<Self> {
+        let dir = Self::dir(settings, name)?;
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("key"), encode_key(key)?)?;
+
+        Ok(Team {
+            name: name.to_string(),
+            dir,
+        })
+    }
+
+    /// Remove the key and all local data for this team
+    pub fn leave(self) -> Result<()> {
+        fs::remove_dir_all(&self.dir)?;
+
+        Ok(())
+    }
+
+    pub fn key(&self) -> Result<Key> {
+        let key = fs::read_to_string(self.dir.join("key"))?;
+
+        decode_key(key).with_context(|| format!("the key for team '{}' is invalid", self.name))
+    }
+
+    pub fn keyring(&self) -> Result<Keyring> {
+        Ok(Keyring::new(self.key()?.into()))
+    }
+
+    pub async fn record_store(&self, settings: &Settings) -> Result<SqliteStore> {
+        SqliteStore::new(self.dir.join("records.db"), settings.local_timeout).await
+    }
+
+    pub fn kv_db_path(&self) -> String {
+        self.dir.join("kv.db").to_string_lossy().to_string()
+    }
+
+    pub fn scripts_db_path(&self) -> String {
+        self.dir.join("scripts.db").to_string_lossy().to_string()
+    }
+}
+
+/// Sync a team's record store with the one it shares on the server, then rebuild its databases
+/// with `build` if anything came down. The kv and script stores depend on this crate, so building
+/// them is left to the caller. Returns how many records were uploaded and downloaded.
+#[cfg(feature = "sync")]
+pub async fn sync<F>(
+    settings: &Settings,
+    team: &Team,
+    build: impl Fn(Team, SqliteStore) -> F,
+) -> Result<(i64, usize)>
+where
+    F: Future<Output = Result<()>>,
+{
+    let store = team.record_store(settings).await?;
+
+    let (uploaded, downloaded) = crate::record::sync::sync_team(settings, &store, &team.name)
+        .await
+        .with_context(|| format!("failed to sync team '{}'", team.name))?;
+
+    if !downloaded.is_empty() {
+        build(team.clone(), store).await?;
+    }
+
+    Ok((uploaded, downloaded.len()))
+}
+
+/// Sync every team joined on this machine, as with [`sync`]. A team that fails to sync doesn't
+/// hold up the others, so each team's name is returned with how it went.
+#[cfg(feature = "sync")]
+pub async fn sync_all<F>(
+    settings: &Settings,
+    build: impl Fn(Team, SqliteStore) -> F,
+) -> Result<Vec<(String, Result<(i64, usize)>)>>
+where
+    F: Future<Output = Result<()>>,
+{
+    let mut synced = Vec::new();
+
+    for team in list(settings)? {
+        let result = sync(settings, &team, &build).await;
+        synced.push((team.name, result));
+    }
+
+    Ok(synced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::generate_encoded_key;
+
+    #[test]
+    fn join_list_leave() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings {
+            teams_path: dir.path().join("teams").to_string_lossy().to_string(),
+            ..Settings::utc()
+        };
+
+        assert!(list(&settings).unwrap().is_empty());
+        assert!(Team::open(&settings, "ops").is_err());
+        assert!(Team::join(&settings, "../ops", &generate_encoded_key().unwrap().0).is_err());
+
+        let (key, _) = generate_encoded_key().unwrap();
+        Team::join(&settings, "ops", &key).unwrap();
+        Team::join(&settings, "dev", &key).unwrap();
+
+        let names: Vec<_> = list(&settings)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, vec!["dev", "ops"]);
+
+        let team = Team::open(&settings, "ops").unwrap();
+        assert_eq!(team.key().unwrap(), key);
+
+        team.leave().unwrap();
+        assert_eq!(list(&settings).unwrap().len(), 1);
+    }
+}
@@ -1,3 +1,4 @@
+use atuin_common::syntax::TokenKind;
 use config::{Config, File as ConfigFile, FileFormat};
 use lazy_static::lazy_static;
 use log;
@@ -31,6 +32,28 @@ pub enum Meaning {
     Important,
     Title,
     Muted,
+    // Syntax highlighting of commands
+    SyntaxCommand,
+    SyntaxFlag,
+    SyntaxString,
+    SyntaxVariable,
+    SyntaxOperator,
+    SyntaxComment,
+}
+
+impl Meaning {
+    /// The meaning used to highlight a token of a command
+    pub fn for_token(kind: TokenKind) -> Meaning {
+        match kind {
+            TokenKind::Command => Meaning::SyntaxCommand,
+            TokenKind::Flag => Meaning::SyntaxFlag,
+            TokenKind::String => Meaning::SyntaxString,
+            TokenKind::Variable | TokenKind::Assignment => Meaning::SyntaxVariable,
+            TokenKind::Pipe | TokenKind::Redirect | TokenKind::Operator => Meaning::SyntaxOperator,
+            TokenKind::Comment => Meaning::SyntaxComment,
+            TokenKind::Argument | TokenKind::Whitespace => Meaning::Base,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -150,16 +173,12 @@ impl Theme {
         parent: Option<&Theme>,
         overrides: &HashMap<Meaning, ContentStyle>,
     ) -> Theme {
-        let styles = match parent {
-            Some(theme) => Box::new(theme.styles.clone()),
-            None => Box::new(DEFAULT_THEME.styles.clone()),
-        }
-        .iter()
-        .map(|(name, color)| match overrides.get(name) {
-            Some(value) => (*name, *value),
-            None => (*name, *color),
-        })
-        .collect();
+        let mut styles = match parent {
+            Some(theme) => theme.styles.clone(),
+            None => DEFAULT_THEME.styles.clone(),
+        };
+        // overrides may also set meanings that the parent leaves to their fallbacks
+        styles.extend(overrides);
         Theme::new(name, parent.map(|p| p.name.clone()), styles)
     }
 }
@@ -254,6 +273,12 @@ lazy_static! {
             (Meaning::Guidance, Meaning::AlertInfo),
             (Meaning::Annotation, Meaning::AlertInfo),
             (Meaning::Title, Meaning::Important),
+            (Meaning::SyntaxCommand, Meaning::Important),
+            (Meaning::SyntaxFlag, Meaning::Guidance),
+            (Meaning::SyntaxString, Meaning::AlertInfo),
+            (Meaning::SyntaxVariable, Meaning::AlertWarn),
+            (Meaning::SyntaxOperator, Meaning::Annotation),
+            (Meaning::SyntaxComment, Meaning::Muted),
         ])
     };
     static ref DEFAULT_THEME: Theme = {
@@ -593,9 +618,48 @@ mod theme_tests {
             .load_theme_from_config("title_theme", title_config, 1)
             .unwrap();
 
+        // The theme sets Title itself, so its colour is used rather than the fallback to
+        // Important, even though the default theme leaves Title to that fallback
         assert_eq!(
             title_theme.as_style(Meaning::Title).foreground_color,
-            Some(Color::White)
+            from_string("white").ok()
+        );
+        assert_ne!(
+            title_theme.as_style(Meaning::Title).foreground_color,
+            title_theme.as_style(Meaning::Important).foreground_color,
+        );
+    }
+
+    #[test]
+    fn test_can_set_meaning_missing_from_parent() {
+        let mut manager = ThemeManager::new(Some(false), Some("".to_string()));
+
+        assert!(!DEFAULT_THEME.styles.contains_key(&Meaning::SyntaxFlag));
+
+        let config = Config::builder()
+            .add_source(ConfigFile::from_str(
+                "
+        [theme]
+        name = \"syntax_theme\"
+
+        [colors]
+        SyntaxFlag = \"#ff0000\"
+        ",
+                FileFormat::Toml,
+            ))
+            .build()
+            .unwrap();
+        let theme = manager
+            .load_theme_from_config("syntax_theme", config, 1)
+            .unwrap();
+
+        assert_eq!(
+            theme.as_style(Meaning::SyntaxFlag).foreground_color,
+            Some(Color::Rgb { r: 255, g: 0, b: 0 })
+        );
+        assert_eq!(
+            theme.as_style(Meaning::SyntaxString).foreground_color,
+            theme.as_style(Meaning::AlertInfo).foreground_color,
         );
     }
 
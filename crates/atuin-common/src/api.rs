@@ -69,6 +69,46 @@ pub struct LoginResponse {
     pub session: String,
 }
 
+/// An encryption key, wrapped client side. The server stores it without being able to read it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyEscrowRequest {
+    pub escrow: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyEscrowResponse {
+    pub escrow: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTeamRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddTeamMemberRequest {
+    pub username: String,
+    #[serde(default)]
+    pub admin: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TeamMemberResponse {
+    pub username: String,
+    pub admin: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TeamResponse {
+    pub name: String,
+    pub members: Vec<TeamMemberResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListTeamsResponse {
+    pub teams: Vec<TeamResponse>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AddHistoryRequest {
     pub id: String,
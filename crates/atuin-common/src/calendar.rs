@@ -1,5 +1,8 @@
 // Calendar data
-use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime, UtcOffset};
 
 pub enum TimePeriod {
     YEAR,
@@ -14,3 +17,31 @@ pub struct TimePeriodInfo {
     // TODO: Use this for merkle tree magic
     pub hash: String,
 }
+
+/// How many of the times fall on each day, in a timezone
+pub fn count_days(
+    times: impl IntoIterator<Item = OffsetDateTime>,
+    timezone: UtcOffset,
+) -> BTreeMap<Date, usize> {
+    let mut days = BTreeMap::new();
+
+    for time in times {
+        *days.entry(time.to_offset(timezone).date()).or_default() += 1;
+    }
+
+    days
+}
+
+/// How many of the times fall in each hour of the day, in a timezone
+pub fn count_hours(
+    times: impl IntoIterator<Item = OffsetDateTime>,
+    timezone: UtcOffset,
+) -> [usize; 24] {
+    let mut hours = [0; 24];
+
+    for time in times {
+        hours[usize::from(time.to_offset(timezone).hour())] += 1;
+    }
+
+    hours
+}
@@ -54,6 +54,8 @@ macro_rules! new_uuid {
 }
 
 pub mod api;
+pub mod calendar;
 pub mod record;
 pub mod shell;
+pub mod syntax;
 pub mod utils;
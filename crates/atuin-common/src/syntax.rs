@@ -0,0 +1,329 @@
+//! A lightweight tokenizer for shell commands, for highlighting and splitting them.
+//!
+//! This is not a shell parser. It knows enough POSIX shell syntax to tell commands from their
+//! arguments, and to find the pipes, redirects and operators between them. It never fails: the
+//! tokens of any input, however malformed, cover all of it.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// The program being run, such as `git` in `git status`
+    Command,
+    /// `NAME=value`, before a command
+    Assignment,
+    Argument,
+    /// An argument starting with `-`
+    Flag,
+    /// A quoted argument
+    String,
+    /// An argument starting with `$`, including command substitutions
+    Variable,
+    /// `|` or `|&`
+    Pipe,
+    /// `>`, `2>&1`, `<<<` and the like
+    Redirect,
+    /// `&&`, `||`, `;`, `&`, parentheses, and keywords such as `if` or `do`
+    Operator,
+    Comment,
+    Whitespace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// Byte offset of the token in the command
+    pub start: usize,
+}
+
+impl Token<'_> {
+    /// Byte offset of the end of the token in the command
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+}
+
+/// Keywords that take the place of a command
+const KEYWORDS: &[&str] = &[
+    "!", "if", "then", "elif", "else", "fi", "do", "done", "while", "until", "time",
+];
+
+/// Characters that end a word, unless quoted
+const METACHARACTERS: &str = "|&;<>()";
+
+pub fn tokenize(command: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    // the next word is a command, rather than an argument
+    let mut at_command = true;
+    // the next word is the target of a redirect
+    let mut at_target = false;
+    let mut start = 0;
+
+    while let Some(c) = command[start..].chars().next() {
+        let s = &command[start..];
+
+        let (kind, len) = if c.is_whitespace() {
+            let len = s.find(|c: char| !c.is_whitespace()).unwrap_or(s.len());
+            (TokenKind::Whitespace, len)
+        } else if c == '#' {
+            (TokenKind::Comment, s.find('\n').unwrap_or(s.len()))
+        } else if let Some(len) = redirect(s) {
+            (TokenKind::Redirect, len)
+        } else if let Some(operator) = operator(s) {
+            operator
+        } else {
+            let len = word(s);
+            let text = &s[..len];
+
+            let kind = if at_target {
+                TokenKind::Argument
+            } else if at_command && KEYWORDS.contains(&text) {
+                TokenKind::Operator
+            } else if at_command && is_assignment(text) {
+                TokenKind::Assignment
+            } else if at_command {
+                TokenKind::Command
+            } else if text.starts_with('-') {
+                TokenKind::Flag
+            } else if text.starts_with(['\'', '"']) || text.starts_with("$'") {
+                TokenKind::String
+            } else if text.starts_with('$') {
+                TokenKind::Variable
+            } else {
+                TokenKind::Argument
+            };
+
+            (kind, len)
+        };
+
+        match kind {
+            TokenKind::Pipe | TokenKind::Operator => at_command = true,
+            TokenKind::Command => at_command = false,
+            _ => {}
+        }
+        match kind {
+            TokenKind::Redirect => at_target = true,
+            TokenKind::Whitespace | TokenKind::Comment => {}
+            _ => at_target = false,
+        }
+
+        tokens.push(Token {
+            kind,
+            text: &s[..len],
+            start,
+        });
+        start += len;
+    }
+
+    tokens
+}
+
+/// Split a command into the commands of its pipeline. Whitespace around the pipes is kept.
+pub fn split_pipeline(command: &str) -> Vec<&str> {
+    let mut commands = Vec::new();
+    let mut start = 0;
+
+    for token in tokenize(command) {
+        if token.kind == TokenKind::Pipe {
+            commands.push(&command[start..token.start]);
+            start = token.end();
+        }
+    }
+    commands.push(&command[start..]);
+
+    commands
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// The length of a redirect at the start of `s`, such as `>`, `2>>`, `&>`, `>&2` or `<<<`
+fn redirect(s: &str) -> Option<usize> {
+    let b = s.as_bytes();
+
+    let mut len = b.iter().take_while(|c| c.is_ascii_digit()).count();
+    if len == 0 && b.first() == Some(&b'&') {
+        len = 1;
+    }
+
+    let arrows = b[len..]
+        .iter()
+        .take_while(|&&c| c == b'>' || c == b'<')
+        .count()
+        .min(3);
+    if arrows == 0 {
+        return None;
+    }
+    len += arrows;
+
+    match b.get(len) {
+        Some(b'&') => {
+            len += 1;
+            len += b[len..]
+                .iter()
+                .take_while(|&&c| c.is_ascii_digit() || c == b'-')
+                .count();
+        }
+        Some(b'|') => len += 1,
+        _ => {}
+    }
+
+    Some(len)
+}
+
+fn operator(s: &str) -> Option<(TokenKind, usize)> {
+    [
+        ("||", TokenKind::Operator),
+        ("|&", TokenKind::Pipe),
+        ("&&", TokenKind::Operator),
+        (";;", TokenKind::Operator),
+        ("|", TokenKind::Pipe),
+        ("&", TokenKind::Operator),
+        (";", TokenKind::Operator),
+        ("(", TokenKind::Operator),
+        (")", TokenKind::Operator),
+    ]
+    .into_iter()
+    .find(|(op, _)| s.starts_with(op))
+    .map(|(op, kind)| (kind, op.len()))
+}
+
+/// The length of the word at the start of `s`, skipping over quotes, escapes and substitutions
+fn word(s: &str) -> usize {
+    let mut chars = s.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\'' => while chars.next().is_some_and(|(_, c)| c != '\'') {},
+            '"' | '`' => {
+                while let Some((_, next)) = chars.next() {
+                    match next {
+                        '\\' => {
+                            chars.next();
+                        }
+                        next if next == c => break,
+                        _ => {}
+                    }
+                }
+            }
+            '$' if matches!(chars.peek(), Some((_, '(' | '{'))) => {
+                let (_, open) = chars.next().unwrap_or_default();
+                let close = if open == '(' { ')' } else { '}' };
+                let mut depth = 1;
+
+                while let Some((_, next)) = chars.next() {
+                    match next {
+                        '\\' => {
+                            chars.next();
+                        }
+                        next if next == open => depth += 1,
+                        next if next == close => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // the first character always belongs to the word, so there's always progress
+            c if i > 0 && (c.is_whitespace() || METACHARACTERS.contains(c)) => return i,
+            _ => {}
+        }
+    }
+
+    s.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TokenKind, split_pipeline, tokenize};
+
+    fn kinds(command: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(command)
+            .into_iter()
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| (t.kind, t.text))
+            .collect()
+    }
+
+    #[test]
+    fn classify() {
+        use TokenKind::*;
+
+        assert_eq!(
+            kinds(r#"FOO=1 git commit -m "fix: a | b" $HOME 2>&1 | less -R # done"#),
+            [
+                (Assignment, "FOO=1"),
+                (Command, "git"),
+                (Argument, "commit"),
+                (Flag, "-m"),
+                (String, r#""fix: a | b""#),
+                (Variable, "$HOME"),
+                (Redirect, "2>&1"),
+                (Pipe, "|"),
+                (Command, "less"),
+                (Flag, "-R"),
+                (Comment, "# done"),
+            ]
+        );
+
+        assert_eq!(
+            kinds("if true; then echo $(date | wc -c)>out; fi"),
+            [
+                (Operator, "if"),
+                (Command, "true"),
+                (Operator, ";"),
+                (Operator, "then"),
+                (Command, "echo"),
+                (Variable, "$(date | wc -c)"),
+                (Redirect, ">"),
+                (Argument, "out"),
+                (Operator, ";"),
+                (Operator, "fi"),
+            ]
+        );
+
+        assert_eq!(
+            kinds("> log cargo build &&make||make clean"),
+            [
+                (Redirect, ">"),
+                (Argument, "log"),
+                (Command, "cargo"),
+                (Argument, "build"),
+                (Operator, "&&"),
+                (Command, "make"),
+                (Operator, "||"),
+                (Command, "make"),
+                (Argument, "clean"),
+            ]
+        );
+    }
+
+    #[test]
+    fn cover_everything() {
+        for command in ["", "  ", "echo 'unterminated", "a\\", "$(", "é | ü", "2>"] {
+            let tokens = tokenize(command);
+            let text: String = tokens.iter().map(|t| t.text).collect();
+            assert_eq!(text, command);
+            assert!(tokens.windows(2).all(|w| w[0].end() == w[1].start));
+        }
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(split_pipeline("fd | rg"), ["fd ", " rg"]);
+        assert_eq!(split_pipeline("make || make clean"), ["make || make clean"]);
+        assert_eq!(
+            split_pipeline("cat $(ls | head -1) |& grep -v '|'"),
+            ["cat $(ls | head -1) ", " grep -v '|'"]
+        );
+    }
+}
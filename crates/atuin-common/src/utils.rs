@@ -61,6 +61,27 @@ pub fn in_git_repo(path: &str) -> Option<PathBuf> {
     None
 }
 
+/// The branch checked out in the git repo containing a path, or the short hash of the commit
+/// checked out if there is no branch
+pub fn git_branch(path: &str) -> Option<String> {
+    let mut gitdir = in_git_repo(path)?.join(".git");
+
+    // in worktrees and submodules, .git is a file pointing to the real git dir
+    if gitdir.is_file() {
+        let pointer = std::fs::read_to_string(&gitdir).ok()?;
+        let target = pointer.strip_prefix("gitdir:")?.trim();
+        gitdir = gitdir.parent()?.join(target);
+    }
+
+    let head = std::fs::read_to_string(gitdir.join("HEAD")).ok()?;
+    let head = head.trim();
+
+    match head.strip_prefix("ref: ") {
+        Some(head) => Some(head.strip_prefix("refs/heads/").unwrap_or(head).to_string()),
+        None => head.get(..7).map(str::to_string),
+    }
+}
+
 // TODO: more reliable, more tested
 // I don't want to use ProjectDirs, it puts config in awkward places on
 // mac. Data too. Seems to be more intended for GUI apps.
@@ -258,6 +279,27 @@ mod tests {
         assert_eq!(uuids.len(), how_many);
     }
 
+    #[test]
+    fn git_branch_of_path() {
+        let repo = tempfile::tempdir().unwrap();
+        let gitdir = repo.path().join(".git");
+        let subdir = repo.path().join("src");
+        std::fs::create_dir_all(&gitdir).unwrap();
+        std::fs::create_dir_all(&subdir).unwrap();
+
+        std::fs::write(gitdir.join("HEAD"), "ref: refs/heads/feature/x\n").unwrap();
+        assert_eq!(
+            git_branch(subdir.to_str().unwrap()).as_deref(),
+            Some("feature/x")
+        );
+
+        std::fs::write(gitdir.join("HEAD"), "0123456789abcdef\n").unwrap();
+        assert_eq!(
+            git_branch(repo.path().to_str().unwrap()).as_deref(),
+            Some("0123456")
+        );
+    }
+
     #[test]
     fn escape_control_characters() {
         use super::Escapable;
@@ -1,10 +1,12 @@
-use eyre::{Context, Result};
+use eyre::{Context, Result, eyre};
 #[cfg(windows)]
 use tokio::net::TcpStream;
 use tonic::transport::{Channel, Endpoint, Uri};
 use tower::service_fn;
 
 use hyper_util::rt::TokioIo;
+use time::OffsetDateTime;
+use tokio_stream::{Stream, StreamExt};
 
 #[cfg(unix)]
 use tokio::net::UnixStream;
@@ -12,9 +14,47 @@ use tokio::net::UnixStream;
 use atuin_client::history::History;
 
 use crate::history::{
-    EndHistoryRequest, StartHistoryRequest, history_client::HistoryClient as HistoryServiceClient,
+    EndHistoryRequest, SearchRequest, StartHistoryRequest, SubscribeRequest,
+    history_client::HistoryClient as HistoryServiceClient, history_event::Kind,
 };
 
+/// A change to the history, as seen by the daemon
+#[derive(Debug, Clone)]
+pub enum HistoryEvent {
+    /// A command started running. It has not been saved yet.
+    Started(History),
+    /// A command finished, and has been saved
+    Ended(History),
+}
+
+impl TryFrom<crate::history::HistoryEvent> for HistoryEvent {
+    type Error = eyre::Report;
+
+    fn try_from(event: crate::history::HistoryEvent) -> Result<Self> {
+        let kind = event.kind();
+        let timestamp = OffsetDateTime::from_unix_timestamp_nanos(i128::from(event.timestamp))
+            .map_err(|_| eyre!("invalid timestamp in history event"))?;
+
+        let h: History = History::from_db()
+            .id(event.id)
+            .timestamp(timestamp)
+            .command(event.command)
+            .cwd(event.cwd)
+            .exit(event.exit)
+            .duration(event.duration)
+            .session(event.session)
+            .hostname(event.hostname)
+            .deleted_at(None)
+            .build()
+            .into();
+
+        Ok(match kind {
+            Kind::Started => HistoryEvent::Started(h),
+            Kind::Ended => HistoryEvent::Ended(h),
+        })
+    }
+}
+
 pub struct HistoryClient {
     client: HistoryServiceClient<Channel>,
 }
@@ -81,17 +121,44 @@ impl HistoryClient {
         Ok(resp.into_inner().id)
     }
 
+    /// End a running command. Returns the id and index of its record, and a notice if the
+    /// command ran unusually.
     pub async fn end_history(
         &mut self,
         id: String,
         duration: u64,
         exit: i64,
-    ) -> Result<(String, u64)> {
+    ) -> Result<(String, u64, Option<String>)> {
         let req = EndHistoryRequest { id, duration, exit };
 
         let resp = self.client.end_history(req).await?;
         let resp = resp.into_inner();
+        let alert = Some(resp.alert).filter(|alert| !alert.is_empty());
+
+        Ok((resp.id, resp.idx, alert))
+    }
+
+    /// Search the saved history, newest first
+    pub async fn search(&mut self, req: SearchRequest) -> Result<Vec<History>> {
+        let resp = self.client.search(req).await?;
+
+        resp.into_inner()
+            .history
+            .into_iter()
+            .map(|event| match HistoryEvent::try_from(event)? {
+                HistoryEvent::Started(h) | HistoryEvent::Ended(h) => Ok(h),
+            })
+            .collect()
+    }
+
+    /// Follow history as commands start and end, until the daemon stops
+    pub async fn subscribe(
+        &mut self,
+    ) -> Result<impl Stream<Item = Result<HistoryEvent>> + Send + Unpin + use<>> {
+        let resp = self.client.subscribe(SubscribeRequest {}).await?;
 
-        Ok((resp.id, resp.idx))
+        Ok(resp
+            .into_inner()
+            .map(|event| HistoryEvent::try_from(event?)))
     }
 }
@@ -1,16 +1,22 @@
 use eyre::WrapErr;
 
-use atuin_client::encryption;
 use atuin_client::history::store::HistoryStore;
+use atuin_client::record::keyring::Keyring;
 use atuin_client::record::sqlite_store::SqliteStore;
-use atuin_client::settings::Settings;
+use atuin_client::settings::{Alerts, FilterMode, SearchMode, Settings};
+use atuin_history::anomaly;
 #[cfg(unix)]
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::{pin::Pin, sync::Arc};
 use time::OffsetDateTime;
+use tokio::sync::broadcast;
+use tokio_stream::{
+    Stream, StreamExt,
+    wrappers::{BroadcastStream, errors::BroadcastStreamRecvError},
+};
 use tracing::{Level, instrument};
 
-use atuin_client::database::{Database, Sqlite as HistoryDatabase};
+use atuin_client::database::{Context, Database, OptFilters, Sqlite as HistoryDatabase};
 use atuin_client::history::{History, HistoryId};
 use dashmap::DashMap;
 use eyre::Result;
@@ -18,8 +24,13 @@ use tonic::{Request, Response, Status, transport::Server};
 
 use crate::history::history_server::{History as HistorySvc, HistoryServer};
 
-use crate::history::{EndHistoryReply, EndHistoryRequest, StartHistoryReply, StartHistoryRequest};
+use crate::history::{
+    EndHistoryReply, EndHistoryRequest, HistoryEvent, SearchReply, SearchRequest,
+    StartHistoryReply, StartHistoryRequest, SubscribeRequest, history_event::Kind,
+};
 
+mod backup;
+mod hooks;
 mod sync;
 
 #[derive(Debug)]
@@ -29,20 +40,79 @@ pub struct HistoryService {
     running: Arc<DashMap<HistoryId, History>>,
     store: HistoryStore,
     history_db: HistoryDatabase,
+    // History events, sent to every subscriber
+    events: broadcast::Sender<HistoryEvent>,
+    alerts: Alerts,
+    hooks: hooks::Runner,
 }
 
+// How many events a slow subscriber can fall behind by, before it starts missing them
+const EVENT_BUFFER: usize = 256;
+
 impl HistoryService {
-    pub fn new(store: HistoryStore, history_db: HistoryDatabase) -> Self {
+    pub fn new(
+        store: HistoryStore,
+        history_db: HistoryDatabase,
+        alerts: Alerts,
+        hooks: hooks::Runner,
+    ) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+
         Self {
             running: Arc::new(DashMap::new()),
             store,
             history_db,
+            events,
+            alerts,
+            hooks,
         }
     }
+
+    /// Check a command that just ended for anything unusual, and run the alert hook if so.
+    /// Returns the notice for the terminal.
+    async fn alert(&self, h: &History) -> Option<String> {
+        let anomaly = match anomaly::detect(&self.history_db, &self.alerts, h).await {
+            Ok(anomaly) => anomaly?,
+            Err(e) => {
+                tracing::warn!("failed to check history for alerts: {e:?}");
+                return None;
+            }
+        };
+
+        let message = anomaly.message(h);
+        tracing::info!(kind = anomaly.kind(), "{message}");
+
+        if let Some(hook) = self.alerts.for_command(&h.command).hook {
+            self.hooks.shell("alert", anomaly.hook(&hook, h));
+        }
+
+        Some(message)
+    }
+
+    fn publish(&self, kind: Kind, h: &History) {
+        // this only fails if there are no subscribers, which is fine
+        let _ = self.events.send(event(kind, h));
+    }
+}
+
+fn event(kind: Kind, h: &History) -> HistoryEvent {
+    HistoryEvent {
+        kind: kind.into(),
+        id: h.id.to_string(),
+        timestamp: h.timestamp.unix_timestamp_nanos() as u64,
+        command: h.command.clone(),
+        cwd: h.cwd.clone(),
+        session: h.session.clone(),
+        hostname: h.hostname.clone(),
+        exit: h.exit,
+        duration: h.duration,
+    }
 }
 
 #[tonic::async_trait()]
 impl HistorySvc for HistoryService {
+    type SubscribeStream = Pin<Box<dyn Stream<Item = Result<HistoryEvent, Status>> + Send>>;
+
     #[instrument(skip_all, level = Level::INFO)]
     async fn start_history(
         &self,
@@ -75,6 +145,7 @@ impl HistorySvc for HistoryService {
         // too. I'd rather keep it pure, unless that ends up being the case.
         let id = h.id.clone();
         tracing::info!(id = id.to_string(), "start history");
+        self.publish(Kind::Started, &h);
         running.insert(id.clone(), h);
 
         let reply = StartHistoryReply { id: id.to_string() };
@@ -114,6 +185,10 @@ impl HistorySvc for HistoryService {
                 "end history"
             );
 
+            self.publish(Kind::Ended, &history);
+
+            let alert = self.alert(&history).await.unwrap_or_default();
+
             let (id, idx) =
                 self.store.push(history).await.map_err(|e| {
                     Status::internal(format!("failed to push record to store: {e:?}"))
@@ -122,6 +197,7 @@ impl HistorySvc for HistoryService {
             let reply = EndHistoryReply {
                 id: id.0.to_string(),
                 idx,
+                alert,
             };
 
             return Ok(Response::new(reply));
@@ -131,6 +207,74 @@ impl HistorySvc for HistoryService {
             "could not find history with id: {id}"
         )))
     }
+
+    #[instrument(skip_all, level = Level::INFO)]
+    async fn subscribe(
+        &self,
+        _request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        tracing::info!("new history subscriber");
+
+        let events =
+            BroadcastStream::new(self.events.subscribe()).filter_map(|event| match event {
+                Ok(event) => Some(Ok(event)),
+                Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                    tracing::warn!(missed, "history subscriber fell behind, dropping events");
+                    None
+                }
+            });
+
+        Ok(Response::new(Box::pin(events)))
+    }
+
+    #[instrument(skip_all, level = Level::INFO)]
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<SearchReply>, Status> {
+        let req = request.into_inner();
+
+        let filter = if req.session.is_some() {
+            FilterMode::Session
+        } else {
+            FilterMode::Global
+        };
+        let context = Context {
+            session: req.session.unwrap_or_default(),
+            cwd: String::new(),
+            hostname: String::new(),
+            host_id: String::new(),
+            git_root: None,
+        };
+        let limit = match req.limit {
+            0 => None,
+            limit => Some(i64::try_from(limit).unwrap_or(i64::MAX)),
+        };
+
+        let history = self
+            .history_db
+            .search(
+                SearchMode::Fuzzy,
+                filter,
+                &context,
+                &req.query,
+                OptFilters {
+                    exit: req.exit,
+                    cwd: req.cwd,
+                    limit,
+                    include_duplicates: req.include_duplicates,
+                    ..OptFilters::default()
+                },
+            )
+            .await
+            .map_err(|e| Status::internal(format!("failed to search history: {e:?}")))?;
+
+        let reply = SearchReply {
+            history: history.iter().map(|h| event(Kind::Ended, h)).collect(),
+        };
+
+        Ok(Response::new(reply))
+    }
 }
 
 #[cfg(unix)]
@@ -250,16 +394,32 @@ pub async fn listen(
     store: SqliteStore,
     history_db: HistoryDatabase,
 ) -> Result<()> {
-    let encryption_key: [u8; 32] = encryption::load_key(&settings)
-        .context("could not load encryption key")?
-        .into();
+    let keyring = Keyring::load(&settings).context("could not load encryption key")?;
 
     let host_id = Settings::host_id().expect("failed to get host_id");
-    let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
+    let history_store = HistoryStore::new(store.clone(), host_id, keyring.clone());
 
-    let history = HistoryService::new(history_store.clone(), history_db.clone());
+    let runner = hooks::Runner::new()?;
+    let history = HistoryService::new(
+        history_store.clone(),
+        history_db.clone(),
+        settings.alerts.clone(),
+        runner.clone(),
+    );
 
     // start services
+    if !settings.hooks.is_empty() {
+        tokio::spawn(hooks::worker(
+            settings.hooks.clone(),
+            runner,
+            history.events.subscribe(),
+        ));
+    }
+
+    if settings.backup.enabled {
+        tokio::spawn(backup::worker(settings.clone(), store.clone()));
+    }
+
     tokio::spawn(sync::worker(
         settings.clone(),
         store,
@@ -269,3 +429,162 @@ pub async fn listen(
 
     start_server(settings, history).await
 }
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::{
+        database::{Database, Sqlite},
+        history::store::HistoryStore,
+        record::sqlite_store::SqliteStore,
+        settings::Alerts,
+    };
+    use atuin_common::{record::HostId, utils::uuid_v7};
+    use tokio_stream::StreamExt;
+    use tonic::Request;
+
+    use super::{HistoryService, hooks};
+    use crate::history::{
+        EndHistoryRequest, SearchRequest, StartHistoryRequest, SubscribeRequest,
+        history_event::Kind, history_server::History as _,
+    };
+
+    #[tokio::test]
+    async fn subscribers_see_history_events() {
+        let store = SqliteStore::new(":memory:", 2.0).await.unwrap();
+        let history_db = Sqlite::new("sqlite::memory:", 2.0).await.unwrap();
+        let history_store = HistoryStore::new(store, HostId(uuid_v7()), [1; 32]);
+        let service = HistoryService::new(
+            history_store,
+            history_db.clone(),
+            Alerts::default(),
+            hooks::Runner::new().unwrap(),
+        );
+
+        let mut events = service
+            .subscribe(Request::new(SubscribeRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let id = service
+            .start_history(Request::new(StartHistoryRequest {
+                timestamp: 1_700_000_000_000_000_000,
+                command: "cargo test".to_string(),
+                cwd: "/src/atuin".to_string(),
+                session: "beep".to_string(),
+                hostname: "laptop:ellie".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .id;
+
+        service
+            .end_history(Request::new(EndHistoryRequest {
+                id: id.clone(),
+                exit: 1,
+                duration: 42,
+            }))
+            .await
+            .unwrap();
+
+        let started = events.next().await.unwrap().unwrap();
+        assert_eq!(started.kind(), Kind::Started);
+        assert_eq!(started.id, id);
+        assert_eq!(started.command, "cargo test");
+
+        let ended = events.next().await.unwrap().unwrap();
+        assert_eq!(ended.kind(), Kind::Ended);
+        assert_eq!(ended.id, id);
+        assert_eq!((ended.exit, ended.duration), (1, 42));
+
+        // by the time a command has ended, it can be found in the database
+        assert!(history_db.load(&id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn search_history() {
+        let store = SqliteStore::new(":memory:", 2.0).await.unwrap();
+        let history_db = Sqlite::new("sqlite::memory:", 2.0).await.unwrap();
+        let history_store = HistoryStore::new(store, HostId(uuid_v7()), [1; 32]);
+        let service = HistoryService::new(
+            history_store,
+            history_db,
+            Alerts::default(),
+            hooks::Runner::new().unwrap(),
+        );
+
+        for (i, (command, session, exit)) in [
+            ("cargo build", "beep", 0),
+            ("cargo test", "beep", 1),
+            ("cargo test", "boop", 0),
+            ("git status", "boop", 0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let id = service
+                .start_history(Request::new(StartHistoryRequest {
+                    timestamp: 1_700_000_000_000_000_000 + i as u64,
+                    command: command.to_string(),
+                    cwd: "/src/atuin".to_string(),
+                    session: session.to_string(),
+                    hostname: "laptop:ellie".to_string(),
+                }))
+                .await
+                .unwrap()
+                .into_inner()
+                .id;
+
+            service
+                .end_history(Request::new(EndHistoryRequest {
+                    id,
+                    exit,
+                    duration: 1,
+                }))
+                .await
+                .unwrap();
+        }
+
+        let search = |req: SearchRequest| async {
+            let reply = service.search(Request::new(req)).await.unwrap();
+            reply
+                .into_inner()
+                .history
+                .into_iter()
+                .map(|h| (h.command, h.session))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            search(SearchRequest {
+                query: "cargo".to_string(),
+                ..SearchRequest::default()
+            })
+            .await,
+            [
+                ("cargo test".to_string(), "boop".to_string()),
+                ("cargo build".to_string(), "beep".to_string()),
+            ]
+        );
+        assert_eq!(
+            search(SearchRequest {
+                session: Some("beep".to_string()),
+                exit: Some(1),
+                ..SearchRequest::default()
+            })
+            .await,
+            [("cargo test".to_string(), "beep".to_string())]
+        );
+        assert_eq!(
+            search(SearchRequest {
+                limit: 1,
+                include_duplicates: true,
+                ..SearchRequest::default()
+            })
+            .await
+            .len(),
+            1
+        );
+    }
+}
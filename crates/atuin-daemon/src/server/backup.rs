@@ -0,0 +1,28 @@
+use eyre::Result;
+use tokio::time::{self, MissedTickBehavior};
+
+use atuin_client::{
+    record::{backup, sqlite_store::SqliteStore},
+    settings::Settings,
+};
+
+pub async fn worker(settings: Settings, store: SqliteStore) -> Result<()> {
+    tracing::info!("booting backup worker");
+
+    let host_id = Settings::host_id().expect("failed to get host_id");
+
+    let mut ticker = time::interval(time::Duration::from_secs(settings.backup.frequency));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+        tracing::info!("backup worker tick");
+
+        match backup::write_scheduled(&store, host_id, &settings.backup.dir, settings.backup.keep)
+            .await
+        {
+            Ok(path) => tracing::info!(path = ?path, "backup complete"),
+            Err(e) => tracing::error!("backup failed with {e}"),
+        }
+    }
+}
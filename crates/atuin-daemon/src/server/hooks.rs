@@ -0,0 +1,248 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use eyre::{Result, bail};
+use serde_json::json;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::{Semaphore, broadcast};
+
+use atuin_client::{
+    history::History,
+    hooks,
+    settings::{Hook, HookOn},
+};
+
+use crate::{client, history::HistoryEvent};
+
+/// How long a webhook has to respond
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many hooks can run at once. Any more are skipped, rather than piling up behind a hook
+/// that hangs.
+const MAX_RUNNING: usize = 16;
+
+/// Runs hooks in the background, a limited number at a time
+#[derive(Debug, Clone)]
+pub struct Runner {
+    http: reqwest::Client,
+    running: Arc<Semaphore>,
+}
+
+impl Runner {
+    pub fn new() -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            http,
+            running: Arc::new(Semaphore::new(MAX_RUNNING)),
+        })
+    }
+
+    /// Run a shell command in the background, killing it if it takes too long
+    pub fn shell(&self, name: &str, command: String) {
+        self.spawn(name, shell(command));
+    }
+
+    fn spawn(&self, name: &str, hook: impl Future<Output = Result<()>> + Send + 'static) {
+        let Ok(permit) = self.running.clone().try_acquire_owned() else {
+            tracing::warn!(hook = name, "too many hooks running, skipping");
+            return;
+        };
+
+        let name = name.to_string();
+        tokio::spawn(async move {
+            match hook.await {
+                Ok(()) => tracing::debug!(hook = name, "hook ran"),
+                Err(e) => tracing::warn!(hook = name, "hook failed: {e:?}"),
+            }
+            drop(permit);
+        });
+    }
+}
+
+/// Run the hooks for each command that ends, until the daemon stops
+pub async fn worker(
+    hooks: Vec<Hook>,
+    runner: Runner,
+    mut events: broadcast::Receiver<HistoryEvent>,
+) -> Result<()> {
+    tracing::info!(hooks = hooks.len(), "booting hooks worker");
+
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                tracing::warn!(missed, "hooks fell behind, skipping commands");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        };
+
+        let h = match client::HistoryEvent::try_from(event) {
+            Ok(client::HistoryEvent::Ended(h)) => h,
+            Ok(client::HistoryEvent::Started(_)) => continue,
+            Err(e) => {
+                tracing::warn!("invalid history event: {e:?}");
+                continue;
+            }
+        };
+
+        for hook in hooks.iter().filter(|hook| matches(hook, &h)) {
+            runner.spawn(
+                &hook.name,
+                run(hook.clone(), h.clone(), runner.http.clone()),
+            );
+        }
+    }
+}
+
+/// Whether a command that ended passes all of the hook's filters
+fn matches(hook: &Hook, h: &History) -> bool {
+    let on = match hook.on {
+        HookOn::Any => true,
+        HookOn::Success => h.exit == 0,
+        HookOn::Failure => h.failed(),
+    };
+
+    let min_duration = hook.min_duration.is_none_or(|min| {
+        i64::try_from(min)
+            .ok()
+            .and_then(|min| min.checked_mul(1_000_000_000))
+            .is_some_and(|min| h.duration >= min)
+    });
+
+    on && min_duration
+        && (hook.exit.is_empty() || hook.exit.contains(&h.exit))
+        && hook
+            .pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(&h.command))
+}
+
+async fn run(hook: Hook, h: History, http: reqwest::Client) -> Result<()> {
+    if let Some(command) = &hook.command {
+        shell(hooks::render(command, &h, &[], hooks::quote)).await?;
+    }
+
+    if let Some(url) = &hook.url {
+        let url = hooks::render(url, &h, &[], urlencoding::encode);
+
+        let body = json!({
+            "id": h.id.0,
+            "timestamp": h.timestamp.format(&Rfc3339)?,
+            "command": h.command,
+            "cwd": h.cwd,
+            "exit": h.exit,
+            "duration": h.duration,
+            "hostname": h.hostname,
+            "session": h.session,
+        });
+
+        http.post(url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+    }
+
+    Ok(())
+}
+
+async fn shell(command: String) -> Result<()> {
+    // dropping the command on timeout kills it
+    let status = hooks::shell(&command).kill_on_drop(true).status();
+
+    let Ok(status) = tokio::time::timeout(hooks::TIMEOUT, status).await else {
+        bail!("command timed out after {}s", hooks::TIMEOUT.as_secs());
+    };
+
+    let status = status?;
+    if !status.success() {
+        bail!("command exited with {status}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::{
+        history::History,
+        hooks::render,
+        settings::{Hook, HookOn},
+    };
+    use regex::Regex;
+    use time::macros::datetime;
+
+    use super::{matches, shell};
+
+    fn history(command: &str, exit: i64, seconds: i64) -> History {
+        History::import()
+            .timestamp(datetime!(2025-01-06 09:00 UTC))
+            .command(command)
+            .cwd("/home/ellie/my app")
+            .exit(exit)
+            .duration(seconds * 1_000_000_000)
+            .build()
+            .into()
+    }
+
+    fn hook() -> Hook {
+        Hook {
+            name: "test".to_string(),
+            command: None,
+            url: None,
+            pattern: None,
+            on: HookOn::Any,
+            exit: Vec::new(),
+            min_duration: None,
+        }
+    }
+
+    #[test]
+    fn filters() {
+        assert!(matches(&hook(), &history("ls", 0, 0)));
+
+        let failures = Hook {
+            on: HookOn::Failure,
+            ..hook()
+        };
+        assert!(matches(&failures, &history("make", 2, 1)));
+        assert!(!matches(&failures, &history("make", 0, 1)));
+        // interrupted, not failed
+        assert!(!matches(&failures, &history("make", 130, 1)));
+
+        let deploys = Hook {
+            pattern: Some(Regex::new("^kubectl apply").unwrap()),
+            exit: vec![0],
+            min_duration: Some(60),
+            ..hook()
+        };
+        assert!(matches(&deploys, &history("kubectl apply -f x", 0, 90)));
+        assert!(!matches(&deploys, &history("kubectl apply -f x", 0, 30)));
+        assert!(!matches(&deploys, &history("kubectl apply -f x", 1, 90)));
+        assert!(!matches(&deploys, &history("kubectl get pods", 0, 90)));
+    }
+
+    #[tokio::test]
+    async fn shell_hooks() {
+        assert!(shell("true".to_string()).await.is_ok());
+        assert!(shell("exit 3".to_string()).await.is_err());
+    }
+
+    #[test]
+    fn urls() {
+        let h = history("make 'my target' {cwd}", 1, 3);
+
+        assert_eq!(
+            render(
+                "http://localhost/?text={command}",
+                &h,
+                &[],
+                urlencoding::encode
+            ),
+            "http://localhost/?text=make%20%27my%20target%27%20%7Bcwd%7D"
+        );
+    }
+}
@@ -4,13 +4,15 @@ use tokio::time::{self, MissedTickBehavior};
 
 use atuin_client::database::Sqlite as HistoryDatabase;
 use atuin_client::{
-    encryption,
     history::store::HistoryStore,
-    record::{sqlite_store::SqliteStore, sync},
+    record::{keyring, sqlite_store::SqliteStore, sync},
     settings::Settings,
+    team::{self, Team},
 };
 
 use atuin_dotfiles::store::{AliasStore, var::VarStore};
+use atuin_kv::store::KvStore;
+use atuin_scripts::store::ScriptStore;
 
 pub async fn worker(
     settings: Settings,
@@ -20,10 +22,11 @@ pub async fn worker(
 ) -> Result<()> {
     tracing::info!("booting sync worker");
 
-    let encryption_key: [u8; 32] = encryption::load_key(&settings)?.into();
+    // Share the history store's keyring, so that rotations learned here are used everywhere
+    let keyring = history_store.keyring.clone();
     let host_id = Settings::host_id().expect("failed to get host_id");
-    let alias_store = AliasStore::new(store.clone(), host_id, encryption_key);
-    let var_store = VarStore::new(store.clone(), host_id, encryption_key);
+    let alias_store = AliasStore::new(store.clone(), host_id, keyring.clone());
+    let var_store = VarStore::new(store.clone(), host_id, keyring.clone());
 
     // Don't backoff by more than 30 mins (with a random jitter of up to 1 min)
     let max_interval: f64 = 60.0 * 30.0 + rand::thread_rng().gen_range(0.0..60.0);
@@ -69,6 +72,16 @@ pub async fn worker(
                 "sync complete"
             );
 
+            if keyring::apply(&store, &keyring).await? {
+                tracing::info!("learned a key rotation from sync");
+                keyring.save(&settings)?;
+            }
+
+            let migrated = keyring::migrate(&store, &keyring).await?;
+            if migrated > 0 {
+                tracing::info!(migrated, "re-encrypted records with the current key");
+            }
+
             history_store
                 .incremental_build(&history_db, &downloaded)
                 .await?;
@@ -76,6 +89,16 @@ pub async fn worker(
             alias_store.build().await?;
             var_store.build().await?;
 
+            // a team that fails to sync shouldn't hold up the user's own store
+            for (team, result) in team::sync_all(&settings, build_team).await? {
+                match result {
+                    Ok((uploaded, downloaded)) => {
+                        tracing::info!(team, uploaded, downloaded, "team sync complete");
+                    }
+                    Err(e) => tracing::error!(team, "team sync failed with {e:?}"),
+                }
+            }
+
             // Reset backoff on success
             if ticker.period().as_secs() != settings.daemon.sync_frequency {
                 ticker = time::interval(time::Duration::from_secs(settings.daemon.sync_frequency));
@@ -86,3 +109,13 @@ pub async fn worker(
         }
     }
 }
+
+/// Rebuild a team's kv and script databases from its record store
+async fn build_team(team: Team, store: SqliteStore) -> Result<()> {
+    let host_id = Settings::host_id().expect("failed to get host_id");
+
+    KvStore::build_team(&team, store.clone(), host_id).await?;
+    ScriptStore::build_team(&team, store, host_id).await?;
+
+    Ok(())
+}
@@ -9,7 +9,7 @@ use atuin_common::record::{DecryptedData, Host, HostId};
 use atuin_common::utils::unquote;
 use eyre::{Result, bail, ensure, eyre};
 
-use atuin_client::record::encryption::PASETO_V4;
+use atuin_client::record::keyring::Keyring;
 use atuin_client::record::store::Store;
 
 use crate::shell::Alias;
@@ -127,16 +127,16 @@ impl AliasRecord {
 pub struct AliasStore {
     pub store: SqliteStore,
     pub host_id: HostId,
-    pub encryption_key: [u8; 32],
+    pub keyring: Keyring,
 }
 
 impl AliasStore {
     // will want to init the actual kv store when that is done
-    pub fn new(store: SqliteStore, host_id: HostId, encryption_key: [u8; 32]) -> AliasStore {
+    pub fn new(store: SqliteStore, host_id: HostId, keyring: impl Into<Keyring>) -> AliasStore {
         AliasStore {
             store,
             host_id,
-            encryption_key,
+            keyring: keyring.into(),
         }
     }
 
@@ -247,9 +247,7 @@ impl AliasStore {
             .data(bytes)
             .build();
 
-        self.store
-            .push(&record.encrypt::<PASETO_V4>(&self.encryption_key))
-            .await?;
+        self.store.push(&self.keyring.encrypt(record)).await?;
 
         // set mutates shell config, so build again
         self.build().await?;
@@ -283,9 +281,7 @@ impl AliasStore {
             .data(bytes)
             .build();
 
-        self.store
-            .push(&record.encrypt::<PASETO_V4>(&self.encryption_key))
-            .await?;
+        self.store.push(&self.keyring.encrypt(record)).await?;
 
         // delete mutates shell config, so build again
         self.build().await?;
@@ -303,7 +299,7 @@ impl AliasStore {
             let version = record.version.clone();
 
             let decrypted = match version.as_str() {
-                CONFIG_SHELL_ALIAS_VERSION => record.decrypt::<PASETO_V4>(&self.encryption_key)?,
+                CONFIG_SHELL_ALIAS_VERSION => self.keyring.decrypt(record)?,
                 version => bail!("unknown version {version:?}"),
             };
 
@@ -8,7 +8,7 @@ use atuin_client::record::sqlite_store::SqliteStore;
 use atuin_common::record::{DecryptedData, Host, HostId};
 use eyre::{Result, bail, ensure, eyre};
 
-use atuin_client::record::encryption::PASETO_V4;
+use atuin_client::record::keyring::Keyring;
 use atuin_client::record::store::Store;
 
 use crate::shell::Var;
@@ -102,16 +102,16 @@ impl VarRecord {
 pub struct VarStore {
     pub store: SqliteStore,
     pub host_id: HostId,
-    pub encryption_key: [u8; 32],
+    pub keyring: Keyring,
 }
 
 impl VarStore {
     // will want to init the actual kv store when that is done
-    pub fn new(store: SqliteStore, host_id: HostId, encryption_key: [u8; 32]) -> VarStore {
+    pub fn new(store: SqliteStore, host_id: HostId, keyring: impl Into<Keyring>) -> VarStore {
         VarStore {
             store,
             host_id,
-            encryption_key,
+            keyring: keyring.into(),
         }
     }
 
@@ -294,9 +294,7 @@ impl VarStore {
             .data(bytes)
             .build();
 
-        self.store
-            .push(&record.encrypt::<PASETO_V4>(&self.encryption_key))
-            .await?;
+        self.store.push(&self.keyring.encrypt(record)).await?;
 
         // set mutates shell config, so build again
         self.build().await?;
@@ -330,9 +328,7 @@ impl VarStore {
             .data(bytes)
             .build();
 
-        self.store
-            .push(&record.encrypt::<PASETO_V4>(&self.encryption_key))
-            .await?;
+        self.store.push(&self.keyring.encrypt(record)).await?;
 
         // delete mutates shell config, so build again
         self.build().await?;
@@ -350,7 +346,7 @@ impl VarStore {
             let version = record.version.clone();
 
             let decrypted = match version.as_str() {
-                DOTFILES_VAR_VERSION => record.decrypt::<PASETO_V4>(&self.encryption_key)?,
+                DOTFILES_VAR_VERSION => self.keyring.decrypt(record)?,
                 version => bail!("unknown version {version:?}"),
             };
 
@@ -0,0 +1,308 @@
+//! Alerts for commands that ran unusually: much slower than they usually take, or failing when
+//! they usually succeed. Each command is compared against its own previous runs, with the
+//! settings in `[alerts]`.
+
+use atuin_client::{database::Database, history::History, hooks, settings::Alerts};
+use eyre::Result;
+
+/// How many previous runs of a command it's compared against
+pub const RUNS: usize = 100;
+
+/// The share of its previous runs that must have succeeded for a failure to be unusual
+const USUALLY_SUCCEEDS: f64 = 0.9;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    /// The command took `factor` times as long as its median duration, `usual`
+    Slow { usual: i64, factor: f64 },
+    /// The command failed, when `successes` of its previous `runs` succeeded
+    Failed { successes: usize, runs: usize },
+}
+
+impl Anomaly {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Slow { .. } => "slow",
+            Self::Failed { .. } => "failed",
+        }
+    }
+
+    /// A one-line notice of the anomaly, for the terminal
+    pub fn message(&self, h: &History) -> String {
+        let command = short_command(&h.command);
+
+        match self {
+            Self::Slow { usual, factor } => format!(
+                "atuin: `{command}` took {}, {factor:.1}x longer than usual ({})",
+                format_duration(h.duration),
+                format_duration(*usual),
+            ),
+            Self::Failed { successes, runs } => format!(
+                "atuin: `{command}` failed with exit {}, when {successes} of its last {runs} runs succeeded",
+                h.exit,
+            ),
+        }
+    }
+
+    /// The alert hook's shell command for the anomaly. Besides the placeholders of any hook,
+    /// `{message}`, `{kind}` and `{usual_duration}` are replaced by the details of the alert.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn hook(&self, hook: &str, h: &History) -> String {
+        let usual = match self {
+            Self::Slow { usual, .. } => *usual,
+            Self::Failed { .. } => 0,
+        };
+        let usual = format!("{:.1}", usual as f64 / 1_000_000_000.0);
+        let message = self.message(h);
+
+        hooks::render(
+            hook,
+            h,
+            &[
+                ("message", &message),
+                ("kind", self.kind()),
+                ("usual_duration", &usual),
+            ],
+            hooks::quote,
+        )
+    }
+}
+
+/// Compare a command that just ended against its previous runs, newest first
+#[allow(clippy::cast_precision_loss)]
+pub fn check(alerts: &Alerts, h: &History, previous: &[History]) -> Option<Anomaly> {
+    let alerts = alerts.for_command(&h.command);
+    if !alerts.enabled {
+        return None;
+    }
+
+    // runs that never finished have no duration or exit to compare
+    let previous: Vec<&History> = previous.iter().filter(|p| p.duration >= 0).collect();
+    if previous.is_empty() || previous.len() < alerts.min_runs {
+        return None;
+    }
+
+    if alerts.failures && h.failed() {
+        let successes = previous.iter().filter(|p| p.exit == 0).count();
+
+        if successes as f64 >= previous.len() as f64 * USUALLY_SUCCEEDS {
+            return Some(Anomaly::Failed {
+                successes,
+                runs: previous.len(),
+            });
+        }
+    }
+
+    let min_duration = i64::try_from(alerts.min_duration)
+        .unwrap_or(i64::MAX)
+        .saturating_mul(1_000_000_000);
+    if h.duration < min_duration {
+        return None;
+    }
+
+    let mut durations: Vec<i64> = previous.iter().map(|p| p.duration).collect();
+    durations.sort_unstable();
+    let usual = durations[durations.len() / 2];
+
+    if usual == 0 {
+        return None;
+    }
+
+    let factor = h.duration as f64 / usual as f64;
+    (factor >= alerts.slowdown).then_some(Anomaly::Slow { usual, factor })
+}
+
+/// Check a command that just ended against its previous runs in the database
+pub async fn detect(db: &impl Database, alerts: &Alerts, h: &History) -> Result<Option<Anomaly>> {
+    if !alerts.enabled && !alerts.rules.iter().any(|r| r.enabled == Some(true)) {
+        return Ok(None);
+    }
+
+    let previous = db.runs(&h.command, h.timestamp, RUNS).await?;
+
+    Ok(check(alerts, h, &previous))
+}
+
+/// The first line of a command, shortened to fit in a notice
+fn short_command(command: &str) -> String {
+    const MAX: usize = 60;
+
+    let line = command.trim().lines().next().unwrap_or_default();
+    if line.chars().count() > MAX || line.len() < command.trim().len() {
+        let short: String = line.chars().take(MAX).collect();
+        format!("{}…", short.trim_end())
+    } else {
+        line.to_string()
+    }
+}
+
+fn format_duration(nanos: i64) -> String {
+    let millis = nanos / 1_000_000;
+    let seconds = millis / 1000;
+
+    if seconds < 1 {
+        format!("{millis}ms")
+    } else if seconds < 60 {
+        format!("{}.{}s", seconds, millis % 1000 / 100)
+    } else if seconds < 60 * 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}h {}m", seconds / 3600, seconds % 3600 / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::{
+        history::History,
+        settings::{AlertRule, Alerts},
+    };
+    use regex::Regex;
+    use time::macros::datetime;
+
+    use super::{Anomaly, check};
+
+    const SECOND: i64 = 1_000_000_000;
+
+    fn run(command: &str, exit: i64, seconds: i64) -> History {
+        History::import()
+            .timestamp(datetime!(2025-01-06 09:00 UTC))
+            .command(command)
+            .exit(exit)
+            .duration(seconds * SECOND)
+            .build()
+            .into()
+    }
+
+    fn alerts() -> Alerts {
+        Alerts {
+            enabled: true,
+            ..Alerts::default()
+        }
+    }
+
+    #[test]
+    fn slow_commands() {
+        let previous: Vec<History> = [10, 12, 11, 9, 10, 30]
+            .into_iter()
+            .map(|seconds| run("cargo build", 0, seconds))
+            .collect();
+
+        assert_eq!(
+            check(&alerts(), &run("cargo build", 0, 35), &previous),
+            Some(Anomaly::Slow {
+                usual: 11 * SECOND,
+                factor: 35.0 / 11.0
+            })
+        );
+        assert_eq!(
+            check(&alerts(), &run("cargo build", 0, 25), &previous),
+            None
+        );
+
+        // not enough history to tell what's usual
+        assert_eq!(
+            check(&alerts(), &run("cargo build", 0, 35), &previous[..4]),
+            None
+        );
+
+        // too quick to matter, however much slower it was
+        let previous: Vec<History> = (0..5)
+            .map(|_| {
+                let mut ls = run("ls", 0, 0);
+                ls.duration = SECOND / 100;
+                ls
+            })
+            .collect();
+        let mut ls = run("ls", 0, 0);
+        ls.duration = SECOND / 2;
+        assert_eq!(check(&alerts(), &ls, &previous), None);
+
+        // but with no minimum, it's 50 times slower
+        let alerts = Alerts {
+            min_duration: 0,
+            ..alerts()
+        };
+        assert!(matches!(
+            check(&alerts, &ls, &previous),
+            Some(Anomaly::Slow { usual, .. }) if usual == SECOND / 100
+        ));
+    }
+
+    #[test]
+    fn failing_commands() {
+        let mut previous: Vec<History> = (0..9).map(|_| run("make", 0, 1)).collect();
+        previous.push(run("make", 2, 1));
+
+        assert_eq!(
+            check(&alerts(), &run("make", 2, 1), &previous),
+            Some(Anomaly::Failed {
+                successes: 9,
+                runs: 10
+            })
+        );
+        // interrupted, not failed
+        assert_eq!(check(&alerts(), &run("make", 130, 1), &previous), None);
+
+        previous.push(run("make", 2, 1));
+        assert_eq!(check(&alerts(), &run("make", 2, 1), &previous), None);
+    }
+
+    #[test]
+    fn rules() {
+        let mut alerts = alerts();
+        alerts.rules = vec![AlertRule {
+            pattern: Regex::new("^grep ").unwrap(),
+            enabled: None,
+            slowdown: None,
+            min_duration: None,
+            min_runs: None,
+            failures: Some(false),
+            hook: None,
+        }];
+
+        let previous: Vec<History> = (0..5).map(|_| run("grep x y", 0, 1)).collect();
+        assert_eq!(check(&alerts, &run("grep x y", 1, 1), &previous), None);
+
+        alerts.enabled = false;
+        alerts.rules[0].enabled = Some(true);
+        alerts.rules[0].failures = None;
+        assert!(check(&alerts, &run("grep x y", 1, 1), &previous).is_some());
+
+        // a rule can ask for more history before its commands are checked
+        alerts.rules[0].min_runs = Some(10);
+        assert_eq!(check(&alerts, &run("grep x y", 1, 1), &previous), None);
+    }
+
+    #[test]
+    fn messages() {
+        let mut h = run("cargo build --release", 0, 95);
+        h.duration += SECOND / 2;
+
+        assert_eq!(
+            Anomaly::Slow {
+                usual: 31 * SECOND,
+                factor: 3.08
+            }
+            .message(&h),
+            "atuin: `cargo build --release` took 1m 35s, 3.1x longer than usual (31.0s)"
+        );
+        assert_eq!(
+            Anomaly::Failed {
+                successes: 9,
+                runs: 10
+            }
+            .message(&run("make\nmake install", 2, 1)),
+            "atuin: `make…` failed with exit 2, when 9 of its last 10 runs succeeded"
+        );
+
+        assert_eq!(
+            Anomaly::Slow {
+                usual: 31 * SECOND,
+                factor: 3.08
+            }
+            .hook("notify {kind} {usual_duration} {message}", &h),
+            "notify slow 31.0 'atuin: `cargo build --release` took 1m 35s, 3.1x longer than usual (31.0s)'"
+        );
+    }
+}
@@ -0,0 +1,407 @@
+//! Stats broken down by where, when and how commands ran: by directory, host, hour of the day,
+//! day of the week, or by command with how often it failed.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use time::UtcOffset;
+
+use atuin_client::{history::History, settings::Settings};
+use atuin_common::utils::in_git_repo;
+
+use crate::stats::{Period, csv_field, interesting_command, strip_leading_env_vars};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum By {
+    /// The git repo commands ran in, or the directory outside of one
+    Dir,
+    /// The host commands ran on
+    Host,
+    /// The hour of the day commands ran at
+    Hour,
+    /// The day of the week commands ran on
+    Weekday,
+    /// The command itself, to compare how often commands fail
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Group {
+    pub key: String,
+    /// How many commands ran in the group
+    pub commands: usize,
+    /// How many of them failed
+    pub failures: usize,
+    /// Total time spent running them, in nanoseconds. Commands without a known duration don't
+    /// count towards it.
+    pub duration: i64,
+    /// The most used commands in the group, with how often they were used
+    pub top: Vec<(String, usize)>,
+}
+
+impl Group {
+    /// The percentage of commands that failed
+    #[allow(clippy::cast_precision_loss)]
+    pub fn failure_rate(&self) -> f64 {
+        if self.commands == 0 {
+            0.0
+        } else {
+            self.failures as f64 * 100.0 / self.commands as f64
+        }
+    }
+}
+
+/// Group the history, with the `count` most used commands of each group. Groups by time are
+/// ordered by time, the others by how many commands they hold.
+pub fn compute(
+    settings: &Settings,
+    history: &[History],
+    by: By,
+    count: usize,
+    timezone: UtcOffset,
+) -> Vec<Group> {
+    // looking for the git repo of a directory hits the file system, so only do it once each
+    let mut roots = HashMap::<&str, String>::new();
+    let mut groups = HashMap::<String, (Group, HashMap<&str, usize>)>::new();
+
+    for h in history {
+        let command = strip_leading_env_vars(h.command.trim());
+        let command = interesting_command(settings, command);
+
+        if settings.stats.ignored_commands.iter().any(|c| c == command) {
+            continue;
+        }
+
+        let timestamp = h.timestamp.to_offset(timezone);
+        let key = match by {
+            By::Dir => roots
+                .entry(h.cwd.as_str())
+                .or_insert_with(|| {
+                    in_git_repo(&h.cwd)
+                        .map_or_else(|| h.cwd.clone(), |root| root.to_string_lossy().to_string())
+                })
+                .clone(),
+            By::Host => h.hostname.split(':').next().unwrap_or_default().to_string(),
+            By::Hour => format!("{:02}", timestamp.hour()),
+            By::Weekday => timestamp.weekday().to_string(),
+            By::Exit => command.to_string(),
+        };
+
+        let (group, commands) = groups.entry(key.clone()).or_insert_with(|| {
+            (
+                Group {
+                    key,
+                    commands: 0,
+                    failures: 0,
+                    duration: 0,
+                    top: Vec::new(),
+                },
+                HashMap::new(),
+            )
+        });
+
+        group.commands += 1;
+        if h.failed() {
+            group.failures += 1;
+        }
+        group.duration += h.duration.max(0);
+        *commands.entry(command).or_default() += 1;
+    }
+
+    let mut groups: Vec<Group> = groups
+        .into_values()
+        .map(|(mut group, commands)| {
+            if by != By::Exit {
+                let mut top: Vec<_> = commands.into_iter().collect();
+                top.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+                top.truncate(count);
+
+                group.top = top.into_iter().map(|(c, n)| (c.to_string(), n)).collect();
+            }
+
+            group
+        })
+        .collect();
+
+    match by {
+        By::Hour => groups.sort_unstable_by(|a, b| a.key.cmp(&b.key)),
+        By::Weekday => groups.sort_unstable_by_key(|g| weekday_index(&g.key)),
+        By::Dir | By::Host | By::Exit => {
+            groups.sort_unstable_by(|a, b| b.commands.cmp(&a.commands).then(a.key.cmp(&b.key)));
+            groups.truncate(count);
+        }
+    }
+
+    groups
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    period: &'a Period,
+    by: By,
+    groups: &'a [Group],
+}
+
+/// The groups and their period as a JSON object
+pub fn to_json(groups: &[Group], by: By, period: &Period) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&Report { period, by, groups })
+}
+
+/// The groups as CSV, a row each, with the period on every row. The top commands of a group are
+/// written as `command (count)`, separated by `; `.
+pub fn to_csv(groups: &[Group], by: By, period: &Period) -> String {
+    let mut csv = format!(
+        "{},by,key,commands,failures,failure_rate,duration,top\n",
+        Period::CSV_HEADER
+    );
+
+    let by = serde_json::to_value(by)
+        .ok()
+        .and_then(|by| by.as_str().map(String::from))
+        .unwrap_or_default();
+
+    for group in groups {
+        let top = group
+            .top
+            .iter()
+            .map(|(command, count)| format!("{command} ({count})"))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        csv.push_str(&format!(
+            "{},{by},{},{},{},{:.1},{},{}\n",
+            period.csv_fields(),
+            csv_field(&group.key),
+            group.commands,
+            group.failures,
+            group.failure_rate(),
+            group.duration,
+            csv_field(&top),
+        ));
+    }
+
+    csv
+}
+
+/// Days of the week from Monday, as `time` names them
+fn weekday_index(day: &str) -> usize {
+    [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ]
+    .iter()
+    .position(|d| *d == day)
+    .unwrap_or(7)
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::{history::History, settings::Settings};
+    use time::{UtcOffset, macros::datetime};
+
+    use crate::stats::Period;
+
+    use super::{By, Group, compute, to_csv, to_json};
+
+    fn history() -> Vec<History> {
+        [
+            (
+                "cargo build --release",
+                0,
+                5,
+                "a:ellie",
+                "/code/atuin",
+                datetime!(2025-01-06 09:15 UTC),
+            ),
+            (
+                "cargo build",
+                101,
+                3,
+                "a:ellie",
+                "/code/atuin",
+                datetime!(2025-01-06 09:30 UTC),
+            ),
+            (
+                "FOO=1 cargo test",
+                0,
+                10,
+                "b:ellie",
+                "/code/atuin",
+                datetime!(2025-01-07 14:00 UTC),
+            ),
+            (
+                "ls",
+                0,
+                -1,
+                "b:ellie",
+                "/tmp",
+                datetime!(2025-01-07 09:00 UTC),
+            ),
+            (
+                "cd /",
+                0,
+                1,
+                "a:ellie",
+                "/tmp",
+                datetime!(2025-01-08 23:00 UTC),
+            ),
+        ]
+        .into_iter()
+        .map(|(command, exit, duration, hostname, cwd, timestamp)| {
+            History::import()
+                .timestamp(timestamp)
+                .command(command)
+                .exit(exit)
+                .duration(duration)
+                .hostname(hostname)
+                .cwd(cwd)
+                .build()
+                .into()
+        })
+        .collect()
+    }
+
+    #[test]
+    fn by_exit() {
+        let mut settings = Settings::utc();
+        settings.stats.ignored_commands.push("cd".to_string());
+
+        let groups = compute(&settings, &history(), By::Exit, 10, UtcOffset::UTC);
+        let groups: Vec<_> = groups
+            .iter()
+            .map(|g| (g.key.as_str(), g.commands, g.failures, g.duration))
+            .collect();
+
+        assert_eq!(
+            groups,
+            [
+                ("cargo build", 2, 1, 8),
+                ("cargo test", 1, 0, 10),
+                ("ls", 1, 0, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn by_host() {
+        let groups = compute(&Settings::utc(), &history(), By::Host, 1, UtcOffset::UTC);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].key, "a");
+        assert_eq!(groups[0].commands, 3);
+        assert_eq!(groups[0].top, [("cargo build".to_string(), 2)]);
+        assert!((groups[0].failure_rate() - 100.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn interrupted_isnt_failed() {
+        let mut history = history();
+        for h in &mut history {
+            h.exit = 130;
+        }
+
+        let groups = compute(&Settings::utc(), &history, By::Host, 1, UtcOffset::UTC);
+        assert_eq!(groups[0].failures, 0);
+    }
+
+    #[test]
+    fn by_time() {
+        let hours: Vec<_> = compute(&Settings::utc(), &history(), By::Hour, 10, UtcOffset::UTC)
+            .into_iter()
+            .map(|g| (g.key, g.commands))
+            .collect();
+        assert_eq!(
+            hours,
+            [
+                ("09".to_string(), 3),
+                ("14".to_string(), 1),
+                ("23".to_string(), 1)
+            ]
+        );
+
+        // in another timezone, the last command ran the next day
+        let offset = UtcOffset::from_hms(2, 0, 0).unwrap();
+        let days: Vec<_> = compute(&Settings::utc(), &history(), By::Weekday, 10, offset)
+            .into_iter()
+            .map(|g| (g.key, g.commands))
+            .collect();
+        assert_eq!(
+            days,
+            [
+                ("Monday".to_string(), 2),
+                ("Tuesday".to_string(), 2),
+                ("Thursday".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn by_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+
+        let mut history = history();
+        history[0].cwd = repo.to_string_lossy().to_string();
+        history[1].cwd = repo.join("src").to_string_lossy().to_string();
+
+        let groups = compute(&Settings::utc(), &history, By::Dir, 10, UtcOffset::UTC);
+        let groups: Vec<_> = groups.iter().map(|g| (g.key.clone(), g.commands)).collect();
+
+        assert_eq!(
+            groups,
+            [
+                ("/tmp".to_string(), 2),
+                (repo.to_string_lossy().to_string(), 2),
+                ("/code/atuin".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn json_schema() {
+        let groups = [Group {
+            key: "/code/atuin".to_string(),
+            commands: 4,
+            failures: 1,
+            duration: 1_500_000_000,
+            top: vec![("cargo build".to_string(), 3), ("ls".to_string(), 1)],
+        }];
+        let period = Period {
+            name: "all".to_string(),
+            start: None,
+            end: None,
+        };
+
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json(&groups, By::Dir, &period).unwrap()).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "period": { "name": "all", "start": null, "end": null },
+                "by": "dir",
+                "groups": [{
+                    "key": "/code/atuin",
+                    "commands": 4,
+                    "failures": 1,
+                    "duration": 1_500_000_000,
+                    "top": [["cargo build", 3], ["ls", 1]],
+                }],
+            })
+        );
+
+        assert_eq!(
+            to_csv(&groups, By::Dir, &period),
+            "period,start,end,by,key,commands,failures,failure_rate,duration,top\n\
+             all,,,dir,/code/atuin,4,1,25.0,1500000000,cargo build (3); ls (1)\n"
+        );
+    }
+}
@@ -0,0 +1,416 @@
+//! A calendar of how many commands ran each day, drawn as a heatmap like GitHub's contribution
+//! graph: a year of weeks, a month of days, or the hours of a day.
+
+use std::{collections::BTreeMap, fmt::Write, str::FromStr};
+
+use crossterm::style::{Color, ResetColor, SetForegroundColor};
+use time::{Date, Duration, Month, OffsetDateTime, Time, UtcOffset, Weekday};
+
+use atuin_client::{
+    history::History,
+    theme::{Meaning, Theme},
+};
+use atuin_common::calendar;
+
+/// The glyphs of the heatmap levels, from no commands to the busiest
+const LEVELS: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// The part of the calendar to draw, written as `YYYY`, `YYYY-MM` or `YYYY-MM-DD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Year(i32),
+    Month(i32, Month),
+    Day(Date),
+}
+
+impl FromStr for Focus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || format!("'{s}' is not a year, month or day, like 2025, 2025-03 or 2025-03-14");
+        let parts: Vec<&str> = s.trim().split('-').collect();
+
+        let year = parts[0].parse::<i32>().map_err(|_| invalid())?;
+        // the day after the focus must be a date too, to end its range
+        if !(Date::MIN.year()..Date::MAX.year()).contains(&year) {
+            return Err(format!(
+                "'{s}' is out of range, years up to {} are supported",
+                Date::MAX.year() - 1
+            ));
+        }
+        let month = |m: &str| {
+            m.parse::<u8>()
+                .ok()
+                .and_then(|m| Month::try_from(m).ok())
+                .ok_or_else(invalid)
+        };
+
+        match parts[..] {
+            [_] => Ok(Self::Year(year)),
+            [_, m] => Ok(Self::Month(year, month(m)?)),
+            [_, m, d] => {
+                let day = d.parse::<u8>().map_err(|_| invalid())?;
+                Date::from_calendar_date(year, month(m)?, day)
+                    .map(Self::Day)
+                    .map_err(|_| invalid())
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Focus {
+    /// The first day of the focus, and the first day after it
+    pub fn days(self) -> (Date, Date) {
+        let first_of = |year, month| {
+            Date::from_calendar_date(year, month, 1).expect("the first of a month is a valid date")
+        };
+
+        match self {
+            Self::Year(year) => (
+                first_of(year, Month::January),
+                first_of(year + 1, Month::January),
+            ),
+            Self::Month(year, month) => {
+                let next = if month == Month::December {
+                    year + 1
+                } else {
+                    year
+                };
+                (first_of(year, month), first_of(next, month.next()))
+            }
+            Self::Day(day) => (day, day + Duration::DAY),
+        }
+    }
+
+    /// The times the focus starts and ends at, in a timezone
+    pub fn range(self, timezone: UtcOffset) -> (OffsetDateTime, OffsetDateTime) {
+        let (start, end) = self.days();
+
+        (
+            start.with_time(Time::MIDNIGHT).assume_offset(timezone),
+            end.with_time(Time::MIDNIGHT).assume_offset(timezone),
+        )
+    }
+}
+
+/// How many commands ran each day, in a timezone
+pub fn days(history: &[History], timezone: UtcOffset) -> BTreeMap<Date, usize> {
+    calendar::count_days(history.iter().map(|h| h.timestamp), timezone)
+}
+
+/// How many commands ran in each hour of the day, in a timezone
+pub fn hours(history: &[History], timezone: UtcOffset) -> [usize; 24] {
+    calendar::count_hours(history.iter().map(|h| h.timestamp), timezone)
+}
+
+/// The heatmap level of a count, from 0 for nothing to 4 for the busiest
+pub fn level(count: usize, max: usize) -> usize {
+    if count == 0 || max == 0 {
+        0
+    } else {
+        (4 * count).div_ceil(max).clamp(1, 4)
+    }
+}
+
+/// The weeks of the days from `start` until `end`, Monday first. Days outside of the range are
+/// left empty, so the first and last weeks can be partial.
+pub fn weeks(start: Date, end: Date) -> Vec<[Option<Date>; 7]> {
+    let mut weeks = Vec::new();
+    let mut week = [None; 7];
+    let mut day = start;
+
+    while day < end {
+        let weekday = usize::from(day.weekday().number_days_from_monday());
+        week[weekday] = Some(day);
+
+        if day.weekday() == Weekday::Sunday {
+            weeks.push(week);
+            week = [None; 7];
+        }
+
+        day += Duration::DAY;
+    }
+
+    if week.iter().any(Option::is_some) {
+        weeks.push(week);
+    }
+
+    weeks
+}
+
+struct Colours {
+    heat: SetForegroundColor,
+    muted: SetForegroundColor,
+}
+
+impl Colours {
+    fn new(theme: &Theme) -> Self {
+        let colour = |meaning, default| {
+            SetForegroundColor(theme.as_style(meaning).foreground_color.unwrap_or(default))
+        };
+
+        Self {
+            heat: colour(Meaning::AlertInfo, Color::Green),
+            muted: colour(Meaning::Muted, Color::Grey),
+        }
+    }
+
+    fn cell(&self, level: usize) -> String {
+        let colour = if level == 0 { self.muted } else { self.heat };
+        format!("{colour}{}{ResetColor}", LEVELS[level])
+    }
+
+    fn legend(&self) -> String {
+        let cells: Vec<String> = (0..LEVELS.len()).map(|l| self.cell(l)).collect();
+        format!(
+            "{}Less{ResetColor} {} {}More{ResetColor}",
+            self.muted,
+            cells.join(" "),
+            self.muted
+        )
+    }
+}
+
+/// A year of days as a heatmap, a column for each week and a row for each day of the week
+pub fn draw_year(year: i32, days: &BTreeMap<Date, usize>, theme: &Theme) -> String {
+    let colours = Colours::new(theme);
+    let (start, end) = Focus::Year(year).days();
+    let weeks = weeks(start, end);
+    let max = days.values().copied().max().unwrap_or(0);
+
+    // month names go over the week their first day is in
+    let mut months = String::new();
+    for (i, week) in weeks.iter().enumerate() {
+        if let Some(day) = week.iter().flatten().find(|d| d.day() == 1) {
+            let name = day.month().to_string();
+            months.push_str(&" ".repeat(i.saturating_sub(months.len())));
+            months.push_str(&name[..3]);
+        }
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{}{year}{ResetColor}", colours.muted);
+    let _ = writeln!(out, "    {}", months.trim_end());
+
+    for (weekday, name) in WEEKDAYS.iter().enumerate() {
+        let label = if weekday % 2 == 0 { *name } else { "" };
+        let _ = write!(out, "{}{label:3}{ResetColor} ", colours.muted);
+
+        for week in &weeks {
+            match week[weekday] {
+                Some(day) => {
+                    let count = days.get(&day).copied().unwrap_or(0);
+                    out.push_str(&colours.cell(level(count, max)));
+                }
+                None => out.push(' '),
+            }
+        }
+
+        out.push('\n');
+    }
+
+    let total: usize = days.values().sum();
+    let _ = writeln!(out, "\n    {}", colours.legend());
+    let _ = write!(out, "\n{total} commands on {} days", days.len());
+    if let Some((day, count)) = days
+        .iter()
+        .max_by_key(|(day, count)| (**count, std::cmp::Reverse(**day)))
+    {
+        let _ = write!(out, ", most on {day} ({count})");
+    }
+    out.push('\n');
+
+    out
+}
+
+/// A month of days, a row for each week, with the day of the month over how many commands ran
+pub fn draw_month(year: i32, month: Month, days: &BTreeMap<Date, usize>, theme: &Theme) -> String {
+    let colours = Colours::new(theme);
+    let (start, end) = Focus::Month(year, month).days();
+    let max = days.values().copied().max().unwrap_or(0);
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{}{month} {year}{ResetColor}", colours.muted);
+    for name in WEEKDAYS {
+        let _ = write!(out, "{name:>7}");
+    }
+    out.push('\n');
+
+    for week in weeks(start, end) {
+        for day in week {
+            match day {
+                Some(day) => {
+                    let _ = write!(out, "{}{:>7}{ResetColor}", colours.muted, day.day());
+                }
+                None => out.push_str(&" ".repeat(7)),
+            }
+        }
+        out.push('\n');
+
+        for day in week {
+            match day {
+                Some(day) => {
+                    let count = days.get(&day).copied().unwrap_or(0);
+                    let _ = write!(out, "{:>5} {}", count, colours.cell(level(count, max)));
+                }
+                None => out.push_str(&" ".repeat(7)),
+            }
+        }
+        out.push('\n');
+    }
+
+    let total: usize = days.values().sum();
+    let _ = writeln!(out, "\n{total} commands on {} days", days.len());
+
+    out
+}
+
+/// A day as a bar for each hour, as long as how many commands ran in it
+pub fn draw_day(day: Date, hours: &[usize; 24], theme: &Theme) -> String {
+    let colours = Colours::new(theme);
+    let max = hours.iter().copied().max().unwrap_or(0);
+    let width = max.max(1).ilog10() as usize + 1;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{}{day} ({}){ResetColor}",
+        colours.muted,
+        day.weekday()
+    );
+
+    for (hour, count) in hours.iter().enumerate() {
+        let bar = if max == 0 {
+            0
+        } else {
+            (40 * count).div_ceil(max)
+        };
+        let _ = writeln!(
+            out,
+            "{}{hour:02}:00{ResetColor} {count:>width$} {}{}{ResetColor}",
+            colours.muted,
+            colours.heat,
+            LEVELS[level(*count, max)].to_string().repeat(bar),
+        );
+    }
+
+    let total: usize = hours.iter().sum();
+    let _ = writeln!(out, "\n{total} commands");
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::history::History;
+    use time::{Month, UtcOffset, macros::date, macros::datetime};
+
+    use super::{Focus, days, hours, level, weeks};
+
+    #[test]
+    fn parse_focus() {
+        assert_eq!("2025".parse(), Ok(Focus::Year(2025)));
+        assert_eq!("2025-03".parse(), Ok(Focus::Month(2025, Month::March)));
+        assert_eq!("2025-3-14".parse(), Ok(Focus::Day(date!(2025 - 03 - 14))));
+
+        assert!("2025-13".parse::<Focus>().is_err());
+        assert!("2025-02-30".parse::<Focus>().is_err());
+        assert!("march".parse::<Focus>().is_err());
+        assert!("2025-03-14-01".parse::<Focus>().is_err());
+        assert!("10000".parse::<Focus>().is_err());
+        assert!("9999-12".parse::<Focus>().is_err());
+        assert_eq!("9998-12-31".parse(), Ok(Focus::Day(date!(9998 - 12 - 31))));
+    }
+
+    #[test]
+    fn focus_days() {
+        assert_eq!(
+            Focus::Year(2024).days(),
+            (date!(2024 - 01 - 01), date!(2025 - 01 - 01))
+        );
+        assert_eq!(
+            Focus::Month(2024, Month::December).days(),
+            (date!(2024 - 12 - 01), date!(2025 - 01 - 01))
+        );
+        assert_eq!(
+            Focus::Day(date!(2024 - 02 - 29)).days(),
+            (date!(2024 - 02 - 29), date!(2024 - 03 - 01))
+        );
+
+        let offset = UtcOffset::from_hms(2, 0, 0).unwrap();
+        assert_eq!(
+            Focus::Month(2024, Month::March).range(offset),
+            (
+                datetime!(2024-03-01 00:00 +2),
+                datetime!(2024-04-01 00:00 +2)
+            )
+        );
+    }
+
+    #[test]
+    fn count_days_and_hours() {
+        let history: Vec<History> = [
+            datetime!(2025-03-14 09:15 UTC),
+            datetime!(2025-03-14 09:45 UTC),
+            datetime!(2025-03-14 23:30 UTC),
+        ]
+        .into_iter()
+        .map(|timestamp| {
+            History::import()
+                .timestamp(timestamp)
+                .command("ls")
+                .build()
+                .into()
+        })
+        .collect();
+
+        let offset = UtcOffset::from_hms(1, 0, 0).unwrap();
+        let days: Vec<_> = days(&history, offset).into_iter().collect();
+        assert_eq!(
+            days,
+            [(date!(2025 - 03 - 14), 2), (date!(2025 - 03 - 15), 1)]
+        );
+
+        let hours = hours(&history, offset);
+        assert_eq!(hours[10], 2);
+        assert_eq!(hours[0], 1);
+        assert_eq!(hours.iter().sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn levels() {
+        assert_eq!(level(0, 0), 0);
+        assert_eq!(level(0, 100), 0);
+        assert_eq!(level(1, 100), 1);
+        assert_eq!(level(25, 100), 1);
+        assert_eq!(level(26, 100), 2);
+        assert_eq!(level(75, 100), 3);
+        assert_eq!(level(100, 100), 4);
+    }
+
+    #[test]
+    fn partial_weeks() {
+        // March 2025 starts on a Saturday and ends on a Monday
+        let weeks = weeks(date!(2025 - 03 - 01), date!(2025 - 04 - 01));
+
+        assert_eq!(weeks.len(), 6);
+        assert_eq!(
+            weeks[0],
+            [
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(date!(2025 - 03 - 01)),
+                Some(date!(2025 - 03 - 02))
+            ]
+        );
+        assert_eq!(weeks[5][0], Some(date!(2025 - 03 - 31)));
+        assert_eq!(weeks[5][1], None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Comparing two periods of history: how many more commands ran, and which tools were picked up
+//! or dropped between them.
+
+use std::collections::HashMap;
+
+use atuin_client::{history::History, settings::Settings};
+
+use crate::stats::strip_leading_env_vars;
+
+/// How many times a tool must have been used in a period to count as adopted or dropped, so a
+/// one-off typo isn't a new tool
+pub const MIN_USES: usize = 3;
+
+/// The tool a command runs: its first word, without leading environment variables, prefixes like
+/// `sudo`, or the directory it's in
+pub fn tool<'a>(settings: &Settings, command: &'a str) -> Option<&'a str> {
+    let mut words = strip_leading_env_vars(command.trim()).split_whitespace();
+    let word = words.find(|w| !settings.stats.common_prefix.iter().any(|p| p == w))?;
+
+    word.rsplit('/').next().filter(|tool| !tool.is_empty())
+}
+
+/// How many times each tool was used, leaving out the ignored commands
+pub fn tools(settings: &Settings, history: &[History]) -> HashMap<String, usize> {
+    let mut tools = HashMap::new();
+
+    for h in history {
+        if let Some(tool) = tool(settings, &h.command)
+            && !settings.stats.ignored_commands.iter().any(|c| c == tool)
+        {
+            *tools.entry(tool.to_string()).or_default() += 1;
+        }
+    }
+
+    tools
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub commands: usize,
+    pub previous_commands: usize,
+    /// Tools used in the period but not in the previous one, most used first
+    pub new_tools: Vec<(String, usize)>,
+    /// Tools used in the previous period but not in this one, most used first
+    pub dropped_tools: Vec<(String, usize)>,
+}
+
+impl Comparison {
+    /// How much more history there is than in the previous period, as a percentage. There is no
+    /// growth from no history at all.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn growth(&self) -> Option<f64> {
+        if self.previous_commands == 0 {
+            None
+        } else {
+            Some(
+                (self.commands as f64 - self.previous_commands as f64) * 100.0
+                    / self.previous_commands as f64,
+            )
+        }
+    }
+}
+
+/// Compare a period of history to the one before it, with at most `count` new and dropped tools
+pub fn compare(
+    settings: &Settings,
+    history: &[History],
+    previous: &[History],
+    count: usize,
+) -> Comparison {
+    let current_tools = tools(settings, history);
+    let previous_tools = tools(settings, previous);
+
+    let only_in = |a: &HashMap<String, usize>, b: &HashMap<String, usize>| {
+        let mut only: Vec<(String, usize)> = a
+            .iter()
+            .filter(|(tool, uses)| **uses >= MIN_USES && !b.contains_key(*tool))
+            .map(|(tool, uses)| (tool.clone(), *uses))
+            .collect();
+        only.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        only.truncate(count);
+        only
+    };
+
+    // without any history before, everything would be new
+    let (new_tools, dropped_tools) = if previous.is_empty() {
+        (Vec::new(), Vec::new())
+    } else {
+        (
+            only_in(&current_tools, &previous_tools),
+            only_in(&previous_tools, &current_tools),
+        )
+    };
+
+    Comparison {
+        commands: history.len(),
+        previous_commands: previous.len(),
+        new_tools,
+        dropped_tools,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::{history::History, settings::Settings};
+    use time::macros::datetime;
+
+    use super::{compare, tool};
+
+    fn history(commands: &[(&str, usize)]) -> Vec<History> {
+        commands
+            .iter()
+            .flat_map(|(command, times)| std::iter::repeat_n(*command, *times))
+            .map(|command| {
+                History::import()
+                    .timestamp(datetime!(2025-01-06 09:00 UTC))
+                    .command(command)
+                    .build()
+                    .into()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tools() {
+        let settings = Settings::utc();
+
+        assert_eq!(tool(&settings, "git commit -m 'x'"), Some("git"));
+        assert_eq!(tool(&settings, "  RUST_LOG=debug cargo run"), Some("cargo"));
+        assert_eq!(tool(&settings, "sudo /usr/bin/apt install jq"), Some("apt"));
+        assert_eq!(tool(&settings, "./configure"), Some("configure"));
+        assert_eq!(tool(&settings, "sudo"), None);
+        assert_eq!(tool(&settings, ""), None);
+    }
+
+    #[test]
+    fn new_and_dropped_tools() {
+        let mut settings = Settings::utc();
+        settings.stats.ignored_commands = vec!["cd".to_string()];
+
+        let previous = history(&[("git status", 5), ("make", 4), ("vim", 1), ("cd ..", 9)]);
+        let current = history(&[
+            ("git status", 3),
+            ("jj log", 6),
+            ("cargo build", 3),
+            ("nvim", 2),
+        ]);
+
+        let comparison = compare(&settings, &current, &previous, 5);
+
+        assert_eq!(comparison.commands, 14);
+        assert_eq!(comparison.previous_commands, 19);
+        // nvim wasn't used enough to count, nor was vim to be missed
+        assert_eq!(
+            comparison.new_tools,
+            [("jj".to_string(), 6), ("cargo".to_string(), 3)]
+        );
+        assert_eq!(comparison.dropped_tools, [("make".to_string(), 4)]);
+
+        let growth = comparison.growth().unwrap();
+        assert!((growth - (14.0 - 19.0) * 100.0 / 19.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn no_previous_history() {
+        let comparison = compare(&Settings::utc(), &history(&[("ls", 3)]), &[], 5);
+
+        assert_eq!(comparison.growth(), None);
+        assert!(comparison.new_tools.is_empty());
+        assert!(comparison.dropped_tools.is_empty());
+    }
+}
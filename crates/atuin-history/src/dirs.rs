@@ -0,0 +1,131 @@
+//! Ranking of the directories in history by frecency, for jumping between them.
+//!
+//! A directory scores the number of commands run in it, weighted by how recently the last of
+//! them ran, much like z and zoxide do.
+
+use atuin_client::database::DirectoryVisits;
+use time::{Duration, OffsetDateTime};
+
+/// The frecency of a directory: its visits, boosted when it was visited recently
+#[allow(clippy::cast_precision_loss)]
+pub fn frecency(dir: &DirectoryVisits, now: OffsetDateTime) -> f64 {
+    let age = now - dir.last_visit;
+
+    let weight = if age < Duration::HOUR {
+        4.0
+    } else if age < Duration::DAY {
+        2.0
+    } else if age < Duration::WEEK {
+        0.5
+    } else {
+        0.25
+    };
+
+    dir.visits as f64 * weight
+}
+
+/// Whether a path matches the keywords, ignoring case. The keywords must appear in the path in
+/// order, and the last of them in its last component, so `foo bar` matches `/foo/bar` but not
+/// `/bar/foo` or `/foo/bar/baz`.
+pub fn matches(path: &str, keywords: &[String]) -> bool {
+    let path = path.to_lowercase();
+    let mut rest = path.as_str();
+
+    for keyword in keywords {
+        let keyword = keyword.to_lowercase();
+
+        match rest.find(&keyword) {
+            Some(i) => rest = &rest[i + keyword.len()..],
+            None => return false,
+        }
+    }
+
+    match keywords.last() {
+        Some(last) => {
+            let name = path.trim_end_matches(['/', '\\']);
+            let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+            name.contains(&last.to_lowercase())
+        }
+        None => true,
+    }
+}
+
+/// The directories matching the keywords, best first
+pub fn rank<'a>(
+    dirs: &'a [DirectoryVisits],
+    keywords: &[String],
+    now: OffsetDateTime,
+) -> Vec<(f64, &'a DirectoryVisits)> {
+    let mut ranked: Vec<_> = dirs
+        .iter()
+        .filter(|dir| matches(&dir.path, keywords))
+        .map(|dir| (frecency(dir, now), dir))
+        .collect();
+
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.path.cmp(&b.1.path)));
+
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::database::DirectoryVisits;
+    use time::{Duration, OffsetDateTime};
+
+    use super::{matches, rank};
+
+    fn keywords(keywords: &[&str]) -> Vec<String> {
+        keywords.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn match_keywords() {
+        assert!(matches("/home/ellie/code/atuin", &keywords(&[])));
+        assert!(matches("/home/ellie/code/atuin", &keywords(&["atu"])));
+        assert!(matches(
+            "/home/ellie/code/atuin",
+            &keywords(&["Code", "at"])
+        ));
+        assert!(matches("/home/ellie/code/atuin/", &keywords(&["atuin"])));
+
+        // the keywords must be in order
+        assert!(!matches(
+            "/home/ellie/code/atuin",
+            &keywords(&["atuin", "code"])
+        ));
+        // and the last one must be in the last component
+        assert!(!matches("/home/ellie/code/atuin", &keywords(&["code"])));
+        assert!(matches(r"C:\Users\ellie\code", &keywords(&["code"])));
+    }
+
+    #[test]
+    fn rank_by_frecency() {
+        let now = OffsetDateTime::now_utc();
+        let dir = |path: &str, visits, age| DirectoryVisits {
+            path: path.to_string(),
+            visits,
+            last_visit: now - age,
+        };
+
+        let dirs = [
+            dir("/code/old", 100, Duration::days(30)),
+            dir("/code/recent", 10, Duration::minutes(5)),
+            dir("/code/today", 30, Duration::hours(3)),
+            dir("/tmp", 1000, Duration::minutes(1)),
+        ];
+
+        let ranked: Vec<_> = rank(&dirs, &keywords(&["code", ""]), now)
+            .into_iter()
+            .map(|(score, dir)| (score, dir.path.as_str()))
+            .collect();
+
+        assert_eq!(
+            ranked,
+            [
+                (60.0, "/code/today"),
+                (40.0, "/code/recent"),
+                (25.0, "/code/old"),
+            ]
+        );
+    }
+}
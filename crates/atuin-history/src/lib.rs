@@ -1,2 +1,10 @@
+pub mod anomaly;
+pub mod breakdown;
+pub mod calendar;
+pub mod compare;
+pub mod dirs;
+pub mod normalize;
+pub mod report;
+pub mod sequences;
 pub mod sort;
 pub mod stats;
@@ -0,0 +1,294 @@
+//! Normalising commands, so those that only differ in their arguments count as the same command.
+//!
+//! Arguments that look like URLs, commit hashes, numbers, paths or git branches are replaced
+//! with placeholders, so `git checkout feature-a` and `git checkout feature-b` both become
+//! `git checkout <branch>`. The rules in `stats.normalize_rules` run first, for anything else.
+
+use std::collections::{HashMap, HashSet};
+
+use atuin_client::{history::History, settings::Settings};
+use atuin_common::syntax::{Token, TokenKind, tokenize};
+
+/// Git subcommands that take branches as arguments
+const GIT_BRANCH_COMMANDS: &[&str] = &["branch", "checkout", "merge", "rebase", "switch"];
+
+/// Git subcommands that take a remote and then branches as arguments
+const GIT_REMOTE_COMMANDS: &[&str] = &["fetch", "pull", "push"];
+
+/// The command, with the user's rules applied and its arguments replaced by placeholders
+pub fn normalize(settings: &Settings, command: &str) -> String {
+    let mut command = command.trim().to_string();
+    for rule in &settings.stats.normalize_rules {
+        command = rule
+            .pattern
+            .replace_all(&command, rule.replacement.as_str())
+            .into_owned();
+    }
+
+    let mut normalized = String::with_capacity(command.len());
+
+    for (token, placeholder) in placeholders(&command) {
+        match token.kind {
+            TokenKind::Flag => match token.text.split_once('=') {
+                Some((flag, value)) => {
+                    normalized.push_str(flag);
+                    normalized.push('=');
+                    normalized.push_str(placeholder.unwrap_or(value));
+                }
+                None => normalized.push_str(token.text),
+            },
+            TokenKind::Whitespace if !token.text.contains('\n') => normalized.push(' '),
+            _ => normalized.push_str(placeholder.unwrap_or(token.text)),
+        }
+    }
+
+    normalized
+}
+
+/// The tokens of a command, with the placeholders for those that are arguments which vary
+/// between runs. The placeholder of a `--flag=value` is for its value.
+pub(crate) fn placeholders(command: &str) -> Vec<(Token<'_>, Option<&'static str>)> {
+    // the program being run, and its arguments so far
+    let mut program = "";
+    let mut arguments = Vec::<&str>::new();
+    let mut after_dashes = false;
+
+    tokenize(command)
+        .into_iter()
+        .map(|token| {
+            let placeholder = match token.kind {
+                TokenKind::Command => {
+                    program = token.text;
+                    arguments.clear();
+                    after_dashes = false;
+                    None
+                }
+                TokenKind::Argument | TokenKind::String => {
+                    let value = unquote(token.text);
+                    arguments.push(value);
+
+                    if !after_dashes && is_branch(program, &arguments[..arguments.len() - 1]) {
+                        Some("<branch>")
+                    } else {
+                        placeholder(value)
+                    }
+                }
+                TokenKind::Flag => match token.text.split_once('=') {
+                    Some((_, value)) => placeholder(unquote(value)),
+                    None => {
+                        after_dashes |= token.text == "--";
+                        None
+                    }
+                },
+                TokenKind::Pipe | TokenKind::Operator => {
+                    program = "";
+                    None
+                }
+                _ => None,
+            };
+
+            (token, placeholder)
+        })
+        .collect()
+}
+
+/// The placeholder for an argument, if it's one that varies between runs of a command
+fn placeholder(value: &str) -> Option<&'static str> {
+    // already a placeholder, from the user's rules
+    if value.starts_with('<') && value.ends_with('>') {
+        None
+    } else if value.contains("://") || value.starts_with("git@") {
+        Some("<url>")
+    } else if is_number(value) {
+        Some("<num>")
+    } else if is_hash(value) {
+        Some("<hash>")
+    } else if value.starts_with(['/', '~']) || value.starts_with("./") || value.contains('/') {
+        Some("<path>")
+    } else {
+        None
+    }
+}
+
+/// Whether the next argument of a program is a git branch, given the arguments before it
+fn is_branch(program: &str, arguments: &[&str]) -> bool {
+    if program != "git" {
+        return false;
+    }
+
+    match arguments.first() {
+        Some(subcommand) if GIT_BRANCH_COMMANDS.contains(subcommand) => true,
+        // the first argument is the remote
+        Some(subcommand) if GIT_REMOTE_COMMANDS.contains(subcommand) => arguments.len() >= 2,
+        _ => false,
+    }
+}
+
+/// Numbers, and dotted numbers such as versions or IP addresses
+fn is_number(value: &str) -> bool {
+    value.starts_with(|c: char| c.is_ascii_digit())
+        && value.ends_with(|c: char| c.is_ascii_digit())
+        && value.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// Hex strings as long as commit hashes and digests, with both digits and letters
+fn is_hash(value: &str) -> bool {
+    (7..=64).contains(&value.len())
+        && value.chars().all(|c| c.is_ascii_hexdigit())
+        && value.chars().any(|c| c.is_ascii_digit())
+        && value.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+pub(crate) fn unquote(text: &str) -> &str {
+    for quote in ['\'', '"'] {
+        if let Some(inner) = text
+            .strip_prefix(quote)
+            .and_then(|text| text.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+
+    text
+}
+
+/// The history beyond the newest `keep` entries of each command, once normalised, run in the
+/// same directory on the same host. Like `Database::get_dups`, but for normalised commands.
+pub fn duplicates<'a>(
+    settings: &Settings,
+    history: &'a [History],
+    keep: usize,
+) -> Vec<&'a History> {
+    let mut history: Vec<&History> = history.iter().collect();
+    history.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+    let mut seen = HashMap::<(String, &str, &str), usize>::new();
+
+    history
+        .into_iter()
+        .filter(|h| {
+            let key = (
+                normalize(settings, &h.command),
+                h.cwd.as_str(),
+                h.hostname.as_str(),
+            );
+            let count = seen.entry(key).or_default();
+            *count += 1;
+
+            *count > keep
+        })
+        .collect()
+}
+
+/// Drop the commands that normalise the same as one before them, for listings that are unique
+/// by command. The first of each is kept, so the newest or best match stays.
+pub fn unique(settings: &Settings, history: &mut Vec<History>) {
+    let mut seen = HashSet::new();
+    history.retain(|h| seen.insert(normalize(settings, &h.command)));
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::{
+        history::History,
+        settings::{NormalizeRule, Settings},
+    };
+    use regex::Regex;
+    use time::macros::datetime;
+
+    use super::{duplicates, normalize, unique};
+
+    #[test]
+    fn placeholders() {
+        let settings = Settings::utc();
+        let cases = [
+            ("git checkout feature-a", "git checkout <branch>"),
+            ("git checkout -b fix/login", "git checkout -b <branch>"),
+            ("git checkout -- src/main.rs", "git checkout -- <path>"),
+            ("git push origin main", "git push origin <branch>"),
+            ("git show 3f2a9c1", "git show <hash>"),
+            ("kill -9 12345", "kill -9 <num>"),
+            ("ssh 10.0.0.1", "ssh <num>"),
+            (
+                "curl -fsSL https://atuin.sh/install.sh | sh",
+                "curl -fsSL <url> | sh",
+            ),
+            ("cat ~/.bashrc   /etc/hosts", "cat <path> <path>"),
+            ("vim './notes/a b.md'", "vim <path>"),
+            ("cargo build --jobs=8", "cargo build --jobs=<num>"),
+            ("ls && cd ..", "ls && cd .."),
+            ("cargo test", "cargo test"),
+            // a branch of something other than git is just an argument
+            ("hg checkout feature-a", "hg checkout feature-a"),
+        ];
+
+        for (command, normalized) in cases {
+            assert_eq!(normalize(&settings, command), normalized, "{command}");
+        }
+    }
+
+    #[test]
+    fn user_rules() {
+        let mut settings = Settings::utc();
+        settings.stats.normalize_rules = vec![NormalizeRule {
+            pattern: Regex::new(r"JIRA-\d+").unwrap(),
+            replacement: "<ticket>".to_string(),
+        }];
+
+        assert_eq!(
+            normalize(&settings, "git commit -m 'JIRA-123 fix'"),
+            "git commit -m '<ticket> fix'"
+        );
+        assert_eq!(normalize(&settings, "open JIRA-42"), "open <ticket>");
+    }
+
+    #[test]
+    fn normalized_duplicates() {
+        let settings = Settings::utc();
+        let history: Vec<History> = [
+            ("git checkout a", "/code", datetime!(2025-01-06 09:00 UTC)),
+            ("git checkout b", "/code", datetime!(2025-01-06 10:00 UTC)),
+            ("git checkout c", "/code", datetime!(2025-01-06 11:00 UTC)),
+            ("git checkout d", "/other", datetime!(2025-01-06 12:00 UTC)),
+            ("git status", "/code", datetime!(2025-01-06 13:00 UTC)),
+        ]
+        .into_iter()
+        .map(|(command, cwd, timestamp)| {
+            History::import()
+                .timestamp(timestamp)
+                .command(command)
+                .cwd(cwd)
+                .build()
+                .into()
+        })
+        .collect();
+
+        let dups: Vec<&str> = duplicates(&settings, &history, 1)
+            .into_iter()
+            .map(|h| h.command.as_str())
+            .collect();
+
+        // the newest checkout in /code is kept
+        assert_eq!(dups, ["git checkout b", "git checkout a"]);
+    }
+
+    #[test]
+    fn unique_results() {
+        let settings = Settings::utc();
+        let mut history: Vec<History> = ["git checkout b", "git status", "git checkout a"]
+            .into_iter()
+            .map(|command| {
+                History::import()
+                    .timestamp(datetime!(2025-01-06 09:00 UTC))
+                    .command(command)
+                    .build()
+                    .into()
+            })
+            .collect();
+
+        unique(&settings, &mut history);
+
+        let commands: Vec<&str> = history.iter().map(|h| h.command.as_str()).collect();
+        assert_eq!(commands, ["git checkout b", "git status"]);
+    }
+}
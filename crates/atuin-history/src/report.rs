@@ -0,0 +1,346 @@
+//! Reports of the time spent on each project, per day or week.
+//!
+//! A command counts towards the project it ran in: the configured project whose directories
+//! contain its cwd, or else the git repo it ran in. Besides the time spent running commands, the
+//! gap until the next command in the same session counts as time spent on the project too, as
+//! long as it's short enough not to be time away.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use time::{Date, Duration, UtcOffset};
+
+use atuin_client::{history::History, settings::Report};
+use atuin_common::utils::in_git_repo;
+
+use crate::stats::Period;
+
+/// The project of commands that ran outside of every project and git repo
+pub const NO_PROJECT: &str = "(none)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Per {
+    Day,
+    Week,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The day, or the Monday of the week, as `YYYY-MM-DD`
+    pub date: String,
+    pub project: String,
+    /// How many commands ran
+    pub commands: usize,
+    /// Time spent running commands, in nanoseconds
+    pub running: i64,
+    /// Time spent on the project, in nanoseconds: running commands, and the gaps between them
+    /// that are no longer than the idle gap
+    pub active: i64,
+}
+
+/// The time spent on each project, ordered by date and then by time spent, most first
+pub fn compute(history: &[History], report: &Report, per: Per, timezone: UtcOffset) -> Vec<Entry> {
+    let idle_gap = Duration::seconds(i64::try_from(report.idle_gap).unwrap_or(i64::MAX));
+
+    let mut history: Vec<&History> = history.iter().collect();
+    history.sort_by(|a, b| {
+        a.session
+            .cmp(&b.session)
+            .then_with(|| a.timestamp.cmp(&b.timestamp))
+    });
+
+    // looking for the git repo of a directory hits the file system, so only do it once each
+    let mut projects = HashMap::<&str, String>::new();
+    let mut entries = HashMap::<(Date, String), Entry>::new();
+
+    for (i, h) in history.iter().enumerate() {
+        let running = Duration::nanoseconds(h.duration.max(0));
+
+        let next = history
+            .get(i + 1)
+            .filter(|next| next.session == h.session)
+            .map(|next| next.timestamp - h.timestamp);
+        let active = match next {
+            Some(until_next) if until_next - running <= idle_gap => until_next.max(running),
+            _ => running,
+        };
+
+        let project = projects
+            .entry(h.cwd.as_str())
+            .or_insert_with(|| project(&h.cwd, report))
+            .clone();
+
+        let date = h.timestamp.to_offset(timezone).date();
+        let date = match per {
+            Per::Day => date,
+            Per::Week => date - Duration::days(i64::from(date.weekday().number_days_from_monday())),
+        };
+
+        let entry = entries
+            .entry((date, project.clone()))
+            .or_insert_with(|| Entry {
+                date: date.to_string(),
+                project,
+                commands: 0,
+                running: 0,
+                active: 0,
+            });
+
+        entry.commands += 1;
+        entry.running += i64::try_from(running.whole_nanoseconds()).unwrap_or(i64::MAX);
+        entry.active += i64::try_from(active.whole_nanoseconds()).unwrap_or(i64::MAX);
+    }
+
+    let mut entries: Vec<Entry> = entries.into_values().collect();
+    entries.sort_by(|a, b| {
+        a.date
+            .cmp(&b.date)
+            .then_with(|| b.active.cmp(&a.active))
+            .then_with(|| a.project.cmp(&b.project))
+    });
+
+    entries
+}
+
+/// The project of a directory: the configured project with the longest directory containing it,
+/// or else the git repo it's in
+fn project(cwd: &str, report: &Report) -> String {
+    let configured = report
+        .projects
+        .iter()
+        .flat_map(|(name, dirs)| {
+            dirs.iter()
+                .map(move |dir| (name, dir.trim_end_matches('/')))
+        })
+        .filter(|(_, dir)| {
+            cwd.strip_prefix(dir)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .max_by_key(|(_, dir)| dir.len());
+
+    match configured {
+        Some((name, _)) => name.clone(),
+        None => in_git_repo(cwd).map_or_else(
+            || NO_PROJECT.to_string(),
+            |root| root.to_string_lossy().to_string(),
+        ),
+    }
+}
+
+#[derive(Serialize)]
+struct TimeReport<'a> {
+    period: &'a Period,
+    per: Per,
+    /// The idle gap the report was computed with, in seconds
+    idle_gap: u64,
+    entries: &'a [Entry],
+}
+
+/// The report and its period as a JSON object
+pub fn to_json(
+    entries: &[Entry],
+    per: Per,
+    report: &Report,
+    period: &Period,
+) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&TimeReport {
+        period,
+        per,
+        idle_gap: report.idle_gap,
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use atuin_client::{history::History, settings::Report};
+    use time::{OffsetDateTime, UtcOffset, macros::datetime};
+
+    use crate::stats::Period;
+
+    use super::{Entry, NO_PROJECT, Per, compute, to_json};
+
+    const SECOND: i64 = 1_000_000_000;
+
+    fn command(session: &str, cwd: &str, timestamp: OffsetDateTime, duration: i64) -> History {
+        History::import()
+            .timestamp(timestamp)
+            .command("cargo build")
+            .duration(duration)
+            .cwd(cwd)
+            .session(session)
+            .build()
+            .into()
+    }
+
+    fn report(projects: &[(&str, &[&str])]) -> Report {
+        Report {
+            idle_gap: 300,
+            projects: projects
+                .iter()
+                .map(|(name, dirs)| {
+                    (
+                        (*name).to_string(),
+                        dirs.iter().map(ToString::to_string).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn idle_gaps() {
+        let history = [
+            // a minute of thinking after the build counts, the hour of lunch doesn't
+            command("a", "/code", datetime!(2025-01-06 09:00 UTC), 30 * SECOND),
+            command(
+                "a",
+                "/code",
+                datetime!(2025-01-06 09:01:30 UTC),
+                10 * SECOND,
+            ),
+            command("a", "/code", datetime!(2025-01-06 10:00 UTC), -1),
+            // the last command of a session only counts its own running time
+            command("a", "/code", datetime!(2025-01-06 10:00:20 UTC), 5 * SECOND),
+            // sessions are separate, even when they interleave
+            command(
+                "b",
+                "/code",
+                datetime!(2025-01-06 09:00:10 UTC),
+                20 * SECOND,
+            ),
+        ];
+
+        let entries = compute(&history, &report(&[]), Per::Day, UtcOffset::UTC);
+
+        assert_eq!(
+            entries,
+            [Entry {
+                date: "2025-01-06".to_string(),
+                project: NO_PROJECT.to_string(),
+                commands: 5,
+                running: 65 * SECOND,
+                active: (90 + 10 + 20 + 5 + 20) * SECOND,
+            }]
+        );
+    }
+
+    #[test]
+    fn projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        let repo = repo.to_string_lossy().to_string();
+
+        let history = [
+            command(
+                "a",
+                &format!("{repo}/src"),
+                datetime!(2025-01-06 09:00 UTC),
+                SECOND,
+            ),
+            command(
+                "b",
+                "/work/api",
+                datetime!(2025-01-06 09:00 UTC),
+                2 * SECOND,
+            ),
+            command(
+                "c",
+                "/work/api/docs",
+                datetime!(2025-01-06 09:00 UTC),
+                3 * SECOND,
+            ),
+            command(
+                "d",
+                "/work/apiary",
+                datetime!(2025-01-06 09:00 UTC),
+                4 * SECOND,
+            ),
+        ];
+        let report = report(&[("work", &["/work/"]), ("docs", &["/work/api/docs"])]);
+
+        let entries: Vec<_> = compute(&history, &report, Per::Day, UtcOffset::UTC)
+            .into_iter()
+            .map(|e| (e.project, e.commands, e.active / SECOND))
+            .collect();
+
+        assert_eq!(
+            entries,
+            [
+                ("work".to_string(), 2, 6),
+                ("docs".to_string(), 1, 3),
+                (repo, 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn weeks() {
+        let history = [
+            command("a", "/", datetime!(2025-01-05 23:30 UTC), SECOND),
+            command("b", "/", datetime!(2025-01-06 09:00 UTC), SECOND),
+            command("c", "/", datetime!(2025-01-12 23:30 UTC), SECOND),
+        ];
+
+        let weeks = |offset| {
+            compute(&history, &report(&[]), Per::Week, offset)
+                .into_iter()
+                .map(|e| (e.date, e.commands))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            weeks(UtcOffset::UTC),
+            [("2024-12-30".to_string(), 1), ("2025-01-06".to_string(), 2)]
+        );
+        // an hour ahead, the first command ran on Monday, and the last on the next Monday
+        assert_eq!(
+            weeks(UtcOffset::from_hms(1, 0, 0).unwrap()),
+            [("2025-01-06".to_string(), 2), ("2025-01-13".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn json_schema() {
+        let entries = [Entry {
+            date: "2025-01-06".to_string(),
+            project: "atuin".to_string(),
+            commands: 3,
+            running: 2 * SECOND,
+            active: 60 * SECOND,
+        }];
+        let period = Period {
+            name: "all".to_string(),
+            start: None,
+            end: None,
+        };
+        let report = Report {
+            idle_gap: 300,
+            projects: HashMap::new(),
+        };
+
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json(&entries, Per::Week, &report, &period).unwrap()).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "period": { "name": "all", "start": null, "end": null },
+                "per": "week",
+                "idle_gap": 300,
+                "entries": [{
+                    "date": "2025-01-06",
+                    "project": "atuin",
+                    "commands": 3,
+                    "running": 2_000_000_000_i64,
+                    "active": 60_000_000_000_i64,
+                }],
+            })
+        );
+    }
+}
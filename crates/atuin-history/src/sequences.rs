@@ -0,0 +1,459 @@
+//! Finding the sequences of commands that are run again and again, to suggest turning them into
+//! scripts.
+//!
+//! Commands are normalised first, so a sequence that checks out and pulls a different branch
+//! each time still counts as the same sequence. The arguments that vary between runs become the
+//! variables of the script's template.
+
+use std::collections::{HashMap, HashSet};
+
+use atuin_client::{history::History, settings::Settings};
+use atuin_common::syntax::{Token, TokenKind, tokenize};
+
+use crate::{
+    normalize::{normalize, placeholders, unquote},
+    stats::{count_ngrams, interesting_command, strip_leading_env_vars},
+};
+
+/// How many runs of a sequence are kept, to find the arguments that vary between them
+const EXAMPLES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequence {
+    /// The normalised commands of the sequence
+    pub commands: Vec<String>,
+    /// How many times the sequence ran
+    pub runs: usize,
+    /// How many sessions it ran in
+    pub sessions: usize,
+    /// The commands of some of its runs, as they were typed
+    pub examples: Vec<Vec<String>>,
+}
+
+/// The sequences of `min_len` to `max_len` consecutive commands in a session that ran at least
+/// `min_runs` times, in more than one session. They're ranked by how many commands running them
+/// as a script would save. Sequences that only ever ran as part of a longer one are left out.
+pub fn find(
+    settings: &Settings,
+    history: &[History],
+    min_len: usize,
+    max_len: usize,
+    min_runs: usize,
+) -> Vec<Sequence> {
+    let min_len = min_len.max(2);
+
+    let mut sessions = HashMap::<&str, Vec<&History>>::new();
+    for h in history {
+        let command = strip_leading_env_vars(h.command.trim());
+        if command.is_empty()
+            || settings
+                .stats
+                .ignored_commands
+                .iter()
+                .any(|c| c == interesting_command(settings, command))
+        {
+            continue;
+        }
+
+        sessions.entry(h.session.as_str()).or_default().push(h);
+    }
+
+    let sessions: Vec<(&str, Vec<&History>, Vec<String>)> = sessions
+        .into_iter()
+        .map(|(session, mut commands)| {
+            commands.sort_by_key(|h| h.timestamp);
+            let normalized = commands
+                .iter()
+                .map(|h| normalize(settings, &h.command))
+                .collect();
+            (session, commands, normalized)
+        })
+        .collect();
+
+    // count the sequences a length at a time. A sequence can only run as often as the shorter
+    // ones it starts and ends with, so only those that ran often enough are grown.
+    let mut runs = HashMap::<Vec<&str>, usize>::new();
+    let mut shorter = HashSet::<Vec<&str>>::new();
+
+    for len in min_len..=max_len {
+        let mut counts = HashMap::new();
+        let grows = |window: &[&str]| {
+            len == min_len
+                || (shorter.contains(&window[..len - 1]) && shorter.contains(&window[1..]))
+        };
+
+        for (_, _, normalized) in &sessions {
+            let keys: Vec<&str> = normalized.iter().map(String::as_str).collect();
+            count_ngrams(&keys, len, grows, &mut counts);
+        }
+
+        counts.retain(|_, runs| *runs >= min_runs);
+        if counts.is_empty() {
+            break;
+        }
+
+        shorter = counts.keys().cloned().collect();
+        runs.extend(counts);
+    }
+
+    // then find where the frequent ones ran, and how they were typed
+    let mut found = HashMap::<&[&str], (HashSet<&str>, Vec<Vec<String>>)>::new();
+    let keys: Vec<Vec<&str>> = sessions
+        .iter()
+        .map(|(_, _, normalized)| normalized.iter().map(String::as_str).collect())
+        .collect();
+
+    for ((session, commands, _), keys) in sessions.iter().zip(&keys) {
+        for len in min_len..=max_len {
+            for (i, window) in keys.windows(len).enumerate() {
+                let Some((window, _)) = runs.get_key_value(window) else {
+                    continue;
+                };
+
+                let (sessions, examples) = found.entry(window.as_slice()).or_default();
+                sessions.insert(session);
+                if examples.len() < EXAMPLES {
+                    examples.push(
+                        commands[i..i + len]
+                            .iter()
+                            .map(|h| h.command.trim().to_string())
+                            .collect(),
+                    );
+                }
+            }
+        }
+    }
+
+    let sequences: Vec<Sequence> = found
+        .into_iter()
+        // running the same command over and over isn't a sequence worth a script
+        .filter(|(commands, (sessions, _))| {
+            sessions.len() > 1 && commands.iter().any(|c| *c != commands[0])
+        })
+        .map(|(commands, (sessions, examples))| Sequence {
+            commands: commands.iter().map(ToString::to_string).collect(),
+            runs: runs[commands],
+            sessions: sessions.len(),
+            examples,
+        })
+        .collect();
+
+    // a sequence is only part of a longer one if it never ran without it. Then the sequence one
+    // command longer that contains it ran as often too, so only those need checking.
+    let mut contained = HashSet::<(&[String], usize)>::new();
+    for longer in &sequences {
+        let len = longer.commands.len();
+        contained.insert((&longer.commands[..len - 1], longer.runs));
+        contained.insert((&longer.commands[1..], longer.runs));
+    }
+
+    let mut maximal: Vec<Sequence> = sequences
+        .iter()
+        .filter(|s| !contained.contains(&(s.commands.as_slice(), s.runs)))
+        .cloned()
+        .collect();
+
+    maximal.sort_by(|a, b| {
+        (b.runs * b.commands.len())
+            .cmp(&(a.runs * a.commands.len()))
+            .then_with(|| b.sessions.cmp(&a.sessions))
+            .then_with(|| a.commands.cmp(&b.commands))
+    });
+
+    maximal
+}
+
+/// The sequence as a script template, with the arguments that vary between its runs as
+/// variables, and the names of those variables. The same values in different commands become
+/// the same variable.
+pub fn template(sequence: &Sequence) -> (String, Vec<String>) {
+    let Some(first) = sequence.examples.first() else {
+        return (sequence.commands.join("\n"), Vec::new());
+    };
+
+    let mut variables = Vec::<String>::new();
+    let mut by_values = HashMap::<Vec<&str>, String>::new();
+    let mut lines = Vec::with_capacity(first.len());
+
+    for (i, command) in first.iter().enumerate() {
+        let runs: Vec<Vec<&str>> = sequence
+            .examples
+            .iter()
+            .map(|example| words(&example[i]))
+            .collect();
+        // the commands must line up word for word in every run to tell which words vary
+        let aligned = sequence
+            .examples
+            .iter()
+            .all(|example| shape(&example[i]) == shape(command));
+
+        let mut line = String::new();
+        let mut word = 0;
+
+        for (token, placeholder) in placeholders(command) {
+            if token.kind == TokenKind::Whitespace {
+                line.push_str(token.text);
+                continue;
+            }
+
+            let values: Vec<&str> = runs.iter().filter_map(|r| r.get(word).copied()).collect();
+            word += 1;
+
+            if !aligned || values.iter().all(|v| *v == values[0]) {
+                line.push_str(&escape(token.text));
+                continue;
+            }
+
+            let variable = by_values.entry(values).or_insert_with(|| {
+                let name = placeholder
+                    .and_then(|p| p.strip_prefix('<')?.strip_suffix('>'))
+                    .unwrap_or("arg");
+                let name = unique_name(name, &variables);
+                variables.push(name.clone());
+                name
+            });
+
+            let (flag, value) = split_flag(&token);
+            let (open, close) = quotes(value);
+            line.push_str(&escape(flag));
+            line.push_str(&format!("{open}{{{{ {variable} }}}}{close}"));
+        }
+
+        lines.push(line);
+    }
+
+    (lines.join("\n"), variables)
+}
+
+/// The words of a command, without the quotes around them. Only the values of `--flag=value`
+/// are kept, as it's the value that varies.
+fn words(command: &str) -> Vec<&str> {
+    tokenize(command)
+        .into_iter()
+        .filter(|t| t.kind != TokenKind::Whitespace)
+        .map(|t| unquote(split_flag(&t).1))
+        .collect()
+}
+
+/// The kind of each word of a command, with the text of those that aren't values. Commands
+/// with the same shape only differ in their values, so they line up word for word.
+fn shape(command: &str) -> Vec<(TokenKind, Option<&str>)> {
+    tokenize(command)
+        .into_iter()
+        .filter_map(|t| match t.kind {
+            TokenKind::Whitespace => None,
+            // a value may be quoted in one run and not another
+            TokenKind::Argument | TokenKind::String | TokenKind::Variable => {
+                Some((TokenKind::Argument, None))
+            }
+            // `--flag=value` and `NAME=value` vary in their value
+            TokenKind::Flag | TokenKind::Assignment => Some((
+                t.kind,
+                Some(t.text.split_once('=').map_or(t.text, |(name, _)| name)),
+            )),
+            _ => Some((t.kind, Some(t.text))),
+        })
+        .collect()
+}
+
+/// A `--flag=` and its value, or no flag and the whole token
+fn split_flag<'a>(token: &Token<'a>) -> (&'a str, &'a str) {
+    match token.text.find('=') {
+        Some(i) if token.kind == TokenKind::Flag => token.text.split_at(i + 1),
+        _ => ("", token.text),
+    }
+}
+
+/// The quotes around a word, if it's quoted
+fn quotes(word: &str) -> (&'static str, &'static str) {
+    for quote in ["'", "\""] {
+        if word.len() >= 2 && word.starts_with(quote) && word.ends_with(quote) {
+            return (quote, quote);
+        }
+    }
+
+    ("", "")
+}
+
+fn unique_name(name: &str, taken: &[String]) -> String {
+    if !taken.iter().any(|t| t == name) {
+        return name.to_string();
+    }
+
+    (2..)
+        .map(|n| format!("{name}_{n}"))
+        .find(|candidate| !taken.contains(candidate))
+        .expect("there is always a free name")
+}
+
+/// Text escaped so the template renders it as it is
+fn escape(text: &str) -> String {
+    if text.contains(['{', '}']) {
+        format!("{{% raw %}}{text}{{% endraw %}}")
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::{history::History, settings::Settings};
+    use time::{Duration, macros::datetime};
+
+    use super::{Sequence, find, template};
+
+    fn history(sessions: &[&[&str]]) -> Vec<History> {
+        let mut timestamp = datetime!(2025-01-06 09:00 UTC);
+
+        sessions
+            .iter()
+            .enumerate()
+            .flat_map(|(session, commands)| {
+                commands
+                    .iter()
+                    .map(|command| {
+                        timestamp += Duration::MINUTE;
+                        History::import()
+                            .timestamp(timestamp)
+                            .command(*command)
+                            .session(session.to_string())
+                            .build()
+                            .into()
+                    })
+                    .collect::<Vec<History>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn find_repeated_sequences() {
+        let history = history(&[
+            &[
+                "ls",
+                "git checkout feature-a",
+                "git pull origin feature-a",
+                "cargo test",
+            ],
+            &[
+                "git checkout fix-b",
+                "git pull origin fix-b",
+                "cargo test",
+                "ls",
+            ],
+            &[
+                "vim",
+                "git checkout main",
+                "git pull origin main",
+                "cargo test",
+            ],
+            // only repeated within one session
+            &["make", "make install", "make", "make install"],
+        ]);
+
+        let sequences = find(&Settings::utc(), &history, 2, 4, 2);
+
+        assert_eq!(sequences.len(), 1);
+        assert_eq!(
+            sequences[0].commands,
+            [
+                "git checkout <branch>",
+                "git pull origin <branch>",
+                "cargo test"
+            ]
+        );
+        assert_eq!(sequences[0].runs, 3);
+        assert_eq!(sequences[0].sessions, 3);
+    }
+
+    #[test]
+    fn shorter_sequences_that_ran_alone() {
+        let history = history(&[
+            &["cargo fmt", "cargo clippy", "cargo test"],
+            &["cargo fmt", "cargo clippy", "cargo test"],
+            &["cargo fmt", "cargo clippy", "git commit"],
+            &["cargo fmt", "cargo clippy"],
+        ]);
+
+        let sequences = find(&Settings::utc(), &history, 2, 3, 2);
+        let found: Vec<(usize, usize)> = sequences
+            .iter()
+            .map(|s| (s.commands.len(), s.runs))
+            .collect();
+
+        // `cargo clippy` then `cargo test` only ran as part of the longer sequence, while
+        // `cargo fmt` then `cargo clippy` also ran without it
+        assert_eq!(found, [(2, 4), (3, 2)]);
+        assert_eq!(sequences[0].commands, ["cargo fmt", "cargo clippy"]);
+    }
+
+    #[test]
+    fn template_variables() {
+        let sequence = Sequence {
+            commands: vec![
+                "git checkout <branch>".to_string(),
+                "git push origin <branch>".to_string(),
+                "kubectl scale --replicas <num> <path>".to_string(),
+                "cargo build --jobs=<num>".to_string(),
+                "echo '{{ done }}'".to_string(),
+            ],
+            runs: 2,
+            sessions: 2,
+            examples: vec![
+                vec![
+                    "git checkout feature-a".to_string(),
+                    "git push origin feature-a".to_string(),
+                    "kubectl scale --replicas 3 deploy/api".to_string(),
+                    "cargo build --jobs=8".to_string(),
+                    "echo '{{ done }}'".to_string(),
+                ],
+                vec![
+                    "git checkout fix-b".to_string(),
+                    "git push origin fix-b".to_string(),
+                    "kubectl scale --replicas 5 deploy/api".to_string(),
+                    "cargo build --jobs=4".to_string(),
+                    "echo '{{ done }}'".to_string(),
+                ],
+            ],
+        };
+
+        let (script, variables) = template(&sequence);
+
+        assert_eq!(
+            script,
+            "git checkout {{ branch }}\n\
+             git push origin {{ branch }}\n\
+             kubectl scale --replicas {{ num }} deploy/api\n\
+             cargo build --jobs={{ num_2 }}\n\
+             echo {% raw %}'{{ done }}'{% endraw %}"
+        );
+        assert_eq!(variables, ["branch", "num", "num_2"]);
+    }
+
+    #[test]
+    fn template_misaligned_runs() {
+        let sequence = Sequence {
+            commands: vec![
+                "git commit -m <arg>".to_string(),
+                "git push <arg>".to_string(),
+            ],
+            runs: 2,
+            sessions: 2,
+            examples: vec![
+                vec![
+                    "git commit -m 'wip'".to_string(),
+                    "git push origin".to_string(),
+                ],
+                vec![
+                    "git commit -m \"fix tests\"".to_string(),
+                    "git push --force".to_string(),
+                ],
+            ],
+        };
+
+        let (script, variables) = template(&sequence);
+
+        // the message varies, but a remote and a flag can't be the same variable
+        assert_eq!(script, "git commit -m '{{ arg }}'\ngit push origin");
+        assert_eq!(variables, ["arg"]);
+    }
+}
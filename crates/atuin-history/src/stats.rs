@@ -1,18 +1,131 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
 
 use crossterm::style::{Color, ResetColor, SetAttribute, SetForegroundColor};
 use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
 use unicode_segmentation::UnicodeSegmentation;
 
 use atuin_client::{history::History, settings::Settings, theme::Meaning, theme::Theme};
+use atuin_common::syntax::split_pipeline;
+
+use crate::normalize::normalize;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Stats {
     pub total_commands: usize,
     pub unique_commands: usize,
+    /// How many consecutive commands each entry of `top` has
+    pub ngram_size: usize,
+    /// The most used commands, or sequences of commands, with how often they were used
+    #[serde(with = "top_entries")]
     pub top: Vec<(Vec<String>, usize)>,
 }
 
+/// Top commands are written as `{ "command": [...], "count": n }`, which reads better than
+/// the tuples they are in memory
+mod top_entries {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Entry {
+        command: Vec<String>,
+        count: usize,
+    }
+
+    #[allow(clippy::ptr_arg)]
+    pub fn serialize<S: Serializer>(
+        top: &Vec<(Vec<String>, usize)>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(top.iter().map(|(command, count)| Entry {
+            command: command.clone(),
+            count: *count,
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(Vec<String>, usize)>, D::Error> {
+        let entries = Vec::<Entry>::deserialize(deserializer)?;
+        Ok(entries.into_iter().map(|e| (e.command, e.count)).collect())
+    }
+}
+
+/// The period stats were computed over: as it was asked for, and the times it covers. Stats of
+/// all history have no start or end.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Period {
+    pub name: String,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub start: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub end: Option<OffsetDateTime>,
+}
+
+impl Period {
+    /// The CSV header of the period's own columns
+    pub(crate) const CSV_HEADER: &str = "period,start,end";
+
+    /// The period's columns of a CSV row
+    pub(crate) fn csv_fields(&self) -> String {
+        let time =
+            |t: Option<OffsetDateTime>| t.and_then(|t| t.format(&Rfc3339).ok()).unwrap_or_default();
+
+        format!(
+            "{},{},{}",
+            csv_field(&self.name),
+            time(self.start),
+            time(self.end)
+        )
+    }
+}
+
+/// A value quoted as a CSV field, if it needs to be
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    period: &'a Period,
+    #[serde(flatten)]
+    stats: &'a Stats,
+}
+
+/// The stats and their period as a JSON object
+pub fn to_json(stats: &Stats, period: &Period) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&Report { period, stats })
+}
+
+/// The stats as CSV, with a row for each top command. The period and totals are repeated on
+/// every row, so each row stands on its own. The commands of an ngram are joined with ` | `.
+pub fn to_csv(stats: &Stats, period: &Period) -> String {
+    let mut csv = format!(
+        "{},total_commands,unique_commands,rank,count,command\n",
+        Period::CSV_HEADER
+    );
+
+    for (rank, (command, count)) in (1..).zip(&stats.top) {
+        csv.push_str(&format!(
+            "{},{},{},{rank},{count},{}\n",
+            period.csv_fields(),
+            stats.total_commands,
+            stats.unique_commands,
+            csv_field(&command.join(" | ")),
+        ));
+    }
+
+    csv
+}
+
 fn first_non_whitespace(s: &str) -> Option<usize> {
     s.char_indices()
         // find the first non whitespace char
@@ -29,7 +142,7 @@ fn first_whitespace(s: &str) -> usize {
         .map_or(s.len(), |(i, _)| i)
 }
 
-fn interesting_command<'a>(settings: &Settings, mut command: &'a str) -> &'a str {
+pub(crate) fn interesting_command<'a>(settings: &Settings, mut command: &'a str) -> &'a str {
     // Sort by length so that we match the longest prefix first
     let mut common_prefix = settings.stats.common_prefix.clone();
     common_prefix.sort_by_key(|b| std::cmp::Reverse(b.len()));
@@ -70,46 +183,7 @@ fn interesting_command<'a>(settings: &Settings, mut command: &'a str) -> &'a str
     &command[..first_whitespace(command)]
 }
 
-fn split_at_pipe(command: &str) -> Vec<&str> {
-    let mut result = vec![];
-    let mut quoted = false;
-    let mut start = 0;
-    let mut graphemes = UnicodeSegmentation::grapheme_indices(command, true);
-
-    while let Some((i, c)) = graphemes.next() {
-        let current = i;
-        match c {
-            "\"" => {
-                if command[start..current] != *"\"" {
-                    quoted = !quoted;
-                }
-            }
-            "'" => {
-                if command[start..current] != *"'" {
-                    quoted = !quoted;
-                }
-            }
-            "\\" => if graphemes.next().is_some() {},
-            "|" => {
-                if !quoted {
-                    if current > start && command[start..].starts_with('|') {
-                        start += 1;
-                    }
-                    result.push(&command[start..current]);
-                    start = current;
-                }
-            }
-            _ => {}
-        }
-    }
-    if command[start..].starts_with('|') {
-        start += 1;
-    }
-    result.push(&command[start..]);
-    result
-}
-
-fn strip_leading_env_vars(command: &str) -> &str {
+pub(crate) fn strip_leading_env_vars(command: &str) -> &str {
     // fast path: no equals sign, no environment variable
     if !command.contains('=') {
         return command;
@@ -245,15 +319,44 @@ pub fn pretty_print(stats: Stats, ngram_size: usize, theme: &Theme) {
     println!("Unique commands:  {}", stats.unique_commands);
 }
 
+/// Count each run of `size` consecutive keys, skipping those `keep` is false for
+pub(crate) fn count_ngrams<K: Clone + Eq + Hash>(
+    keys: &[K],
+    size: usize,
+    keep: impl Fn(&[K]) -> bool,
+    counts: &mut HashMap<Vec<K>, usize>,
+) {
+    for window in keys.windows(size).filter(|w| keep(w)) {
+        *counts.entry(window.to_vec()).or_default() += 1;
+    }
+}
+
 pub fn compute(
     settings: &Settings,
     history: &[History],
     count: usize,
     ngram_size: usize,
 ) -> Option<Stats> {
-    let mut commands = HashSet::<&str>::with_capacity(history.len());
+    let mut commands = HashSet::<Cow<str>>::with_capacity(history.len());
     let mut total_unignored = 0;
-    let mut prefixes = HashMap::<Vec<&str>, usize>::with_capacity(history.len());
+    let mut prefixes = HashMap::<Vec<Cow<str>>, usize>::with_capacity(history.len());
+
+    let key = |command| {
+        if settings.stats.normalize {
+            Cow::Owned(normalize(settings, command))
+        } else {
+            Cow::Borrowed(command)
+        }
+    };
+    // when normalising, whole commands are counted rather than their first words, as that's
+    // where the placeholders are
+    let top_key = |command| {
+        if settings.stats.normalize {
+            key(command)
+        } else {
+            Cow::Borrowed(interesting_command(settings, command))
+        }
+    };
 
     for i in history {
         // just in case it somehow has a leading tab or space or something (legacy atuin didn't ignore space prefixes)
@@ -265,22 +368,17 @@ pub fn compute(
         }
 
         total_unignored += 1;
-        commands.insert(command);
+        commands.insert(key(command));
 
-        split_at_pipe(command)
+        let keys: Vec<Cow<str>> = split_pipeline(command)
             .iter()
             .map(|l| {
-                let command = l.trim();
-                commands.insert(command);
-                command
+                let command = strip_leading_env_vars(l.trim());
+                commands.insert(key(command));
+                top_key(command)
             })
-            .collect::<Vec<_>>()
-            .windows(ngram_size)
-            .for_each(|w| {
-                *prefixes
-                    .entry(w.iter().map(|c| interesting_command(settings, c)).collect())
-                    .or_default() += 1;
-            });
+            .collect();
+        count_ngrams(&keys, ngram_size, |_| true, &mut prefixes);
     }
 
     let unique = commands.len();
@@ -296,6 +394,7 @@ pub fn compute(
     Some(Stats {
         unique_commands: unique,
         total_commands: total_unignored,
+        ngram_size,
         top: top
             .into_iter()
             .map(|t| (t.0.into_iter().map(|s| s.to_string()).collect(), t.1))
@@ -309,8 +408,85 @@ mod tests {
     use atuin_client::settings::Settings;
     use time::OffsetDateTime;
 
-    use super::compute;
-    use super::{interesting_command, split_at_pipe, strip_leading_env_vars};
+    use super::{Period, Stats, compute, to_csv, to_json};
+    use super::{interesting_command, split_pipeline, strip_leading_env_vars};
+
+    fn report() -> (Stats, Period) {
+        let stats = Stats {
+            total_commands: 12,
+            unique_commands: 5,
+            ngram_size: 2,
+            top: vec![
+                (vec!["cargo build".to_string(), "cargo test".to_string()], 4),
+                (vec!["git add".to_string(), "git commit".to_string()], 2),
+            ],
+        };
+        let period = Period {
+            name: "today".to_string(),
+            start: Some(time::macros::datetime!(2025-01-01 00:00 UTC)),
+            end: Some(time::macros::datetime!(2025-01-02 00:00 UTC)),
+        };
+
+        (stats, period)
+    }
+
+    #[test]
+    fn json_schema() {
+        let (stats, period) = report();
+        let json: serde_json::Value = serde_json::from_str(&to_json(&stats, &period).unwrap())
+            .expect("stats should be valid JSON");
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "period": {
+                    "name": "today",
+                    "start": "2025-01-01T00:00:00Z",
+                    "end": "2025-01-02T00:00:00Z",
+                },
+                "total_commands": 12,
+                "unique_commands": 5,
+                "ngram_size": 2,
+                "top": [
+                    { "command": ["cargo build", "cargo test"], "count": 4 },
+                    { "command": ["git add", "git commit"], "count": 2 },
+                ],
+            })
+        );
+
+        // the stats read back as they were written
+        let read: Stats = serde_json::from_value(json).unwrap();
+        assert_eq!(read.top, stats.top);
+    }
+
+    #[test]
+    fn json_schema_all_history() {
+        let (stats, mut period) = report();
+        period.name = "all".to_string();
+        period.start = None;
+        period.end = None;
+
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json(&stats, &period).unwrap()).unwrap();
+
+        assert_eq!(
+            json["period"],
+            serde_json::json!({ "name": "all", "start": null, "end": null })
+        );
+    }
+
+    #[test]
+    fn csv_rows() {
+        let (mut stats, period) = report();
+        stats.top[1].0[1] = r#"git commit -m "a, b""#.to_string();
+
+        assert_eq!(
+            to_csv(&stats, &period),
+            "period,start,end,total_commands,unique_commands,rank,count,command\n\
+             today,2025-01-01T00:00:00Z,2025-01-02T00:00:00Z,12,5,1,4,cargo build | cargo test\n\
+             today,2025-01-01T00:00:00Z,2025-01-02T00:00:00Z,12,5,2,2,\"git add | git commit -m \"\"a, b\"\"\"\n"
+        );
+    }
 
     #[test]
     fn ignored_env_vars() {
@@ -327,6 +503,21 @@ mod tests {
         assert_eq!(stats.top.first().unwrap().0, vec!["echo"]);
     }
 
+    #[test]
+    fn pipelines() {
+        let settings = Settings::utc();
+
+        let history: History = History::capture()
+            .timestamp(time::OffsetDateTime::now_utc())
+            .command("make || make clean | LC_ALL=C sort")
+            .cwd("/")
+            .build()
+            .into();
+
+        let stats = compute(&settings, &[history], 10, 2).expect("failed to compute stats");
+        assert_eq!(stats.top.first().unwrap().0, vec!["make", "sort"]);
+    }
+
     #[test]
     fn ignored_commands() {
         let mut settings = Settings::utc();
@@ -350,6 +541,39 @@ mod tests {
         assert_eq!(stats.unique_commands, 1);
     }
 
+    #[test]
+    fn normalized_commands() {
+        let mut settings = Settings::utc();
+        settings.stats.normalize = true;
+
+        let history: Vec<History> = [
+            "git checkout feature-a",
+            "git checkout feature-b",
+            "git checkout feature-a",
+            "cat /etc/hosts",
+        ]
+        .into_iter()
+        .map(|command| {
+            History::import()
+                .timestamp(OffsetDateTime::now_utc())
+                .command(command)
+                .build()
+                .into()
+        })
+        .collect();
+
+        let stats = compute(&settings, &history, 10, 1).expect("failed to compute stats");
+        assert_eq!(stats.total_commands, 4);
+        assert_eq!(stats.unique_commands, 2);
+        assert_eq!(
+            stats.top,
+            [
+                (vec!["git checkout <branch>".to_string()], 3),
+                (vec!["cat <path>".to_string()], 1)
+            ]
+        );
+    }
+
     #[test]
     fn interesting_commands() {
         let settings = Settings::utc();
@@ -451,13 +675,13 @@ mod tests {
 
     #[test]
     fn split_simple() {
-        assert_eq!(split_at_pipe("fd | rg"), ["fd ", " rg"]);
+        assert_eq!(split_pipeline("fd | rg"), ["fd ", " rg"]);
     }
 
     #[test]
     fn split_multi() {
         assert_eq!(
-            split_at_pipe("kubectl | jq | rg"),
+            split_pipeline("kubectl | jq | rg"),
             ["kubectl ", " jq ", " rg"]
         );
     }
@@ -465,7 +689,7 @@ mod tests {
     #[test]
     fn split_simple_quoted() {
         assert_eq!(
-            split_at_pipe("foo | bar 'baz {} | quux' | xyzzy"),
+            split_pipeline("foo | bar 'baz {} | quux' | xyzzy"),
             ["foo ", " bar 'baz {} | quux' ", " xyzzy"]
         );
     }
@@ -473,7 +697,7 @@ mod tests {
     #[test]
     fn split_multi_quoted() {
         assert_eq!(
-            split_at_pipe("foo | bar 'baz \"{}\" | quux' | xyzzy"),
+            split_pipeline("foo | bar 'baz \"{}\" | quux' | xyzzy"),
             ["foo ", " bar 'baz \"{}\" | quux' ", " xyzzy"]
         );
     }
@@ -481,7 +705,7 @@ mod tests {
     #[test]
     fn escaped_pipes() {
         assert_eq!(
-            split_at_pipe("foo | bar baz \\| quux"),
+            split_pipeline("foo | bar baz \\| quux"),
             ["foo ", " bar baz \\| quux"]
         );
     }
@@ -489,7 +713,7 @@ mod tests {
     #[test]
     fn emoji() {
         assert_eq!(
-            split_at_pipe("git commit -m \"🚀\""),
+            split_pipeline("git commit -m \"🚀\""),
             ["git commit -m \"🚀\""]
         );
     }
@@ -497,7 +721,7 @@ mod tests {
     #[test]
     fn starts_with_pipe() {
         assert_eq!(
-            split_at_pipe("| sed 's/[0-9a-f]//g'"),
+            split_pipeline("| sed 's/[0-9a-f]//g'"),
             ["", " sed 's/[0-9a-f]//g'"]
         );
     }
@@ -505,7 +729,7 @@ mod tests {
     #[test]
     fn starts_with_spaces_and_pipe() {
         assert_eq!(
-            split_at_pipe("  | sed 's/[0-9a-f]//g'"),
+            split_pipeline("  | sed 's/[0-9a-f]//g'"),
             ["  ", " sed 's/[0-9a-f]//g'"]
         );
     }
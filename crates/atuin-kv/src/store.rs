@@ -3,7 +3,8 @@ use std::collections::HashSet;
 use eyre::{Result, bail};
 
 use atuin_client::record::sqlite_store::SqliteStore;
-use atuin_client::record::{encryption::PASETO_V4, store::Store};
+use atuin_client::record::{keyring::Keyring, store::Store};
+use atuin_client::team::Team;
 use atuin_common::record::{Host, HostId, Record, RecordId, RecordIdx};
 use entry::KvEntry;
 use record::{KV_TAG, KV_VERSION, KvRecord};
@@ -18,7 +19,7 @@ pub struct KvStore {
     pub record_store: SqliteStore,
     pub kv_db: Database,
     pub host_id: HostId,
-    pub encryption_key: [u8; 32],
+    pub keyring: Keyring,
 }
 
 impl KvStore {
@@ -26,13 +27,13 @@ impl KvStore {
         record_store: SqliteStore,
         kv_db: Database,
         host_id: HostId,
-        encryption_key: [u8; 32],
+        keyring: impl Into<Keyring>,
     ) -> Self {
         KvStore {
             record_store,
             kv_db,
             host_id,
-            encryption_key,
+            keyring: keyring.into(),
         }
     }
 
@@ -101,12 +102,21 @@ impl KvStore {
         let id = record.id;
 
         self.record_store
-            .push(&record.encrypt::<PASETO_V4>(&self.encryption_key))
+            .push(&self.keyring.encrypt(record))
             .await?;
 
         Ok((id, idx))
     }
 
+    /// Rebuild a team's kv database from its record store
+    pub async fn build_team(team: &Team, store: SqliteStore, host_id: HostId) -> Result<()> {
+        let kv_db = Database::new(team.kv_db_path(), 1.0).await?;
+
+        KvStore::new(store, kv_db, host_id, team.keyring()?)
+            .build()
+            .await
+    }
+
     pub async fn build(&self) -> Result<()> {
         let mut tagged = self.record_store.all_tagged(KV_TAG).await?;
         tagged.reverse();
@@ -119,7 +129,7 @@ impl KvStore {
         // only visit each KV once, inserting or deleting based on the first time we see it
         for record in tagged {
             let decrypted = match record.version.as_str() {
-                "v0" | KV_VERSION => record.decrypt::<PASETO_V4>(&self.encryption_key)?,
+                "v0" | KV_VERSION => self.keyring.decrypt(record)?,
                 version => bail!("unknown version {version:?}"),
             };
 
@@ -1,7 +1,8 @@
 use eyre::{Result, bail};
 
 use atuin_client::record::sqlite_store::SqliteStore;
-use atuin_client::record::{encryption::PASETO_V4, store::Store};
+use atuin_client::record::{keyring::Keyring, store::Store};
+use atuin_client::team::Team;
 use atuin_common::record::{Host, HostId, Record, RecordId, RecordIdx};
 use record::ScriptRecord;
 use script::{SCRIPT_TAG, SCRIPT_VERSION, Script};
@@ -15,15 +16,15 @@ pub mod script;
 pub struct ScriptStore {
     pub store: SqliteStore,
     pub host_id: HostId,
-    pub encryption_key: [u8; 32],
+    pub keyring: Keyring,
 }
 
 impl ScriptStore {
-    pub fn new(store: SqliteStore, host_id: HostId, encryption_key: [u8; 32]) -> Self {
+    pub fn new(store: SqliteStore, host_id: HostId, keyring: impl Into<Keyring>) -> Self {
         ScriptStore {
             store,
             host_id,
-            encryption_key,
+            keyring: keyring.into(),
         }
     }
 
@@ -45,9 +46,7 @@ impl ScriptStore {
 
         let id = record.id;
 
-        self.store
-            .push(&record.encrypt::<PASETO_V4>(&self.encryption_key))
-            .await?;
+        self.store.push(&self.keyring.encrypt(record)).await?;
 
         Ok((id, idx))
     }
@@ -77,7 +76,7 @@ impl ScriptStore {
         for record in records.into_iter() {
             let script = match record.version.as_str() {
                 SCRIPT_VERSION => {
-                    let decrypted = record.decrypt::<PASETO_V4>(&self.encryption_key)?;
+                    let decrypted = self.keyring.decrypt(record)?;
 
                     ScriptRecord::deserialize(&decrypted.data, SCRIPT_VERSION)
                 }
@@ -90,6 +89,15 @@ impl ScriptStore {
         Ok(ret)
     }
 
+    /// Rebuild a team's script database from its record store
+    pub async fn build_team(team: &Team, store: SqliteStore, host_id: HostId) -> Result<()> {
+        let database = Database::new(team.scripts_db_path(), 1.0).await?;
+
+        ScriptStore::new(store, host_id, team.keyring()?)
+            .build(database)
+            .await
+    }
+
     pub async fn build(&self, database: Database) -> Result<()> {
         // Get all the scripts from the database - they are already sorted by timestamp
         let scripts = self.scripts().await?;
@@ -11,7 +11,7 @@ use std::{
 
 use self::{
     calendar::{TimePeriod, TimePeriodInfo},
-    models::{History, NewHistory, NewSession, NewUser, Session, User},
+    models::{History, NewHistory, NewSession, NewUser, Session, Team, TeamMember, User},
 };
 use async_trait::async_trait;
 use atuin_common::record::{EncryptedData, HostId, Record, RecordIdx, RecordStatus};
@@ -98,12 +98,22 @@ pub trait Database: Sized + Clone + Send + Sync + 'static {
     async fn get_user_session(&self, u: &User) -> DbResult<Session>;
     async fn add_user(&self, user: &NewUser) -> DbResult<i64>;
 
+    /// List every user on the server, ordered by id
+    async fn list_users(&self) -> DbResult<Vec<User>>;
+    /// List every session belonging to a user
+    async fn list_user_sessions(&self, u: &User) -> DbResult<Vec<Session>>;
+
     async fn user_verified(&self, id: i64) -> DbResult<bool>;
     async fn verify_user(&self, id: i64) -> DbResult<()>;
     async fn user_verification_token(&self, id: i64) -> DbResult<String>;
 
     async fn update_user_password(&self, u: &User) -> DbResult<()>;
 
+    /// Get the user's escrowed encryption key. This is wrapped client side, and opaque to us.
+    async fn get_key_escrow(&self, u: &User) -> DbResult<String>;
+    async fn set_key_escrow(&self, u: &User, escrow: &str) -> DbResult<()>;
+    async fn delete_key_escrow(&self, u: &User) -> DbResult<()>;
+
     async fn total_history(&self) -> DbResult<i64>;
     async fn count_history(&self, user: &User) -> DbResult<i64>;
     async fn count_history_cached(&self, user: &User) -> DbResult<i64>;
@@ -126,6 +136,30 @@ pub trait Database: Sized + Clone + Send + Sync + 'static {
     // Return the tail record ID for each store, so (HostID, Tag, TailRecordID)
     async fn status(&self, user: &User) -> DbResult<RecordStatus>;
 
+    /// Create a team, with `owner` as its first admin
+    async fn create_team(&self, name: &str, owner: &User) -> DbResult<Team>;
+    async fn get_team(&self, name: &str) -> DbResult<Team>;
+    /// List every team on the server, ordered by id
+    async fn list_all_teams(&self) -> DbResult<Vec<Team>>;
+    /// List the teams a user is a member of
+    async fn list_teams(&self, u: &User) -> DbResult<Vec<Team>>;
+    async fn list_team_members(&self, team: &Team) -> DbResult<Vec<TeamMember>>;
+    async fn add_team_member(&self, team: &Team, u: &User, admin: bool) -> DbResult<()>;
+    async fn remove_team_member(&self, team: &Team, u: &User) -> DbResult<()>;
+
+    /// The same as the record functions above, but for the store shared by a team
+    async fn add_team_records(&self, team: &Team, record: &[Record<EncryptedData>])
+    -> DbResult<()>;
+    async fn next_team_records(
+        &self,
+        team: &Team,
+        host: HostId,
+        tag: String,
+        start: Option<RecordIdx>,
+        count: u64,
+    ) -> DbResult<Vec<Record<EncryptedData>>>;
+    async fn team_status(&self, team: &Team) -> DbResult<RecordStatus>;
+
     async fn count_history_range(&self, user: &User, range: Range<OffsetDateTime>)
     -> DbResult<i64>;
 
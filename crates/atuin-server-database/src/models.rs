@@ -51,3 +51,14 @@ pub struct NewSession {
     pub user_id: i64,
     pub token: String,
 }
+
+pub struct Team {
+    pub id: i64,
+    pub name: String,
+}
+
+pub struct TeamMember {
+    pub user_id: i64,
+    pub username: String,
+    pub admin: bool,
+}
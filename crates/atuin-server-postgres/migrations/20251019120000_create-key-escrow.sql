@@ -0,0 +1,8 @@
+-- A copy of the user's encryption key, wrapped client side with a passphrase.
+-- The server can never read it.
+create table key_escrow(
+  id bigserial primary key,
+  user_id bigint unique not null references users(id),
+  data text not null,
+  updated_at timestamp with time zone not null default now()
+);
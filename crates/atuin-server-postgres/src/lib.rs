@@ -6,7 +6,9 @@ use rand::Rng;
 use async_trait::async_trait;
 use atuin_common::record::{EncryptedData, HostId, Record, RecordIdx, RecordStatus};
 use atuin_common::utils::crypto_random_string;
-use atuin_server_database::models::{History, NewHistory, NewSession, NewUser, Session, User};
+use atuin_server_database::models::{
+    History, NewHistory, NewSession, NewUser, Session, Team, TeamMember, User,
+};
 use atuin_server_database::{Database, DbError, DbResult, DbSettings};
 use futures_util::TryStreamExt;
 use sqlx::Row;
@@ -15,7 +17,7 @@ use sqlx::postgres::PgPoolOptions;
 use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
 use tracing::{instrument, trace};
 use uuid::Uuid;
-use wrappers::{DbHistory, DbRecord, DbSession, DbUser};
+use wrappers::{DbHistory, DbRecord, DbSession, DbTeam, DbTeamMember, DbUser};
 
 mod wrappers;
 
@@ -405,6 +407,18 @@ impl Database for Postgres {
             .await
             .map_err(fix_error)?;
 
+        sqlx::query("delete from key_escrow where user_id = $1")
+            .bind(u.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        sqlx::query("delete from team_members where user_id = $1")
+            .bind(u.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
         sqlx::query("delete from user_verification_token where user_id = $1")
             .bind(u.id)
             .execute(&self.pool)
@@ -426,6 +440,45 @@ impl Database for Postgres {
         Ok(())
     }
 
+    #[instrument(skip_all)]
+    async fn get_key_escrow(&self, u: &User) -> DbResult<String> {
+        let res: (String,) = sqlx::query_as("select data from key_escrow where user_id = $1")
+            .bind(u.id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        Ok(res.0)
+    }
+
+    #[instrument(skip_all)]
+    async fn set_key_escrow(&self, u: &User, escrow: &str) -> DbResult<()> {
+        sqlx::query(
+            "insert into key_escrow (user_id, data, updated_at)
+            values ($1, $2, $3)
+            on conflict(user_id) do update set data = $2, updated_at = $3",
+        )
+        .bind(u.id)
+        .bind(escrow)
+        .bind(OffsetDateTime::now_utc())
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_key_escrow(&self, u: &User) -> DbResult<()> {
+        sqlx::query("delete from key_escrow where user_id = $1")
+            .bind(u.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        Ok(())
+    }
+
     #[instrument(skip_all)]
     async fn update_user_password(&self, user: &User) -> DbResult<()> {
         sqlx::query(
@@ -442,6 +495,27 @@ impl Database for Postgres {
         Ok(())
     }
 
+    #[instrument(skip_all)]
+    async fn list_users(&self) -> DbResult<Vec<User>> {
+        sqlx::query_as("select id, username, email, password, verified_at from users order by id")
+            .fetch(&self.pool)
+            .map_ok(|DbUser(user)| user)
+            .try_collect()
+            .await
+            .map_err(fix_error)
+    }
+
+    #[instrument(skip_all)]
+    async fn list_user_sessions(&self, u: &User) -> DbResult<Vec<Session>> {
+        sqlx::query_as("select id, user_id, token from sessions where user_id = $1 order by id")
+            .bind(u.id)
+            .fetch(&self.pool)
+            .map_ok(|DbSession(session)| session)
+            .try_collect()
+            .await
+            .map_err(fix_error)
+    }
+
     #[instrument(skip_all)]
     async fn add_user(&self, user: &NewUser) -> DbResult<i64> {
         let email: &str = &user.email;
@@ -672,6 +746,198 @@ impl Database for Postgres {
 
         Ok(status)
     }
+
+    #[instrument(skip_all)]
+    async fn create_team(&self, name: &str, owner: &User) -> DbResult<Team> {
+        let mut tx = self.pool.begin().await.map_err(fix_error)?;
+
+        let (id,): (i64,) = sqlx::query_as("insert into teams (name) values ($1) returning id")
+            .bind(name)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(fix_error)?;
+
+        sqlx::query("insert into team_members (team_id, user_id, admin) values ($1, $2, true)")
+            .bind(id)
+            .bind(owner.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(fix_error)?;
+
+        tx.commit().await.map_err(fix_error)?;
+
+        Ok(Team {
+            id,
+            name: name.to_string(),
+        })
+    }
+
+    #[instrument(skip_all)]
+    async fn get_team(&self, name: &str) -> DbResult<Team> {
+        sqlx::query_as("select id, name from teams where name = $1")
+            .bind(name)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(fix_error)
+            .map(|DbTeam(team)| team)
+    }
+
+    #[instrument(skip_all)]
+    async fn list_all_teams(&self) -> DbResult<Vec<Team>> {
+        sqlx::query_as("select id, name from teams order by id")
+            .fetch(&self.pool)
+            .map_ok(|DbTeam(team)| team)
+            .try_collect()
+            .await
+            .map_err(fix_error)
+    }
+
+    #[instrument(skip_all)]
+    async fn list_teams(&self, u: &User) -> DbResult<Vec<Team>> {
+        sqlx::query_as(
+            "select teams.id, teams.name from teams
+            inner join team_members on team_members.team_id = teams.id
+            where team_members.user_id = $1
+            order by teams.name",
+        )
+        .bind(u.id)
+        .fetch(&self.pool)
+        .map_ok(|DbTeam(team)| team)
+        .try_collect()
+        .await
+        .map_err(fix_error)
+    }
+
+    #[instrument(skip_all)]
+    async fn list_team_members(&self, team: &Team) -> DbResult<Vec<TeamMember>> {
+        sqlx::query_as(
+            "select team_members.user_id, users.username, team_members.admin from team_members
+            inner join users on users.id = team_members.user_id
+            where team_members.team_id = $1
+            order by users.username",
+        )
+        .bind(team.id)
+        .fetch(&self.pool)
+        .map_ok(|DbTeamMember(member)| member)
+        .try_collect()
+        .await
+        .map_err(fix_error)
+    }
+
+    #[instrument(skip_all)]
+    async fn add_team_member(&self, team: &Team, u: &User, admin: bool) -> DbResult<()> {
+        sqlx::query(
+            "insert into team_members (team_id, user_id, admin) values ($1, $2, $3)
+            on conflict(team_id, user_id) do update set admin = $3",
+        )
+        .bind(team.id)
+        .bind(u.id)
+        .bind(admin)
+        .execute(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn remove_team_member(&self, team: &Team, u: &User) -> DbResult<()> {
+        sqlx::query("delete from team_members where team_id = $1 and user_id = $2")
+            .bind(team.id)
+            .bind(u.id)
+            .execute(&self.pool)
+            .await
+            .map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn add_team_records(
+        &self,
+        team: &Team,
+        records: &[Record<EncryptedData>],
+    ) -> DbResult<()> {
+        let mut tx = self.pool.begin().await.map_err(fix_error)?;
+
+        for i in records {
+            let id = atuin_common::utils::uuid_v7();
+
+            sqlx::query(
+                "insert into team_store
+                    (id, client_id, host, idx, timestamp, version, tag, data, cek, team_id)
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                on conflict do nothing
+                ",
+            )
+            .bind(id)
+            .bind(i.id)
+            .bind(i.host.id)
+            .bind(i.idx as i64)
+            .bind(i.timestamp as i64)
+            .bind(&i.version)
+            .bind(&i.tag)
+            .bind(&i.data.data)
+            .bind(&i.data.content_encryption_key)
+            .bind(team.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(fix_error)?;
+        }
+
+        tx.commit().await.map_err(fix_error)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    async fn next_team_records(
+        &self,
+        team: &Team,
+        host: HostId,
+        tag: String,
+        start: Option<RecordIdx>,
+        count: u64,
+    ) -> DbResult<Vec<Record<EncryptedData>>> {
+        let records: Vec<DbRecord> = sqlx::query_as(
+            "select client_id, host, idx, timestamp, version, tag, data, cek from team_store
+                    where team_id = $1
+                    and tag = $2
+                    and host = $3
+                    and idx >= $4
+                    order by idx asc
+                    limit $5",
+        )
+        .bind(team.id)
+        .bind(tag)
+        .bind(host)
+        .bind(start.unwrap_or(0) as i64)
+        .bind(count as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        Ok(records.into_iter().map(Into::into).collect())
+    }
+
+    #[instrument(skip_all)]
+    async fn team_status(&self, team: &Team) -> DbResult<RecordStatus> {
+        let res: Vec<(Uuid, String, i64)> = sqlx::query_as(
+            "select host, tag, max(idx) from team_store where team_id = $1 group by host, tag",
+        )
+        .bind(team.id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(fix_error)?;
+
+        let mut status = RecordStatus::new();
+
+        for i in res {
+            status.set_raw(HostId(i.0), i.1, i.2 as u64);
+        }
+
+        Ok(status)
+    }
 }
 
 fn into_utc(x: OffsetDateTime) -> PrimitiveDateTime {
@@ -0,0 +1,32 @@
+-- Teams share a record store, encrypted with a key that only their members hold
+create table teams(
+  id integer primary key autoincrement,
+  name text not null unique,
+  created_at timestamp with time zone not null default current_timestamp
+);
+
+create table team_members(
+  team_id bigint not null references teams(id),
+  user_id bigint not null references users(id),
+  admin boolean not null default false,
+  created_at timestamp with time zone not null default current_timestamp,
+
+  primary key (team_id, user_id)
+);
+
+create table team_store(
+  id text primary key,
+  client_id text not null,
+  host text not null,
+  idx bigint not null,
+  timestamp bigint not null,
+  version text not null,
+  tag text not null,
+  data text not null,
+  cek text not null,
+
+  team_id bigint not null references teams(id),
+  created_at timestamp not null default current_timestamp
+);
+
+create unique index team_store_uniq on team_store(team_id, host, tag, idx);
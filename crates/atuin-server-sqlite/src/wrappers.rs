@@ -1,12 +1,14 @@
 use ::sqlx::{FromRow, Result};
 use atuin_common::record::{EncryptedData, Host, Record};
-use atuin_server_database::models::{History, Session, User};
+use atuin_server_database::models::{History, Session, Team, TeamMember, User};
 use sqlx::{Row, sqlite::SqliteRow};
 
 pub struct DbUser(pub User);
 pub struct DbSession(pub Session);
 pub struct DbHistory(pub History);
 pub struct DbRecord(pub Record<EncryptedData>);
+pub struct DbTeam(pub Team);
+pub struct DbTeamMember(pub TeamMember);
 
 impl<'a> FromRow<'a, SqliteRow> for DbUser {
     fn from_row(row: &'a SqliteRow) -> Result<Self> {
@@ -71,3 +73,22 @@ impl From<DbRecord> for Record<EncryptedData> {
         Record { ..other.0 }
     }
 }
+
+impl<'a> ::sqlx::FromRow<'a, SqliteRow> for DbTeam {
+    fn from_row(row: &'a SqliteRow) -> ::sqlx::Result<Self> {
+        Ok(Self(Team {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+        }))
+    }
+}
+
+impl<'a> ::sqlx::FromRow<'a, SqliteRow> for DbTeamMember {
+    fn from_row(row: &'a SqliteRow) -> ::sqlx::Result<Self> {
+        Ok(Self(TeamMember {
+            user_id: row.try_get("user_id")?,
+            username: row.try_get("username")?,
+            admin: row.try_get("admin")?,
+        }))
+    }
+}
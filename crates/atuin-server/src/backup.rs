@@ -0,0 +1,653 @@
+//! Database-engine independent backups of the server.
+//!
+//! A backup is a gzip compressed stream of newline delimited JSON entries. It starts with a
+//! header, then for every user contains their account and sessions, followed by all of their
+//! (still encrypted) records and a trailer holding the record count and a sha256 checksum of the
+//! record lines. Teams follow the users, each with its members and shared records in the same
+//! way. A footer with the totals closes the archive, so a truncated backup is detected.
+//!
+//! Only the record store is included. The legacy history table is not backed up.
+
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+
+use atuin_common::record::{EncryptedData, Record};
+use atuin_server_database::{
+    Database, DbError,
+    models::{NewSession, NewUser, Team, User},
+};
+use eyre::{Result, bail, eyre};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use tracing::{debug, info, instrument};
+
+/// Bumped whenever the format of an entry changes incompatibly
+pub const BACKUP_VERSION: u32 = 1;
+
+const PAGE_SIZE: u64 = 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    Header {
+        version: u32,
+        created_at: OffsetDateTime,
+    },
+    User {
+        username: String,
+        email: String,
+        /// The password hash, exactly as stored in the database
+        password: String,
+        verified: Option<OffsetDateTime>,
+        sessions: Vec<String>,
+        /// The client-side wrapped encryption key, if the user escrowed one
+        #[serde(default)]
+        key_escrow: Option<String>,
+    },
+    Record(Record<EncryptedData>),
+    UserEnd {
+        records: u64,
+        checksum: String,
+    },
+    Team {
+        name: String,
+        members: Vec<TeamMemberEntry>,
+    },
+    TeamEnd {
+        records: u64,
+        checksum: String,
+    },
+    Footer {
+        users: u64,
+        #[serde(default)]
+        teams: u64,
+        records: u64,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TeamMemberEntry {
+    username: String,
+    admin: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BackupSummary {
+    pub users: u64,
+    pub teams: u64,
+    pub records: u64,
+}
+
+impl fmt::Display for BackupSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} users, {} teams, {} records",
+            self.users, self.teams, self.records
+        )
+    }
+}
+
+fn write_entry(writer: &mut impl Write, entry: &Entry) -> Result<Vec<u8>> {
+    let line = serde_json::to_vec(entry)?;
+    writer.write_all(&line)?;
+    writer.write_all(b"\n")?;
+
+    Ok(line)
+}
+
+/// Whose record store to read or write
+#[derive(Clone, Copy)]
+enum Owner<'a> {
+    User(&'a User),
+    Team(&'a Team),
+}
+
+/// Write every record in a store, returning the count and checksum of the record lines
+async fn write_records<Db: Database>(
+    db: &Db,
+    writer: &mut impl Write,
+    owner: Owner<'_>,
+) -> Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let mut count = 0;
+
+    let status = match owner {
+        Owner::User(user) => db.status(user).await,
+        Owner::Team(team) => db.team_status(team).await,
+    }
+    .map_err(|e| eyre!("{e}"))?;
+
+    for (host, tags) in status.hosts {
+        for tag in tags.into_keys() {
+            let mut start = None;
+
+            loop {
+                let page = match owner {
+                    Owner::User(user) => {
+                        db.next_records(user, host, tag.clone(), start, PAGE_SIZE)
+                            .await
+                    }
+                    Owner::Team(team) => {
+                        db.next_team_records(team, host, tag.clone(), start, PAGE_SIZE)
+                            .await
+                    }
+                }
+                .map_err(|e| eyre!("{e}"))?;
+
+                let Some(last) = page.last() else {
+                    break;
+                };
+                start = Some(last.idx + 1);
+
+                let full = page.len() as u64 == PAGE_SIZE;
+
+                for record in page {
+                    let line = write_entry(writer, &Entry::Record(record))?;
+                    hasher.update(&line);
+                    count += 1;
+                }
+
+                if !full {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok((count, format!("{:x}", hasher.finalize())))
+}
+
+/// Write a backup of every user, their sessions and their record stores, and every team and its
+/// shared store, to `writer`
+#[instrument(skip_all)]
+pub async fn backup<Db: Database>(db: &Db, writer: impl Write) -> Result<BackupSummary> {
+    let mut writer = GzEncoder::new(writer, Compression::default());
+    let mut summary = BackupSummary::default();
+
+    write_entry(
+        &mut writer,
+        &Entry::Header {
+            version: BACKUP_VERSION,
+            created_at: OffsetDateTime::now_utc(),
+        },
+    )?;
+
+    for user in db.list_users().await.map_err(|e| eyre!("{e}"))? {
+        let sessions = db
+            .list_user_sessions(&user)
+            .await
+            .map_err(|e| eyre!("{e}"))?
+            .into_iter()
+            .map(|s| s.token)
+            .collect();
+
+        let key_escrow = match db.get_key_escrow(&user).await {
+            Ok(escrow) => Some(escrow),
+            Err(DbError::NotFound) => None,
+            Err(e) => return Err(eyre!("{e}")),
+        };
+
+        write_entry(
+            &mut writer,
+            &Entry::User {
+                username: user.username.clone(),
+                email: user.email.clone(),
+                password: user.password.clone(),
+                verified: user.verified,
+                sessions,
+                key_escrow,
+            },
+        )?;
+
+        let (records, checksum) = write_records(db, &mut writer, Owner::User(&user)).await?;
+
+        debug!(user = user.username, records, "backed up user");
+
+        write_entry(&mut writer, &Entry::UserEnd { records, checksum })?;
+
+        summary.users += 1;
+        summary.records += records;
+    }
+
+    for team in db.list_all_teams().await.map_err(|e| eyre!("{e}"))? {
+        let members = db
+            .list_team_members(&team)
+            .await
+            .map_err(|e| eyre!("{e}"))?
+            .into_iter()
+            .map(|m| TeamMemberEntry {
+                username: m.username,
+                admin: m.admin,
+            })
+            .collect();
+
+        write_entry(
+            &mut writer,
+            &Entry::Team {
+                name: team.name.clone(),
+                members,
+            },
+        )?;
+
+        let (records, checksum) = write_records(db, &mut writer, Owner::Team(&team)).await?;
+
+        debug!(team = team.name, records, "backed up team");
+
+        write_entry(&mut writer, &Entry::TeamEnd { records, checksum })?;
+
+        summary.teams += 1;
+        summary.records += records;
+    }
+
+    write_entry(
+        &mut writer,
+        &Entry::Footer {
+            users: summary.users,
+            teams: summary.teams,
+            records: summary.records,
+        },
+    )?;
+
+    writer.finish()?.flush()?;
+
+    info!(%summary, "backup complete");
+
+    Ok(summary)
+}
+
+/// Reads entries from a backup, checking the structure and checksums as it goes.
+///
+/// A `UserEnd` or `TeamEnd` entry is only returned once the records before it have been
+/// verified, and `Footer` only once the totals match.
+struct BackupReader<R> {
+    lines: std::io::Lines<BufReader<GzDecoder<R>>>,
+    line: usize,
+    open: Option<Group>,
+    hasher: Sha256,
+    group_records: u64,
+    summary: BackupSummary,
+    finished: bool,
+}
+
+impl<R: Read> BackupReader<R> {
+    fn new(reader: R) -> Result<Self> {
+        let mut reader = Self {
+            lines: BufReader::new(GzDecoder::new(reader)).lines(),
+            line: 0,
+            open: None,
+            hasher: Sha256::new(),
+            group_records: 0,
+            summary: BackupSummary::default(),
+            finished: false,
+        };
+
+        match reader.read_line()? {
+            Some((Entry::Header { version, .. }, _)) if version == BACKUP_VERSION => Ok(reader),
+            Some((Entry::Header { version, .. }, _)) => {
+                bail!("unsupported backup version {version}, expected {BACKUP_VERSION}")
+            }
+            _ => bail!("not an atuin server backup: missing header"),
+        }
+    }
+
+    fn read_line(&mut self) -> Result<Option<(Entry, String)>> {
+        let Some(line) = self.lines.next() else {
+            return Ok(None);
+        };
+        self.line += 1;
+
+        let line = line?;
+        let entry = serde_json::from_str(&line)
+            .map_err(|e| eyre!("invalid entry on line {}: {e}", self.line))?;
+
+        Ok(Some((entry, line)))
+    }
+
+    fn next_entry(&mut self) -> Result<Option<Entry>> {
+        let Some((entry, line)) = self.read_line()? else {
+            if self.finished {
+                return Ok(None);
+            }
+
+            bail!("backup is truncated: missing footer");
+        };
+
+        if self.finished {
+            bail!("unexpected data after footer on line {}", self.line);
+        }
+
+        match &entry {
+            Entry::Header { .. } => bail!("unexpected header on line {}", self.line),
+            Entry::User { .. } => self.open(Group::User)?,
+            Entry::Team { .. } => self.open(Group::Team)?,
+            Entry::Record(_) => {
+                if self.open.is_none() {
+                    bail!(
+                        "record on line {} does not belong to a user or team",
+                        self.line
+                    );
+                }
+
+                self.hasher.update(line.as_bytes());
+                self.group_records += 1;
+            }
+            Entry::UserEnd { records, checksum } => {
+                self.close(Group::User, *records, checksum)?;
+                self.summary.users += 1;
+            }
+            Entry::TeamEnd { records, checksum } => {
+                self.close(Group::Team, *records, checksum)?;
+                self.summary.teams += 1;
+            }
+            Entry::Footer {
+                users,
+                teams,
+                records,
+            } => {
+                if let Some(group) = self.open {
+                    bail!("backup is truncated: footer inside a {group}");
+                }
+
+                let expected = BackupSummary {
+                    users: *users,
+                    teams: *teams,
+                    records: *records,
+                };
+
+                if expected != self.summary {
+                    bail!(
+                        "integrity check failed: footer lists {expected}, backup contains {}",
+                        self.summary
+                    );
+                }
+
+                self.finished = true;
+            }
+        }
+
+        Ok(Some(entry))
+    }
+
+    fn open(&mut self, group: Group) -> Result<()> {
+        if let Some(open) = self.open {
+            bail!(
+                "{group} on line {} started before the previous {open} ended",
+                self.line
+            );
+        }
+
+        self.open = Some(group);
+        self.hasher = Sha256::new();
+        self.group_records = 0;
+
+        Ok(())
+    }
+
+    fn close(&mut self, group: Group, records: u64, checksum: &str) -> Result<()> {
+        if self.open != Some(group) {
+            bail!("unexpected {group} end on line {}", self.line);
+        }
+
+        let actual = format!("{:x}", std::mem::take(&mut self.hasher).finalize());
+
+        if records != self.group_records || checksum != actual {
+            bail!(
+                "integrity check failed on line {}: expected {records} records with checksum {checksum}, found {} with checksum {actual}",
+                self.line,
+                self.group_records
+            );
+        }
+
+        self.open = None;
+        self.summary.records += self.group_records;
+
+        Ok(())
+    }
+}
+
+/// The kind of entry that records in a backup belong to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Group {
+    User,
+    Team,
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Group::User => f.write_str("user"),
+            Group::Team => f.write_str("team"),
+        }
+    }
+}
+
+/// Check the integrity of a backup without touching a database
+pub fn verify(reader: impl Read) -> Result<BackupSummary> {
+    let mut reader = BackupReader::new(reader)?;
+    while reader.next_entry()?.is_some() {}
+
+    Ok(reader.summary)
+}
+
+async fn restore_user<Db: Database>(
+    db: &Db,
+    username: String,
+    email: String,
+    password: String,
+    verified: bool,
+    merge: bool,
+) -> Result<User> {
+    match db.get_user(&username).await {
+        Ok(_) if !merge => {
+            return Err(eyre!(
+                "user {username} already exists, restore with merging enabled to add the backup's records to it"
+            ));
+        }
+        Ok(user) => {
+            info!(user = username, "user already exists, merging records");
+            return Ok(user);
+        }
+        Err(DbError::NotFound) => {}
+        Err(e) => return Err(eyre!("{e}")),
+    }
+
+    let id = db
+        .add_user(&NewUser {
+            username: username.clone(),
+            email,
+            password,
+        })
+        .await
+        .map_err(|e| eyre!("failed to restore user {username}: {e}"))?;
+
+    if verified {
+        db.verify_user(id).await.map_err(|e| eyre!("{e}"))?;
+    }
+
+    db.get_user(&username).await.map_err(|e| eyre!("{e}"))
+}
+
+async fn restore_team<Db: Database>(
+    db: &Db,
+    name: String,
+    members: Vec<TeamMemberEntry>,
+    merge: bool,
+) -> Result<Team> {
+    let mut users = Vec::with_capacity(members.len());
+    for member in members {
+        // every member was backed up as a user before the team
+        let user = db.get_user(&member.username).await.map_err(|e| {
+            eyre!(
+                "failed to find member {} of team {name}: {e}",
+                member.username
+            )
+        })?;
+        users.push((user, member.admin));
+    }
+
+    let team = match db.get_team(&name).await {
+        Ok(_) if !merge => {
+            return Err(eyre!(
+                "team {name} already exists, restore with merging enabled to add the backup's records to it"
+            ));
+        }
+        Ok(team) => {
+            info!(team = name, "team already exists, merging records");
+            team
+        }
+        Err(DbError::NotFound) => {
+            let owner = users
+                .first()
+                .ok_or_else(|| eyre!("team {name} has no members"))?;
+
+            db.create_team(&name, &owner.0)
+                .await
+                .map_err(|e| eyre!("failed to restore team {name}: {e}"))?
+        }
+        Err(e) => return Err(eyre!("{e}")),
+    };
+
+    for (user, admin) in users {
+        db.add_team_member(&team, &user, admin)
+            .await
+            .map_err(|e| eyre!("{e}"))?;
+    }
+
+    Ok(team)
+}
+
+/// Where restored records are written
+enum Target {
+    User(User),
+    Team(Team),
+}
+
+async fn add_records<Db: Database>(
+    db: &Db,
+    target: Option<&Target>,
+    batch: &mut Vec<Record<EncryptedData>>,
+) -> Result<()> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    match target {
+        Some(Target::User(user)) => db.add_records(user, batch).await,
+        Some(Target::Team(team)) => db.add_team_records(team, batch).await,
+        None => return Err(eyre!("record without a user or team")),
+    }
+    .map_err(|e| eyre!("{e}"))?;
+
+    batch.clear();
+
+    Ok(())
+}
+
+/// The users and teams in a backup that already exist in `db`
+pub async fn existing<Db: Database>(db: &Db, reader: impl Read) -> Result<Vec<String>> {
+    let mut reader = BackupReader::new(reader)?;
+    let mut existing = Vec::new();
+
+    while let Some(entry) = reader.next_entry()? {
+        let (kind, found) = match entry {
+            Entry::User { username, .. } => match db.get_user(&username).await {
+                Ok(_) => ("user", username),
+                Err(DbError::NotFound) => continue,
+                Err(e) => return Err(eyre!("{e}")),
+            },
+            Entry::Team { name, .. } => match db.get_team(&name).await {
+                Ok(_) => ("team", name),
+                Err(DbError::NotFound) => continue,
+                Err(e) => return Err(eyre!("{e}")),
+            },
+            _ => continue,
+        };
+
+        existing.push(format!("{kind} {found}"));
+    }
+
+    Ok(existing)
+}
+
+/// Restore a backup into `db`.
+///
+/// Users and teams that already exist are an error, unless `merge` is set, when they're matched by
+/// name and have the records from the backup merged into their store. Records are written as they
+/// are read, so a damaged backup can leave a partial restore behind - run [`verify`] and
+/// [`existing`] over the backup first.
+#[instrument(skip_all)]
+pub async fn restore<Db: Database>(
+    db: &Db,
+    reader: impl Read,
+    merge: bool,
+) -> Result<BackupSummary> {
+    let mut reader = BackupReader::new(reader)?;
+    let mut target = None;
+    let mut batch = Vec::new();
+
+    while let Some(entry) = reader.next_entry()? {
+        match entry {
+            Entry::User {
+                username,
+                email,
+                password,
+                verified,
+                sessions,
+                key_escrow,
+            } => {
+                let restored =
+                    restore_user(db, username, email, password, verified.is_some(), merge).await?;
+
+                for token in sessions {
+                    match db.get_session(&token).await {
+                        Ok(_) => continue,
+                        Err(DbError::NotFound) => {}
+                        Err(e) => return Err(eyre!("{e}")),
+                    }
+
+                    db.add_session(&NewSession {
+                        user_id: restored.id,
+                        token,
+                    })
+                    .await
+                    .map_err(|e| eyre!("{e}"))?;
+                }
+
+                if let Some(escrow) = key_escrow {
+                    db.set_key_escrow(&restored, &escrow)
+                        .await
+                        .map_err(|e| eyre!("{e}"))?;
+                }
+
+                target = Some(Target::User(restored));
+            }
+            Entry::Team { name, members } => {
+                target = Some(Target::Team(restore_team(db, name, members, merge).await?));
+            }
+            Entry::Record(record) => {
+                batch.push(record);
+
+                if batch.len() as u64 >= PAGE_SIZE {
+                    add_records(db, target.as_ref(), &mut batch).await?;
+                }
+            }
+            Entry::UserEnd { .. } | Entry::TeamEnd { .. } => {
+                add_records(db, target.as_ref(), &mut batch).await?;
+
+                match target.take() {
+                    Some(Target::User(user)) => debug!(user = user.username, "restored user"),
+                    Some(Target::Team(team)) => debug!(team = team.name, "restored team"),
+                    None => return Err(eyre!("end without a user or team")),
+                }
+            }
+            Entry::Header { .. } | Entry::Footer { .. } => {}
+        }
+    }
+
+    info!(summary = %reader.summary, "restore complete");
+
+    Ok(reader.summary)
+}
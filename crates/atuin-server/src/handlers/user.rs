@@ -166,6 +166,10 @@ pub async fn delete<DB: Database>(
     debug!("request to delete user {}", user.id);
 
     let db = &state.0.database;
+
+    // teams need an admin to manage them once the user is gone
+    super::v0::team::check_can_leave_teams(db, &user).await?;
+
     if let Err(e) = db.delete_user(&user).await {
         error!("failed to delete user: {}", e);
 
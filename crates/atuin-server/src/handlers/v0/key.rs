@@ -0,0 +1,70 @@
+use axum::{Json, extract::State, http::StatusCode};
+use metrics::counter;
+use tracing::{error, instrument};
+
+use crate::{
+    handlers::{ErrorResponse, ErrorResponseStatus, RespExt},
+    router::{AppState, UserAuth},
+};
+use atuin_server_database::{Database, DbError};
+
+use atuin_common::api::*;
+
+// A wrapped key is a few hundred bytes. Anything much larger is not something we should store.
+const MAX_ESCROW_SIZE: usize = 16 * 1024;
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn get<DB: Database>(
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+) -> Result<Json<KeyEscrowResponse>, ErrorResponseStatus<'static>> {
+    match state.database.get_key_escrow(&user).await {
+        Ok(escrow) => Ok(Json(KeyEscrowResponse { escrow })),
+        Err(DbError::NotFound) => Err(ErrorResponse::reply("no key escrowed for this account")
+            .with_status(StatusCode::NOT_FOUND)),
+        Err(e) => {
+            error!("failed to get key escrow: {e}");
+            Err(ErrorResponse::reply("failed to get key escrow")
+                .with_status(StatusCode::INTERNAL_SERVER_ERROR))
+        }
+    }
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn put<DB: Database>(
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+    Json(req): Json<KeyEscrowRequest>,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    if req.escrow.is_empty() || req.escrow.len() > MAX_ESCROW_SIZE {
+        return Err(
+            ErrorResponse::reply("invalid key escrow size").with_status(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    if let Err(e) = state.database.set_key_escrow(&user, &req.escrow).await {
+        error!("failed to set key escrow: {e}");
+        return Err(ErrorResponse::reply("failed to set key escrow")
+            .with_status(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    counter!("atuin_key_escrow_set").increment(1);
+
+    Ok(())
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn delete<DB: Database>(
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    if let Err(e) = state.database.delete_key_escrow(&user).await {
+        error!("failed to delete key escrow: {e}");
+        return Err(ErrorResponse::reply("failed to delete key escrow")
+            .with_status(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    counter!("atuin_key_escrow_deleted").increment(1);
+
+    Ok(())
+}
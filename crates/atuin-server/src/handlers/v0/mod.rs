@@ -1,3 +1,5 @@
+pub(crate) mod key;
 pub(crate) mod me;
 pub(crate) mod record;
 pub(crate) mod store;
+pub(crate) mod team;
@@ -3,6 +3,7 @@ use metrics::counter;
 use serde::Deserialize;
 use tracing::{error, instrument};
 
+use super::team::member_team;
 use crate::{
     handlers::{ErrorResponse, ErrorResponseStatus, RespExt},
     router::{AppState, UserAuth},
@@ -11,8 +12,15 @@ use atuin_server_database::Database;
 
 use atuin_common::record::{EncryptedData, HostId, Record, RecordIdx, RecordStatus};
 
+/// Which store a request is for. Without a team, it is the user's own store.
+#[derive(Deserialize)]
+pub struct StoreParams {
+    team: Option<String>,
+}
+
 #[instrument(skip_all, fields(user.id = user.id))]
 pub async fn post<DB: Database>(
+    Query(StoreParams { team }): Query<StoreParams>,
     UserAuth(user): UserAuth,
     state: State<AppState<DB>>,
     Json(records): Json<Vec<Record<EncryptedData>>>,
@@ -40,7 +48,15 @@ pub async fn post<DB: Database>(
         );
     }
 
-    if let Err(e) = database.add_records(&user, &records).await {
+    let res = match team {
+        Some(team) => {
+            let (team, _) = member_team(&database, &user, &team).await?;
+            database.add_team_records(&team, &records).await
+        }
+        None => database.add_records(&user, &records).await,
+    };
+
+    if let Err(e) = res {
         error!("failed to add record: {}", e);
 
         return Err(ErrorResponse::reply("failed to add record")
@@ -52,6 +68,7 @@ pub async fn post<DB: Database>(
 
 #[instrument(skip_all, fields(user.id = user.id))]
 pub async fn index<DB: Database>(
+    Query(StoreParams { team }): Query<StoreParams>,
     UserAuth(user): UserAuth,
     state: State<AppState<DB>>,
 ) -> Result<Json<RecordStatus>, ErrorResponseStatus<'static>> {
@@ -60,7 +77,15 @@ pub async fn index<DB: Database>(
         settings: _,
     }) = state;
 
-    let record_index = match database.status(&user).await {
+    let res = match team {
+        Some(team) => {
+            let (team, _) = member_team(&database, &user, &team).await?;
+            database.team_status(&team).await
+        }
+        None => database.status(&user).await,
+    };
+
+    let record_index = match res {
         Ok(index) => index,
         Err(e) => {
             error!("failed to get record index: {}", e);
@@ -81,6 +106,7 @@ pub struct NextParams {
     tag: String,
     start: Option<RecordIdx>,
     count: u64,
+    team: Option<String>,
 }
 
 #[instrument(skip_all, fields(user.id = user.id))]
@@ -95,10 +121,21 @@ pub async fn next<DB: Database>(
     }) = state;
     let params = params.0;
 
-    let records = match database
-        .next_records(&user, params.host, params.tag, params.start, params.count)
-        .await
-    {
+    let res = match params.team {
+        Some(team) => {
+            let (team, _) = member_team(&database, &user, &team).await?;
+            database
+                .next_team_records(&team, params.host, params.tag, params.start, params.count)
+                .await
+        }
+        None => {
+            database
+                .next_records(&user, params.host, params.tag, params.start, params.count)
+                .await
+        }
+    };
+
+    let records = match res {
         Ok(records) => records,
         Err(e) => {
             error!("failed to get record index: {}", e);
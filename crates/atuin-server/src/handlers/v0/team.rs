@@ -0,0 +1,261 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use metrics::counter;
+use tracing::{debug, error, instrument};
+
+use crate::{
+    handlers::{ErrorResponse, ErrorResponseStatus, RespExt},
+    router::{AppState, UserAuth},
+};
+use atuin_server_database::{
+    Database, DbError,
+    models::{Team, TeamMember, User},
+};
+
+use atuin_common::api::*;
+
+const MAX_TEAM_NAME_LENGTH: usize = 32;
+
+fn database_error(context: &'static str, e: DbError) -> ErrorResponseStatus<'static> {
+    error!("{context}: {e}");
+
+    ErrorResponse::reply(context).with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Look up a team that `user` is a member of. Teams the user is not a member of are reported as
+/// not found, so that their existence is not leaked.
+pub(crate) async fn member_team<DB: Database>(
+    database: &DB,
+    user: &User,
+    name: &str,
+) -> Result<(Team, TeamMember), ErrorResponseStatus<'static>> {
+    let not_found = || ErrorResponse::reply("team not found").with_status(StatusCode::NOT_FOUND);
+
+    let team = match database.get_team(name).await {
+        Ok(team) => team,
+        Err(DbError::NotFound) => return Err(not_found()),
+        Err(e) => return Err(database_error("failed to get team", e)),
+    };
+
+    let member = database
+        .list_team_members(&team)
+        .await
+        .map_err(|e| database_error("failed to get team members", e))?
+        .into_iter()
+        .find(|m| m.user_id == user.id);
+
+    match member {
+        Some(member) => Ok((team, member)),
+        None => {
+            debug!(
+                user = user.username,
+                team = name,
+                "user is not a team member"
+            );
+            Err(not_found())
+        }
+    }
+}
+
+/// Refuse to let `user` leave `team` if they're its last admin, and other members would be left
+/// with nobody to manage it
+async fn check_not_last_admin<DB: Database>(
+    database: &DB,
+    team: &Team,
+    user: &User,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    let members = database
+        .list_team_members(team)
+        .await
+        .map_err(|e| database_error("failed to get team members", e))?;
+
+    let admin = members.iter().any(|m| m.user_id == user.id && m.admin);
+    let mut others = members.iter().filter(|m| m.user_id != user.id).peekable();
+    let orphaned = others.peek().is_some() && !others.any(|m| m.admin);
+
+    if admin && orphaned {
+        return Err(ErrorResponse {
+            reason: format!(
+                "{} is the last admin of team {}. Make another member an admin first",
+                user.username, team.name
+            )
+            .into(),
+        }
+        .with_status(StatusCode::CONFLICT));
+    }
+
+    Ok(())
+}
+
+/// Refuse to delete `user` while they're the last admin of any of their teams
+pub(crate) async fn check_can_leave_teams<DB: Database>(
+    database: &DB,
+    user: &User,
+) -> Result<(), ErrorResponseStatus<'static>> {
+    let teams = database
+        .list_teams(user)
+        .await
+        .map_err(|e| database_error("failed to list teams", e))?;
+
+    for team in teams {
+        check_not_last_admin(database, &team, user).await?;
+    }
+
+    Ok(())
+}
+
+async fn team_response<DB: Database>(
+    database: &DB,
+    team: Team,
+) -> Result<TeamResponse, ErrorResponseStatus<'static>> {
+    let members = database
+        .list_team_members(&team)
+        .await
+        .map_err(|e| database_error("failed to get team members", e))?
+        .into_iter()
+        .map(|m| TeamMemberResponse {
+            username: m.username,
+            admin: m.admin,
+        })
+        .collect();
+
+    Ok(TeamResponse {
+        name: team.name,
+        members,
+    })
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn list<DB: Database>(
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+) -> Result<Json<ListTeamsResponse>, ErrorResponseStatus<'static>> {
+    let database = &state.database;
+
+    let teams = database
+        .list_teams(&user)
+        .await
+        .map_err(|e| database_error("failed to list teams", e))?;
+
+    let mut resp = Vec::with_capacity(teams.len());
+    for team in teams {
+        resp.push(team_response(database, team).await?);
+    }
+
+    Ok(Json(ListTeamsResponse { teams: resp }))
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn create<DB: Database>(
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+    Json(req): Json<CreateTeamRequest>,
+) -> Result<Json<TeamResponse>, ErrorResponseStatus<'static>> {
+    let database = &state.database;
+
+    let valid = !req.name.is_empty()
+        && req.name.len() <= MAX_TEAM_NAME_LENGTH
+        && req
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+    if !valid {
+        return Err(ErrorResponse::reply(
+            "Team names must be at most 32 characters, and only contain alphanumerics and hyphens (-)",
+        )
+        .with_status(StatusCode::BAD_REQUEST));
+    }
+
+    match database.get_team(&req.name).await {
+        Ok(_) => {
+            return Err(ErrorResponse::reply("a team with that name already exists")
+                .with_status(StatusCode::CONFLICT));
+        }
+        Err(DbError::NotFound) => {}
+        Err(e) => return Err(database_error("failed to get team", e)),
+    }
+
+    let team = database
+        .create_team(&req.name, &user)
+        .await
+        .map_err(|e| database_error("failed to create team", e))?;
+
+    counter!("atuin_team_created").increment(1);
+
+    Ok(Json(team_response(database, team).await?))
+}
+
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn add_member<DB: Database>(
+    Path(name): Path<String>,
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+    Json(req): Json<AddTeamMemberRequest>,
+) -> Result<Json<TeamResponse>, ErrorResponseStatus<'static>> {
+    let database = &state.database;
+    let (team, member) = member_team(database, &user, &name).await?;
+
+    if !member.admin {
+        return Err(ErrorResponse::reply("only team admins can add members")
+            .with_status(StatusCode::FORBIDDEN));
+    }
+
+    // adding an existing member again changes whether they're an admin
+    if req.username == user.username && !req.admin {
+        check_not_last_admin(database, &team, &user).await?;
+    }
+
+    let new_member = match database.get_user(&req.username).await {
+        Ok(u) => u,
+        Err(DbError::NotFound) => {
+            return Err(ErrorResponse::reply("user not found").with_status(StatusCode::NOT_FOUND));
+        }
+        Err(e) => return Err(database_error("failed to get user", e)),
+    };
+
+    database
+        .add_team_member(&team, &new_member, req.admin)
+        .await
+        .map_err(|e| database_error("failed to add team member", e))?;
+
+    Ok(Json(team_response(database, team).await?))
+}
+
+/// Remove a member from a team. Admins can remove anyone, and anyone can remove themselves.
+#[instrument(skip_all, fields(user.id = user.id))]
+pub async fn remove_member<DB: Database>(
+    Path((name, username)): Path<(String, String)>,
+    UserAuth(user): UserAuth,
+    state: State<AppState<DB>>,
+) -> Result<Json<TeamResponse>, ErrorResponseStatus<'static>> {
+    let database = &state.database;
+    let (team, member) = member_team(database, &user, &name).await?;
+
+    if !member.admin && username != user.username {
+        return Err(
+            ErrorResponse::reply("only team admins can remove other members")
+                .with_status(StatusCode::FORBIDDEN),
+        );
+    }
+
+    let removed = match database.get_user(&username).await {
+        Ok(u) => u,
+        Err(DbError::NotFound) => {
+            return Err(ErrorResponse::reply("user not found").with_status(StatusCode::NOT_FOUND));
+        }
+        Err(e) => return Err(database_error("failed to get user", e)),
+    };
+
+    check_not_last_admin(database, &team, &removed).await?;
+
+    database
+        .remove_team_member(&team, &removed)
+        .await
+        .map_err(|e| database_error("failed to remove team member", e))?;
+
+    Ok(Json(team_response(database, team).await?))
+}
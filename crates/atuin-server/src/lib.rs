@@ -17,6 +17,7 @@ mod utils;
 pub use settings::Settings;
 pub use settings::example_config;
 
+pub mod backup;
 pub mod settings;
 
 use tokio::net::TcpListener;
@@ -6,7 +6,7 @@ use axum::{
     http::{self, request::Parts},
     middleware::Next,
     response::{IntoResponse, Response},
-    routing::{delete, get, patch, post},
+    routing::{delete, get, patch, post, put},
 };
 use eyre::Result;
 use tower::ServiceBuilder;
@@ -135,7 +135,20 @@ pub fn router<DB: Database>(database: DB, settings: Settings) -> Router {
         .route("/api/v0/record", post(handlers::v0::record::post))
         .route("/api/v0/record", get(handlers::v0::record::index))
         .route("/api/v0/record/next", get(handlers::v0::record::next))
-        .route("/api/v0/store", delete(handlers::v0::store::delete));
+        .route("/api/v0/store", delete(handlers::v0::store::delete))
+        .route("/api/v0/key", get(handlers::v0::key::get))
+        .route("/api/v0/key", put(handlers::v0::key::put))
+        .route("/api/v0/key", delete(handlers::v0::key::delete))
+        .route("/api/v0/team", get(handlers::v0::team::list))
+        .route("/api/v0/team", post(handlers::v0::team::create))
+        .route(
+            "/api/v0/team/:name/member",
+            post(handlers::v0::team::add_member),
+        )
+        .route(
+            "/api/v0/team/:name/member/:username",
+            delete(handlers::v0::team::remove_member),
+        );
 
     let path = settings.path.as_str();
     if path.is_empty() {
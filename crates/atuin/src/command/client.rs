@@ -14,10 +14,14 @@ mod sync;
 #[cfg(feature = "sync")]
 mod account;
 
+#[cfg(feature = "sync")]
+mod team;
+
 #[cfg(feature = "daemon")]
 mod daemon;
 
 mod default_config;
+mod dir;
 mod doctor;
 mod dotfiles;
 mod history;
@@ -25,6 +29,8 @@ mod import;
 mod info;
 mod init;
 mod kv;
+mod plugins;
+mod report;
 mod scripts;
 mod search;
 mod stats;
@@ -48,6 +54,14 @@ pub enum Cmd {
     /// Interactive history search
     Search(search::Cmd),
 
+    /// Jump to the directories you use most, ranked by frecency. The shell integrations define
+    /// `z` and `zi` with it, unless they already exist. In nushell, they always replace them.
+    Dir(dir::Cmd),
+
+    /// Report on where your time goes
+    #[command(subcommand)]
+    Report(report::Cmd),
+
     #[cfg(feature = "sync")]
     #[command(flatten)]
     Sync(sync::Cmd),
@@ -56,8 +70,12 @@ pub enum Cmd {
     #[cfg(feature = "sync")]
     Account(account::Cmd),
 
-    /// Get or set small key-value pairs
+    /// Manage teams, which share kv pairs and scripts through the sync server
+    #[cfg(feature = "sync")]
     #[command(subcommand)]
+    Team(team::Cmd),
+
+    /// Get or set small key-value pairs
     Kv(kv::Cmd),
 
     /// Manage the atuin data store
@@ -69,9 +87,12 @@ pub enum Cmd {
     Dotfiles(dotfiles::Cmd),
 
     /// Manage your scripts with Atuin
-    #[command(subcommand)]
     Scripts(scripts::Cmd),
 
+    /// Manage the plugins that provide `atuin <name>` subcommands
+    #[command(subcommand)]
+    Plugins(plugins::Cmd),
+
     /// Print Atuin's shell init script
     #[command()]
     Init(init::Cmd),
@@ -84,8 +105,8 @@ pub enum Cmd {
     #[command()]
     Doctor,
 
-    #[command()]
-    Wrapped { year: Option<i32> },
+    /// Your year, month or any period in shell history, with how it compares to the one before
+    Wrapped(wrapped::Cmd),
 
     /// *Experimental* Start the background daemon
     #[cfg(feature = "daemon")]
@@ -135,6 +156,7 @@ impl Cmd {
             Self::History(history) => return history.run(&settings).await,
             Self::Init(init) => return init.run(&settings).await,
             Self::Doctor => return doctor::run(&settings).await,
+            Self::Plugins(plugins) => return plugins.run(),
             _ => {}
         }
 
@@ -151,6 +173,8 @@ impl Cmd {
             Self::Import(import) => import.run(&db).await,
             Self::Stats(stats) => stats.run(&db, &settings, theme).await,
             Self::Search(search) => search.run(db, &mut settings, sqlite_store, theme).await,
+            Self::Dir(dir) => dir.run(&db, &settings, theme).await,
+            Self::Report(report) => report.run(&db, &settings).await,
 
             #[cfg(feature = "sync")]
             Self::Sync(sync) => sync.run(settings, &db, sqlite_store).await,
@@ -158,6 +182,9 @@ impl Cmd {
             #[cfg(feature = "sync")]
             Self::Account(account) => account.run(settings, sqlite_store).await,
 
+            #[cfg(feature = "sync")]
+            Self::Team(team) => team.run(&settings).await,
+
             Self::Kv(kv) => kv.run(&settings, &sqlite_store).await,
 
             Self::Store(store) => store.run(&settings, &db, sqlite_store).await,
@@ -176,12 +203,12 @@ impl Cmd {
                 Ok(())
             }
 
-            Self::Wrapped { year } => wrapped::run(year, &db, &settings, theme).await,
+            Self::Wrapped(wrapped) => wrapped.run(&db, &settings, theme).await,
 
             #[cfg(feature = "daemon")]
             Self::Daemon => daemon::run(settings, sqlite_store, db).await,
 
-            Self::History(_) | Self::Init(_) | Self::Doctor => unreachable!(),
+            Self::History(_) | Self::Init(_) | Self::Doctor | Self::Plugins(_) => unreachable!(),
         }
     }
 }
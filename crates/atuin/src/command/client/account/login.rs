@@ -6,7 +6,8 @@ use tokio::{fs::File, io::AsyncWriteExt};
 
 use atuin_client::{
     api_client,
-    encryption::{Key, decode_key, encode_key, load_key},
+    encryption::{Key, decode_key, encode_key},
+    record::keyring::Keyring,
     record::sqlite_store::SqliteStore,
     record::store::Store,
     settings::Settings,
@@ -14,6 +15,8 @@ use atuin_client::{
 use atuin_common::api::LoginRequest;
 use rpassword::prompt_password;
 
+use crate::command::client::sync::key;
+
 #[derive(Parser, Debug)]
 pub struct Cmd {
     #[clap(long, short)]
@@ -25,6 +28,11 @@ pub struct Cmd {
     /// The encryption key for your account
     #[clap(long, short)]
     pub key: Option<String>,
+
+    /// Read the passphrase or recovery code for a key escrowed with 'atuin key escrow' from the
+    /// first line of stdin. Otherwise it's read from `$ATUIN_KEY_PASSPHRASE`, or prompted for
+    #[clap(long)]
+    pub passphrase_stdin: bool,
 }
 
 fn get_input() -> Result<String> {
@@ -63,7 +71,7 @@ impl Cmd {
 
         let key = or_user_input(
             self.key.clone(),
-            "encryption key [blank to use existing key file]",
+            "encryption key [blank to use existing key file or escrowed key]",
         );
 
         // if provided, the key may be EITHER base64, or a bip mnemonic
@@ -99,26 +107,26 @@ impl Cmd {
             }
         };
 
+        // No key, and no key file. We can only continue if a key was escrowed on the server,
+        // which we need to be logged in to fetch.
+        let recover_escrow = key.is_empty() && !key_path.exists();
+
         // I've simplified this a little, but it could really do with a refactor
         // Annoyingly, it's also very important to get it correct
-        if key.is_empty() {
-            if key_path.exists() {
-                let bytes = fs_err::read_to_string(key_path)
-                    .context("existing key file couldn't be read")?;
-                if decode_key(bytes).is_err() {
-                    bail!("the key in existing key file was invalid");
-                }
-            } else {
-                panic!(
-                    "No key provided. Please use 'atuin key' on your other machine, or recover your key from a backup."
-                )
+        if recover_escrow {
+            // handled once we have a session
+        } else if key.is_empty() {
+            let bytes =
+                fs_err::read_to_string(&key_path).context("existing key file couldn't be read")?;
+            if decode_key(bytes).is_err() {
+                bail!("the key in existing key file was invalid");
             }
         } else if !key_path.exists() {
             if decode_key(key.clone()).is_err() {
                 bail!("the specified key was invalid");
             }
 
-            let mut file = File::create(key_path).await?;
+            let mut file = File::create(&key_path).await?;
             file.write_all(key.as_bytes()).await?;
         } else {
             // we now know that the user has logged in specifying a key, AND that the key path
@@ -126,21 +134,20 @@ impl Cmd {
 
             // 1. check if the saved key and the provided key match. if so, nothing to do.
             // 2. if not, re-encrypt the local history and overwrite the key
-            let current_key: [u8; 32] = load_key(settings)?.into();
+            let keyring = Keyring::load(settings)?;
 
-            let encoded = key.clone(); // gonna want to save it in a bit
             let new_key: [u8; 32] = decode_key(key)
                 .context("could not decode provided key - is not valid base64")?
                 .into();
 
-            if new_key != current_key {
+            if new_key != keyring.current() {
                 println!("\nRe-encrypting local store with new key");
 
-                store.re_encrypt(&current_key, &new_key).await?;
+                store.re_encrypt(&keyring, &new_key).await?;
 
                 println!("Writing new key");
-                let mut file = File::create(key_path).await?;
-                file.write_all(encoded.as_bytes()).await?;
+                keyring.set_current(new_key);
+                keyring.save(settings)?;
             }
         }
 
@@ -150,6 +157,15 @@ impl Cmd {
         )
         .await?;
 
+        if recover_escrow {
+            let key = key::recover(settings, &session.session, self.passphrase_stdin).await?;
+
+            let mut file = File::create(key_path).await?;
+            file.write_all(key.as_bytes()).await?;
+
+            println!("Recovered escrowed key");
+        }
+
         let session_path = settings.session_path.as_str();
         let mut file = File::create(session_path).await?;
         file.write_all(session.session.as_bytes()).await?;
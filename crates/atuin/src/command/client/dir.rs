@@ -0,0 +1,244 @@
+//! Frecency-ranked directory jumping. The shell integrations build `z` and `zi` on this: `z foo
+//! bar` changes to the directory matching the keywords that ranks best, and `zi` picks one
+//! interactively. They're only defined if `z` and `zi` don't exist yet, so another jumper set up
+//! first, such as zoxide, keeps them.
+//!
+//! Nushell can't define commands conditionally, so there atuin's `z` and `zi` always are, and
+//! whichever of atuin and another jumper is sourced last wins.
+
+use std::{
+    env,
+    fs::File,
+    io::{Write, stdout},
+    path::Path,
+};
+
+use clap::Parser;
+use eyre::{Result, bail};
+use ratatui::{
+    Terminal, TerminalOptions, Viewport,
+    backend::CrosstermBackend,
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+        execute, terminal,
+    },
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{List, ListItem, ListState, Paragraph},
+};
+use time::OffsetDateTime;
+
+use atuin_client::{
+    database::{Database, DirectoryVisits},
+    settings::Settings,
+    theme::{Meaning, Theme},
+};
+use atuin_history::dirs::rank;
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// Keywords the directory must match, in order. The last of them must match the last
+    /// component of its path
+    keywords: Vec<String>,
+
+    /// List the matching directories and their scores, best first
+    #[arg(long, short, conflicts_with = "interactive")]
+    list: bool,
+
+    /// Pick a directory interactively. The picker is drawn on stdout, and the picked directory
+    /// printed on stderr, like `atuin search -i`
+    #[arg(long, short)]
+    interactive: bool,
+
+    /// Include directories that don't exist on this machine, such as those synced from others
+    #[arg(long, short)]
+    all: bool,
+
+    /// File name to write the picked directory to (hidden from help as this is meant to be used
+    /// from a script)
+    #[arg(long = "result-file", hide = true)]
+    result_file: Option<String>,
+}
+
+impl Cmd {
+    pub async fn run(self, db: &impl Database, settings: &Settings, theme: &Theme) -> Result<()> {
+        let now = OffsetDateTime::now_utc();
+        let cwd = env::current_dir()
+            .map(|cwd| cwd.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let dirs: Vec<DirectoryVisits> = db
+            .directories()
+            .await?
+            .into_iter()
+            .filter(|dir| self.all || Path::new(&dir.path).is_dir())
+            .collect();
+
+        if self.list {
+            for (score, dir) in rank(&dirs, &self.keywords, now) {
+                println!("{score:>10.2} {}", dir.path);
+            }
+
+            return Ok(());
+        }
+
+        // jumping to the directory we're already in is never what's wanted
+        let dirs: Vec<DirectoryVisits> = dirs.into_iter().filter(|dir| dir.path != cwd).collect();
+
+        if self.interactive {
+            let dir = pick(&dirs, &self.keywords, now, settings, theme)?.unwrap_or_default();
+
+            if let Some(result_file) = self.result_file {
+                let mut file = File::create(result_file)?;
+                write!(file, "{dir}")?;
+            } else if !dir.is_empty() {
+                eprintln!("{dir}");
+            }
+
+            return Ok(());
+        }
+
+        match rank(&dirs, &self.keywords, now).first() {
+            Some((_, dir)) => println!("{}", dir.path),
+            None => bail!("no directory matches '{}'", self.keywords.join(" ")),
+        }
+
+        Ok(())
+    }
+}
+
+/// Let the user pick one of the directories, refining the keywords as they type
+fn pick(
+    dirs: &[DirectoryVisits],
+    keywords: &[String],
+    now: OffsetDateTime,
+    settings: &Settings,
+    theme: &Theme,
+) -> Result<Option<String>> {
+    let home = env::var("HOME").ok().filter(|home| !home.is_empty());
+    let inline_height = match terminal::size() {
+        Ok((_, rows)) if settings.inline_height >= rows => 0,
+        _ => settings.inline_height,
+    };
+
+    let _raw = RawMode::enter(inline_height == 0)?;
+
+    let mut terminal = Terminal::with_options(
+        CrosstermBackend::new(stdout()),
+        TerminalOptions {
+            viewport: if inline_height > 0 {
+                Viewport::Inline(inline_height)
+            } else {
+                Viewport::Fullscreen
+            },
+        },
+    )?;
+
+    let mut query = keywords.join(" ");
+    let mut state = ListState::default().with_selected(Some(0));
+
+    let picked = loop {
+        let keywords: Vec<String> = query.split_whitespace().map(String::from).collect();
+        let ranked = rank(dirs, &keywords, now);
+
+        terminal.draw(|f| {
+            let [list_area, input_area] =
+                Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(f.area());
+
+            let items: Vec<ListItem> = ranked
+                .iter()
+                .map(|(_, dir)| {
+                    let path = match &home {
+                        Some(home) if dir.path.starts_with(home.as_str()) => {
+                            format!("~{}", &dir.path[home.len()..])
+                        }
+                        _ => dir.path.clone(),
+                    };
+                    ListItem::new(path).style(theme.as_style(Meaning::Base))
+                })
+                .collect();
+
+            let list = List::new(items).highlight_symbol(" > ").highlight_style(
+                Style::from(theme.as_style(Meaning::Important)).add_modifier(Modifier::BOLD),
+            );
+            f.render_stateful_widget(list, list_area, &mut state);
+
+            let input = Line::from(vec![
+                Span::styled("[ DIR ] ", theme.as_style(Meaning::Annotation)),
+                Span::raw(query.as_str()),
+            ]);
+            f.render_widget(Paragraph::new(input), input_area);
+        })?;
+
+        let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+
+        match code {
+            KeyCode::Esc => break None,
+            KeyCode::Char('c' | 'd' | 'g') if ctrl => break None,
+            KeyCode::Enter => {
+                break state
+                    .selected()
+                    .and_then(|i| ranked.get(i))
+                    .map(|(_, dir)| dir.path.clone());
+            }
+            KeyCode::Up => state.select_previous(),
+            KeyCode::Char('p' | 'k') if ctrl => state.select_previous(),
+            KeyCode::Down => state.select_next(),
+            KeyCode::Char('n' | 'j') if ctrl => state.select_next(),
+            KeyCode::Char('u') if ctrl => {
+                query.clear();
+                state.select_first();
+            }
+            KeyCode::Backspace => {
+                query.pop();
+                state.select_first();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                query.push(c);
+                state.select_first();
+            }
+            _ => {}
+        }
+    };
+
+    terminal.clear()?;
+
+    Ok(picked)
+}
+
+/// The terminal in raw mode, on the alternate screen unless drawing inline, until dropped. It's
+/// put back however the picker ends, errors included.
+struct RawMode {
+    alternate: bool,
+}
+
+impl RawMode {
+    fn enter(alternate: bool) -> Result<Self> {
+        terminal::enable_raw_mode()?;
+
+        // set up first, so raw mode is left even if the alternate screen can't be entered
+        let mut raw = Self { alternate: false };
+        if alternate {
+            execute!(stdout(), terminal::EnterAlternateScreen)?;
+            raw.alternate = true;
+        }
+
+        Ok(raw)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        if self.alternate {
+            let _ = execute!(stdout(), terminal::LeaveAlternateScreen);
+        }
+        let _ = terminal::disable_raw_mode();
+    }
+}
@@ -1,7 +1,10 @@
 use clap::Subcommand;
 use eyre::{Context, Result, eyre};
 
-use atuin_client::{encryption, record::sqlite_store::SqliteStore, settings::Settings};
+use atuin_client::{
+    record::{keyring::Keyring, sqlite_store::SqliteStore},
+    settings::Settings,
+};
 
 use atuin_dotfiles::{shell::Alias, store::AliasStore};
 
@@ -99,12 +102,10 @@ impl Cmd {
             return Ok(());
         }
 
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
-            .context("could not load encryption key")?
-            .into();
+        let keyring = Keyring::load(settings).context("could not load encryption key")?;
         let host_id = Settings::host_id().expect("failed to get host_id");
 
-        let alias_store = AliasStore::new(store, host_id, encryption_key);
+        let alias_store = AliasStore::new(store, host_id, keyring.clone());
 
         match self {
             Self::Set { name, value } => self.set(&alias_store, name.clone(), value.clone()).await,
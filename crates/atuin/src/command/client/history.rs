@@ -1,4 +1,5 @@
 use std::{
+    collections::HashSet,
     fmt::{self, Display},
     io::{self, IsTerminal, Write},
     path::PathBuf,
@@ -7,14 +8,15 @@ use std::{
 
 use atuin_common::utils::{self, Escapable as _};
 use clap::Subcommand;
-use eyre::{Context, Result};
+use eyre::{Context, Result, bail};
+use futures_util::{Stream, StreamExt, stream::BoxStream};
 use runtime_format::{FormatKey, FormatKeyError, ParseSegment, ParsedFmt};
 
 use atuin_client::{
     database::{Database, Sqlite, current_context},
-    encryption,
-    history::{History, store::HistoryStore},
-    record::sqlite_store::SqliteStore,
+    history::{History, HistoryId, store::HistoryStore},
+    hooks,
+    record::{keyring::Keyring, sqlite_store::SqliteStore},
     settings::{
         FilterMode::{Directory, Global, Session},
         Settings, Timezone,
@@ -27,6 +29,8 @@ use atuin_client::{record, sync};
 use log::{debug, warn};
 use time::{OffsetDateTime, macros::format_description};
 
+use atuin_history::anomaly;
+
 use super::search::format_duration_into;
 
 #[derive(Subcommand, Debug)]
@@ -114,6 +118,37 @@ pub enum Cmd {
         format: Option<String>,
     },
 
+    /// Print the most recent commands, oldest first
+    Tail {
+        /// How many commands to print
+        #[arg(long, short = 'n', default_value_t = 10)]
+        count: usize,
+
+        /// Keep printing commands as they finish, in any shell. Requires the daemon
+        #[arg(long, short)]
+        follow: bool,
+
+        #[arg(long)]
+        human: bool,
+
+        /// Show only the text of the command
+        #[arg(long)]
+        cmd_only: bool,
+
+        /// Display the command time in another timezone other than the configured default.
+        ///
+        /// This option takes one of the following kinds of values:
+        /// - the special value "local" (or "l") which refers to the system time zone
+        /// - an offset from UTC (e.g. "+9", "-2:30")
+        #[arg(long, visible_alias = "tz")]
+        timezone: Option<Timezone>,
+
+        /// Available variables: {command}, {directory}, {duration}, {user}, {host}, {exit} and {time}.
+        /// Example: --format "{time} - [{duration}] - {directory}$\t{command}"
+        #[arg(long, short)]
+        format: Option<String>,
+    },
+
     InitStore,
 
     /// Delete history entries matching the configured exclusion filters
@@ -136,6 +171,11 @@ pub enum Cmd {
         /// How many recent duplicates to keep
         #[arg(long)]
         dupkeep: u32,
+
+        /// Treat commands that only differ in their arguments, like paths, hashes or branches,
+        /// as duplicates, normalising them like `atuin stats --normalize` does
+        #[arg(long)]
+        normalize: bool,
     },
 }
 
@@ -254,6 +294,19 @@ fn check_for_write_errors(write: Result<(), io::Error>) {
     }
 }
 
+/// Print an alert about the command that just ended. The shell hooks run `history end` with its
+/// output thrown away, so it's written straight to the terminal where there is one.
+fn print_alert(message: &str) {
+    let tty = std::fs::OpenOptions::new().write(true).open("/dev/tty");
+
+    match tty {
+        Ok(mut tty) => {
+            let _ = writeln!(tty, "{message}");
+        }
+        Err(_) => eprintln!("{message}"),
+    }
+}
+
 /// Type wrapper around `History` with formatting settings.
 #[derive(Clone, Copy, Debug)]
 struct FmtHistory<'a> {
@@ -450,6 +503,23 @@ impl Cmd {
         };
 
         db.update(&h).await?;
+
+        match anomaly::detect(db, &settings.alerts, &h).await {
+            Ok(Some(anomaly)) => {
+                print_alert(&anomaly.message(&h));
+
+                // without the daemon, the hook carries on after atuin exits, so it can't be
+                // timed out
+                if let Some(hook) = settings.alerts.for_command(&h.command).hook
+                    && let Err(e) = hooks::shell(&anomaly.hook(&hook, &h)).spawn()
+                {
+                    warn!("failed to run alert hook: {e}");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("failed to check history for alerts: {e}"),
+        }
+
         history_store.push(h).await?;
 
         if settings.should_sync()? {
@@ -482,7 +552,7 @@ impl Cmd {
         exit: i64,
         duration: Option<u64>,
     ) -> Result<()> {
-        let resp = atuin_daemon::client::HistoryClient::new(
+        let (_, _, alert) = atuin_daemon::client::HistoryClient::new(
             #[cfg(not(unix))]
             settings.daemon.tcp_port,
             #[cfg(unix)]
@@ -492,6 +562,10 @@ impl Cmd {
         .end_history(id.to_string(), duration.unwrap_or(0), exit)
         .await?;
 
+        if let Some(alert) = alert {
+            print_alert(&alert);
+        }
+
         Ok(())
     }
 
@@ -571,11 +645,9 @@ impl Cmd {
                 settings.timezone,
             );
         } else {
-            let encryption_key: [u8; 32] = encryption::load_key(settings)
-                .context("could not load encryption key")?
-                .into();
+            let keyring = Keyring::load(settings).context("could not load encryption key")?;
             let host_id = Settings::host_id().expect("failed to get host_id");
-            let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
+            let history_store = HistoryStore::new(store.clone(), host_id, keyring.clone());
 
             for entry in matches {
                 eprintln!("deleting {}", entry.id);
@@ -597,6 +669,7 @@ impl Cmd {
         before: i64,
         dupkeep: u32,
         dry_run: bool,
+        normalize: bool,
     ) -> Result<()> {
         if dupkeep == 0 {
             eprintln!(
@@ -605,7 +678,17 @@ impl Cmd {
             std::process::exit(1);
         }
 
-        let matches: Vec<History> = db.get_dups(before, dupkeep).await?;
+        let matches: Vec<History> = if normalize {
+            let history = db.list(&[], &current_context(), None, false, false).await?;
+
+            atuin_history::normalize::duplicates(settings, &history, dupkeep as usize)
+                .into_iter()
+                .filter(|h| h.timestamp.unix_timestamp_nanos() < i128::from(before))
+                .cloned()
+                .collect()
+        } else {
+            db.get_dups(before, dupkeep).await?
+        };
 
         match matches.len() {
             0 => {
@@ -626,11 +709,9 @@ impl Cmd {
                 settings.timezone,
             );
         } else {
-            let encryption_key: [u8; 32] = encryption::load_key(settings)
-                .context("could not load encryption key")?
-                .into();
+            let keyring = Keyring::load(settings).context("could not load encryption key")?;
             let host_id = Settings::host_id().expect("failed to get host_id");
-            let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
+            let history_store = HistoryStore::new(store.clone(), host_id, keyring.clone());
 
             for entry in matches {
                 eprintln!("deleting {}", entry.id);
@@ -645,6 +726,7 @@ impl Cmd {
         Ok(())
     }
 
+    #[allow(clippy::too_many_lines)]
     pub async fn run(self, settings: &Settings) -> Result<()> {
         let context = current_context();
 
@@ -671,12 +753,10 @@ impl Cmd {
         let db = Sqlite::new(db_path, settings.local_timeout).await?;
         let store = SqliteStore::new(record_store_path, settings.local_timeout).await?;
 
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
-            .context("could not load encryption key")?
-            .into();
+        let keyring = Keyring::load(settings).context("could not load encryption key")?;
 
         let host_id = Settings::host_id().expect("failed to get host_id");
-        let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
+        let history_store = HistoryStore::new(store.clone(), host_id, keyring.clone());
 
         match self {
             Self::Start { .. } => {
@@ -728,6 +808,21 @@ impl Cmd {
                 Ok(())
             }
 
+            Self::Tail {
+                count,
+                follow,
+                human,
+                cmd_only,
+                timezone,
+                format,
+            } => {
+                let mode = ListMode::from_flags(human, cmd_only);
+                let format = format.unwrap_or_else(|| settings.history_format.clone());
+                let tz = timezone.unwrap_or(settings.timezone);
+
+                Self::handle_tail(&db, settings, &context, count, follow, mode, &format, tz).await
+            }
+
             Self::InitStore => history_store.init_store(&db).await,
 
             Self::Prune { dry_run } => {
@@ -738,6 +833,7 @@ impl Cmd {
                 dry_run,
                 before,
                 dupkeep,
+                normalize,
             } => {
                 let before = i64::try_from(
                     interim::parse_date_string(
@@ -747,11 +843,79 @@ impl Cmd {
                     )?
                     .unix_timestamp_nanos(),
                 )?;
-                Self::handle_dedup(&db, settings, store, before, dupkeep, dry_run).await
+                Self::handle_dedup(&db, settings, store, before, dupkeep, dry_run, normalize).await
             }
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_tail(
+        db: &impl Database,
+        settings: &Settings,
+        context: &atuin_client::database::Context,
+        count: usize,
+        follow: bool,
+        mode: ListMode,
+        format: &str,
+        tz: Timezone,
+    ) -> Result<()> {
+        // subscribe before listing, so nothing ending in between is missed
+        let ended = if follow {
+            Some(Self::subscribe(settings).await?)
+        } else {
+            None
+        };
+
+        let recent = db
+            .list(&[Global], context, Some(count), false, false)
+            .await?;
+
+        let print = |h: &[History]| print_list(h, mode, Some(format), false, false, tz);
+        print_list(&recent, mode, Some(format), false, true, tz);
+
+        if let Some(ended) = ended {
+            follow_after(&recent, ended, print).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The commands that end from now on, until the daemon stops
+    #[cfg(feature = "daemon")]
+    async fn subscribe(settings: &Settings) -> Result<BoxStream<'static, Result<History>>> {
+        use atuin_daemon::client::{HistoryClient, HistoryEvent};
+
+        if !settings.daemon.enabled {
+            bail!("following history requires the daemon. Enable it with daemon.enabled");
+        }
+
+        let events = HistoryClient::new(
+            #[cfg(not(unix))]
+            settings.daemon.tcp_port,
+            #[cfg(unix)]
+            settings.daemon.socket_path.clone(),
+        )
+        .await?
+        .subscribe()
+        .await?;
+
+        Ok(events
+            .filter_map(|event| async {
+                match event {
+                    Ok(HistoryEvent::Ended(h)) => Some(Ok(h)),
+                    Ok(HistoryEvent::Started(_)) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            })
+            .boxed())
+    }
+
+    #[cfg(not(feature = "daemon"))]
+    #[allow(clippy::unused_async)]
+    async fn subscribe(_settings: &Settings) -> Result<BoxStream<'static, Result<History>>> {
+        bail!("following history requires the daemon, which this build of atuin does not include");
+    }
+
     /// Returns the command line to use for the `Start` variant.
     /// Returns `None` for any other variant.
     fn get_start_command(&self) -> Option<String> {
@@ -765,6 +929,25 @@ impl Cmd {
     }
 }
 
+/// Print each command that ends, skipping those already listed in `recent`. The subscription
+/// starts before the listing, so a command can turn up in both.
+async fn follow_after(
+    recent: &[History],
+    mut ended: impl Stream<Item = Result<History>> + Unpin,
+    mut print: impl FnMut(&[History]),
+) -> Result<()> {
+    let listed: HashSet<&HistoryId> = recent.iter().map(|h| &h.id).collect();
+
+    while let Some(h) = ended.next().await {
+        let h = h?;
+        if !listed.contains(&h.id) {
+            print(&[h]);
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -779,6 +962,34 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn follow_skips_listed() {
+        let history = |command: &str| -> History {
+            History::import()
+                .timestamp(OffsetDateTime::now_utc())
+                .command(command)
+                .build()
+                .into()
+        };
+
+        let recent = vec![history("ls"), history("cd /")];
+        // the subscription started before the listing, so it saw the last listed command too
+        let ended = futures_util::stream::iter(vec![
+            Ok(recent[1].clone()),
+            Ok(history("make")),
+            Ok(history("make test")),
+        ]);
+
+        let mut printed = Vec::new();
+        follow_after(&recent, ended, |h| {
+            printed.extend(h.iter().map(|h| h.command.clone()));
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(printed, ["make", "make test"]);
+    }
+
     #[test]
     fn test_valid_formats_still_work() {
         assert!(std::panic::catch_unwind(|| parse_fmt("{command}")).is_ok());
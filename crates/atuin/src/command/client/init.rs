@@ -1,6 +1,9 @@
 use std::path::PathBuf;
 
-use atuin_client::{encryption, record::sqlite_store::SqliteStore, settings::Settings};
+use atuin_client::{
+    record::{keyring::Keyring, sqlite_store::SqliteStore},
+    settings::Settings,
+};
 use atuin_dotfiles::store::{AliasStore, var::VarStore};
 use clap::{Parser, ValueEnum};
 use eyre::{Result, WrapErr};
@@ -115,13 +118,11 @@ $env.config = (
         let record_store_path = PathBuf::from(settings.record_store_path.as_str());
         let sqlite_store = SqliteStore::new(record_store_path, settings.local_timeout).await?;
 
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
-            .context("could not load encryption key")?
-            .into();
+        let keyring = Keyring::load(settings).context("could not load encryption key")?;
         let host_id = Settings::host_id().expect("failed to get host_id");
 
-        let alias_store = AliasStore::new(sqlite_store.clone(), host_id, encryption_key);
-        let var_store = VarStore::new(sqlite_store.clone(), host_id, encryption_key);
+        let alias_store = AliasStore::new(sqlite_store.clone(), host_id, keyring.clone());
+        let var_store = VarStore::new(sqlite_store.clone(), host_id, keyring.clone());
 
         match self.shell {
             Shell::Zsh => {
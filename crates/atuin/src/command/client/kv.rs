@@ -1,12 +1,26 @@
-use clap::Subcommand;
+use clap::{Args, Subcommand};
 use eyre::{Context, Result, eyre};
 
-use atuin_client::{encryption, record::sqlite_store::SqliteStore, settings::Settings};
+use atuin_client::{
+    record::{keyring::Keyring, sqlite_store::SqliteStore},
+    settings::Settings,
+    team::Team,
+};
 use atuin_kv::store::KvStore;
 
+#[derive(Args, Debug)]
+pub struct Cmd {
+    /// Use the key-value pairs shared by a team, rather than your own
+    #[arg(long, global = true)]
+    team: Option<String>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
 #[derive(Subcommand, Debug)]
 #[command(infer_subcommands = true)]
-pub enum Cmd {
+pub enum Commands {
     /// Set a key-value pair
     Set {
         /// Key to set
@@ -61,17 +75,27 @@ pub enum Cmd {
 
 impl Cmd {
     pub async fn run(&self, settings: &Settings, store: &SqliteStore) -> Result<()> {
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
-            .context("could not load encryption key")?
-            .into();
-
         let host_id = Settings::host_id().expect("failed to get host_id");
 
-        let kv_db = atuin_kv::database::Database::new(settings.kv.db_path.clone(), 1.0).await?;
-        let kv_store = KvStore::new(store.clone(), kv_db, host_id, encryption_key);
-
-        match self {
-            Self::Set {
+        let kv_store = if let Some(name) = &self.team {
+            let team = Team::open(settings, name)?;
+            let kv_db = atuin_kv::database::Database::new(team.kv_db_path(), 1.0).await?;
+
+            KvStore::new(
+                team.record_store(settings).await?,
+                kv_db,
+                host_id,
+                team.keyring()?,
+            )
+        } else {
+            let keyring = Keyring::load(settings).context("could not load encryption key")?;
+            let kv_db = atuin_kv::database::Database::new(settings.kv.db_path.clone(), 1.0).await?;
+
+            KvStore::new(store.clone(), kv_db, host_id, keyring)
+        };
+
+        match &self.command {
+            Commands::Set {
                 key,
                 value,
                 namespace,
@@ -83,9 +107,9 @@ impl Cmd {
                 kv_store.set(namespace, key, value).await
             }
 
-            Self::Delete { keys, namespace } => kv_store.delete(namespace, keys).await,
+            Commands::Delete { keys, namespace } => kv_store.delete(namespace, keys).await,
 
-            Self::Get { key, namespace } => {
+            Commands::Get { key, namespace } => {
                 let kv = kv_store.get(namespace, key).await?;
 
                 if let Some(val) = kv {
@@ -95,7 +119,7 @@ impl Cmd {
                 Ok(())
             }
 
-            Self::List {
+            Commands::List {
                 namespace,
                 all_namespaces,
             } => {
@@ -116,7 +140,7 @@ impl Cmd {
                 Ok(())
             }
 
-            Self::Rebuild {} => kv_store.build().await,
+            Commands::Rebuild => kv_store.build().await,
         }
     }
 }
@@ -0,0 +1,119 @@
+use std::{
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use eyre::{Result, WrapErr};
+
+use atuin_client::plugin::{self, OfficialPluginRegistry};
+
+#[derive(Subcommand, Debug)]
+#[command(infer_subcommands = true)]
+pub enum Cmd {
+    /// List the plugins that are registered, official or on your PATH
+    #[command(alias = "ls")]
+    List,
+
+    /// Register a plugin with its manifest, a TOML file with its name, and optionally its
+    /// description, version, executable and the plugin protocol version it speaks
+    Register {
+        /// The manifest to register, or - to read it from stdin
+        manifest: PathBuf,
+    },
+}
+
+impl Cmd {
+    pub fn run(self) -> Result<()> {
+        match self {
+            Self::List => list(),
+            Self::Register { manifest } => register(&manifest),
+        }
+    }
+}
+
+fn list() -> Result<()> {
+    let manifests = plugin::manifests(&plugin::plugins_dir())?;
+    let installed = plugin::discover();
+    let official = OfficialPluginRegistry::new();
+
+    let mut rows = Vec::new();
+
+    for manifest in manifests.values() {
+        let mut status = String::from("registered");
+        if let Some(version) = &manifest.version {
+            status = format!("{status}, v{version}");
+        }
+        if let Some(protocol) = manifest.protocol {
+            status = format!("{status}, protocol {protocol}");
+        }
+        if manifest.bin.as_ref().map_or_else(
+            || !installed.contains_key(&manifest.name),
+            |bin| !bin.exists(),
+        ) {
+            status = format!("{status}, not installed");
+        }
+
+        rows.push((manifest.name.clone(), status, manifest.description.clone()));
+    }
+
+    for plugin in official.plugins() {
+        if manifests.contains_key(&plugin.name) {
+            continue;
+        }
+
+        let status = if installed.contains_key(&plugin.name) {
+            "official"
+        } else {
+            "official, not installed"
+        };
+        rows.push((
+            plugin.name.clone(),
+            status.to_string(),
+            plugin.description.clone(),
+        ));
+    }
+
+    for (name, path) in &installed {
+        if manifests.contains_key(name) || official.is_official_plugin(name) {
+            continue;
+        }
+
+        rows.push((
+            name.clone(),
+            String::from("on PATH"),
+            path.display().to_string(),
+        ));
+    }
+
+    if rows.is_empty() {
+        println!("No plugins found");
+        return Ok(());
+    }
+
+    rows.sort();
+    let name_width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+    let status_width = rows.iter().map(|r| r.1.len()).max().unwrap_or(0);
+
+    for (name, status, description) in rows {
+        println!("{name:name_width$}  {status:status_width$}  {description}");
+    }
+
+    Ok(())
+}
+
+fn register(path: &Path) -> Result<()> {
+    let manifest = if path.as_os_str() == "-" {
+        let mut manifest = String::new();
+        io::stdin().read_to_string(&mut manifest)?;
+        manifest
+    } else {
+        std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("could not read {}", path.display()))?
+    };
+
+    let manifest = plugin::register(&plugin::plugins_dir(), &manifest)?;
+    println!("Registered plugin '{}'", manifest.name);
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::{collections::BTreeMap, env};
+
+use clap::{Args, Subcommand, ValueEnum};
+use eyre::Result;
+use time::{Duration, OffsetDateTime};
+
+use atuin_client::{
+    database::{Database, current_context},
+    settings::Settings,
+};
+use atuin_history::{
+    report::{self, Entry},
+    stats::Period,
+};
+
+use super::{search::duration::format_duration, stats::period_range};
+
+#[derive(Subcommand, Debug)]
+#[command(infer_subcommands = true)]
+pub enum Cmd {
+    /// Report the time spent on each project, per day or week. Commands count towards the
+    /// project configured for their directory in `[report.projects]`, or else the git repo they
+    /// ran in
+    Time(Time),
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Per {
+    #[default]
+    Day,
+    Week,
+}
+
+impl From<Per> for report::Per {
+    fn from(per: Per) -> Self {
+        match per {
+            Per::Day => Self::Day,
+            Per::Week => Self::Week,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// A table for each day or week, for reading
+    #[default]
+    Table,
+    /// The report and the period it covers, as a JSON object
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub struct Time {
+    /// The period to report on, like `atuin stats` takes. Leave blank to report on the last week
+    period: Vec<String>,
+
+    /// Report the time spent per day or per week
+    #[arg(long, value_enum, default_value_t)]
+    per: Per,
+
+    /// How to print the report
+    #[arg(long, short, value_enum, default_value_t)]
+    format: Format,
+
+    /// Override the idle gap from the config, in seconds
+    #[arg(long)]
+    idle_gap: Option<u64>,
+}
+
+impl Cmd {
+    pub async fn run(self, db: &impl Database, settings: &Settings) -> Result<()> {
+        match self {
+            Self::Time(time) => time.run(db, settings).await,
+        }
+    }
+}
+
+impl Time {
+    async fn run(self, db: &impl Database, settings: &Settings) -> Result<()> {
+        let words = if self.period.is_empty() {
+            String::from("week")
+        } else {
+            self.period.join(" ")
+        };
+
+        let range = report_range(&words, settings)?;
+        let history = match range {
+            Some((start, end)) => db.range(start, end).await?,
+            None => db.list(&[], &current_context(), None, false, false).await?,
+        };
+
+        let period = Period {
+            name: words,
+            start: range.map(|(start, _)| start),
+            end: range.map(|(_, end)| end),
+        };
+
+        let mut config = settings.report.clone();
+        if let Some(idle_gap) = self.idle_gap {
+            config.idle_gap = idle_gap;
+        }
+
+        let entries = report::compute(&history, &config, self.per.into(), settings.timezone.0);
+
+        match self.format {
+            Format::Table => print_table(&entries),
+            Format::Json => println!(
+                "{}",
+                report::to_json(&entries, self.per.into(), &config, &period)?
+            ),
+        }
+
+        Ok(())
+    }
+}
+
+/// The range a report covers. Unlike the stats, which stop at midnight, the last week, month or
+/// year runs up to now, so the report includes the work done today.
+fn report_range(
+    words: &str,
+    settings: &Settings,
+) -> Result<Option<(OffsetDateTime, OffsetDateTime)>> {
+    let range = period_range(words, settings)?;
+
+    Ok(match words.trim() {
+        "week" | "month" | "year" => {
+            range.map(|(start, _)| (start + Duration::days(1), OffsetDateTime::now_utc()))
+        }
+        _ => range,
+    })
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn print_table(entries: &[Entry]) {
+    let home = env::var("HOME").ok().filter(|home| !home.is_empty());
+    let project = |entry: &Entry| match &home {
+        Some(home) if entry.project.starts_with(home.as_str()) => {
+            format!("~{}", &entry.project[home.len()..])
+        }
+        _ => entry.project.clone(),
+    };
+    let duration = |nanos: i64| format_duration(std::time::Duration::from_nanos(nanos as u64));
+
+    let width = entries
+        .iter()
+        .map(|e| project(e).chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut dates = BTreeMap::<&str, Vec<&Entry>>::new();
+    for entry in entries {
+        dates.entry(entry.date.as_str()).or_default().push(entry);
+    }
+
+    for (i, (date, entries)) in dates.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+
+        let active: i64 = entries.iter().map(|e| e.active).sum();
+        println!("{date}  {}", duration(active));
+
+        for entry in entries {
+            println!(
+                "  {:width$}  {:>8} active  {:>8} running  {:>5} commands",
+                project(entry),
+                duration(entry.active),
+                duration(entry.running),
+                entry.commands,
+            );
+        }
+    }
+}
@@ -9,12 +9,18 @@ use atuin_scripts::{
     execution::{build_executable_script, execute_script_interactive, template_variables},
     store::{ScriptStore, script::Script},
 };
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use eyre::OptionExt;
 use eyre::{Result, bail};
 use tempfile::NamedTempFile;
 
-use atuin_client::{database::Database, record::sqlite_store::SqliteStore, settings::Settings};
+use atuin_client::{
+    database::{Database, current_context},
+    record::{keyring::Keyring, sqlite_store::SqliteStore},
+    settings::Settings,
+    team::Team,
+};
+use atuin_history::sequences;
 use tracing::debug;
 
 #[derive(Parser, Debug)]
@@ -105,9 +111,42 @@ pub struct Delete {
     pub force: bool,
 }
 
+#[derive(Parser, Debug)]
+pub struct Suggest {
+    /// How many sequences to show
+    #[arg(long, short, default_value_t = 10)]
+    pub count: usize,
+
+    /// How many times a sequence must have run to be suggested
+    #[arg(long, default_value_t = 3)]
+    pub min_runs: usize,
+
+    /// The most commands a sequence can have
+    #[arg(long, default_value_t = 5)]
+    pub max_length: usize,
+
+    /// Create a script from the suggestion with this number
+    #[arg(long, requires = "name")]
+    pub create: Option<usize>,
+
+    /// The name of the script to create
+    #[arg(long, requires = "create")]
+    pub name: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct Cmd {
+    /// Use the scripts shared by a team, rather than your own
+    #[arg(long, global = true)]
+    team: Option<String>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
 #[derive(Subcommand, Debug)]
 #[command(infer_subcommands = true)]
-pub enum Cmd {
+pub enum Commands {
     New(NewScript),
     Run(Run),
     #[command(alias = "ls")]
@@ -117,11 +156,14 @@ pub enum Cmd {
     Edit(Edit),
     #[command(alias = "rm")]
     Delete(Delete),
+
+    /// Suggest scripts for the sequences of commands you run over and over
+    Suggest(Suggest),
 }
 
 impl Cmd {
     // Helper function to open an editor with optional initial content
-    fn open_editor(initial_content: Option<&str>) -> Result<String> {
+    pub(crate) fn open_editor(initial_content: Option<&str>) -> Result<String> {
         // Create a temporary file
         let temp_file = NamedTempFile::new()?;
         let path = temp_file.into_temp_path();
@@ -560,6 +602,82 @@ impl Cmd {
         }
     }
 
+    async fn handle_suggest(
+        settings: &Settings,
+        suggest: Suggest,
+        script_store: ScriptStore,
+        script_db: atuin_scripts::database::Database,
+        history_db: &impl Database,
+    ) -> Result<()> {
+        let history = history_db
+            .list(&[], &current_context(), None, false, false)
+            .await?;
+
+        let mut suggestions =
+            sequences::find(settings, &history, 2, suggest.max_length, suggest.min_runs);
+        suggestions.truncate(suggest.count);
+
+        let (Some(number), Some(name)) = (suggest.create, suggest.name) else {
+            if suggestions.is_empty() {
+                println!("No repeated sequences of commands found");
+                return Ok(());
+            }
+
+            for (i, sequence) in suggestions.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+
+                println!(
+                    "{}. {} runs in {} sessions",
+                    i + 1,
+                    sequence.runs,
+                    sequence.sessions
+                );
+                for command in &sequence.commands {
+                    println!("    {command}");
+                }
+            }
+
+            println!();
+            println!(
+                "Create a script from one with `atuin scripts suggest --create <N> --name <NAME>`"
+            );
+            return Ok(());
+        };
+
+        let Some(sequence) = number.checked_sub(1).and_then(|i| suggestions.get(i)) else {
+            bail!("there is no suggestion {number}");
+        };
+
+        if script_db.get_by_name(&name).await?.is_some() {
+            bail!("script '{name}' already exists");
+        }
+
+        let (template, variables) = sequences::template(sequence);
+
+        let script = Script::builder()
+            .name(name.clone())
+            .description(format!(
+                "Suggested from {} runs in your history",
+                sequence.runs
+            ))
+            .tags(vec!["suggested".to_string()])
+            .script(template)
+            .build();
+
+        script_store.create(script).await?;
+        script_store.build(script_db).await?;
+
+        println!("Script '{name}' created");
+        if !variables.is_empty() {
+            let vars: Vec<String> = variables.iter().map(|v| format!("-v {v}=...")).collect();
+            println!("Run it with: atuin scripts run {name} {}", vars.join(" "));
+        }
+
+        Ok(())
+    }
+
     pub async fn run(
         self,
         settings: &Settings,
@@ -567,24 +685,40 @@ impl Cmd {
         history_db: &impl Database,
     ) -> Result<()> {
         let host_id = Settings::host_id().expect("failed to get host_id");
-        let encryption_key: [u8; 32] = atuin_client::encryption::load_key(settings)?.into();
 
-        let script_store = ScriptStore::new(store, host_id, encryption_key);
-        let script_db =
-            atuin_scripts::database::Database::new(settings.scripts.db_path.clone(), 1.0).await?;
+        let (script_store, script_db) = match &self.team {
+            Some(name) => {
+                let team = Team::open(settings, name)?;
+
+                (
+                    ScriptStore::new(team.record_store(settings).await?, host_id, team.keyring()?),
+                    atuin_scripts::database::Database::new(team.scripts_db_path(), 1.0).await?,
+                )
+            }
+            None => (
+                ScriptStore::new(store, host_id, Keyring::load(settings)?),
+                atuin_scripts::database::Database::new(settings.scripts.db_path.clone(), 1.0)
+                    .await?,
+            ),
+        };
 
-        match self {
-            Self::New(new_script) => {
+        match self.command {
+            Commands::New(new_script) => {
                 Self::handle_new_script(settings, new_script, script_store, script_db, history_db)
                     .await
             }
-            Self::Run(run) => Self::handle_run(settings, run, script_db).await,
-            Self::List(list) => Self::handle_list(settings, list, script_db).await,
-            Self::Get(get) => Self::handle_get(settings, get, script_db).await,
-            Self::Edit(edit) => Self::handle_edit(settings, edit, script_store, script_db).await,
-            Self::Delete(delete) => {
+            Commands::Run(run) => Self::handle_run(settings, run, script_db).await,
+            Commands::List(list) => Self::handle_list(settings, list, script_db).await,
+            Commands::Get(get) => Self::handle_get(settings, get, script_db).await,
+            Commands::Edit(edit) => {
+                Self::handle_edit(settings, edit, script_store, script_db).await
+            }
+            Commands::Delete(delete) => {
                 Self::handle_delete(settings, delete, script_store, script_db).await
             }
+            Commands::Suggest(suggest) => {
+                Self::handle_suggest(settings, suggest, script_store, script_db, history_db).await
+            }
         }
     }
 }
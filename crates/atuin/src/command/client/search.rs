@@ -8,9 +8,8 @@ use eyre::Result;
 use atuin_client::{
     database::Database,
     database::{OptFilters, current_context},
-    encryption,
     history::{History, store::HistoryStore},
-    record::sqlite_store::SqliteStore,
+    record::{keyring::Keyring, sqlite_store::SqliteStore},
     settings::{FilterMode, KeymapMode, SearchMode, Settings, Timezone},
     theme::Theme,
 };
@@ -18,8 +17,10 @@ use atuin_client::{
 use super::history::ListMode;
 
 mod cursor;
-mod duration;
+pub mod duration;
+mod editor;
 mod engines;
+mod groups;
 mod history_list;
 mod inspector;
 mod interactive;
@@ -136,6 +137,10 @@ pub struct Cmd {
     /// File name to write the result to (hidden from help as this is meant to be used from a script)
     #[arg(long = "result-file", hide = true)]
     result_file: Option<String>,
+
+    /// Print the keybindings of the interactive search for every keymap mode, and exit
+    #[arg(long)]
+    list_keybindings: bool,
 }
 
 impl Cmd {
@@ -150,6 +155,11 @@ impl Cmd {
         store: SqliteStore,
         theme: &Theme,
     ) -> Result<()> {
+        if self.list_keybindings {
+            list_keybindings(settings);
+            return Ok(());
+        }
+
         let query = self.query.map_or_else(
             || {
                 std::env::var("ATUIN_QUERY").map_or_else(
@@ -211,10 +221,10 @@ impl Cmd {
         };
         settings.keymap_mode_shell = self.keymap_mode;
 
-        let encryption_key: [u8; 32] = encryption::load_key(settings)?.into();
+        let keyring = Keyring::load(settings)?;
 
         let host_id = Settings::host_id().expect("failed to get host_id");
-        let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
+        let history_store = HistoryStore::new(store.clone(), host_id, keyring.clone());
 
         if self.interactive {
             let item = interactive::history(&query, settings, db, &history_store, theme).await?;
@@ -269,6 +279,10 @@ impl Cmd {
                         run_non_interactive(settings, opt_filter.clone(), &query, &db).await?;
                 }
             } else {
+                if !self.include_duplicates && settings.stats.normalize {
+                    atuin_history::normalize::unique(settings, &mut entries);
+                }
+
                 let format = match self.format {
                     None => Some(settings.history_format.as_str()),
                     _ => self.format.as_deref(),
@@ -328,3 +342,27 @@ async fn run_non_interactive(
 
     Ok(results)
 }
+
+fn list_keybindings(settings: &Settings) {
+    let prefix = settings.keys.prefix.chars().next().unwrap_or('a');
+
+    println!(
+        "# ctrl-{prefix} is the prefix key (keys.prefix). ctrl-{prefix} d deletes the selected entry"
+    );
+    println!(
+        "# 1-9 with alt (or ctrl, with ctrl_n_shortcuts) accept the nth entry after the selection"
+    );
+
+    for (name, mode) in [
+        ("emacs", KeymapMode::Emacs),
+        ("vim-normal", KeymapMode::VimNormal),
+        ("vim-insert", KeymapMode::VimInsert),
+    ] {
+        println!("\n[keymap.{name}]");
+
+        for (chord, action, custom) in settings.keymap.bindings(mode) {
+            let custom = if custom { " # custom" } else { "" };
+            println!("{:?} = \"{action}\"{custom}", chord.to_string());
+        }
+    }
+}
@@ -203,6 +203,16 @@ impl Cursor {
     pub fn position(&self) -> usize {
         self.index
     }
+
+    /// Splits the string at the cursor, returning everything after it
+    pub fn split_off(&mut self) -> String {
+        self.source.split_off(self.index)
+    }
+
+    /// Appends a string, leaving the cursor where it is
+    pub fn push_str(&mut self, s: &str) {
+        self.source.push_str(s);
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,175 @@
+//! A small multi-line editor, for tweaking the selected command before accepting it.
+//!
+//! Every line is a [`Cursor`], so editing within a line works just like editing the query.
+
+use super::cursor::Cursor;
+
+pub struct Editor {
+    lines: Vec<Cursor>,
+    row: usize,
+}
+
+impl Editor {
+    /// Start editing a command, with the cursor at its end
+    pub fn new(command: &str) -> Self {
+        let mut lines: Vec<Cursor> = command
+            .split('\n')
+            .map(|line| Cursor::from(line.to_string()))
+            .collect();
+        let row = lines.len() - 1;
+        lines[row].end();
+
+        Self { lines, row }
+    }
+
+    pub fn text(&self) -> String {
+        self.lines().collect::<Vec<_>>().join("\n")
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(Cursor::as_str)
+    }
+
+    /// The row of the line being edited
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    /// The line being edited
+    pub fn line(&self) -> &Cursor {
+        &self.lines[self.row]
+    }
+
+    pub fn line_mut(&mut self) -> &mut Cursor {
+        &mut self.lines[self.row]
+    }
+
+    /// Move to another line, keeping the cursor in the same column where possible
+    fn move_to(&mut self, row: usize) {
+        let column = self.line().substring().chars().count();
+        self.row = row;
+
+        let line = self.line_mut();
+        line.start();
+        for _ in 0..column {
+            line.right();
+        }
+    }
+
+    pub fn up(&mut self) {
+        if self.row > 0 {
+            self.move_to(self.row - 1);
+        }
+    }
+
+    pub fn down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.move_to(self.row + 1);
+        }
+    }
+
+    /// Move to the start of the command
+    pub fn first(&mut self) {
+        self.row = 0;
+        self.line_mut().start();
+    }
+
+    /// Move to the end of the command
+    pub fn last(&mut self) {
+        self.row = self.lines.len() - 1;
+        self.line_mut().end();
+    }
+
+    /// Break the line at the cursor
+    pub fn new_line(&mut self) {
+        let rest = self.line_mut().split_off();
+        self.row += 1;
+        self.lines.insert(self.row, Cursor::from(rest));
+    }
+
+    pub fn insert(&mut self, c: char) {
+        match c {
+            '\n' => self.new_line(),
+            '\r' => {}
+            c => self.line_mut().insert(c),
+        }
+    }
+
+    /// Delete the character before the cursor, joining the line onto the one above at its start
+    pub fn back(&mut self) {
+        if self.line().position() > 0 {
+            self.line_mut().back();
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row).into_inner();
+            self.row -= 1;
+
+            let above = self.line_mut();
+            above.end();
+            above.push_str(&line);
+        }
+    }
+
+    /// Delete the character after the cursor, joining the line below on at its end
+    pub fn remove(&mut self) {
+        if self.line().char().is_some() {
+            self.line_mut().remove();
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1).into_inner();
+            self.line_mut().push_str(&line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Editor;
+
+    #[test]
+    fn edit_lines() {
+        let mut editor = Editor::new("git commit \\\n  -m wip");
+        assert_eq!(editor.row(), 1);
+        assert_eq!(editor.line().substring(), "  -m wip");
+
+        editor.up();
+        assert_eq!(editor.line().substring(), "git comm");
+
+        editor.line_mut().end();
+        editor.back();
+        editor.back();
+        assert_eq!(editor.text(), "git commit\n  -m wip");
+
+        // deleting at the end of a line joins the next one on
+        editor.remove();
+        assert_eq!(editor.text(), "git commit  -m wip");
+        assert_eq!(editor.line().substring(), "git commit");
+
+        editor.insert('\n');
+        assert_eq!(editor.text(), "git commit\n  -m wip");
+        assert_eq!(editor.row(), 1);
+        assert_eq!(editor.line().position(), 0);
+
+        // deleting at the start of a line joins it onto the one above
+        editor.back();
+        assert_eq!(editor.text(), "git commit  -m wip");
+        assert_eq!(editor.row(), 0);
+    }
+
+    #[test]
+    fn keep_column() {
+        let mut editor = Editor::new("échö one\nx\nthird line");
+        editor.first();
+        for _ in 0..4 {
+            editor.line_mut().right();
+        }
+
+        // a short line clamps the column, and the next line uses the clamped column
+        editor.down();
+        assert_eq!(editor.line().substring(), "x");
+        editor.down();
+        assert_eq!(editor.line().substring(), "t");
+
+        editor.up();
+        editor.up();
+        assert_eq!(editor.line().substring(), "é");
+    }
+}
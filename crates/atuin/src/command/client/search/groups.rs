@@ -0,0 +1,300 @@
+//! Folding of search results into groups, by session, day or directory.
+//!
+//! Every group is shown as a header row. The header row holds the group's most recent entry, so
+//! the preview and inspector still have something to show while the group is folded, but it
+//! stands for the whole group - actions on a single entry skip it, as do the numeric shortcuts.
+//! Unfolding a group lists its entries next to the header.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use atuin_client::{history::History, settings::GroupBy};
+use time::{OffsetDateTime, UtcOffset, macros::format_description};
+
+pub struct Header {
+    pub label: String,
+    pub start: OffsetDateTime,
+    pub host: String,
+    pub count: usize,
+    pub expanded: bool,
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = self
+            .start
+            .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+            .map_err(|_| fmt::Error)?;
+        let plural = if self.count == 1 { "" } else { "s" };
+
+        write!(
+            f,
+            "{} {}  started {start}  on {}  {} command{plural}",
+            if self.expanded { "▾" } else { "▸" },
+            self.label,
+            self.host,
+            self.count,
+        )
+    }
+}
+
+pub struct Groups {
+    by: GroupBy,
+    timezone: UtcOffset,
+    /// Put the header after the entries of its group, for lists drawn from the bottom up
+    header_last: bool,
+    expanded: HashSet<String>,
+    headers: HashMap<usize, Header>,
+    /// The group key of every row
+    keys: Vec<String>,
+}
+
+impl Groups {
+    pub fn new(by: GroupBy, timezone: UtcOffset, header_last: bool) -> Self {
+        Self {
+            by,
+            timezone,
+            header_last,
+            expanded: HashSet::new(),
+            headers: HashMap::new(),
+            keys: Vec::new(),
+        }
+    }
+
+    pub fn by(&self) -> GroupBy {
+        self.by
+    }
+
+    /// Switch to the next way of grouping, folding every group
+    pub fn cycle(&mut self) {
+        self.by = self.by.next();
+        self.expanded.clear();
+    }
+
+    fn key(&self, h: &History) -> String {
+        match self.by {
+            GroupBy::None => String::new(),
+            GroupBy::Session => h.session.clone(),
+            GroupBy::Day => h
+                .timestamp
+                .to_offset(self.timezone)
+                .date()
+                .format(format_description!("[year]-[month]-[day]"))
+                .unwrap_or_default(),
+            GroupBy::Directory => h.cwd.clone(),
+        }
+    }
+
+    fn label(&self, key: &str) -> String {
+        match self.by {
+            GroupBy::Session => format!("session {}", key.get(..8).unwrap_or(key)),
+            _ => key.to_string(),
+        }
+    }
+
+    /// Lay out results as rows. Groups are ordered by their first result, and entries keep their
+    /// order within a group. Without grouping, the rows are the results.
+    pub fn layout(&mut self, results: &[History]) -> Vec<History> {
+        self.headers.clear();
+        self.keys.clear();
+
+        if self.by == GroupBy::None {
+            return results.to_vec();
+        }
+
+        let mut order: Vec<String> = Vec::new();
+        let mut groups: HashMap<String, Vec<&History>> = HashMap::new();
+
+        for h in results {
+            let key = self.key(h);
+
+            groups
+                .entry(key.clone())
+                .or_insert_with(|| {
+                    order.push(key);
+                    Vec::new()
+                })
+                .push(h);
+        }
+
+        let mut rows = Vec::with_capacity(order.len());
+
+        for key in order {
+            let entries = &groups[&key];
+            let expanded = self.expanded.contains(&key);
+
+            let mut hosts: Vec<&str> = entries
+                .iter()
+                .map(|h| h.hostname.split(':').next().unwrap_or_default())
+                .collect();
+            hosts.sort_unstable();
+            hosts.dedup();
+
+            let header = Header {
+                label: self.label(&key),
+                start: entries
+                    .iter()
+                    .map(|h| h.timestamp)
+                    .min()
+                    .unwrap_or_else(OffsetDateTime::now_utc)
+                    .to_offset(self.timezone),
+                host: if let [host] = hosts[..] {
+                    host.to_string()
+                } else {
+                    format!("{} hosts", hosts.len())
+                },
+                count: entries.len(),
+                expanded,
+            };
+
+            if expanded && !self.header_last {
+                self.push_header(&mut rows, &key, entries[0], header);
+                self.push_entries(&mut rows, &key, entries);
+            } else if expanded {
+                self.push_entries(&mut rows, &key, entries);
+                self.push_header(&mut rows, &key, entries[0], header);
+            } else {
+                self.push_header(&mut rows, &key, entries[0], header);
+            }
+        }
+
+        rows
+    }
+
+    fn push_header(&mut self, rows: &mut Vec<History>, key: &str, h: &History, header: Header) {
+        self.headers.insert(rows.len(), header);
+        self.keys.push(key.to_string());
+        rows.push(h.clone());
+    }
+
+    fn push_entries(&mut self, rows: &mut Vec<History>, key: &str, entries: &[&History]) {
+        for h in entries {
+            self.keys.push(key.to_string());
+            rows.push((*h).clone());
+        }
+    }
+
+    pub fn header(&self, row: usize) -> Option<&Header> {
+        self.headers.get(&row)
+    }
+
+    pub fn is_header(&self, row: usize) -> bool {
+        self.headers.contains_key(&row)
+    }
+
+    /// The numeric shortcut of a row, counting the entries after the selected row. Headers have
+    /// none.
+    pub fn shortcut(&self, selected: usize, row: usize) -> Option<usize> {
+        if row < selected || (row > selected && self.is_header(row)) {
+            return None;
+        }
+
+        Some((selected + 1..=row).filter(|r| !self.is_header(*r)).count())
+    }
+
+    /// The row a numeric shortcut refers to. Past the end of the list if there's no such entry.
+    pub fn shortcut_row(&self, selected: usize, shortcut: usize) -> usize {
+        (selected + 1..)
+            .filter(|r| !self.is_header(*r))
+            .nth(shortcut.saturating_sub(1))
+            .expect("there are only so many headers")
+    }
+
+    /// Fold or unfold the group a row belongs to, returning the group's key
+    pub fn toggle(&mut self, row: usize) -> Option<String> {
+        let key = self.keys.get(row)?.clone();
+
+        if !self.expanded.remove(&key) {
+            self.expanded.insert(key.clone());
+        }
+
+        Some(key)
+    }
+
+    /// The row of a group's header, after the last layout
+    pub fn header_row(&self, key: &str) -> Option<usize> {
+        self.headers
+            .keys()
+            .copied()
+            .find(|row| self.keys[*row] == key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::{history::History, settings::GroupBy};
+    use time::{OffsetDateTime, UtcOffset};
+
+    use super::Groups;
+
+    fn history(command: &str, session: &str, minutes_ago: i64) -> History {
+        History::import()
+            .timestamp(OffsetDateTime::now_utc() - time::Duration::minutes(minutes_ago))
+            .command(command)
+            .session(session)
+            .hostname("laptop:ellie")
+            .build()
+            .into()
+    }
+
+    #[test]
+    fn fold_by_session() {
+        let results = vec![
+            history("make test", "aaaaaaaaaaaa", 1),
+            history("vim", "bbbbbbbbbbbb", 2),
+            history("make", "aaaaaaaaaaaa", 3),
+        ];
+
+        let mut groups = Groups::new(GroupBy::None, UtcOffset::UTC, false);
+        assert_eq!(groups.layout(&results).len(), 3);
+        assert!(!groups.is_header(0));
+
+        groups.cycle();
+        assert_eq!(groups.by(), GroupBy::Session);
+
+        let rows = groups.layout(&results);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].command, "make test");
+        let header = groups.header(0).unwrap();
+        assert_eq!(header.label, "session aaaaaaaa");
+        assert_eq!(header.host, "laptop");
+        assert_eq!(header.count, 2);
+        assert!(!header.expanded);
+
+        let key = groups.toggle(0).unwrap();
+        let rows = groups.layout(&results);
+        let commands: Vec<_> = rows.iter().map(|h| h.command.as_str()).collect();
+        assert_eq!(commands, vec!["make test", "make test", "make", "vim"]);
+        assert_eq!(groups.header_row(&key), Some(0));
+        assert!(groups.header(0).unwrap().expanded);
+        assert!(groups.is_header(3));
+
+        // shortcuts skip headers
+        assert_eq!(groups.shortcut(0, 2), Some(2));
+        assert_eq!(groups.shortcut(0, 3), None);
+        assert_eq!(groups.shortcut_row(0, 2), 2);
+        assert_eq!(groups.shortcut_row(0, 3), 4);
+
+        // entries toggle the group they belong to
+        groups.toggle(2);
+        assert_eq!(groups.layout(&results).len(), 2);
+    }
+
+    #[test]
+    fn header_last() {
+        let results = vec![
+            history("make test", "aaaaaaaaaaaa", 1),
+            history("make", "aaaaaaaaaaaa", 3),
+        ];
+
+        let mut groups = Groups::new(GroupBy::Session, UtcOffset::UTC, true);
+        groups.layout(&results);
+        let key = groups.toggle(0).unwrap();
+
+        let rows = groups.layout(&results);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(groups.header_row(&key), Some(2));
+    }
+}
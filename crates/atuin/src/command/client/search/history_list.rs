@@ -1,12 +1,14 @@
-use std::time::Duration;
+use std::{borrow::Cow, collections::HashMap, time::Duration};
 
 use super::duration::format_duration;
 use super::engines::SearchEngine;
+use super::groups::{Groups, Header};
 use atuin_client::{
     history::History,
+    settings::columns::{Align, Column, ResultColumn},
     theme::{Meaning, Theme},
 };
-use atuin_common::utils::Escapable as _;
+use atuin_common::{syntax::tokenize, utils::Escapable as _};
 use itertools::Itertools;
 use ratatui::{
     buffer::Buffer,
@@ -15,7 +17,8 @@ use ratatui::{
     style::{Modifier, Style},
     widgets::{Block, StatefulWidget, Widget},
 };
-use time::OffsetDateTime;
+use time::{OffsetDateTime, UtcOffset, macros::format_description};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 pub struct HistoryHighlighter<'a> {
     pub engine: &'a dyn SearchEngine,
@@ -40,6 +43,14 @@ pub struct HistoryList<'a> {
     theme: &'a Theme,
     history_highlighter: HistoryHighlighter<'a>,
     show_numeric_shortcuts: bool,
+    /// Entries marked for batch actions
+    marked: &'a [History],
+    groups: Option<&'a Groups>,
+    /// The columns shown before each command
+    columns: Vec<&'a ResultColumn>,
+    timezone: UtcOffset,
+    /// The git branch of each directory, for the branch column
+    branches: Option<&'a HashMap<String, Option<String>>>,
 }
 
 #[derive(Default)]
@@ -58,6 +69,11 @@ impl ListState {
         self.max_entries
     }
 
+    /// The first entry shown
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
     pub fn select(&mut self, index: usize) {
         self.selected = index;
     }
@@ -95,13 +111,29 @@ impl StatefulWidget for HistoryList<'_> {
             theme: self.theme,
             history_highlighter: self.history_highlighter,
             show_numeric_shortcuts: self.show_numeric_shortcuts,
+            marked: self.marked,
+            groups: self.groups,
+            columns: &self.columns,
+            timezone: self.timezone,
+            branches: self.branches,
+            home: std::env::var("HOME").ok(),
         };
 
-        for item in self.history.iter().skip(state.offset).take(end - start) {
-            s.index();
-            s.duration(item);
-            s.time(item);
-            s.command(item);
+        for (row, item) in self
+            .history
+            .iter()
+            .enumerate()
+            .skip(state.offset)
+            .take(end - start)
+        {
+            s.index(item);
+
+            if let Some(header) = self.groups.and_then(|g| g.header(row)) {
+                s.header(header);
+            } else {
+                s.columns(item);
+                s.command(item);
+            }
 
             // reset line
             s.y += 1;
@@ -132,9 +164,41 @@ impl<'a> HistoryList<'a> {
             theme,
             history_highlighter,
             show_numeric_shortcuts,
+            marked: &[],
+            groups: None,
+            columns: Vec::new(),
+            timezone: UtcOffset::UTC,
+            branches: None,
         }
     }
 
+    /// Show columns before each command, leaving out those hidden in the compact styles if
+    /// `compact` is set
+    pub fn columns(mut self, columns: &'a [ResultColumn], compact: bool) -> Self {
+        self.columns = columns.iter().filter(|c| c.compact || !compact).collect();
+        self
+    }
+
+    pub fn timezone(mut self, timezone: UtcOffset) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    pub fn branches(mut self, branches: &'a HashMap<String, Option<String>>) -> Self {
+        self.branches = Some(branches);
+        self
+    }
+
+    pub fn marked(mut self, marked: &'a [History]) -> Self {
+        self.marked = marked;
+        self
+    }
+
+    pub fn groups(mut self, groups: &'a Groups) -> Self {
+        self.groups = Some(groups);
+        self
+    }
+
     pub fn block(mut self, block: Block<'a>) -> Self {
         self.block = Some(block);
         self
@@ -168,72 +232,180 @@ struct DrawState<'a> {
     theme: &'a Theme,
     history_highlighter: HistoryHighlighter<'a>,
     show_numeric_shortcuts: bool,
+    marked: &'a [History],
+    groups: Option<&'a Groups>,
+    columns: &'a [&'a ResultColumn],
+    timezone: UtcOffset,
+    branches: Option<&'a HashMap<String, Option<String>>>,
+    home: Option<String>,
 }
 
-// longest line prefix I could come up with
-#[allow(clippy::cast_possible_truncation)] // we know that this is <65536 length
-pub const PREFIX_LENGTH: u16 = " > 123ms 59s ago".len() as u16;
-static SPACES: &str = "                ";
-static _ASSERT: () = assert!(SPACES.len() == PREFIX_LENGTH as usize);
+/// The width of each row before its command: the prompt, then the columns shown
+pub fn prefix_width(columns: &[ResultColumn], compact: bool) -> usize {
+    let columns: Vec<&ResultColumn> = columns.iter().filter(|c| c.compact || !compact).collect();
+
+    3 + columns.iter().map(|c| usize::from(c.width)).sum::<usize>()
+        + columns.len().saturating_sub(1)
+}
 
 // these encode the slices of `" > "`, `" {n} "`, or `"   "` in a compact form.
 // Yes, this is a hack, but it makes me feel happy
 static SLICES: &str = " > 1 2 3 4 5 6 7 8 9   ";
 
 impl DrawState<'_> {
-    fn index(&mut self) {
+    fn index(&mut self, h: &History) {
+        let row = self.y as usize + self.state.offset;
+
+        // a header's entry stands for its group, so isn't shown as marked
+        let marked = !self.groups.is_some_and(|g| g.is_header(row))
+            && self.marked.iter().any(|m| m.id == h.id);
+
         if !self.show_numeric_shortcuts {
-            let i = self.y as usize + self.state.offset;
-            let is_selected = i == self.state.selected();
+            let is_selected = row == self.state.selected();
             let prompt: &str = if is_selected { self.indicator } else { "   " };
-            self.draw(prompt, Style::default());
+            self.prompt(prompt, marked);
             return;
         }
 
         // these encode the slices of `" > "`, `" {n} "`, or `"   "` in a compact form.
         // Yes, this is a hack, but it makes me feel happy
 
-        let i = self.y as usize + self.state.offset;
-        let i = i.checked_sub(self.state.selected);
+        let i = match self.groups {
+            Some(groups) => groups.shortcut(self.state.selected, row),
+            None => row.checked_sub(self.state.selected),
+        };
         let i = i.unwrap_or(10).min(10) * 2;
         let prompt: &str = if i == 0 {
             self.indicator
         } else {
             &SLICES[i..i + 3]
         };
-        self.draw(prompt, Style::default());
+        self.prompt(prompt, marked);
+    }
+
+    /// Draw the row prompt, replacing its first character with a marker on marked rows
+    fn prompt(&mut self, prompt: &str, marked: bool) {
+        if !marked {
+            self.draw(prompt, Style::default());
+            return;
+        }
+
+        let rest = prompt
+            .char_indices()
+            .nth(1)
+            .map_or("", |(i, _)| &prompt[i..]);
+        self.draw("+", self.theme.as_style(Meaning::Important).into());
+        self.draw(rest, Style::default());
     }
 
-    fn duration(&mut self, h: &History) {
-        let status = self.theme.as_style(if h.success() {
+    fn header(&mut self, header: &Header) {
+        let mut style = self.theme.as_style(Meaning::Title);
+        if !self.alternate_highlight && (self.y as usize + self.state.offset == self.state.selected)
+        {
+            style.attributes.set(style::Attribute::Bold);
+        }
+
+        self.draw(&header.to_string(), style.into());
+    }
+
+    /// Draw the columns before the command. Every column ends at a fixed position, so a value
+    /// too long for its column takes space from the padding of the next ones.
+    #[allow(clippy::cast_possible_truncation)]
+    fn columns(&mut self, h: &History) {
+        let mut end = usize::from(self.x);
+
+        for (i, column) in self.columns.iter().enumerate() {
+            if i > 0 {
+                self.draw(" ", Style::default());
+                end += 1;
+            }
+            end += usize::from(column.width);
+
+            let (value, meaning) = self.column_value(column.column, h);
+            let value = if column.column.truncates() {
+                truncate(
+                    &value,
+                    column.width.into(),
+                    column.column == Column::Directory,
+                )
+            } else {
+                Cow::from(value)
+            };
+
+            let padding = " ".repeat(end.saturating_sub(usize::from(self.x) + value.width()));
+            let style = self.theme.as_style(meaning).into();
+
+            match column.align {
+                Align::Left => {
+                    self.draw(&value, style);
+                    self.draw(&padding, Style::default());
+                }
+                Align::Right => {
+                    self.draw(&padding, Style::default());
+                    self.draw(&value, style);
+                }
+            }
+        }
+    }
+
+    fn column_value(&self, column: Column, h: &History) -> (String, Meaning) {
+        let status = if h.success() {
             Meaning::AlertInfo
         } else {
             Meaning::AlertError
-        });
-        let duration = Duration::from_nanos(u64::try_from(h.duration).unwrap_or(0));
-        self.draw(&format_duration(duration), status.into());
-    }
+        };
 
-    #[allow(clippy::cast_possible_truncation)] // we know that time.len() will be <6
-    fn time(&mut self, h: &History) {
-        let style = self.theme.as_style(Meaning::Guidance);
-
-        // Account for the chance that h.timestamp is "in the future"
-        // This would mean that "since" is negative, and the unwrap here
-        // would fail.
-        // If the timestamp would otherwise be in the future, display
-        // the time since as 0.
-        let since = (self.now)() - h.timestamp;
-        let time = format_duration(since.try_into().unwrap_or_default());
-
-        // pad the time a little bit before we write. this aligns things nicely
-        // skip padding if for some reason it is already too long to align nicely
-        let padding =
-            usize::from(PREFIX_LENGTH).saturating_sub(usize::from(self.x) + 4 + time.len());
-        self.draw(&SPACES[..padding], Style::default());
-
-        self.draw(&time, style.into());
-        self.draw(" ago", style.into());
+        match column {
+            Column::Duration => {
+                let duration = Duration::from_nanos(u64::try_from(h.duration).unwrap_or(0));
+                (format_duration(duration), status)
+            }
+            Column::Time => {
+                // Account for the chance that h.timestamp is "in the future"
+                // This would mean that "since" is negative, and the unwrap here
+                // would fail.
+                // If the timestamp would otherwise be in the future, display
+                // the time since as 0.
+                let since = (self.now)() - h.timestamp;
+                let time = format_duration(since.try_into().unwrap_or_default());
+                (format!("{time} ago"), Meaning::Guidance)
+            }
+            Column::Datetime => (
+                h.timestamp
+                    .to_offset(self.timezone)
+                    .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+                    .unwrap_or_default(),
+                Meaning::Guidance,
+            ),
+            Column::Exit => (h.exit.to_string(), status),
+            Column::Host => (
+                h.hostname.split(':').next().unwrap_or_default().to_string(),
+                Meaning::Annotation,
+            ),
+            Column::User => (
+                h.hostname.split(':').nth(1).unwrap_or_default().to_string(),
+                Meaning::Annotation,
+            ),
+            Column::Directory => {
+                let cwd = match &self.home {
+                    Some(home) if !home.is_empty() && h.cwd.starts_with(home.as_str()) => {
+                        format!("~{}", &h.cwd[home.len()..])
+                    }
+                    _ => h.cwd.clone(),
+                };
+                (cwd, Meaning::Annotation)
+            }
+            Column::Session => (
+                h.session.get(..8).unwrap_or(&h.session).to_string(),
+                Meaning::Annotation,
+            ),
+            Column::Branch => (
+                self.branches
+                    .and_then(|branches| branches.get(&h.cwd).cloned().flatten())
+                    .unwrap_or_default(),
+                Meaning::Guidance,
+            ),
+        }
     }
 
     fn command(&mut self, h: &History) {
@@ -247,25 +419,32 @@ impl DrawState<'_> {
             style.attributes.set(style::Attribute::Bold);
         }
 
-        let highlight_indices = self.history_highlighter.get_highlight_indices(
-            h.command
-                .escape_control()
-                .split_ascii_whitespace()
-                .join(" ")
-                .as_str(),
-        );
-
-        let mut pos = 0;
-        for section in h.command.escape_control().split_ascii_whitespace() {
-            self.draw(" ", style.into());
-            for ch in section.chars() {
+        let command = h
+            .command
+            .escape_control()
+            .split_ascii_whitespace()
+            .join(" ");
+        let highlight_indices = self
+            .history_highlighter
+            .get_highlight_indices(command.as_str());
+
+        self.draw(" ", style.into());
+        for token in tokenize(&command) {
+            // the selected row is already coloured, so it isn't highlighted
+            let token_style = if row_highlighted {
+                style
+            } else {
+                self.theme.as_style(Meaning::for_token(token.kind))
+            };
+
+            for (i, ch) in token.text.char_indices() {
                 if self.x > self.list_area.width {
                     // Avoid attempting to draw a command section beyond the width
                     // of the list
                     return;
                 }
-                let mut style = style;
-                if highlight_indices.contains(&pos) {
+                let mut style = token_style;
+                if highlight_indices.contains(&(token.start + i)) {
                     if row_highlighted {
                         // if the row is highlighted bold is not enough as the whole row is bold
                         // change the color too
@@ -273,11 +452,8 @@ impl DrawState<'_> {
                     }
                     style.attributes.set(style::Attribute::Bold);
                 }
-                let s = ch.to_string();
-                self.draw(&s, style.into());
-                pos += s.len();
+                self.draw(&ch.to_string(), style.into());
             }
-            pos += 1;
         }
     }
 
@@ -299,3 +475,59 @@ impl DrawState<'_> {
         self.x += self.buf.set_stringn(cx, cy, s, w, style).0 - cx;
     }
 }
+
+/// Cut a value down to a width, marking the cut with an ellipsis. Keeping the end suits
+/// directories, which differ more at their ends.
+fn truncate(value: &str, width: usize, keep_end: bool) -> Cow<'_, str> {
+    if value.width() <= width {
+        return Cow::from(value);
+    }
+
+    let mut kept = 0;
+    let fits = |c: &char| {
+        kept += c.width().unwrap_or(0);
+        kept < width
+    };
+
+    if keep_end {
+        let end: String = value.chars().rev().take_while(fits).collect();
+        Cow::from(format!("…{}", end.chars().rev().collect::<String>()))
+    } else {
+        let start: String = value.chars().take_while(fits).collect();
+        Cow::from(format!("{start}…"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atuin_client::settings::columns::{Column, ResultColumn};
+
+    use super::{prefix_width, truncate};
+
+    #[test]
+    fn prefix_widths() {
+        let default = [Column::Duration, Column::Time].map(ResultColumn::from);
+        // lines commands up with the search input
+        assert_eq!(prefix_width(&default, false), " > 123ms 59s ago".len());
+
+        let wide = [
+            ResultColumn {
+                compact: false,
+                ..Column::Directory.into()
+            },
+            Column::Exit.into(),
+        ];
+        assert_eq!(prefix_width(&wide, false), 3 + 24 + 1 + 3);
+        assert_eq!(prefix_width(&wide, true), 3 + 3);
+        assert_eq!(prefix_width(&[], false), 3);
+    }
+
+    #[test]
+    fn truncate_values() {
+        assert_eq!(truncate("main", 16, false), "main");
+        assert_eq!(truncate("feature/columns", 8, false), "feature…");
+        assert_eq!(truncate("~/code/atuin/crates", 10, true), "…in/crates");
+        // wide characters count for their display width
+        assert_eq!(truncate("日本語のブランチ", 5, false), "日本…");
+    }
+}
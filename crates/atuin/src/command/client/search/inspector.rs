@@ -2,17 +2,18 @@ use std::time::Duration;
 use time::macros::format_description;
 
 use atuin_client::{
-    history::{History, HistoryStats},
-    settings::{Settings, Timezone},
+    history::{self, History, HistoryStats},
+    settings::{Settings, Timezone, keymap::Action},
 };
 use ratatui::{
     Frame,
-    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
     layout::Rect,
     prelude::{Constraint, Direction, Layout},
     style::Style,
-    text::{Span, Text},
-    widgets::{Bar, BarChart, BarGroup, Block, Borders, Padding, Paragraph, Row, Table},
+    text::{Line, Span, Text},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Padding, Paragraph, Row, Table, TableState,
+    },
 };
 
 use super::duration::format_duration;
@@ -123,8 +124,22 @@ pub fn draw_stats_table(
 ) {
     let duration = Duration::from_nanos(u64_or_zero(history.duration));
     let avg_duration = Duration::from_nanos(stats.average_duration);
+    let median_duration = Duration::from_nanos(stats.median_duration);
+    let p95_duration = Duration::from_nanos(stats.p95_duration);
     let (host, user) = history.hostname.split_once(':').unwrap_or(("", ""));
 
+    let failures: i64 = stats
+        .exits
+        .iter()
+        .filter(|(exit, _)| history::failed(*exit))
+        .map(|(_, count)| count)
+        .sum();
+    let failure_rate = if stats.total > 0 {
+        u64_or_zero(failures) * 100 / stats.total
+    } else {
+        0
+    };
+
     let rows = [
         Row::new(vec!["Host".to_string(), host.to_string()]),
         Row::new(vec!["User".to_string(), user.to_string()]),
@@ -137,7 +152,26 @@ pub fn draw_stats_table(
             "Avg duration".to_string(),
             format_duration(avg_duration),
         ]),
+        Row::new(vec![
+            "Median / p95".to_string(),
+            format!(
+                "{} / {}",
+                format_duration(median_duration),
+                format_duration(p95_duration)
+            ),
+        ]),
         Row::new(vec!["Exit".to_string(), history.exit.to_string()]),
+        Row::new(vec![
+            "Failures".to_string(),
+            format!("{failures} ({failure_rate}%)"),
+        ]),
+        Row::new(vec![
+            "Fail streak".to_string(),
+            format!(
+                "{} now, {} at most",
+                stats.current_failure_streak, stats.longest_failure_streak
+            ),
+        ]),
         Row::new(vec!["Directory".to_string(), history.cwd.clone()]),
         Row::new(vec!["Session".to_string(), history.session.clone()]),
         Row::new(vec!["Total runs".to_string(), stats.total.to_string()]),
@@ -156,6 +190,66 @@ pub fn draw_stats_table(
     f.render_widget(table, parent);
 }
 
+/// The commands around the inspected one in its session. The inspected one is selected, so
+/// moving through the session moves through the list, scrolling it to keep the selection shown.
+pub fn draw_context(
+    f: &mut Frame<'_>,
+    parent: Rect,
+    history: &History,
+    context: &[History],
+    tz: Timezone,
+    theme: &Theme,
+) {
+    let format = format_description!("[hour]:[minute]:[second]");
+
+    let rows = context.iter().map(|h| {
+        let time = h
+            .timestamp
+            .to_offset(tz.0)
+            .format(format)
+            .unwrap_or_default();
+        let duration = format_duration(Duration::from_nanos(u64_or_zero(h.duration)));
+        let exit = Span::styled(
+            h.exit.to_string(),
+            theme.as_style(if h.success() {
+                Meaning::AlertInfo
+            } else {
+                Meaning::AlertError
+            }),
+        );
+
+        Row::new(vec![
+            Span::raw(time),
+            exit,
+            Span::raw(duration),
+            Span::raw(h.command.clone()),
+        ])
+    });
+
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(4),
+        Constraint::Length(6),
+        Constraint::Min(0),
+    ];
+
+    let table = Table::new(rows, widths)
+        .column_spacing(1)
+        .row_highlight_style(theme.as_style(Meaning::Important))
+        .highlight_symbol("> ")
+        .block(
+            Block::default()
+                .title("Session context")
+                .borders(Borders::ALL)
+                .style(theme.as_style(Meaning::Base))
+                .padding(Padding::horizontal(1)),
+        );
+
+    let mut state =
+        TableState::default().with_selected(context.iter().position(|h| h.id == history.id));
+    f.render_stateful_widget(table, parent, &mut state);
+}
+
 fn num_to_day(num: &str) -> String {
     match num {
         "0" => "Sunday".to_string(),
@@ -169,11 +263,11 @@ fn num_to_day(num: &str) -> String {
     }
 }
 
-fn sort_duration_over_time(durations: &[(String, i64)]) -> Vec<(String, i64)> {
+fn sort_duration_over_time<T: Copy>(durations: &[(String, T)]) -> Vec<(String, T)> {
     let format = format_description!("[day]-[month]-[year]");
     let output = format_description!("[month]/[year repr:last_two]");
 
-    let mut durations: Vec<(time::Date, i64)> = durations
+    let mut durations: Vec<(time::Date, T)> = durations
         .iter()
         .map(|d| {
             (
@@ -196,14 +290,39 @@ fn sort_duration_over_time(durations: &[(String, i64)]) -> Vec<(String, i64)> {
         .collect()
 }
 
+/// A table of places a command runs in, with how often it runs there
+fn places_table<'a>(title: &'a str, places: &'a [(String, i64)], theme: &Theme) -> Table<'a> {
+    let rows = places
+        .iter()
+        .map(|(place, count)| Row::new(vec![count.to_string(), place.clone()]));
+
+    Table::new(rows, [Constraint::Length(6), Constraint::Min(0)])
+        .column_spacing(1)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .style(theme.as_style(Meaning::Base))
+                .padding(Padding::horizontal(1)),
+        )
+}
+
+#[allow(clippy::too_many_lines)]
 fn draw_stats_charts(f: &mut Frame<'_>, parent: Rect, stats: &HistoryStats, theme: &Theme) {
     let exits: Vec<Bar> = stats
         .exits
         .iter()
         .map(|(exit, count)| {
+            let meaning = if *exit == 0 {
+                Meaning::AlertInfo
+            } else {
+                Meaning::AlertError
+            };
+
             Bar::default()
                 .label(exit.to_string().into())
                 .value(u64_or_zero(*count))
+                .style(Style::from(theme.as_style(meaning)))
         })
         .collect();
 
@@ -271,25 +390,80 @@ fn draw_stats_charts(f: &mut Frame<'_>, parent: Rect, stats: &HistoryStats, them
         .label_style(Style::default())
         .data(BarGroup::default().bars(&duration_over_time));
 
+    // the median and p95 side by side for each month, to show when the command got slower
+    let percentiles: Vec<(String, (i64, i64))> = stats
+        .duration_percentiles
+        .iter()
+        .map(|(month, median, p95)| (month.clone(), (*median, *p95)))
+        .collect();
+    let mut duration_trend = BarChart::default()
+        .block(
+            Block::default()
+                .title(Line::from(vec![
+                    Span::raw("Duration trend: "),
+                    Span::styled("median", theme.as_style(Meaning::AlertInfo)),
+                    Span::raw(" / "),
+                    Span::styled("p95", theme.as_style(Meaning::AlertWarn)),
+                ]))
+                .style(theme.as_style(Meaning::Base))
+                .borders(Borders::ALL),
+        )
+        .bar_width(5)
+        .bar_gap(0)
+        .group_gap(2)
+        .value_style(Style::default())
+        .label_style(Style::default());
+    for (month, (median, p95)) in sort_duration_over_time(&percentiles) {
+        let bar = |duration: i64, meaning| {
+            Bar::default()
+                .value(u64_or_zero(duration))
+                .text_value(format_duration(Duration::from_nanos(u64_or_zero(duration))))
+                .style(Style::from(theme.as_style(meaning)))
+        };
+
+        duration_trend = duration_trend.data(BarGroup::default().label(month.into()).bars(&[
+            bar(median, Meaning::AlertInfo),
+            bar(p95, Meaning::AlertWarn),
+        ]));
+    }
+
+    let hosts = places_table("Hosts", &stats.hosts, theme);
+    let directories = places_table("Directories", &stats.directories, theme);
+
     let layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Ratio(1, 3),
-            Constraint::Ratio(1, 3),
-            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 4),
+            Constraint::Ratio(1, 4),
+            Constraint::Ratio(1, 4),
+            Constraint::Ratio(1, 4),
         ])
         .split(parent);
+    let halves = |area| {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+            .split(area)
+    };
 
-    f.render_widget(exits, layout[0]);
-    f.render_widget(day_of_week, layout[1]);
-    f.render_widget(duration_over_time, layout[2]);
+    let top = halves(layout[0]);
+    f.render_widget(exits, top[0]);
+    f.render_widget(day_of_week, top[1]);
+    f.render_widget(duration_over_time, layout[1]);
+    f.render_widget(duration_trend, layout[2]);
+
+    let bottom = halves(layout[3]);
+    f.render_widget(hosts, bottom[0]);
+    f.render_widget(directories, bottom[1]);
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn draw(
     f: &mut Frame<'_>,
     chunk: Rect,
     history: &History,
     stats: &HistoryStats,
+    context: &[History],
     settings: &Settings,
     theme: &Theme,
     tz: Timezone,
@@ -298,7 +472,7 @@ pub fn draw(
 
     match compactness {
         Compactness::Ultracompact => draw_ultracompact(f, chunk, history, stats, theme),
-        _ => draw_full(f, chunk, history, stats, theme, tz),
+        _ => draw_full(f, chunk, history, stats, context, theme, tz),
     }
 }
 
@@ -317,41 +491,76 @@ pub fn draw_full(
     chunk: Rect,
     history: &History,
     stats: &HistoryStats,
+    context: &[History],
     theme: &Theme,
     tz: Timezone,
 ) {
+    // Without any other commands in the session, there is nothing to show beyond the
+    // previous/next view
+    if context.len() <= 1 {
+        let vert_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Ratio(1, 5), Constraint::Ratio(4, 5)])
+            .split(chunk);
+
+        draw_commands(f, vert_layout[0], history, stats, false, theme);
+        draw_stats(f, vert_layout[1], history, stats, theme, tz);
+        return;
+    }
+
+    // rows, plus the borders
+    let context_height = u16::try_from(context.len() + 2)
+        .unwrap_or(u16::MAX)
+        .min(chunk.height / 2);
+
     let vert_layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Ratio(1, 5), Constraint::Ratio(4, 5)])
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(context_height),
+            Constraint::Min(0),
+        ])
         .split(chunk);
 
+    draw_commands(f, vert_layout[0], history, stats, false, theme);
+    draw_context(f, vert_layout[1], history, context, tz, theme);
+    draw_stats(f, vert_layout[2], history, stats, theme, tz);
+}
+
+fn draw_stats(
+    f: &mut Frame<'_>,
+    chunk: Rect,
+    history: &History,
+    stats: &HistoryStats,
+    theme: &Theme,
+    tz: Timezone,
+) {
     let stats_layout = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Ratio(1, 3), Constraint::Ratio(2, 3)])
-        .split(vert_layout[1]);
+        .split(chunk);
 
-    draw_commands(f, vert_layout[0], history, stats, false, theme);
     draw_stats_table(f, stats_layout[0], history, tz, stats, theme);
     draw_stats_charts(f, stats_layout[1], stats, theme);
 }
 
-// I'm going to break this out more, but just starting to move things around before changing
-// structure and making it nicer.
+/// Handle a key bound to `action` in the inspector. Moving up and down walks through the
+/// session, inspecting each command in turn, and accepting picks the inspected command.
 pub fn input(
     state: &mut State,
-    _settings: &Settings,
+    settings: &Settings,
     selected: usize,
-    input: &KeyEvent,
+    action: Option<Action>,
 ) -> InputAction {
-    let ctrl = input.modifiers.contains(KeyModifiers::CONTROL);
-
-    match input.code {
-        KeyCode::Char('d') if ctrl => InputAction::Delete(selected),
-        KeyCode::Up => {
+    match action {
+        // there's no query to edit here, so deleting a character deletes the entry instead
+        Some(Action::Delete | Action::DeleteOrReturnOriginal) => InputAction::Delete(selected),
+        Some(Action::Accept) => state.handle_inspector_accept(settings),
+        Some(Action::ScrollUp | Action::SelectPrevious) => {
             state.inspecting_state.move_to_previous();
             InputAction::Redraw
         }
-        KeyCode::Down => {
+        Some(Action::ScrollDown | Action::SelectNext) => {
             state.inspecting_state.move_to_next();
             InputAction::Redraw
         }
@@ -361,13 +570,14 @@ pub fn input(
 
 #[cfg(test)]
 mod tests {
-    use super::draw_ultracompact;
+    use super::{draw_context, draw_stats_table, draw_ultracompact};
     use atuin_client::{
         history::{History, HistoryId, HistoryStats},
+        settings::Timezone,
         theme::ThemeManager,
     };
     use ratatui::{backend::TestBackend, prelude::*};
-    use time::OffsetDateTime;
+    use time::{OffsetDateTime, UtcOffset};
 
     fn mock_history_stats() -> (History, HistoryStats) {
         let history = History {
@@ -404,13 +614,20 @@ mod tests {
             deleted_at: None,
         };
         let stats = HistoryStats {
-            next: Some(next.clone()),
-            previous: Some(prev.clone()),
+            next: Some(next),
+            previous: Some(prev),
             total: 2,
             average_duration: 3,
             exits: Vec::new(),
             day_of_week: Vec::new(),
             duration_over_time: Vec::new(),
+            duration_percentiles: Vec::new(),
+            median_duration: 3,
+            p95_duration: 3,
+            current_failure_streak: 0,
+            longest_failure_streak: 0,
+            hosts: Vec::new(),
+            directories: Vec::new(),
         };
         (history, stats)
     }
@@ -424,10 +641,10 @@ mod tests {
         let prev = stats.previous.clone().unwrap();
         let next = stats.next.clone().unwrap();
 
-        let mut manager = ThemeManager::new(Some(true), Some("".to_string()));
+        let mut manager = ThemeManager::new(Some(true), Some(String::new()));
         let theme = manager.load_theme("(none)", None);
-        let _ = terminal.draw(|f| draw_ultracompact(f, chunk, &history, &stats, &theme));
-        let mut lines = ["                      "; 5].map(|l| Line::from(l));
+        let _ = terminal.draw(|f| draw_ultracompact(f, chunk, &history, &stats, theme));
+        let mut lines = ["                      "; 5].map(Line::from);
         for (n, entry) in [prev, history, next].iter().enumerate() {
             let mut l = lines[n].to_string();
             l.replace_range(0..entry.command.len(), &entry.command);
@@ -436,4 +653,78 @@ mod tests {
 
         terminal.backend().assert_buffer_lines(lines);
     }
+
+    #[test]
+    fn test_context_lists_session_in_order() {
+        let backend = TestBackend::new(40, 5);
+        let mut terminal = Terminal::new(backend).expect("Could not create terminal");
+        let chunk = Rect::new(0, 0, 40, 5);
+        let (history, stats) = mock_history_stats();
+        let context = [
+            stats.previous.clone().unwrap(),
+            history.clone(),
+            stats.next.unwrap(),
+        ];
+
+        let mut manager = ThemeManager::new(Some(true), Some(String::new()));
+        let theme = manager.load_theme("(none)", None);
+        let _ = terminal.draw(|f| {
+            draw_context(
+                f,
+                chunk,
+                &history,
+                &context,
+                Timezone(UtcOffset::UTC),
+                theme,
+            );
+        });
+
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (1..4)
+            .map(|y| {
+                (0..40)
+                    .map(|x| buffer[(x, y)].symbol().to_string())
+                    .collect::<String>()
+            })
+            .collect();
+
+        for (row, entry) in rows.iter().zip(&context) {
+            assert!(
+                row.trim_end_matches([' ', '│']).ends_with(&entry.command),
+                "{row:?} should end with {:?}",
+                entry.command
+            );
+        }
+    }
+
+    #[test]
+    fn test_stats_table_shows_failures() {
+        let backend = TestBackend::new(60, 18);
+        let mut terminal = Terminal::new(backend).expect("Could not create terminal");
+        let chunk = Rect::new(0, 0, 60, 18);
+        let (history, mut stats) = mock_history_stats();
+        stats.total = 4;
+        stats.exits = vec![(0, 1), (1, 2), (2, 1)];
+        stats.current_failure_streak = 2;
+        stats.longest_failure_streak = 3;
+
+        let mut manager = ThemeManager::new(Some(true), Some(String::new()));
+        let theme = manager.load_theme("(none)", None);
+        let _ = terminal.draw(|f| {
+            draw_stats_table(f, chunk, &history, Timezone(UtcOffset::UTC), &stats, theme);
+        });
+
+        let buffer = terminal.backend().buffer();
+        let text: String = (0..18)
+            .map(|y| {
+                (0..60)
+                    .map(|x| buffer[(x, y)].symbol().to_string())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        assert!(text.contains("3 (75%)"), "{text}");
+        assert!(text.contains("2 now, 3 at most"), "{text}");
+    }
 }
@@ -1,30 +1,44 @@
 use std::{
+    collections::HashMap,
     io::{Write, stdout},
+    ops::Range,
     time::Duration,
 };
 
-use atuin_common::{shell::Shell, utils::Escapable as _};
+use atuin_common::{
+    shell::Shell,
+    syntax::{Token, tokenize},
+    utils::{self, Escapable as _},
+};
 use eyre::Result;
 use futures_util::FutureExt;
 use semver::Version;
-use time::OffsetDateTime;
+use time::{OffsetDateTime, macros::format_description};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
 use unicode_width::UnicodeWidthStr;
 
 use super::{
     cursor::Cursor,
+    editor::Editor,
     engines::{SearchEngine, SearchState},
-    history_list::{HistoryList, ListState, PREFIX_LENGTH},
+    groups::Groups,
+    history_list::{HistoryList, ListState, prefix_width},
 };
 use atuin_client::{
     database::{Database, current_context},
     history::{History, HistoryId, HistoryStats, store::HistoryStore},
     settings::{
-        CursorStyle, ExitMode, FilterMode, KeymapMode, PreviewStrategy, SearchMode, Settings,
+        CursorStyle, ExitMode, FilterMode, GroupBy, KeymapMode, PreviewStrategy, SearchMode,
+        Settings,
+        columns::Column,
+        keymap::{Action, Key, KeyChord},
     },
 };
 
-use crate::command::client::search::history_list::HistoryHighlighter;
+use atuin_scripts::store::{ScriptStore, script::Script};
+
 use crate::command::client::theme::{Meaning, Theme};
+use crate::command::client::{scripts, search::history_list::HistoryHighlighter};
 use crate::{VERSION, command::client::search::engines};
 
 use ratatui::{
@@ -52,11 +66,31 @@ use ratatui::crossterm::event::{
 
 const TAB_TITLES: [&str; 2] = ["Search", "Inspect"];
 
+/// The ways of grouping the results, shown as tabs next to the others
+const GROUP_TITLES: [(GroupBy, &str); 4] = [
+    (GroupBy::None, "Flat"),
+    (GroupBy::Session, "Sessions"),
+    (GroupBy::Day, "Days"),
+    (GroupBy::Directory, "Directories"),
+];
+
 pub enum InputAction {
     Accept(usize),
     AcceptInspecting,
+    /// Accept a command edited before accepting it
+    AcceptEdited(String),
+    EditCommand,
+    OpenEditor,
     Copy(usize),
     Delete(usize),
+    ToggleMark,
+    MarkRange,
+    DeleteMarked,
+    /// Copy the marked commands, joined with the given separator
+    CopyMarked(&'static str),
+    SaveScript,
+    ToggleGroup,
+    Regroup,
     ReturnOriginal,
     ReturnQuery,
     Continue,
@@ -120,7 +154,21 @@ pub struct State {
     current_cursor: Option<CursorStyle>,
     tab_index: usize,
 
+    /// Entries marked for the batch actions, kept across searches
+    marked: Vec<History>,
+    /// The last entry marked or unmarked, where a range of marks starts
+    mark_anchor: Option<HistoryId>,
+    groups: Groups,
+
     pub inspecting_state: InspectingState,
+    /// The commands around the inspected one in its session
+    context: Vec<History>,
+    /// The command being edited before accepting it, if any
+    editing: Option<Editor>,
+    /// The git branch of each directory in the results, for the branch column
+    branches: HashMap<String, Option<String>>,
+    /// An error to show in place of the help, until the next key
+    notice: Option<String>,
 
     search: SearchState,
     engine: Box<dyn SearchEngine>,
@@ -139,15 +187,33 @@ struct StyleState {
     compactness: Compactness,
     invert: bool,
     inner_width: usize,
+    prefix_width: usize,
+}
+
+impl StyleState {
+    /// The width of the box showing the current mode. It's as wide as the row prefix, to line
+    /// the query up with the commands, as long as that leaves room for every mode.
+    fn mode_box_width(self) -> usize {
+        /// The narrowest the box can be, fitting the longest mode between its brackets
+        const MIN_WIDTH: usize = 16;
+
+        self.prefix_width.max(MIN_WIDTH)
+    }
 }
 
 impl State {
     async fn query_results(
         &mut self,
         db: &mut dyn Database,
-        smart_sort: bool,
+        settings: &Settings,
     ) -> Result<Vec<History>> {
-        let results = self.engine.query(&self.search, db).await?;
+        let mut results = self.engine.query(&self.search, db).await?;
+
+        // the results are unique by command, and normalising makes them unique by what the
+        // commands do
+        if settings.stats.normalize {
+            atuin_history::normalize::unique(settings, &mut results);
+        }
 
         self.inspecting_state = InspectingState {
             current: None,
@@ -157,7 +223,7 @@ impl State {
         self.results_state.select(0);
         self.results_len = results.len();
 
-        if smart_sort {
+        if settings.smart_sort {
             Ok(atuin_history::sort::sort(
                 self.search.input.as_str(),
                 results,
@@ -178,7 +244,10 @@ impl State {
     {
         execute!(w, EnableMouseCapture)?;
         let r = match input {
-            Event::Key(k) => self.handle_key_input(settings, k),
+            Event::Key(k) => {
+                self.notice = None;
+                self.handle_key_input(settings, k)
+            }
             Event::Mouse(m) => self.handle_mouse_input(*m),
             Event::Paste(d) => self.handle_paste_input(d),
             _ => InputAction::Continue,
@@ -201,8 +270,14 @@ impl State {
     }
 
     fn handle_paste_input(&mut self, input: &str) -> InputAction {
-        for i in input.chars() {
-            self.search.input.insert(i);
+        if let Some(editor) = self.editing.as_mut() {
+            for i in input.chars() {
+                editor.insert(i);
+            }
+        } else {
+            for i in input.chars() {
+                self.search.input.insert(i);
+            }
         }
         InputAction::Continue
     }
@@ -266,11 +341,18 @@ impl State {
         }
 
         let ctrl = input.modifiers.contains(KeyModifiers::CONTROL);
-        let esc_allow_exit = !(self.tab_index == 0 && self.keymap_mode == KeymapMode::VimInsert);
         let cursor_at_end_of_line =
             self.search.input.position() == UnicodeWidthStr::width(self.search.input.as_str());
         let cursor_at_start_of_line = self.search.input.position() == 0;
 
+        let action =
+            key_chord(input).and_then(|chord| settings.keymap.action(self.keymap_mode, &chord));
+
+        // the editor takes every key while editing a command, without the prefix mappings
+        if self.editing.is_some() {
+            return self.handle_editor_input(settings, input, action);
+        }
+
         // support ctrl-a prefix, like screen or tmux
         if !self.prefix
             && ctrl
@@ -281,56 +363,89 @@ impl State {
         }
 
         // core input handling, common for all tabs
-        let common: Option<InputAction> = match input.code {
-            KeyCode::Char('c' | 'g') if ctrl => Some(InputAction::ReturnOriginal),
-            KeyCode::Esc if esc_allow_exit => Some(Self::handle_key_exit(settings)),
-            KeyCode::Char('[') if ctrl && esc_allow_exit => Some(Self::handle_key_exit(settings)),
-            KeyCode::Tab => match self.tab_index {
+        let common: Option<InputAction> = match (input.code, action) {
+            (_, Some(Action::ReturnOriginal)) => Some(InputAction::ReturnOriginal),
+            (_, Some(Action::ReturnQuery)) => Some(InputAction::ReturnQuery),
+            (_, Some(Action::Exit)) => Some(Self::handle_key_exit(settings)),
+            // there is no input to edit in the inspector, so leaving insert mode exits instead
+            (_, Some(Action::VimNormal)) if self.tab_index == 1 => {
+                Some(Self::handle_key_exit(settings))
+            }
+            (_, Some(Action::AcceptAndEdit)) => match self.tab_index {
                 0 => Some(InputAction::Accept(self.results_state.selected())),
 
                 1 => Some(InputAction::AcceptInspecting),
 
                 _ => panic!("invalid tab index on input"),
             },
-            KeyCode::Right if cursor_at_end_of_line && settings.keys.accept_past_line_end => {
+            (_, Some(Action::ToggleInspector)) => {
+                self.tab_index = (self.tab_index + 1) % TAB_TITLES.len();
+                Some(InputAction::Continue)
+            }
+            (KeyCode::Right, _) if cursor_at_end_of_line && settings.keys.accept_past_line_end => {
                 Some(InputAction::Accept(self.results_state.selected()))
             }
-            KeyCode::Left if cursor_at_start_of_line && settings.keys.accept_past_line_start => {
+            (KeyCode::Left, _)
+                if cursor_at_start_of_line && settings.keys.accept_past_line_start =>
+            {
                 Some(InputAction::Accept(self.results_state.selected()))
             }
-            KeyCode::Left if cursor_at_start_of_line && settings.keys.exit_past_line_start => {
+            (KeyCode::Left, _) if cursor_at_start_of_line && settings.keys.exit_past_line_start => {
                 Some(Self::handle_key_exit(settings))
             }
-            KeyCode::Backspace
+            (KeyCode::Backspace, _)
                 if cursor_at_start_of_line && settings.keys.accept_with_backspace =>
             {
                 Some(InputAction::Accept(self.results_state.selected()))
             }
-            KeyCode::Char('o') if ctrl => {
-                self.tab_index = (self.tab_index + 1) % TAB_TITLES.len();
-                Some(InputAction::Continue)
-            }
             _ => None,
         };
 
         if let Some(ret) = common {
             self.prefix = false;
 
-            return ret;
+            return self.skip_headers(ret);
         }
 
         // handle tab-specific input
         let action = match self.tab_index {
-            0 => self.handle_search_input(settings, input),
+            0 => self.handle_search_input(settings, input, action),
 
-            1 => super::inspector::input(self, settings, self.results_state.selected(), input),
+            1 => super::inspector::input(self, settings, self.results_state.selected(), action),
 
             _ => panic!("invalid tab index on input"),
         };
 
         self.prefix = false;
 
-        action
+        self.skip_headers(action)
+    }
+
+    /// A group's header row stands for the whole group, so actions on a single entry don't
+    /// apply to it. Accepting a header folds or unfolds its group instead.
+    fn skip_headers(&self, action: InputAction) -> InputAction {
+        let selected = self.results_state.selected();
+        let on_header = |row| self.tab_index == 0 && self.groups.is_header(row);
+
+        match action {
+            InputAction::Accept(row) if on_header(row) && row == selected => {
+                InputAction::ToggleGroup
+            }
+            InputAction::Accept(row) | InputAction::Copy(row) | InputAction::Delete(row)
+                if on_header(row) =>
+            {
+                InputAction::Continue
+            }
+            InputAction::ToggleMark | InputAction::EditCommand | InputAction::OpenEditor
+                if on_header(selected) =>
+            {
+                InputAction::Continue
+            }
+            InputAction::SaveScript if self.marked.is_empty() && on_header(selected) => {
+                InputAction::Continue
+            }
+            action => action,
+        }
     }
 
     fn handle_search_scroll_one_line(
@@ -358,16 +473,270 @@ impl State {
         self.handle_search_scroll_one_line(settings, enable_exit, !settings.invert)
     }
 
+    fn handle_delete(&self) -> InputAction {
+        let selected = self.results_state.selected();
+
+        if !self.marked.is_empty() {
+            InputAction::DeleteMarked
+        } else if self.groups.is_header(selected) {
+            // a folded group stands for all of its entries, so don't delete just one of them
+            InputAction::Continue
+        } else {
+            InputAction::Delete(selected)
+        }
+    }
+
+    /// Lay out the results as the rows of the list, folded into groups if grouping is enabled
+    fn group_results(&mut self, results: &[History]) -> Vec<History> {
+        let rows = self.groups.layout(results);
+        self.results_len = rows.len();
+
+        rows
+    }
+
+    /// Fold or unfold the group of the selected row, keeping its header selected
+    fn toggle_group(&mut self, results: &[History]) -> Option<Vec<History>> {
+        let key = self.groups.toggle(self.results_state.selected())?;
+        let rows = self.group_results(results);
+
+        if let Some(row) = self.groups.header_row(&key) {
+            self.results_state.select(row);
+        }
+
+        Some(rows)
+    }
+
+    fn is_marked(&self, id: &HistoryId) -> bool {
+        self.marked.iter().any(|h| &h.id == id)
+    }
+
+    /// Mark or unmark the selected entry
+    fn toggle_mark(&mut self, results: &[History]) {
+        let Some(entry) = results.get(self.results_state.selected()) else {
+            return;
+        };
+
+        if let Some(i) = self.marked.iter().position(|h| h.id == entry.id) {
+            self.marked.remove(i);
+        } else {
+            self.marked.push(entry.clone());
+        }
+
+        self.mark_anchor = Some(entry.id.clone());
+    }
+
+    /// Mark every entry between the anchor and the selected entry. Without an anchor in the
+    /// current results, only the selected entry is marked.
+    fn mark_range(&mut self, results: &[History]) {
+        let selected = self.results_state.selected();
+        let Some(entry) = results.get(selected) else {
+            return;
+        };
+
+        let anchor = self
+            .mark_anchor
+            .as_ref()
+            .and_then(|id| results.iter().position(|h| &h.id == id))
+            .unwrap_or(selected);
+
+        for (row, h) in results
+            .iter()
+            .enumerate()
+            .take(anchor.max(selected) + 1)
+            .skip(anchor.min(selected))
+        {
+            if !self.groups.is_header(row) && !self.is_marked(&h.id) {
+                self.marked.push(h.clone());
+            }
+        }
+
+        self.mark_anchor = Some(entry.id.clone());
+    }
+
+    /// Look up the git branches of the shown results' directories in the background, for those
+    /// not looked up yet. Each is sent back on `found` once known, so drawing never waits on
+    /// the filesystem.
+    fn lookup_branches(
+        &mut self,
+        settings: &Settings,
+        results: &[History],
+        found: &UnboundedSender<(String, Option<String>)>,
+    ) {
+        if !settings
+            .result_columns
+            .iter()
+            .any(|c| c.column == Column::Branch)
+        {
+            return;
+        }
+
+        let shown = results
+            .iter()
+            .skip(self.results_state.offset())
+            .take(self.results_state.max_entries());
+
+        for h in shown {
+            if self.branches.contains_key(&h.cwd) {
+                continue;
+            }
+
+            // shown without a branch until the lookup finishes
+            self.branches.insert(h.cwd.clone(), None);
+
+            let cwd = h.cwd.clone();
+            let found = found.clone();
+            tokio::task::spawn_blocking(move || {
+                let branch = utils::git_branch(&cwd);
+                let _ = found.send((cwd, branch));
+            });
+        }
+    }
+
+    /// The marked commands, oldest first
+    fn marked_commands(&self) -> Vec<&str> {
+        let mut marked: Vec<_> = self.marked.iter().collect();
+        marked.sort_by_key(|h| h.timestamp);
+
+        marked.into_iter().map(|h| h.command.as_str()).collect()
+    }
+
+    pub fn handle_inspector_accept(&mut self, settings: &Settings) -> InputAction {
+        if settings.enter_accept {
+            self.accept = true;
+        }
+        InputAction::AcceptInspecting
+    }
+
     fn handle_search_accept(&mut self, settings: &Settings) -> InputAction {
+        if self.groups.is_header(self.results_state.selected()) {
+            return InputAction::ToggleGroup;
+        }
+
         if settings.enter_accept {
             self.accept = true;
         }
         InputAction::Accept(self.results_state.selected())
     }
 
-    #[allow(clippy::too_many_lines)]
-    #[allow(clippy::cognitive_complexity)]
-    fn handle_search_input(&mut self, settings: &Settings, input: &KeyEvent) -> InputAction {
+    /// Handle a key while editing a command. Keys are bound as when searching, except that they
+    /// move around and edit the command, and exiting goes back to the results.
+    fn handle_editor_input(
+        &mut self,
+        settings: &Settings,
+        input: &KeyEvent,
+        action: Option<Action>,
+    ) -> InputAction {
+        let vim_normal = self.keymap_mode == KeymapMode::VimNormal;
+
+        // switching modes needs the whole state, so do that before borrowing the editor
+        match action {
+            Some(Action::VimNormal) => {
+                self.set_keymap_mode(settings, KeymapMode::VimNormal);
+                return InputAction::Continue;
+            }
+            Some(
+                action @ (Action::VimInsert
+                | Action::VimInsertStart
+                | Action::VimAppend
+                | Action::VimAppendEnd),
+            ) => {
+                if let Some(editor) = self.editing.as_mut() {
+                    match action {
+                        Action::VimInsertStart => editor.line_mut().start(),
+                        Action::VimAppend => editor.line_mut().right(),
+                        Action::VimAppendEnd => editor.line_mut().end(),
+                        _ => {}
+                    }
+                }
+                self.set_keymap_mode(settings, KeymapMode::VimInsert);
+                return InputAction::Continue;
+            }
+            Some(Action::Accept) if settings.enter_accept => self.accept = true,
+            _ => {}
+        }
+
+        let Some(editor) = self.editing.as_mut() else {
+            return InputAction::Continue;
+        };
+
+        let Some(action) = action else {
+            match input.code {
+                // unbound keys do nothing in normal mode
+                KeyCode::Char(_)
+                    if vim_normal || input.modifiers.contains(KeyModifiers::CONTROL) => {}
+                KeyCode::Char(c) => editor.insert(c),
+                _ => {}
+            }
+            return InputAction::Continue;
+        };
+
+        match action {
+            Action::Accept | Action::AcceptAndEdit => {
+                return InputAction::AcceptEdited(editor.text());
+            }
+            Action::Exit | Action::EditCommand => self.editing = None,
+            Action::ReturnOriginal => return InputAction::ReturnOriginal,
+            Action::ReturnQuery => return InputAction::ReturnQuery,
+            Action::OpenEditor => return InputAction::OpenEditor,
+            Action::Redraw => return InputAction::Redraw,
+            Action::NewLine => editor.new_line(),
+            Action::ScrollUp | Action::SelectPrevious => editor.up(),
+            Action::ScrollDown | Action::SelectNext => editor.down(),
+            Action::PageUp => editor.first(),
+            Action::PageDown => editor.last(),
+            Action::CursorLeft => {
+                editor.line_mut().left();
+            }
+            Action::CursorRight => editor.line_mut().right(),
+            Action::CursorWordLeft => editor
+                .line_mut()
+                .prev_word(&settings.word_chars, settings.word_jump_mode),
+            Action::CursorWordRight => editor
+                .line_mut()
+                .next_word(&settings.word_chars, settings.word_jump_mode),
+            Action::CursorStart => editor.line_mut().start(),
+            Action::CursorEnd => editor.line_mut().end(),
+            Action::DeleteCharBefore => editor.back(),
+            Action::DeleteCharAfter | Action::DeleteOrReturnOriginal => editor.remove(),
+            Action::DeleteWordBefore => editor
+                .line_mut()
+                .remove_prev_word(&settings.word_chars, settings.word_jump_mode),
+            Action::DeleteWordAfter => editor
+                .line_mut()
+                .remove_next_word(&settings.word_chars, settings.word_jump_mode),
+            Action::DeleteToWhitespace => delete_to_whitespace(editor.line_mut()),
+            Action::ClearInput => editor.line_mut().clear(),
+            // the rest act on the results, which are hidden while editing
+            Action::None
+            | Action::Copy
+            | Action::Delete
+            | Action::ToggleMark
+            | Action::MarkRange
+            | Action::ClearMarks
+            | Action::CopyChained
+            | Action::SaveScript
+            | Action::ToggleInspector
+            | Action::CycleFilterMode
+            | Action::CycleSearchMode
+            | Action::CycleGroupBy
+            | Action::ToggleGroup
+            | Action::VimNormal
+            | Action::VimInsert
+            | Action::VimInsertStart
+            | Action::VimAppend
+            | Action::VimAppendEnd
+            | Action::VimSearch => {}
+        }
+
+        InputAction::Continue
+    }
+
+    fn handle_search_input(
+        &mut self,
+        settings: &Settings,
+        input: &KeyEvent,
+        action: Option<Action>,
+    ) -> InputAction {
         let ctrl = input.modifiers.contains(KeyModifiers::CONTROL);
         let alt = input.modifiers.contains(KeyModifiers::ALT);
 
@@ -384,7 +753,7 @@ impl State {
             #[allow(clippy::single_match)]
             match input.code {
                 KeyCode::Char('d') => {
-                    return InputAction::Delete(self.results_state.selected());
+                    return self.handle_delete();
                 }
                 KeyCode::Char('a') => {
                     self.search.input.start();
@@ -396,199 +765,151 @@ impl State {
             }
         }
 
-        // handle keymap specific keybindings.
-        match self.keymap_mode {
-            KeymapMode::VimNormal => match input.code {
-                KeyCode::Char('?' | '/') if !ctrl => {
-                    self.search.input.clear();
-                    self.set_keymap_cursor(settings, "vim_insert");
-                    self.keymap_mode = KeymapMode::VimInsert;
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('j') if !ctrl => {
-                    return self.handle_search_down(settings, true);
-                }
-                KeyCode::Char('k') if !ctrl => {
-                    return self.handle_search_up(settings, true);
-                }
-                KeyCode::Char('h') if !ctrl => {
-                    self.search.input.left();
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('l') if !ctrl => {
-                    self.search.input.right();
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('a') if !ctrl => {
-                    self.search.input.right();
-                    self.set_keymap_cursor(settings, "vim_insert");
-                    self.keymap_mode = KeymapMode::VimInsert;
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('A') if !ctrl => {
-                    self.search.input.end();
-                    self.set_keymap_cursor(settings, "vim_insert");
-                    self.keymap_mode = KeymapMode::VimInsert;
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('i') if !ctrl => {
-                    self.set_keymap_cursor(settings, "vim_insert");
-                    self.keymap_mode = KeymapMode::VimInsert;
-                    return InputAction::Continue;
-                }
-                KeyCode::Char('I') if !ctrl => {
-                    self.search.input.start();
-                    self.set_keymap_cursor(settings, "vim_insert");
-                    self.keymap_mode = KeymapMode::VimInsert;
-                    return InputAction::Continue;
-                }
-                KeyCode::Char(c @ '1'..='9') => {
-                    return c.to_digit(10).map_or(InputAction::Continue, |c| {
-                        InputAction::Accept(self.results_state.selected() + c as usize)
-                    });
-                }
-                KeyCode::Char(_) if !ctrl => {
-                    return InputAction::Continue;
-                }
-                _ => {}
-            },
-            KeymapMode::VimInsert => {
-                if input.code == KeyCode::Esc || (ctrl && input.code == KeyCode::Char('[')) {
-                    self.set_keymap_cursor(settings, "vim_normal");
-                    self.keymap_mode = KeymapMode::VimNormal;
-                    return InputAction::Continue;
-                }
+        if let Some(action) = action {
+            return self.handle_search_action(settings, action);
+        }
+
+        let vim_normal = self.keymap_mode == KeymapMode::VimNormal;
+
+        match input.code {
+            KeyCode::Char(c @ '1'..='9') if modfr || vim_normal => {
+                return c.to_digit(10).map_or(InputAction::Continue, |c| {
+                    InputAction::Accept(
+                        self.groups
+                            .shortcut_row(self.results_state.selected(), c as usize),
+                    )
+                });
+            }
+            // unbound keys do nothing in normal mode
+            KeyCode::Char(_) if vim_normal && !ctrl => {}
+            KeyCode::Char(c) => {
+                self.search.input.insert(c);
             }
             _ => {}
         }
 
-        match input.code {
-            KeyCode::Enter => return self.handle_search_accept(settings),
-            KeyCode::Char('m') if ctrl => return self.handle_search_accept(settings),
-            KeyCode::Char('y') if ctrl => {
+        InputAction::Continue
+    }
+
+    fn set_keymap_mode(&mut self, settings: &Settings, mode: KeymapMode) {
+        let cursor = match mode {
+            KeymapMode::VimNormal => "vim_normal",
+            KeymapMode::VimInsert => "vim_insert",
+            KeymapMode::Emacs | KeymapMode::Auto => "emacs",
+        };
+
+        self.set_keymap_cursor(settings, cursor);
+        self.keymap_mode = mode;
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn handle_search_action(&mut self, settings: &Settings, action: Action) -> InputAction {
+        match action {
+            Action::Accept => return self.handle_search_accept(settings),
+            Action::Copy if !self.marked.is_empty() => return InputAction::CopyMarked("\n"),
+            Action::CopyChained if !self.marked.is_empty() => {
+                return InputAction::CopyMarked(" && ");
+            }
+            Action::Copy | Action::CopyChained => {
                 return InputAction::Copy(self.results_state.selected());
             }
-            KeyCode::Char(c @ '1'..='9') if modfr => {
-                return c.to_digit(10).map_or(InputAction::Continue, |c| {
-                    InputAction::Accept(self.results_state.selected() + c as usize)
-                });
+            Action::Delete => return self.handle_delete(),
+            Action::ToggleMark => return InputAction::ToggleMark,
+            Action::MarkRange => return InputAction::MarkRange,
+            Action::ClearMarks => {
+                self.marked.clear();
+                self.mark_anchor = None;
             }
-            KeyCode::Left if ctrl => self
-                .search
-                .input
-                .prev_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Char('b') if alt => self
-                .search
-                .input
-                .prev_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Left => {
-                self.search.input.left();
+            Action::SaveScript => return InputAction::SaveScript,
+            Action::EditCommand => return InputAction::EditCommand,
+            Action::OpenEditor => return InputAction::OpenEditor,
+            Action::CycleGroupBy => {
+                self.groups.cycle();
+                return InputAction::Regroup;
+            }
+            Action::ToggleGroup => return InputAction::ToggleGroup,
+            Action::Redraw => return InputAction::Redraw,
+            Action::ScrollUp => return self.handle_search_up(settings, true),
+            Action::ScrollDown => return self.handle_search_down(settings, true),
+            Action::SelectPrevious => return self.handle_search_up(settings, false),
+            Action::SelectNext => return self.handle_search_down(settings, false),
+            Action::PageUp | Action::PageDown => {
+                let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
+
+                if (action == Action::PageDown) == settings.invert {
+                    self.scroll_up(scroll_len);
+                } else {
+                    self.scroll_down(scroll_len);
+                }
+            }
+            Action::CycleFilterMode => self.search.rotate_filter_mode(settings, 1),
+            Action::CycleSearchMode => {
+                self.switched_search_mode = true;
+                self.search_mode = self.search_mode.next(settings);
+                self.engine = engines::engine(self.search_mode);
             }
-            KeyCode::Char('b') if ctrl => {
+            Action::CursorLeft => {
                 self.search.input.left();
             }
-            KeyCode::Right if ctrl => self
+            Action::CursorRight => self.search.input.right(),
+            Action::CursorWordLeft => self
                 .search
                 .input
-                .next_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Char('f') if alt => self
+                .prev_word(&settings.word_chars, settings.word_jump_mode),
+            Action::CursorWordRight => self
                 .search
                 .input
                 .next_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Right => self.search.input.right(),
-            KeyCode::Char('f') if ctrl => self.search.input.right(),
-            KeyCode::Home => self.search.input.start(),
-            KeyCode::Char('a') if ctrl => self.search.input.start(),
-            KeyCode::Char('e') if ctrl => self.search.input.end(),
-            KeyCode::End => self.search.input.end(),
-            KeyCode::Backspace if ctrl => self
-                .search
-                .input
-                .remove_prev_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Backspace => {
+            Action::CursorStart => self.search.input.start(),
+            Action::CursorEnd => self.search.input.end(),
+            Action::DeleteCharBefore => {
                 self.search.input.back();
             }
-            KeyCode::Char('h' | '?') if ctrl => {
-                // Depending on the terminal, [Backspace] can be transmitted as
-                // \x08 or \x7F.  Also, [Ctrl+Backspace] can be transmitted as
-                // \x08 or \x7F or \x1F.  On the other hand, [Ctrl+h] and
-                // [Ctrl+?] are also transmitted as \x08 or \x7F by the
-                // terminals.
-                //
-                // The crossterm library translates \x08 and \x7F to C-h and
-                // Backspace, respectively.  With the extended keyboard
-                // protocol enabled, crossterm can faithfully translate
-                // [Ctrl+h] and [Ctrl+?] to C-h and C-?.  There is no perfect
-                // solution, but we treat C-h and C-? the same as backspace to
-                // suppress quirks as much as possible.
-                self.search.input.back();
+            Action::DeleteCharAfter => {
+                self.search.input.remove();
             }
-            KeyCode::Delete if ctrl => self
+            Action::DeleteWordBefore => self
+                .search
+                .input
+                .remove_prev_word(&settings.word_chars, settings.word_jump_mode),
+            Action::DeleteWordAfter => self
                 .search
                 .input
                 .remove_next_word(&settings.word_chars, settings.word_jump_mode),
-            KeyCode::Delete => {
-                self.search.input.remove();
-            }
-            KeyCode::Char('d') if ctrl => {
+            Action::DeleteToWhitespace => delete_to_whitespace(&mut self.search.input),
+            Action::DeleteOrReturnOriginal => {
                 if self.search.input.as_str().is_empty() {
                     return InputAction::ReturnOriginal;
                 }
                 self.search.input.remove();
             }
-            KeyCode::Char('w') if ctrl => {
-                // remove the first batch of whitespace
-                while matches!(self.search.input.back(), Some(c) if c.is_whitespace()) {}
-                while self.search.input.left() {
-                    if self.search.input.char().unwrap().is_whitespace() {
-                        self.search.input.right(); // found whitespace, go back right
-                        break;
-                    }
-                    self.search.input.remove();
-                }
-            }
-            KeyCode::Char('u') if ctrl => self.search.input.clear(),
-            KeyCode::Char('r') if ctrl => self.search.rotate_filter_mode(settings, 1),
-            KeyCode::Char('s') if ctrl => {
-                self.switched_search_mode = true;
-                self.search_mode = self.search_mode.next(settings);
-                self.engine = engines::engine(self.search_mode);
-            }
-            KeyCode::Down => {
-                return self.handle_search_down(settings, true);
-            }
-            KeyCode::Up => {
-                return self.handle_search_up(settings, true);
-            }
-            KeyCode::Char('n' | 'j') if ctrl => {
-                return self.handle_search_down(settings, false);
-            }
-            KeyCode::Char('p' | 'k') if ctrl => {
-                return self.handle_search_up(settings, false);
-            }
-            KeyCode::Char('l') if ctrl => {
-                return InputAction::Redraw;
-            }
-            KeyCode::Char(c) => {
-                self.search.input.insert(c);
-            }
-            KeyCode::PageDown if !settings.invert => {
-                let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
-                self.scroll_down(scroll_len);
+            Action::ClearInput => self.search.input.clear(),
+            Action::VimNormal => self.set_keymap_mode(settings, KeymapMode::VimNormal),
+            Action::VimInsert => self.set_keymap_mode(settings, KeymapMode::VimInsert),
+            Action::VimInsertStart => {
+                self.search.input.start();
+                self.set_keymap_mode(settings, KeymapMode::VimInsert);
             }
-            KeyCode::PageDown if settings.invert => {
-                let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
-                self.scroll_up(scroll_len);
+            Action::VimAppend => {
+                self.search.input.right();
+                self.set_keymap_mode(settings, KeymapMode::VimInsert);
             }
-            KeyCode::PageUp if !settings.invert => {
-                let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
-                self.scroll_up(scroll_len);
+            Action::VimAppendEnd => {
+                self.search.input.end();
+                self.set_keymap_mode(settings, KeymapMode::VimInsert);
             }
-            KeyCode::PageUp if settings.invert => {
-                let scroll_len = self.results_state.max_entries() - settings.scroll_context_lines;
-                self.scroll_down(scroll_len);
+            Action::VimSearch => {
+                self.search.input.clear();
+                self.set_keymap_mode(settings, KeymapMode::VimInsert);
             }
-            _ => {}
+            // handled for every tab in handle_key_input, or only used while editing a command
+            Action::None
+            | Action::NewLine
+            | Action::AcceptAndEdit
+            | Action::Exit
+            | Action::ReturnOriginal
+            | Action::ReturnQuery
+            | Action::ToggleInspector => {}
         }
 
         InputAction::Continue
@@ -767,12 +1088,34 @@ impl State {
                 .highlight_style(theme.as_style(Meaning::Important));
 
             f.render_widget(tabs, tabs_chunk);
+
+            // each title is padded by a space either side, and divided from the next by one
+            let width = GROUP_TITLES.iter().map(|(_, t)| t.len() + 3).sum::<usize>() - 1;
+            let [_, grouping_chunk] = Layout::horizontal([
+                Constraint::Min(0),
+                Constraint::Length(u16::try_from(width).unwrap_or(u16::MAX)),
+            ])
+            .areas(tabs_chunk);
+
+            let grouping = Tabs::new(GROUP_TITLES.iter().map(|(_, t)| Line::from(*t)))
+                .select(
+                    GROUP_TITLES
+                        .iter()
+                        .position(|(by, _)| *by == self.groups.by()),
+                )
+                .style(theme.as_style(Meaning::Annotation))
+                .highlight_style(theme.as_style(Meaning::Important));
+            f.render_widget(grouping, grouping_chunk);
         }
 
         let style = StyleState {
             compactness,
             invert,
             inner_width: input_chunk.width.into(),
+            prefix_width: prefix_width(
+                &settings.result_columns,
+                !matches!(compactness, Compactness::Full),
+            ),
         };
 
         let header_chunks = Layout::default()
@@ -811,6 +1154,13 @@ impl State {
         };
 
         match self.tab_index {
+            0 if self.editing.is_some() => {
+                f.render_widget(self.build_input(style), input_chunk);
+                self.draw_editor(f, results_list_chunk, compactness);
+
+                return;
+            }
+
             0 => {
                 let history_highlighter = HistoryHighlighter {
                     engine: self.engine.as_ref(),
@@ -825,7 +1175,15 @@ impl State {
                     theme,
                     history_highlighter,
                     settings.show_numeric_shortcuts,
-                );
+                )
+                .marked(&self.marked)
+                .groups(&self.groups)
+                .columns(
+                    &settings.result_columns,
+                    !matches!(compactness, Compactness::Full),
+                )
+                .timezone(settings.timezone.0)
+                .branches(&self.branches);
                 f.render_stateful_widget(results_list, results_list_chunk, &mut self.results_state);
             }
 
@@ -851,6 +1209,7 @@ impl State {
                         results_list_chunk,
                         inspecting,
                         &stats.expect("Drawing inspector, but no stats"),
+                        &self.context,
                         settings,
                         theme,
                         settings.timezone,
@@ -911,11 +1270,43 @@ impl State {
         };
         f.set_cursor_position((
             // Put cursor past the end of the input text
-            input_chunk.x + extra_width as u16 + PREFIX_LENGTH + 1 + cursor_offset,
+            input_chunk.x + extra_width as u16 + style.mode_box_width() as u16 + 1 + cursor_offset,
             input_chunk.y + cursor_offset,
         ));
     }
 
+    #[allow(clippy::cast_possible_truncation)]
+    fn draw_editor(&self, f: &mut Frame, chunk: Rect, compactness: Compactness) {
+        let Some(editor) = &self.editing else {
+            return;
+        };
+
+        let block = match compactness {
+            Compactness::Full => Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(" Edit command "),
+            _ => Block::default(),
+        };
+        let inner = block.inner(chunk);
+
+        // keep the line being edited in view
+        let scroll = editor
+            .row()
+            .saturating_sub(inner.height.saturating_sub(1).into());
+        let lines: Vec<Line> = editor.lines().map(Line::from).collect();
+        let text = Paragraph::new(lines)
+            .block(block)
+            .scroll((scroll as u16, 0));
+        f.render_widget(text, chunk);
+
+        let column = UnicodeWidthStr::width(editor.line().substring()) as u16;
+        f.set_cursor_position((
+            (inner.x + column).min(inner.right().saturating_sub(1)),
+            inner.y + (editor.row() - scroll) as u16,
+        ));
+    }
+
     fn build_title(&self, theme: &Theme) -> Paragraph<'_> {
         let title = if self.update_needed.is_some() {
             let error_style: Style = theme.get_error().into();
@@ -933,16 +1324,22 @@ impl State {
         title.alignment(Alignment::Left)
     }
 
-    #[allow(clippy::unused_self)]
     fn build_help(&self, settings: &Settings, theme: &Theme) -> Paragraph<'_> {
+        if let Some(notice) = &self.notice {
+            return Paragraph::new(Text::from(Span::styled(
+                notice.as_str(),
+                theme.as_style(Meaning::AlertError),
+            )));
+        }
+
         match self.tab_index {
-            // search
-            0 => Paragraph::new(Text::from(Line::from(vec![
+            // editing the selected command
+            0 if self.editing.is_some() => Paragraph::new(Text::from(Line::from(vec![
                 Span::styled("<esc>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": exit"),
+                Span::raw(": back"),
                 Span::raw(", "),
-                Span::styled("<tab>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": edit"),
+                Span::styled("<alt-enter>", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": new line"),
                 Span::raw(", "),
                 Span::styled("<enter>", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(if settings.enter_accept {
@@ -951,17 +1348,43 @@ impl State {
                     ": edit"
                 }),
                 Span::raw(", "),
-                Span::styled("<ctrl-o>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": inspect"),
+                Span::styled("<ctrl-x>", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": $EDITOR"),
             ]))),
 
-            1 => Paragraph::new(Text::from(Line::from(vec![
+            // search
+            0 => Paragraph::new(Text::from(Line::from(vec![
                 Span::styled("<esc>", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(": exit"),
                 Span::raw(", "),
-                Span::styled("<ctrl-o>", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled("<tab>", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": edit"),
+                Span::raw(", "),
+                Span::styled("<enter>", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(if settings.enter_accept {
+                    ": run"
+                } else {
+                    ": edit"
+                }),
+                Span::raw(", "),
+                Span::styled("<ctrl-o>", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": inspect"),
+            ]))),
+
+            1 => Paragraph::new(Text::from(Line::from(vec![
+                Span::styled("<esc>", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": exit"),
+                Span::raw(", "),
+                Span::styled("<ctrl-o>", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(": search"),
                 Span::raw(", "),
+                Span::styled("<enter>", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(if settings.enter_accept {
+                    ": run"
+                } else {
+                    ": edit"
+                }),
+                Span::raw(", "),
                 Span::styled("<ctrl-d>", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(": delete"),
             ]))),
@@ -973,8 +1396,14 @@ impl State {
     }
 
     fn build_stats(&self, theme: &Theme) -> Paragraph<'_> {
+        let marked = if self.marked.is_empty() {
+            String::new()
+        } else {
+            format!("{} marked, ", self.marked.len())
+        };
+
         Paragraph::new(Text::from(Span::raw(format!(
-            "history count: {}",
+            "{marked}history count: {}",
             self.history_count,
         ))))
         .style(theme.as_style(Meaning::Annotation))
@@ -1025,14 +1454,13 @@ impl State {
     }
 
     fn build_input(&self, style: StyleState) -> Paragraph<'_> {
-        /// Max width of the UI box showing current mode
-        const MAX_WIDTH: usize = 14;
         let (pref, mode) = if self.switched_search_mode {
             (" SRCH:", self.search_mode.as_str())
         } else {
             ("", self.search.filter_mode.as_str())
         };
-        let mode_width = MAX_WIDTH - pref.len();
+        // less the brackets around the mode
+        let mode_width = style.mode_box_width() - 2 - pref.len();
         // sanity check to ensure we don't exceed the layout limits
         debug_assert!(mode_width >= mode.len(), "mode name '{mode}' is too long!");
         let input = format!("[{pref}{mode:^mode_width$}] {}", self.search.input.as_str(),);
@@ -1067,21 +1495,30 @@ impl State {
         theme: &Theme,
     ) -> Paragraph<'_> {
         let selected = self.results_state.selected();
-        let command = if results.is_empty() {
-            String::new()
+        // the compact preview is dimmed as a whole, so only the full one is highlighted
+        let highlight = matches!(compactness, Compactness::Full);
+        let command: Vec<Line> = if results.is_empty() {
+            Vec::new()
         } else {
             use itertools::Itertools as _;
             let s = &results[selected].command;
+            let tokens = tokenize(s);
+            let mut offset = 0;
+
             s.split('\n')
                 .flat_map(|line| {
+                    let start = offset;
+                    offset += line.len() + 1;
+
                     line.char_indices()
                         .step_by(preview_width.into())
                         .map(|(i, _)| i)
                         .chain(Some(line.len()))
                         .tuple_windows()
-                        .map(|(a, b)| (&line[a..b]).escape_control().to_string())
+                        .map(move |(a, b)| start + a..start + b)
                 })
-                .join("\n")
+                .map(|range| highlight_range(s, &tokens, range, highlight.then_some(theme)))
+                .collect()
         };
 
         match compactness {
@@ -1103,8 +1540,18 @@ struct Stdout {
 
 impl Stdout {
     pub fn new(inline_mode: bool) -> std::io::Result<Self> {
-        terminal::enable_raw_mode()?;
         let mut stdout = stdout();
+        Self::enter(&mut stdout, inline_mode)?;
+
+        Ok(Self {
+            stdout,
+            inline_mode,
+        })
+    }
+
+    /// Set the terminal up for the search
+    fn enter(stdout: &mut std::io::Stdout, inline_mode: bool) -> std::io::Result<()> {
+        terminal::enable_raw_mode()?;
 
         if !inline_mode {
             execute!(stdout, terminal::EnterAlternateScreen)?;
@@ -1126,29 +1573,42 @@ impl Stdout {
             ),
         )?;
 
-        Ok(Self {
-            stdout,
-            inline_mode,
-        })
+        Ok(())
     }
-}
 
-impl Drop for Stdout {
-    fn drop(&mut self) {
+    /// Put the terminal back the way it was before the search
+    fn leave(stdout: &mut std::io::Stdout, inline_mode: bool) -> std::io::Result<()> {
         #[cfg(not(target_os = "windows"))]
-        execute!(self.stdout, PopKeyboardEnhancementFlags).unwrap();
+        execute!(stdout, PopKeyboardEnhancementFlags)?;
 
-        if !self.inline_mode {
-            execute!(self.stdout, terminal::LeaveAlternateScreen).unwrap();
+        if !inline_mode {
+            execute!(stdout, terminal::LeaveAlternateScreen)?;
         }
         execute!(
-            self.stdout,
+            stdout,
             event::DisableMouseCapture,
             event::DisableBracketedPaste,
-        )
-        .unwrap();
+        )?;
+
+        terminal::disable_raw_mode()
+    }
+
+    /// Hand the terminal over to `$EDITOR` to edit a command
+    fn open_editor(inline_mode: bool, command: &str) -> Result<String> {
+        let mut stdout = stdout();
 
-        terminal::disable_raw_mode().unwrap();
+        Self::leave(&mut stdout, inline_mode)?;
+        let edited = scripts::Cmd::open_editor(Some(command));
+        Self::enter(&mut stdout, inline_mode)?;
+
+        // editors tend to end the file with a newline, which isn't part of the command
+        Ok(edited?.trim_end_matches('\n').to_string())
+    }
+}
+
+impl Drop for Stdout {
+    fn drop(&mut self) {
+        Self::leave(&mut self.stdout, self.inline_mode).unwrap();
     }
 }
 
@@ -1250,11 +1710,18 @@ pub async fn history(
         switched_search_mode: false,
         search_mode,
         tab_index: 0,
+        marked: Vec::new(),
+        mark_anchor: None,
+        groups: Groups::new(settings.group_by, settings.timezone.0, !settings.invert),
         inspecting_state: InspectingState {
             current: None,
             next: None,
             previous: None,
         },
+        context: Vec::new(),
+        editing: None,
+        branches: HashMap::new(),
+        notice: None,
         search: SearchState {
             input,
             filter_mode: settings
@@ -1284,14 +1751,23 @@ pub async fn history(
 
     app.initialize_keymap_cursor(settings);
 
-    let mut results = app.query_results(&mut db, settings.smart_sort).await?;
+    let mut found = app.query_results(&mut db, settings).await?;
+    let mut results = app.group_results(&found);
 
     if inline_height > 0 {
         terminal.clear()?;
     }
 
+    // commands finishing in other shells, to keep the results up to date
+    let mut updates = subscribe(settings).await;
+    let mut stale = false;
+
+    // git branches of the results' directories, as they're looked up
+    let (branches_tx, mut branches_rx) = unbounded_channel();
+
     let mut stats: Option<HistoryStats> = None;
     let mut inspecting: Option<History> = None;
+    let mut saved_script = None;
     let accept;
     let result = 'render: loop {
         terminal.draw(|f| {
@@ -1304,6 +1780,7 @@ pub async fn history(
                 theme,
             );
         })?;
+        app.lookup_branches(settings, &results, &branches_tx);
 
         let initial_input = app.search.input.as_str().to_owned();
         let initial_filter_mode = app.search.filter_mode;
@@ -1321,15 +1798,17 @@ pub async fn history(
                                 if results.is_empty() {
                                     break;
                                 }
-                                app.results_len -= 1;
+
+                                let entry = results.remove(index);
+                                found.retain(|h| h.id != entry.id);
+                                results = app.group_results(&found);
+
                                 let selected = app.results_state.selected();
-                                if selected == app.results_len {
+                                if selected >= app.results_len {
                                     app.inspecting_state.reset();
-                                    app.results_state.select(selected - 1);
+                                    app.results_state.select(app.results_len.saturating_sub(1));
                                 }
 
-                                let entry = results.remove(index);
-
                                 if settings.sync.records {
                                     let (id, _) = history_store.delete(entry.id).await?;
                                     history_store.incremental_build(&db, &[id]).await?;
@@ -1339,6 +1818,83 @@ pub async fn history(
 
                                 app.tab_index  = 0;
                             },
+                            InputAction::EditCommand => {
+                                app.editing = results
+                                    .get(app.results_state.selected())
+                                    .map(|h| Editor::new(&h.command));
+                            },
+                            InputAction::OpenEditor => {
+                                let command = match &app.editing {
+                                    Some(editor) => Some(editor.text()),
+                                    None => results.get(app.results_state.selected()).map(|h| h.command.clone()),
+                                };
+
+                                if let Some(command) = command {
+                                    let edited = Stdout::open_editor(inline_height > 0, &command)?;
+                                    app.editing = Some(Editor::new(&edited));
+                                    terminal.clear()?;
+                                }
+                            },
+                            InputAction::ToggleMark => app.toggle_mark(&results),
+                            InputAction::MarkRange => app.mark_range(&results),
+                            InputAction::DeleteMarked => {
+                                let marked = std::mem::take(&mut app.marked);
+                                app.mark_anchor = None;
+
+                                found.retain(|h| !marked.iter().any(|m| m.id == h.id));
+                                results = app.group_results(&found);
+                                app.inspecting_state.reset();
+                                app.results_state.select(
+                                    app.results_state.selected().min(app.results_len.saturating_sub(1)),
+                                );
+
+                                if settings.sync.records {
+                                    let mut ids = Vec::with_capacity(marked.len());
+                                    for entry in marked {
+                                        let (id, _) = history_store.delete(entry.id).await?;
+                                        ids.push(id);
+                                    }
+                                    history_store.incremental_build(&db, &ids).await?;
+                                } else {
+                                    for entry in marked {
+                                        db.delete(entry).await?;
+                                    }
+                                }
+
+                                app.tab_index = 0;
+                            },
+                            InputAction::ToggleGroup => {
+                                if let Some(rows) = app.toggle_group(&found) {
+                                    results = rows;
+                                }
+                            },
+                            InputAction::Regroup => {
+                                results = app.group_results(&found);
+                                app.inspecting_state.reset();
+                                app.results_state.select(0);
+                            },
+                            InputAction::SaveScript => {
+                                let commands = if app.marked.is_empty() {
+                                    results
+                                        .get(app.results_state.selected())
+                                        .map(|h| vec![h.command.as_str()])
+                                        .unwrap_or_default()
+                                } else {
+                                    app.marked_commands()
+                                };
+
+                                // stay in the search on failure, so nothing is lost
+                                if !commands.is_empty() {
+                                    match save_script(settings, history_store, &commands).await {
+                                        Ok(name) => {
+                                            saved_script = Some((commands.len(), name));
+                                            accept = app.accept;
+                                            break 'render InputAction::SaveScript;
+                                        }
+                                        Err(e) => app.notice = Some(format!("Could not save the script: {e}")),
+                                    }
+                                }
+                            },
                             InputAction::Redraw => {
                                 terminal.clear()?;
                                 terminal.draw(|f| app.draw(f, &results, stats.clone(), inspecting.as_ref(), settings, theme))?;
@@ -1357,13 +1913,42 @@ pub async fn history(
             update_needed = &mut update_needed => {
                 app.update_needed = update_needed?;
             }
+            Some((cwd, branch)) = branches_rx.recv() => {
+                app.branches.insert(cwd, branch);
+                while let Ok((cwd, branch)) = branches_rx.try_recv() {
+                    app.branches.insert(cwd, branch);
+                }
+            }
+            update = next_update(&mut updates) => {
+                if update.is_some() {
+                    stale = true;
+                } else {
+                    // the daemon went away
+                    updates = None;
+                }
+            }
         }
 
         if initial_input != app.search.input.as_str()
             || initial_filter_mode != app.search.filter_mode
             || initial_search_mode != app.search_mode
         {
-            results = app.query_results(&mut db, settings.smart_sort).await?;
+            found = app.query_results(&mut db, settings).await?;
+            results = app.group_results(&found);
+            stale = false;
+        } else if stale && app.tab_index == 0 {
+            // refresh without moving the selection, so new entries don't get in the way
+            let selected = results
+                .get(app.results_state.selected())
+                .map(|h| h.id.clone());
+
+            found = app.query_results(&mut db, settings).await?;
+            results = app.group_results(&found);
+            stale = false;
+
+            if let Some(row) = selected.and_then(|id| results.iter().position(|h| h.id == id)) {
+                app.results_state.select(row);
+            }
         }
 
         let inspecting_id = app.inspecting_state.clone().current;
@@ -1389,6 +1974,9 @@ pub async fn history(
                 None => results[app.results_state.selected()].clone(),
             };
             let stats = db.stats(&selected).await?;
+            app.context = db
+                .context(&selected, settings.inspector_context_lines)
+                .await?;
             app.inspecting_state.current = Some(selected.id);
             app.inspecting_state.previous = match stats.previous.clone() {
                 Some(p) => Some(p.id),
@@ -1446,24 +2034,216 @@ pub async fn history(
             // index is in bounds so we return that entry
             Ok(command)
         }
+        InputAction::AcceptEdited(mut command) => {
+            if is_command_chaining {
+                command = format!("{} {}", original_query.trim_end(), command);
+            } else if accept {
+                command = String::from(accept_prefix) + &command;
+            }
+
+            Ok(command)
+        }
         InputAction::ReturnOriginal => Ok(String::new()),
         InputAction::Copy(index) => {
             let cmd = results.swap_remove(index).command;
             set_clipboard(cmd);
             Ok(String::new())
         }
+        InputAction::CopyMarked(separator) => {
+            set_clipboard(app.marked_commands().join(separator));
+            Ok(String::new())
+        }
+        InputAction::SaveScript => {
+            if let Some((count, name)) = saved_script {
+                eprintln!(
+                    "Saved {count} command(s) as script {name}. Run it with 'atuin scripts run {name}'"
+                );
+            }
+
+            Ok(String::new())
+        }
         InputAction::ReturnQuery | InputAction::Accept(_) => {
             // Either:
             // * index == RETURN_QUERY, in which case we should return the input
             // * out of bounds -> usually implies no selected entry so we return the input
             Ok(app.search.input.into_inner())
         }
-        InputAction::Continue | InputAction::Redraw | InputAction::Delete(_) => {
+        InputAction::Continue
+        | InputAction::Redraw
+        | InputAction::EditCommand
+        | InputAction::OpenEditor
+        | InputAction::Delete(_)
+        | InputAction::ToggleMark
+        | InputAction::MarkRange
+        | InputAction::DeleteMarked
+        | InputAction::ToggleGroup
+        | InputAction::Regroup => {
             unreachable!("should have been handled!")
         }
     }
 }
 
+/// Subscribe to commands finishing in any shell, if the daemon is running
+#[cfg(feature = "daemon")]
+async fn subscribe(settings: &Settings) -> Option<UnboundedReceiver<History>> {
+    use atuin_daemon::client::{HistoryClient, HistoryEvent};
+    use futures_util::StreamExt;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    if !settings.daemon.enabled {
+        return None;
+    }
+
+    let events = async {
+        HistoryClient::new(
+            #[cfg(not(unix))]
+            settings.daemon.tcp_port,
+            #[cfg(unix)]
+            settings.daemon.socket_path.clone(),
+        )
+        .await?
+        .subscribe()
+        .await
+    };
+
+    let mut events = events
+        .await
+        .inspect_err(|e| log::debug!("not following history from the daemon: {e}"))
+        .ok()?;
+
+    let (tx, rx) = unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Some(Ok(event)) = events.next().await {
+            if let HistoryEvent::Ended(h) = event
+                && tx.send(h).is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    Some(rx)
+}
+
+#[cfg(not(feature = "daemon"))]
+#[allow(clippy::unused_async)]
+async fn subscribe(_settings: &Settings) -> Option<UnboundedReceiver<History>> {
+    None
+}
+
+/// The next command to finish elsewhere. Never resolves when not subscribed.
+async fn next_update(updates: &mut Option<UnboundedReceiver<History>>) -> Option<History> {
+    match updates {
+        Some(updates) => updates.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Save commands as a new script, named after the current time, returning its name
+async fn save_script(
+    settings: &Settings,
+    history_store: &HistoryStore,
+    commands: &[&str],
+) -> Result<String> {
+    let name = OffsetDateTime::now_utc().format(format_description!(
+        "history-[year][month][day]-[hour][minute][second]"
+    ))?;
+
+    let script = Script::builder()
+        .name(name.clone())
+        .description("Saved from the interactive search".to_string())
+        .shebang(String::new())
+        .tags(vec!["history".to_string()])
+        .script(commands.join("\n"))
+        .build();
+
+    let script_store = ScriptStore::new(
+        history_store.store.clone(),
+        history_store.host_id,
+        history_store.keyring.clone(),
+    );
+    script_store.create(script).await?;
+
+    let script_db =
+        atuin_scripts::database::Database::new(settings.scripts.db_path.clone(), 1.0).await?;
+    script_store.build(script_db).await?;
+
+    Ok(name)
+}
+
+/// A line showing part of a command, with its tokens highlighted if there's a theme to do so
+fn highlight_range<'a>(
+    command: &str,
+    tokens: &[Token],
+    range: Range<usize>,
+    theme: Option<&Theme>,
+) -> Line<'a> {
+    tokens
+        .iter()
+        .filter(|t| t.start < range.end && t.end() > range.start)
+        .map(|t| {
+            let text = (&command[t.start.max(range.start)..t.end().min(range.end)])
+                .escape_control()
+                .to_string();
+
+            match theme {
+                Some(theme) => Span::styled(text, theme.as_style(Meaning::for_token(t.kind))),
+                None => Span::raw(text),
+            }
+        })
+        .collect()
+}
+
+/// Delete back to the previous whitespace, like `unix-word-rubout`
+fn delete_to_whitespace(input: &mut Cursor) {
+    // remove the first batch of whitespace
+    while matches!(input.back(), Some(c) if c.is_whitespace()) {}
+    while input.left() {
+        if input.char().unwrap().is_whitespace() {
+            input.right(); // found whitespace, go back right
+            break;
+        }
+        input.remove();
+    }
+}
+
+/// Translate a key event into the chord used to look up keybindings
+fn key_chord(input: &KeyEvent) -> Option<KeyChord> {
+    let mut shift = input.modifiers.contains(KeyModifiers::SHIFT);
+
+    let key = match input.code {
+        KeyCode::Char(c) => Key::Char(c),
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::BackTab => {
+            shift = true;
+            Key::Tab
+        }
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::F(n) => Key::F(n),
+        _ => return None,
+    };
+
+    Some(KeyChord::new(
+        key,
+        input.modifiers.contains(KeyModifiers::CONTROL),
+        input.modifiers.contains(KeyModifiers::ALT),
+        shift,
+    ))
+}
+
 // cli-clipboard only works on Windows, Mac, and Linux.
 
 #[cfg(all(
@@ -1485,18 +2265,63 @@ fn set_clipboard(_s: String) {}
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use atuin_client::database::Context;
     use atuin_client::history::History;
     use atuin_client::settings::{
-        FilterMode, KeymapMode, Preview, PreviewStrategy, SearchMode, Settings,
+        FilterMode, GroupBy, KeymapMode, Preview, PreviewStrategy, SearchMode, Settings,
     };
-    use time::OffsetDateTime;
+    use time::{OffsetDateTime, UtcOffset};
 
     use crate::command::client::search::engines::{self, SearchState};
+    use crate::command::client::search::groups::Groups;
     use crate::command::client::search::history_list::ListState;
 
     use super::{Compactness, InspectingState, State};
 
+    /// An empty search, in emacs mode
+    fn test_state() -> State {
+        State {
+            history_count: 0,
+            update_needed: None,
+            results_state: ListState::default(),
+            switched_search_mode: false,
+            search_mode: SearchMode::Fuzzy,
+            results_len: 0,
+            accept: false,
+            keymap_mode: KeymapMode::Emacs,
+            prefix: false,
+            current_cursor: None,
+            tab_index: 0,
+            marked: Vec::new(),
+            mark_anchor: None,
+            groups: Groups::new(GroupBy::None, UtcOffset::UTC, false),
+            inspecting_state: InspectingState {
+                current: None,
+                next: None,
+                previous: None,
+            },
+            context: Vec::new(),
+            editing: None,
+            branches: HashMap::new(),
+            notice: None,
+            search: SearchState {
+                input: String::new().into(),
+                filter_mode: FilterMode::Global,
+                context: Context {
+                    session: String::new(),
+                    cwd: String::new(),
+                    hostname: String::new(),
+                    host_id: String::new(),
+                    git_root: None,
+                },
+            },
+            engine: engines::engine(SearchMode::Fuzzy),
+            now: Box::new(OffsetDateTime::now_utc),
+        }
+    }
+
     #[test]
     #[allow(clippy::too_many_lines)]
     fn calc_preview_height_test() {
@@ -1655,35 +2480,8 @@ mod tests {
     #[test]
     fn state_scroll_up_underflow() {
         let mut state = State {
-            history_count: 0,
-            update_needed: None,
-            results_state: ListState::default(),
-            switched_search_mode: false,
-            search_mode: SearchMode::Fuzzy,
-            results_len: 0,
-            accept: false,
             keymap_mode: KeymapMode::Auto,
-            prefix: false,
-            current_cursor: None,
-            tab_index: 0,
-            inspecting_state: InspectingState {
-                current: None,
-                next: None,
-                previous: None,
-            },
-            search: SearchState {
-                input: String::new().into(),
-                filter_mode: FilterMode::Directory,
-                context: Context {
-                    session: String::new(),
-                    cwd: String::new(),
-                    hostname: String::new(),
-                    host_id: String::new(),
-                    git_root: None,
-                },
-            },
-            engine: engines::engine(SearchMode::Fuzzy),
-            now: Box::new(OffsetDateTime::now_utc),
+            ..test_state()
         };
 
         state.scroll_up(1);
@@ -1691,6 +2489,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::too_many_lines)]
     fn test_accept_keybindings() {
         use atuin_client::settings::Keys;
         use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
@@ -1707,34 +2506,8 @@ mod tests {
 
         let mut state = State {
             history_count: 1,
-            update_needed: None,
-            results_state: ListState::default(),
-            switched_search_mode: false,
-            search_mode: SearchMode::Fuzzy,
             results_len: 1,
-            accept: false,
-            keymap_mode: KeymapMode::Emacs,
-            prefix: false,
-            current_cursor: None,
-            tab_index: 0,
-            inspecting_state: InspectingState {
-                current: None,
-                next: None,
-                previous: None,
-            },
-            search: SearchState {
-                input: String::new().into(),
-                filter_mode: FilterMode::Global,
-                context: Context {
-                    session: String::new(),
-                    cwd: String::new(),
-                    hostname: String::new(),
-                    host_id: String::new(),
-                    git_root: None,
-                },
-            },
-            engine: engines::engine(SearchMode::Fuzzy),
-            now: Box::new(OffsetDateTime::now_utc),
+            ..test_state()
         };
 
         let tab_event = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
@@ -1806,4 +2579,238 @@ mod tests {
         );
         settings.keys.accept_with_backspace = false;
     }
+
+    #[test]
+    fn test_custom_keybindings() {
+        use atuin_client::settings::keymap::{Action, Bindings};
+        use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let mut settings = Settings::utc();
+        settings.keymap.emacs =
+            Bindings::parse([("ctrl-r", Action::Copy), ("ctrl-y", Action::None)]).unwrap();
+        settings.keymap.vim_normal = Bindings::parse([("x", Action::DeleteCharAfter)]).unwrap();
+
+        let mut state = State {
+            history_count: 1,
+            results_len: 1,
+            ..test_state()
+        };
+
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert!(matches!(
+            state.handle_key_input(&settings, &ctrl_r),
+            super::InputAction::Copy(_)
+        ));
+
+        // unbinding a chord falls back to inserting the character
+        let ctrl_y = KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL);
+        state.handle_key_input(&settings, &ctrl_y);
+        assert_eq!(state.search.input.as_str(), "y");
+
+        // bindings are per mode
+        state.keymap_mode = KeymapMode::VimNormal;
+        state.search.input.start();
+        let x = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
+        state.handle_key_input(&settings, &x);
+        assert_eq!(state.search.input.as_str(), "");
+
+        let i = KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE);
+        state.handle_key_input(&settings, &i);
+        assert_eq!(state.keymap_mode, KeymapMode::VimInsert);
+    }
+
+    #[test]
+    fn test_inspector_keybindings() {
+        use atuin_client::{
+            history::HistoryId,
+            settings::keymap::{Action, Bindings},
+        };
+        use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let mut settings = Settings::utc();
+        settings.keymap.emacs = Bindings::parse([("ctrl-b", Action::SelectPrevious)]).unwrap();
+
+        let mut state = State {
+            history_count: 1,
+            results_len: 1,
+            tab_index: 1,
+            ..test_state()
+        };
+        let previous = HistoryId::from("previous".to_string());
+        state.inspecting_state.previous = Some(previous.clone());
+
+        // the inspector moves with the bound keys, not just the arrows
+        let ctrl_b = KeyEvent::new(KeyCode::Char('b'), KeyModifiers::CONTROL);
+        assert!(matches!(
+            state.handle_key_input(&settings, &ctrl_b),
+            super::InputAction::Redraw
+        ));
+        assert_eq!(state.inspecting_state.current, Some(previous));
+
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            state.handle_key_input(&settings, &enter),
+            super::InputAction::AcceptInspecting
+        ));
+    }
+
+    #[test]
+    fn test_marks() {
+        use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let settings = Settings::utc();
+        let now = OffsetDateTime::now_utc();
+
+        // newest first, as the search returns them
+        let results: Vec<History> = ["make test", "make", "git pull", "cd repo"]
+            .into_iter()
+            .zip(0..)
+            .map(|(command, i)| {
+                History::import()
+                    .timestamp(now - time::Duration::minutes(i))
+                    .command(command)
+                    .build()
+                    .into()
+            })
+            .collect();
+
+        let mut state = State {
+            history_count: 4,
+            results_len: 4,
+            ..test_state()
+        };
+
+        let key = |c, modifiers| KeyEvent::new(KeyCode::Char(c), modifiers);
+        let ctrl = |c| key(c, KeyModifiers::CONTROL);
+        let alt = |c| key(c, KeyModifiers::ALT);
+        let delete = |state: &mut State| {
+            state.handle_key_input(&settings, &ctrl('a'));
+            state.handle_key_input(&settings, &key('d', KeyModifiers::NONE))
+        };
+
+        // without marks, the actions apply to the selected entry
+        assert!(matches!(delete(&mut state), super::InputAction::Delete(0)));
+        assert!(matches!(
+            state.handle_key_input(&settings, &alt('y')),
+            super::InputAction::Copy(0)
+        ));
+
+        assert!(matches!(
+            state.handle_key_input(&settings, &ctrl('t')),
+            super::InputAction::ToggleMark
+        ));
+        state.toggle_mark(&results);
+        state.results_state.select(2);
+        state.mark_range(&results);
+        assert_eq!(
+            state.marked_commands(),
+            vec!["git pull", "make", "make test"]
+        );
+
+        // toggling a marked entry unmarks it
+        state.results_state.select(1);
+        state.toggle_mark(&results);
+        assert_eq!(state.marked_commands(), vec!["git pull", "make test"]);
+
+        assert!(matches!(
+            delete(&mut state),
+            super::InputAction::DeleteMarked
+        ));
+        assert!(matches!(
+            state.handle_key_input(&settings, &ctrl('y')),
+            super::InputAction::CopyMarked("\n")
+        ));
+        assert!(matches!(
+            state.handle_key_input(&settings, &alt('y')),
+            super::InputAction::CopyMarked(" && ")
+        ));
+
+        state.handle_key_input(&settings, &alt('u'));
+        assert!(state.marked.is_empty());
+    }
+
+    #[test]
+    fn test_edit_command() {
+        use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        use super::Editor;
+
+        let mut settings = Settings::utc();
+        settings.enter_accept = true;
+
+        let mut state = State {
+            history_count: 1,
+            results_len: 1,
+            ..test_state()
+        };
+
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let alt = |code| KeyEvent::new(code, KeyModifiers::ALT);
+
+        assert!(matches!(
+            state.handle_key_input(&settings, &alt(KeyCode::Char('e'))),
+            super::InputAction::EditCommand
+        ));
+        state.editing = Some(Editor::new("cargo test"));
+
+        // keys edit the command rather than the query
+        state.handle_key_input(&settings, &alt(KeyCode::Enter));
+        for c in "-- --nocapture".chars() {
+            state.handle_key_input(&settings, &key(KeyCode::Char(c)));
+        }
+        state.handle_key_input(&settings, &key(KeyCode::Up));
+        state.handle_key_input(&settings, &alt(KeyCode::Char('b')));
+        state.handle_key_input(&settings, &key(KeyCode::Backspace));
+        assert_eq!(state.search.input.as_str(), "");
+        assert_eq!(
+            state.editing.as_ref().unwrap().text(),
+            "cargotest\n-- --nocapture"
+        );
+
+        // exiting goes back to the results
+        state.handle_key_input(&settings, &key(KeyCode::Esc));
+        assert!(state.editing.is_none());
+
+        state.editing = Some(Editor::new("ls"));
+        state.handle_key_input(&settings, &key(KeyCode::Char('a')));
+        assert!(matches!(
+            state.handle_key_input(&settings, &key(KeyCode::Enter)),
+            super::InputAction::AcceptEdited(command) if command == "lsa"
+        ));
+        assert!(state.accept);
+    }
+
+    #[test]
+    fn test_highlight_range() {
+        use atuin_client::theme::{Meaning, ThemeManager};
+        use atuin_common::syntax::tokenize;
+
+        use super::highlight_range;
+
+        let command = "ls -la | grep 'x'";
+        let tokens = tokenize(command);
+        let mut manager = ThemeManager::new(Some(false), Some(String::new()));
+        let theme = manager.load_theme("default", None);
+
+        // ranges can split tokens, as when wrapping long lines
+        let line = highlight_range(command, &tokens, 3..11, Some(theme));
+        let spans: Vec<_> = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(spans, ["-la", " ", "|", " ", "gr"]);
+        assert_eq!(
+            line.spans[0].style,
+            theme.as_style(Meaning::SyntaxFlag).into()
+        );
+        assert_eq!(
+            line.spans[4].style,
+            theme.as_style(Meaning::SyntaxCommand).into()
+        );
+
+        let line = highlight_range(command, &tokens, 0..command.len(), None);
+        assert_eq!(line.to_string(), command);
+        assert!(
+            line.spans
+                .iter()
+                .all(|s| s.style == ratatui::style::Style::default())
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use eyre::Result;
 use interim::parse_date_string;
 use time::{Duration, OffsetDateTime, Time};
@@ -9,7 +9,50 @@ use atuin_client::{
     theme::Theme,
 };
 
-use atuin_history::stats::{compute, pretty_print};
+use atuin_history::{
+    breakdown::{self, Group},
+    calendar::{self, Focus},
+    stats::{Period, Stats, compute, pretty_print, to_csv, to_json},
+};
+
+use super::search::duration::format_duration;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// A coloured table, for reading
+    #[default]
+    Pretty,
+    /// The full stats and the period they cover, as a JSON object
+    Json,
+    /// A row for each top command, with the period and totals on every row
+    Csv,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum By {
+    /// The git repo commands ran in, or the directory outside of one
+    Dir,
+    /// The host commands ran on
+    Host,
+    /// The hour of the day commands ran at
+    Hour,
+    /// The day of the week commands ran on
+    Weekday,
+    /// Each command, with how often it failed and the time spent running it
+    Exit,
+}
+
+impl From<By> for breakdown::By {
+    fn from(by: By) -> Self {
+        match by {
+            By::Dir => Self::Dir,
+            By::Host => Self::Host,
+            By::Hour => Self::Hour,
+            By::Weekday => Self::Weekday,
+            By::Exit => Self::Exit,
+        }
+    }
+}
 
 fn parse_ngram_size(s: &str) -> Result<usize, String> {
     let value = s
@@ -23,9 +66,22 @@ fn parse_ngram_size(s: &str) -> Result<usize, String> {
     Ok(value)
 }
 
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Draw a heatmap of how many commands ran each day, like GitHub's contribution graph
+    Calendar {
+        /// The year, month or day to draw, like 2025, 2025-03 or 2025-03-14. A month is drawn
+        /// day by day, and a day hour by hour. Defaults to this year
+        focus: Option<Focus>,
+    },
+}
+
 #[derive(Parser, Debug)]
-#[command(infer_subcommands = true)]
+#[command(infer_subcommands = true, args_conflicts_with_subcommands = true)]
 pub struct Cmd {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Compute statistics for the specified period, leave blank for statistics since the beginning. See [this](https://docs.atuin.sh/reference/stats/) for more details.
     period: Vec<String>,
 
@@ -36,10 +92,28 @@ pub struct Cmd {
     /// The number of consecutive commands to consider
     #[arg(long, short, default_value = "1", value_parser = parse_ngram_size)]
     ngram_size: usize,
+
+    /// How to print the statistics
+    #[arg(long, short, value_enum, default_value_t)]
+    format: Format,
+
+    /// Break the statistics down by where, when or how commands ran, instead of listing the top
+    /// commands
+    #[arg(long, value_enum, conflicts_with = "ngram_size")]
+    by: Option<By>,
+
+    /// Count commands that only differ in their arguments, like paths, hashes or branches, as
+    /// the same command. Set `normalize` in `[stats]` to always do so
+    #[arg(long)]
+    normalize: bool,
 }
 
 impl Cmd {
     pub async fn run(&self, db: &impl Database, settings: &Settings, theme: &Theme) -> Result<()> {
+        if let Some(Command::Calendar { focus }) = self.command {
+            return draw_calendar(focus, db, settings, theme).await;
+        }
+
         let context = current_context();
         let words = if self.period.is_empty() {
             String::from("all")
@@ -47,39 +121,147 @@ impl Cmd {
             self.period.join(" ")
         };
 
-        let now = OffsetDateTime::now_utc().to_offset(settings.timezone.0);
-        let last_night = now.replace_time(Time::MIDNIGHT);
-
-        let history = if words.as_str() == "all" {
-            db.list(&[], &context, None, false, false).await?
-        } else if words.trim() == "today" {
-            let start = last_night;
-            let end = start + Duration::days(1);
-            db.range(start, end).await?
-        } else if words.trim() == "month" {
-            let end = last_night;
-            let start = end - Duration::days(31);
-            db.range(start, end).await?
-        } else if words.trim() == "week" {
-            let end = last_night;
-            let start = end - Duration::days(7);
-            db.range(start, end).await?
-        } else if words.trim() == "year" {
-            let end = last_night;
-            let start = end - Duration::days(365);
-            db.range(start, end).await?
+        let range = period_range(&words, settings)?;
+
+        let history = match range {
+            Some((start, end)) => db.range(start, end).await?,
+            None => db.list(&[], &context, None, false, false).await?,
+        };
+
+        let period = Period {
+            name: words,
+            start: range.map(|(start, _)| start),
+            end: range.map(|(_, end)| end),
+        };
+
+        if let Some(by) = self.by {
+            let groups = breakdown::compute(
+                settings,
+                &history,
+                by.into(),
+                self.count,
+                settings.timezone.0,
+            );
+
+            match self.format {
+                Format::Pretty => print_groups(&groups),
+                Format::Json => println!("{}", breakdown::to_json(&groups, by.into(), &period)?),
+                Format::Csv => print!("{}", breakdown::to_csv(&groups, by.into(), &period)),
+            }
+
+            return Ok(());
+        }
+
+        let stats = if self.normalize {
+            let mut settings = settings.clone();
+            settings.stats.normalize = true;
+            compute(&settings, &history, self.count, self.ngram_size)
         } else {
-            let start = parse_date_string(&words, now, settings.dialect.into())?;
-            let end = start + Duration::days(1);
-            db.range(start, end).await?
+            compute(settings, &history, self.count, self.ngram_size)
         };
 
-        let stats = compute(settings, &history, self.count, self.ngram_size);
+        // machine readable output is always printed, even without any history in the period
+        let or_empty = |stats: Option<Stats>| {
+            stats.unwrap_or(Stats {
+                total_commands: 0,
+                unique_commands: 0,
+                ngram_size: self.ngram_size,
+                top: Vec::new(),
+            })
+        };
 
-        if let Some(stats) = stats {
-            pretty_print(stats, self.ngram_size, theme);
+        match self.format {
+            Format::Pretty => {
+                if let Some(stats) = stats {
+                    pretty_print(stats, self.ngram_size, theme);
+                }
+            }
+            Format::Json => println!("{}", to_json(&or_empty(stats), &period)?),
+            Format::Csv => print!("{}", to_csv(&or_empty(stats), &period)),
         }
 
         Ok(())
     }
 }
+
+/// The start and end of a period given in words, like `today`, `week` or a date. The `all`
+/// period has no bounds.
+pub fn period_range(
+    words: &str,
+    settings: &Settings,
+) -> Result<Option<(OffsetDateTime, OffsetDateTime)>> {
+    let now = OffsetDateTime::now_utc().to_offset(settings.timezone.0);
+    let last_night = now.replace_time(Time::MIDNIGHT);
+
+    let range = if words == "all" {
+        None
+    } else if words.trim() == "today" {
+        let start = last_night;
+        Some((start, start + Duration::days(1)))
+    } else if words.trim() == "month" {
+        let end = last_night;
+        Some((end - Duration::days(31), end))
+    } else if words.trim() == "week" {
+        let end = last_night;
+        Some((end - Duration::days(7), end))
+    } else if words.trim() == "year" {
+        let end = last_night;
+        Some((end - Duration::days(365), end))
+    } else {
+        let start = parse_date_string(words, now, settings.dialect.into())?;
+        Some((start, start + Duration::days(1)))
+    };
+
+    Ok(range)
+}
+
+async fn draw_calendar(
+    focus: Option<Focus>,
+    db: &impl Database,
+    settings: &Settings,
+    theme: &Theme,
+) -> Result<()> {
+    let timezone = settings.timezone.0;
+    let focus =
+        focus.unwrap_or_else(|| Focus::Year(OffsetDateTime::now_utc().to_offset(timezone).year()));
+
+    let (start, end) = focus.range(timezone);
+    let history = db.range(start, end).await?;
+
+    let drawn = match focus {
+        Focus::Year(year) => calendar::draw_year(year, &calendar::days(&history, timezone), theme),
+        Focus::Month(year, month) => {
+            calendar::draw_month(year, month, &calendar::days(&history, timezone), theme)
+        }
+        Focus::Day(day) => calendar::draw_day(day, &calendar::hours(&history, timezone), theme),
+    };
+    print!("{drawn}");
+
+    Ok(())
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn print_groups(groups: &[Group]) {
+    let key_width = groups.iter().map(|g| g.key.len()).max().unwrap_or(0);
+    let max = groups.iter().map(|g| g.commands).max().unwrap_or(0);
+    let count_width = max.max(1).ilog10() as usize + 1;
+
+    for group in groups {
+        let bar = "▮".repeat(10 * group.commands / max.max(1));
+        let duration = format_duration(std::time::Duration::from_nanos(group.duration as u64));
+
+        let top = group
+            .top
+            .iter()
+            .map(|(command, count)| format!("{command} ({count})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        println!(
+            "{:key_width$}  [{bar:10}] {:count_width$} runs  {:5.1}% failed  {duration:>6}  {top}",
+            group.key,
+            group.commands,
+            group.failure_rate(),
+        );
+    }
+}
@@ -15,9 +15,11 @@ mod push;
 #[cfg(feature = "sync")]
 mod pull;
 
+mod backup;
 mod purge;
 mod rebuild;
-mod rekey;
+pub mod rekey;
+mod restore;
 mod verify;
 
 #[derive(Subcommand, Debug)]
@@ -38,6 +40,12 @@ pub enum Cmd {
     /// Verify that all records in the store can be decrypted with the current key
     Verify(verify::Verify),
 
+    /// Write a compressed backup of all records in the store. Records stay encrypted
+    Backup(backup::Backup),
+
+    /// Restore records from a backup, then rebuild history, dotfiles and scripts from them
+    Restore(restore::Restore),
+
     /// Push all records to the remote sync server (one way sync)
     #[cfg(feature = "sync")]
     Push(push::Push),
@@ -60,6 +68,8 @@ impl Cmd {
             Self::Rekey(rekey) => rekey.run(settings, store).await,
             Self::Verify(verify) => verify.run(settings, store).await,
             Self::Purge(purge) => purge.run(settings, store).await,
+            Self::Backup(backup) => backup.run(store).await,
+            Self::Restore(restore) => restore.run(settings, store, database).await,
 
             #[cfg(feature = "sync")]
             Self::Push(push) => push.run(settings, store).await,
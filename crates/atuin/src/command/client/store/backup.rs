@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use eyre::Result;
+
+use atuin_client::{
+    record::{backup, sqlite_store::SqliteStore},
+    settings::Settings,
+};
+
+#[derive(Args, Debug)]
+pub struct Backup {
+    /// The file to write the backup to. Records stay encrypted, so keep your key safe too.
+    pub path: PathBuf,
+}
+
+impl Backup {
+    pub async fn run(&self, store: SqliteStore) -> Result<()> {
+        let host_id = Settings::host_id().expect("failed to get host_id");
+
+        let file = fs_err::File::create_new(&self.path)?;
+        let count = backup::write(&store, host_id, std::io::BufWriter::new(file)).await?;
+
+        println!("Backed up {count} records to {}", self.path.display());
+
+        Ok(())
+    }
+}
@@ -2,8 +2,7 @@ use clap::Args;
 use eyre::Result;
 
 use atuin_client::{
-    encryption::load_key,
-    record::{sqlite_store::SqliteStore, store::Store},
+    record::{keyring::Keyring, sqlite_store::SqliteStore, store::Store},
     settings::Settings,
 };
 
@@ -14,9 +13,9 @@ impl Purge {
     pub async fn run(&self, settings: &Settings, store: SqliteStore) -> Result<()> {
         println!("Purging local records that cannot be decrypted");
 
-        let key = load_key(settings)?;
+        let keyring = Keyring::load(settings)?;
 
-        match store.purge(&key.into()).await {
+        match store.purge(&keyring).await {
             Ok(()) => println!("Local store purge completed OK"),
             Err(e) => println!("Failed to purge local store: {e:?}"),
         }
@@ -4,8 +4,10 @@ use clap::Args;
 use eyre::{Result, bail};
 
 use atuin_client::{
-    database::Database, encryption, history::store::HistoryStore,
-    record::sqlite_store::SqliteStore, settings::Settings,
+    database::Database,
+    history::store::HistoryStore,
+    record::{keyring::Keyring, sqlite_store::SqliteStore},
+    settings::Settings,
 };
 
 #[derive(Args, Debug)]
@@ -50,10 +52,10 @@ impl Rebuild {
         store: SqliteStore,
         database: &dyn Database,
     ) -> Result<()> {
-        let encryption_key: [u8; 32] = encryption::load_key(settings)?.into();
+        let keyring = Keyring::load(settings)?;
 
         let host_id = Settings::host_id().expect("failed to get host_id");
-        let history_store = HistoryStore::new(store, host_id, encryption_key);
+        let history_store = HistoryStore::new(store, host_id, keyring.clone());
 
         history_store.build(database).await?;
 
@@ -61,12 +63,12 @@ impl Rebuild {
     }
 
     async fn rebuild_dotfiles(&self, settings: &Settings, store: SqliteStore) -> Result<()> {
-        let encryption_key: [u8; 32] = encryption::load_key(settings)?.into();
+        let keyring = Keyring::load(settings)?;
 
         let host_id = Settings::host_id().expect("failed to get host_id");
 
-        let alias_store = AliasStore::new(store.clone(), host_id, encryption_key);
-        let var_store = VarStore::new(store.clone(), host_id, encryption_key);
+        let alias_store = AliasStore::new(store.clone(), host_id, keyring.clone());
+        let var_store = VarStore::new(store.clone(), host_id, keyring.clone());
 
         alias_store.build().await?;
         var_store.build().await?;
@@ -75,9 +77,9 @@ impl Rebuild {
     }
 
     async fn rebuild_scripts(&self, settings: &Settings, store: SqliteStore) -> Result<()> {
-        let encryption_key: [u8; 32] = encryption::load_key(settings)?.into();
+        let keyring = Keyring::load(settings)?;
         let host_id = Settings::host_id().expect("failed to get host_id");
-        let script_store = ScriptStore::new(store, host_id, encryption_key);
+        let script_store = ScriptStore::new(store, host_id, keyring.clone());
         let database =
             atuin_scripts::database::Database::new(settings.scripts.db_path.clone(), 1.0).await?;
 
@@ -1,11 +1,9 @@
 use clap::Args;
 use eyre::{Result, bail};
-use tokio::{fs::File, io::AsyncWriteExt};
 
 use atuin_client::{
-    encryption::{Key, decode_key, encode_key, generate_encoded_key, load_key},
-    record::sqlite_store::SqliteStore,
-    record::store::Store,
+    encryption::{Key, decode_key, encode_key, generate_encoded_key},
+    record::{keyring::Keyring, sqlite_store::SqliteStore, store::Store},
     settings::Settings,
 };
 
@@ -20,46 +18,52 @@ impl Rekey {
         let key = if let Some(key) = self.key.clone() {
             println!("Re-encrypting store with specified key");
 
-            match bip39::Mnemonic::from_phrase(&key, bip39::Language::English) {
-                Ok(mnemonic) => encode_key(Key::from_slice(mnemonic.entropy()))?,
-                Err(err) => {
-                    match err.downcast_ref::<bip39::ErrorKind>() {
-                        Some(err) => {
-                            match err {
-                                // assume they copied in the base64 key
-                                bip39::ErrorKind::InvalidWord => key,
-                                bip39::ErrorKind::InvalidChecksum => {
-                                    bail!("key mnemonic was not valid")
-                                }
-                                bip39::ErrorKind::InvalidKeysize(_)
-                                | bip39::ErrorKind::InvalidWordLength(_)
-                                | bip39::ErrorKind::InvalidEntropyLength(_, _) => {
-                                    bail!("key was not the correct length")
-                                }
-                            }
-                        }
-                        _ => {
-                            // unknown error. assume they copied the base64 key
-                            key
-                        }
-                    }
-                }
-            }
+            parse_key(key)?
         } else {
             println!("Re-encrypting store with freshly-generated key");
             let (_, encoded) = generate_encoded_key()?;
             encoded
         };
 
-        let current_key: [u8; 32] = load_key(settings)?.into();
-        let new_key: [u8; 32] = decode_key(key.clone())?.into();
+        // records written with retired keys are read with them, and rewritten too
+        let keyring = Keyring::load(settings)?;
+        let new_key: [u8; 32] = decode_key(key)?.into();
 
-        store.re_encrypt(&current_key, &new_key).await?;
+        store.re_encrypt(&keyring, &new_key).await?;
 
         println!("Store rewritten. Saving new key");
-        let mut file = File::create(settings.key_path.clone()).await?;
-        file.write_all(key.as_bytes()).await?;
+        keyring.set_current(new_key);
+        keyring.save(settings)?;
 
         Ok(())
     }
 }
+
+/// Normalise a key given as either a bip39 mnemonic or base64, to base64
+pub fn parse_key(key: String) -> Result<String> {
+    match bip39::Mnemonic::from_phrase(&key, bip39::Language::English) {
+        Ok(mnemonic) => encode_key(Key::from_slice(mnemonic.entropy())),
+        Err(err) => {
+            match err.downcast_ref::<bip39::ErrorKind>() {
+                Some(err) => {
+                    match err {
+                        // assume they copied in the base64 key
+                        bip39::ErrorKind::InvalidWord => Ok(key),
+                        bip39::ErrorKind::InvalidChecksum => {
+                            bail!("key mnemonic was not valid")
+                        }
+                        bip39::ErrorKind::InvalidKeysize(_)
+                        | bip39::ErrorKind::InvalidWordLength(_)
+                        | bip39::ErrorKind::InvalidEntropyLength(_, _) => {
+                            bail!("key was not the correct length")
+                        }
+                    }
+                }
+                _ => {
+                    // unknown error. assume they copied the base64 key
+                    Ok(key)
+                }
+            }
+        }
+    }
+}
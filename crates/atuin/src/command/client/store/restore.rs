@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use eyre::Result;
+use time::UtcOffset;
+
+use atuin_client::{
+    database::Database,
+    record::{backup, keyring::Keyring, sqlite_store::SqliteStore, store::Store},
+    settings::Settings,
+};
+
+use super::rebuild::Rebuild;
+
+#[derive(Args, Debug)]
+pub struct Restore {
+    /// The backup file to restore from
+    pub path: PathBuf,
+
+    /// Only check the backup, without restoring it
+    #[arg(long)]
+    pub check: bool,
+}
+
+impl Restore {
+    pub async fn run(
+        &self,
+        settings: &Settings,
+        store: SqliteStore,
+        database: &dyn Database,
+    ) -> Result<()> {
+        let backup = backup::read(fs_err::File::open(&self.path)?)?;
+
+        let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+        println!(
+            "Backup of host {} taken at {} by atuin {}, containing {} records",
+            backup.metadata.host_id.0.as_hyphenated(),
+            backup.metadata.created_at.to_offset(offset),
+            backup.metadata.atuin_version,
+            backup.records.len(),
+        );
+
+        if self.check {
+            return Ok(());
+        }
+
+        backup::restore(&store, &backup).await?;
+        println!("Records restored");
+
+        // Records are restored as-is. They are only useful with the key they were encrypted with.
+        let keyring = Keyring::load(settings)?;
+        if let Err(e) = store.verify(&keyring).await {
+            println!(
+                "Some records cannot be decrypted with any known key ({e}). Restore the key they were written with, then run `atuin store rebuild`"
+            );
+            return Ok(());
+        }
+
+        for tag in ["history", "dotfiles", "scripts"] {
+            println!("Rebuilding {tag}");
+
+            Rebuild {
+                tag: tag.to_string(),
+            }
+            .run(settings, store.clone(), database)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
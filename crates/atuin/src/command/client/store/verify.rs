@@ -2,8 +2,7 @@ use clap::Args;
 use eyre::Result;
 
 use atuin_client::{
-    encryption::load_key,
-    record::{sqlite_store::SqliteStore, store::Store},
+    record::{keyring::Keyring, sqlite_store::SqliteStore, store::Store},
     settings::Settings,
 };
 
@@ -14,9 +13,9 @@ impl Verify {
     pub async fn run(&self, settings: &Settings, store: SqliteStore) -> Result<()> {
         println!("Verifying local store can be decrypted with the current key");
 
-        let key = load_key(settings)?;
+        let keyring = Keyring::load(settings)?;
 
-        match store.verify(&key.into()).await {
+        match store.verify(&keyring).await {
             Ok(()) => println!("Local store encryption verified OK"),
             Err(e) => println!("Failed to verify local store encryption: {e:?}"),
         }
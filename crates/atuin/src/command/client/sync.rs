@@ -3,12 +3,13 @@ use eyre::{Result, WrapErr};
 
 use atuin_client::{
     database::Database,
-    encryption,
     history::store::HistoryStore,
-    record::{sqlite_store::SqliteStore, store::Store, sync},
+    record::{keyring::Keyring, sqlite_store::SqliteStore, store::Store, sync},
     settings::Settings,
+    team,
 };
 
+pub mod key;
 mod status;
 
 use crate::command::client::account;
@@ -32,12 +33,8 @@ pub enum Cmd {
     /// Register with the configured server
     Register(account::register::Cmd),
 
-    /// Print the encryption key for transfer to another machine
-    Key {
-        /// Switch to base64 output of the key
-        #[arg(long)]
-        base64: bool,
-    },
+    /// Print the encryption key for transfer to another machine, or escrow it on the server
+    Key(key::Cmd),
 
     /// Display the sync status
     Status,
@@ -56,20 +53,7 @@ impl Cmd {
             Self::Logout => account::logout::run(&settings),
             Self::Register(r) => r.run(&settings).await,
             Self::Status => status::run(&settings, db).await,
-            Self::Key { base64 } => {
-                use atuin_client::encryption::{encode_key, load_key};
-                let key = load_key(&settings).wrap_err("could not load encryption key")?;
-
-                if base64 {
-                    let encode = encode_key(&key).wrap_err("could not encode encryption key")?;
-                    println!("{encode}");
-                } else {
-                    let mnemonic = bip39::Mnemonic::from_entropy(&key, bip39::Language::English)
-                        .map_err(|_| eyre::eyre!("invalid key"))?;
-                    println!("{mnemonic}");
-                }
-                Ok(())
-            }
+            Self::Key(key) => key.run(&settings, db, store).await,
         }
     }
 }
@@ -81,12 +65,10 @@ async fn run(
     store: SqliteStore,
 ) -> Result<()> {
     if settings.sync.records {
-        let encryption_key: [u8; 32] = encryption::load_key(settings)
-            .context("could not load encryption key")?
-            .into();
+        let keyring = Keyring::load(settings).context("could not load encryption key")?;
 
         let host_id = Settings::host_id().expect("failed to get host_id");
-        let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
+        let history_store = HistoryStore::new(store.clone(), host_id, keyring.clone());
 
         let (uploaded, downloaded) = sync::sync(settings, &store).await?;
 
@@ -117,6 +99,15 @@ async fn run(
 
             println!("{uploaded}/{} up/down to record store", downloaded.len());
         }
+
+        for (team, result) in team::sync_all(settings, crate::sync::build_team).await? {
+            match result {
+                Ok((uploaded, downloaded)) => {
+                    println!("{uploaded}/{downloaded} up/down to team store {team}");
+                }
+                Err(e) => eprintln!("{e:?}"),
+            }
+        }
     } else {
         atuin_client::sync::sync(settings, force, db).await?;
     }
@@ -0,0 +1,296 @@
+use std::io;
+
+use clap::{Args, Subcommand};
+use eyre::{Result, WrapErr, bail};
+use rpassword::prompt_password;
+
+use atuin_client::{
+    api_client,
+    database::Database,
+    encryption::{KeyEscrow, decode_key, encode_key, load_key},
+    record::{
+        keyring::{self, Keyring},
+        sqlite_store::SqliteStore,
+        sync,
+    },
+    settings::Settings,
+};
+
+use crate::command::client::store::rekey::parse_key;
+
+const MIN_PASSPHRASE_LENGTH: usize = 8;
+
+/// The environment variable a key passphrase can be given in, for scripted setups
+const PASSPHRASE_VAR: &str = "ATUIN_KEY_PASSPHRASE";
+
+#[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cmd {
+    /// Switch to base64 output of the key
+    #[arg(long)]
+    base64: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Store a passphrase-wrapped copy of the key on the sync server, so that new machines can
+    /// login with just a username, password and passphrase
+    Escrow {
+        /// Read the passphrase to wrap the key with from the first line of stdin. Otherwise it's
+        /// read from `$ATUIN_KEY_PASSPHRASE`, or prompted for
+        #[arg(long)]
+        passphrase_stdin: bool,
+
+        /// Remove the escrowed key from the server
+        #[arg(long, conflicts_with = "passphrase_stdin")]
+        delete: bool,
+    },
+
+    /// Generate a new key for all new records. Older records remain readable with the previous
+    /// key. Other machines switch over when they next sync
+    Rotate,
+
+    /// Switch to a key rotated on another machine, learning the keys it retired. Only needed on
+    /// machines that can't read the rotation, as they never had the key it retired
+    Use {
+        /// The new key, as shown by 'atuin key' on the machine that rotated it. Recovered from
+        /// the escrowed key if omitted
+        key: Option<String>,
+
+        /// Read the passphrase for the escrowed key from the first line of stdin. Otherwise it's
+        /// read from `$ATUIN_KEY_PASSPHRASE`, or prompted for
+        #[arg(long, conflicts_with = "key")]
+        passphrase_stdin: bool,
+    },
+}
+
+impl Cmd {
+    pub async fn run(
+        self,
+        settings: &Settings,
+        db: &impl Database,
+        store: SqliteStore,
+    ) -> Result<()> {
+        match self.command {
+            None => print_key(settings, self.base64),
+            Some(Commands::Escrow {
+                passphrase_stdin,
+                delete,
+            }) => escrow(settings, passphrase_stdin, delete).await,
+            Some(Commands::Rotate) => rotate(settings, db, store).await,
+            Some(Commands::Use {
+                key,
+                passphrase_stdin,
+            }) => use_key(settings, db, store, key, passphrase_stdin).await,
+        }
+    }
+}
+
+fn print_key(settings: &Settings, base64: bool) -> Result<()> {
+    let key = load_key(settings).wrap_err("could not load encryption key")?;
+
+    if base64 {
+        let encode = encode_key(&key).wrap_err("could not encode encryption key")?;
+        println!("{encode}");
+    } else {
+        let mnemonic = bip39::Mnemonic::from_entropy(&key, bip39::Language::English)
+            .map_err(|_| eyre::eyre!("invalid key"))?;
+        println!("{mnemonic}");
+    }
+
+    Ok(())
+}
+
+/// The passphrase from stdin if asked for, or else from the environment. None if it should be
+/// prompted for.
+pub fn given_passphrase(stdin: bool) -> Result<Option<String>> {
+    if stdin {
+        let mut passphrase = String::new();
+        io::stdin().read_line(&mut passphrase)?;
+        return Ok(Some(passphrase.trim_end_matches(['\r', '\n']).to_string()));
+    }
+
+    Ok(std::env::var(PASSPHRASE_VAR).ok().filter(|p| !p.is_empty()))
+}
+
+fn read_new_passphrase() -> Result<String> {
+    let passphrase = prompt_password("Please enter a passphrase to protect your key: ")?;
+    let confirm = prompt_password("Please confirm the passphrase: ")?;
+
+    if passphrase != confirm {
+        bail!("passphrases do not match");
+    }
+
+    Ok(passphrase)
+}
+
+async fn escrow(settings: &Settings, passphrase_stdin: bool, delete: bool) -> Result<()> {
+    if !settings.logged_in() {
+        bail!("You are not logged in");
+    }
+
+    let client = api_client::Client::new(
+        &settings.sync_address,
+        settings.session_token()?.as_str(),
+        settings.network_connect_timeout,
+        settings.network_timeout,
+    )?;
+
+    if delete {
+        client.delete_key_escrow().await?;
+        println!("Escrowed key removed from the server");
+
+        return Ok(());
+    }
+
+    let passphrase = match given_passphrase(passphrase_stdin)? {
+        Some(p) => p,
+        None => read_new_passphrase()?,
+    };
+
+    if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+        bail!("passphrase must be at least {MIN_PASSPHRASE_LENGTH} characters");
+    }
+
+    let key = load_key(settings).wrap_err("could not load encryption key")?;
+    let (escrow, recovery_code) = KeyEscrow::new(&key, &passphrase)?;
+
+    client.put_key_escrow(escrow.encode()?).await?;
+
+    println!("Your key has been wrapped with your passphrase and stored on the server.");
+    println!("New machines can now login with your username, password and passphrase.\n");
+    println!("If you forget your passphrase, you can use this recovery code instead:\n");
+    println!("    {recovery_code}\n");
+    println!("It will not be shown again. Store it somewhere safe, and do not share it.");
+
+    Ok(())
+}
+
+async fn rotate(settings: &Settings, db: &impl Database, store: SqliteStore) -> Result<()> {
+    let host_id = Settings::host_id().expect("failed to get host_id");
+
+    // Catch up with rotations made elsewhere first, so ours follows on from the latest key
+    if settings.logged_in() {
+        let (_, downloaded) = sync::sync(settings, &store).await?;
+        crate::sync::build(settings, &store, db, Some(&downloaded)).await?;
+    }
+
+    let keyring = Keyring::load(settings).wrap_err("could not load encryption key")?;
+    keyring::rotate(&store, host_id, &keyring).await?;
+    keyring.save(settings)?;
+
+    let migrated = keyring::migrate(&store, &keyring).await?;
+
+    println!(
+        "Rotated to a new key, and re-encrypted {migrated} records with it. {} older keys are kept to read records from the server.",
+        keyring.len() - 1
+    );
+
+    if !settings.logged_in() {
+        println!("You are not logged in, so the new key is only used on this machine");
+        return Ok(());
+    }
+
+    let (uploaded, _) = sync::sync(settings, &store).await?;
+    println!("{uploaded} records uploaded.\n");
+
+    let client = api_client::Client::new(
+        &settings.sync_address,
+        settings.session_token()?.as_str(),
+        settings.network_connect_timeout,
+        settings.network_timeout,
+    )?;
+
+    // Other machines read the rotation with the key it retired. New machines log in with the
+    // escrowed key, so keep it current
+    if client.get_key_escrow().await?.is_some() {
+        println!("Escrowing the new key, for new machines to log in with");
+        escrow(settings, false, false).await?;
+    }
+
+    println!("Your other machines will switch to the new key when they next sync");
+
+    Ok(())
+}
+
+async fn use_key(
+    settings: &Settings,
+    db: &impl Database,
+    store: SqliteStore,
+    key: Option<String>,
+    passphrase_stdin: bool,
+) -> Result<()> {
+    let key = match key {
+        Some(key) => parse_key(key)?,
+        None if settings.logged_in() => {
+            recover(
+                settings,
+                settings.session_token()?.as_str(),
+                passphrase_stdin,
+            )
+            .await?
+        }
+        None => bail!("No key provided, and you are not logged in to recover an escrowed key"),
+    };
+    let key: [u8; 32] = decode_key(key).wrap_err("the key was invalid")?.into();
+
+    let keyring = Keyring::load(settings).wrap_err("could not load encryption key")?;
+    if key == keyring.current() {
+        println!("Already using this key");
+        return Ok(());
+    }
+
+    // Fetch the rotation first, to check the key against it
+    let downloaded = if settings.logged_in() {
+        sync::sync(settings, &store).await?.1
+    } else {
+        Vec::new()
+    };
+
+    if !keyring::is_rotation(&store, &key).await? {
+        bail!("This key was not rotated to on any of your machines");
+    }
+
+    keyring.set_current(key);
+    keyring::apply(&store, &keyring).await?;
+    keyring.save(settings)?;
+
+    let migrated = keyring::migrate(&store, &keyring).await?;
+    crate::sync::build(settings, &store, db, Some(&downloaded)).await?;
+
+    println!(
+        "Switched to the new key, and re-encrypted {migrated} records with it. {} older keys are kept to read records from the server.",
+        keyring.len() - 1
+    );
+
+    Ok(())
+}
+
+/// Fetch the escrowed key from the server, and unwrap it with a passphrase or recovery code
+pub async fn recover(settings: &Settings, session: &str, passphrase_stdin: bool) -> Result<String> {
+    let client = api_client::Client::new(
+        &settings.sync_address,
+        session,
+        settings.network_connect_timeout,
+        settings.network_timeout,
+    )?;
+
+    let Some(escrow) = client.get_key_escrow().await? else {
+        bail!(
+            "No key provided, and no key is escrowed for this account. Please use 'atuin key' on your other machine, or recover your key from a backup."
+        );
+    };
+    let escrow = KeyEscrow::decode(&escrow)?;
+
+    let passphrase = match given_passphrase(passphrase_stdin)? {
+        Some(p) => p,
+        None => prompt_password("Please enter your key passphrase or recovery code: ")?,
+    };
+
+    let key = escrow.unwrap(&passphrase)?;
+
+    encode_key(&key)
+}
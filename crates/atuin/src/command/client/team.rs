@@ -0,0 +1,198 @@
+use clap::Subcommand;
+use eyre::{Result, WrapErr, bail, eyre};
+use rpassword::prompt_password;
+
+use atuin_client::{
+    api_client,
+    encryption::{Key, decode_key, encode_key, generate_encoded_key},
+    settings::Settings,
+    team::{self, Team},
+};
+
+#[derive(Subcommand, Debug)]
+#[command(infer_subcommands = true)]
+pub enum Cmd {
+    /// Create a team on the sync server, with you as its admin, and generate its key
+    Create { name: String },
+
+    /// Join a team you have been added to, using the key shared by another member
+    Join {
+        name: String,
+
+        /// The team key, as a mnemonic or base64. Prompted for if not provided
+        #[arg(long)]
+        key: Option<String>,
+    },
+
+    /// Leave a team, removing yourself from it on the server and deleting its local data
+    Leave { name: String },
+
+    /// List the teams you are a member of
+    #[command(alias = "ls")]
+    List,
+
+    /// Add a user to a team. Only team admins can add members
+    Add {
+        name: String,
+        username: String,
+
+        /// Allow the new member to manage the team
+        #[arg(long)]
+        admin: bool,
+    },
+
+    /// Remove a user from a team. Only team admins can remove other members
+    #[command(alias = "rm")]
+    Remove { name: String, username: String },
+
+    /// Print the key of a team, to share with new members
+    Key {
+        name: String,
+
+        /// Switch to base64 output of the key
+        #[arg(long)]
+        base64: bool,
+    },
+}
+
+impl Cmd {
+    pub async fn run(self, settings: &Settings) -> Result<()> {
+        match self {
+            Self::Key { name, base64 } => print_key(&Team::open(settings, &name)?, base64),
+            Self::Join { name, key } => join(settings, &name, key).await,
+            cmd => {
+                if !settings.logged_in() {
+                    bail!("You are not logged in");
+                }
+
+                let client = api_client::Client::new(
+                    &settings.sync_address,
+                    settings.session_token()?.as_str(),
+                    settings.network_connect_timeout,
+                    settings.network_timeout,
+                )?;
+
+                cmd.run_remote(settings, &client).await
+            }
+        }
+    }
+
+    async fn run_remote(self, settings: &Settings, client: &api_client::Client<'_>) -> Result<()> {
+        match self {
+            Self::Create { name } => {
+                team::validate_name(&name)?;
+
+                client.create_team(&name).await?;
+
+                let (key, _) = generate_encoded_key()?;
+                let team = Team::join(settings, &name, &key)?;
+
+                println!("Created team {name}. Share its key with new members:\n");
+                print_key(&team, false)?;
+                println!("\nAdd them with 'atuin team add {name} <username>'");
+
+                Ok(())
+            }
+
+            Self::Leave { name } => {
+                let me = client.me().await?;
+                client.remove_team_member(&name, &me.username).await?;
+
+                if let Ok(team) = Team::open(settings, &name) {
+                    team.leave()?;
+                }
+
+                println!("Left team {name}");
+
+                Ok(())
+            }
+
+            Self::List => {
+                let joined = team::list(settings)?;
+
+                for team in client.list_teams().await? {
+                    let local = if joined.iter().any(|t| t.name == team.name) {
+                        ""
+                    } else {
+                        " (not joined on this machine)"
+                    };
+
+                    println!("{}{local}", team.name);
+
+                    for member in team.members {
+                        let admin = if member.admin { " (admin)" } else { "" };
+                        println!("  {}{admin}", member.username);
+                    }
+                }
+
+                Ok(())
+            }
+
+            Self::Add {
+                name,
+                username,
+                admin,
+            } => {
+                client.add_team_member(&name, &username, admin).await?;
+                println!("Added {username} to team {name}");
+
+                Ok(())
+            }
+
+            Self::Remove { name, username } => {
+                client.remove_team_member(&name, &username).await?;
+                println!("Removed {username} from team {name}");
+
+                Ok(())
+            }
+
+            Self::Key { .. } | Self::Join { .. } => unreachable!(),
+        }
+    }
+}
+
+async fn join(settings: &Settings, name: &str, key: Option<String>) -> Result<()> {
+    team::validate_name(name)?;
+
+    let key = match key {
+        Some(key) => key,
+        None => prompt_password(format!("Please enter the key for team {name}: "))?,
+    };
+    let key = parse_key(key.trim())?;
+
+    let team = Team::join(settings, name, &key)?;
+
+    if settings.logged_in()
+        && let Err(e) = team::sync(settings, &team, crate::sync::build_team).await
+    {
+        team.leave()?;
+        return Err(e).wrap_err(format!("could not sync team {name}"));
+    }
+
+    println!("Joined team {name}");
+
+    Ok(())
+}
+
+/// Parse a key given as either a bip39 mnemonic or base64
+fn parse_key(key: &str) -> Result<Key> {
+    if let Ok(mnemonic) = bip39::Mnemonic::from_phrase(key, bip39::Language::English) {
+        return Ok(*Key::from_slice(mnemonic.entropy()));
+    }
+
+    decode_key(key.to_string()).wrap_err("the team key was not valid")
+}
+
+fn print_key(team: &Team, base64: bool) -> Result<()> {
+    let key = team.key()?;
+
+    if base64 {
+        println!("{}", encode_key(&key)?);
+    } else {
+        let mnemonic = bip39::Mnemonic::from_entropy(&key, bip39::Language::English)
+            .map_err(|_| eyre!("invalid key"))?;
+        println!("{mnemonic}");
+    }
+
+    Ok(())
+}
@@ -1,11 +1,158 @@
+use clap::{Parser, ValueEnum};
 use crossterm::style::{ResetColor, SetAttribute};
-use eyre::Result;
+use eyre::{Result, bail};
+use interim::parse_date_string;
 use std::collections::{HashMap, HashSet};
-use time::{Date, Duration, Month, OffsetDateTime, Time};
+use std::fmt::Write;
+use time::{Duration, Month, OffsetDateTime, UtcOffset};
+
+use atuin_client::{database::Database, history::History, settings::Settings, theme::Theme};
+
+use atuin_history::{
+    calendar::Focus,
+    compare::{self, Comparison},
+    stats::{Stats, compute},
+};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// The full wrapped, for reading in the terminal
+    #[default]
+    Terminal,
+    /// A self-contained HTML page with a card to share
+    Html,
+    /// The card to share as an SVG image
+    Svg,
+}
+
+fn parse_year(s: &str) -> Result<i32, String> {
+    match s.parse::<Focus>()? {
+        Focus::Year(year) => Ok(year),
+        _ => Err(format!("'{s}' is not a year, like 2025")),
+    }
+}
+
+fn parse_month(s: &str) -> Result<(i32, Month), String> {
+    match s.parse::<Focus>()? {
+        Focus::Month(year, month) => Ok((year, month)),
+        _ => Err(format!("'{s}' is not a month, like 2025-03")),
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Cmd {
+    /// The year to wrap. Defaults to this year in December, and to last year before then
+    #[arg(value_parser = parse_year, conflicts_with_all = ["month", "since"])]
+    year: Option<i32>,
+
+    /// Wrap a month instead of a year, like 2025-03
+    #[arg(long, value_parser = parse_month, conflicts_with = "since")]
+    month: Option<(i32, Month)>,
+
+    /// Wrap the history since a date, like `atuin stats` takes
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Wrap the history until a date, which isn't included. Defaults to now
+    #[arg(long, requires = "since")]
+    until: Option<String>,
+
+    /// How to print the wrapped. The shareable cards only show totals and the names of the tools
+    /// used, never the commands themselves
+    #[arg(long, short, value_enum, default_value_t)]
+    format: Format,
+}
+
+/// The period of history being wrapped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Span {
+    Year(i32),
+    Month(i32, Month),
+    Custom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Period {
+    span: Span,
+    start: OffsetDateTime,
+    /// The end of the period, which isn't part of it
+    end: OffsetDateTime,
+}
+
+impl Period {
+    fn year(year: i32, timezone: UtcOffset) -> Self {
+        let (start, end) = Focus::Year(year).range(timezone);
+        Self {
+            span: Span::Year(year),
+            start,
+            end,
+        }
+    }
+
+    fn month(year: i32, month: Month, timezone: UtcOffset) -> Self {
+        let (start, end) = Focus::Month(year, month).range(timezone);
+        Self {
+            span: Span::Month(year, month),
+            start,
+            end,
+        }
+    }
+
+    /// The period of the same length right before this one
+    fn previous(&self) -> Self {
+        let timezone = self.start.offset();
+
+        match self.span {
+            Span::Year(year) => Self::year(year - 1, timezone),
+            Span::Month(year, Month::January) => Self::month(year - 1, Month::December, timezone),
+            Span::Month(year, month) => Self::month(year, month.previous(), timezone),
+            Span::Custom => Self {
+                span: Span::Custom,
+                start: self.start - (self.end - self.start),
+                end: self.start,
+            },
+        }
+    }
+
+    fn label(&self) -> String {
+        match self.span {
+            Span::Year(year) => year.to_string(),
+            Span::Month(year, month) => format!("{month} {year}"),
+            Span::Custom => format!(
+                "{} to {}",
+                self.start.date(),
+                (self.end - Duration::nanoseconds(1)).date()
+            ),
+        }
+    }
+
+    fn noun(&self) -> &'static str {
+        match self.span {
+            Span::Year(_) => "Year",
+            Span::Month(..) => "Month",
+            Span::Custom => "Time",
+        }
+    }
 
-use atuin_client::{database::Database, settings::Settings, theme::Theme};
+    /// How many days the period has, counting a partial day as a whole one
+    fn days(&self) -> i64 {
+        let length = self.end - self.start;
+        let days = length.whole_days();
 
-use atuin_history::stats::{Stats, compute};
+        if length > Duration::days(days) {
+            days + 1
+        } else {
+            days.max(1)
+        }
+    }
+
+    async fn history(&self, db: &impl Database) -> Result<Vec<History>> {
+        // the range is inclusive of its end
+        Ok(db
+            .range(self.start, self.end - Duration::nanoseconds(1))
+            .await?)
+    }
+}
 
 #[derive(Debug)]
 struct WrappedStats {
@@ -20,7 +167,7 @@ struct WrappedStats {
 
 impl WrappedStats {
     #[allow(clippy::too_many_lines, clippy::cast_precision_loss)]
-    fn new(settings: &Settings, stats: &Stats, history: &[atuin_client::history::History]) -> Self {
+    fn new(settings: &Settings, stats: &Stats, history: &[History], period: &Period) -> Self {
         let nav_commands = stats
             .top
             .iter()
@@ -89,7 +236,7 @@ impl WrappedStats {
 
         // Error analysis
         let mut command_errors: HashMap<String, (usize, usize)> = HashMap::new(); // (total_uses, errors)
-        let midyear = history[0].timestamp + Duration::days(182); // Split year in half
+        let midpoint = period.start + (period.end - period.start) / 2; // Split the period in half
 
         let mut first_half_commands: HashMap<String, usize> = HashMap::new();
         let mut second_half_commands: HashMap<String, usize> = HashMap::new();
@@ -109,7 +256,7 @@ impl WrappedStats {
             }
 
             // Track command evolution
-            if entry.timestamp < midyear {
+            if entry.timestamp < midpoint {
                 *first_half_commands.entry(cmd.clone()).or_default() += 1;
             } else {
                 *second_half_commands.entry(cmd).or_default() += 1;
@@ -159,19 +306,24 @@ impl WrappedStats {
     }
 }
 
-pub fn print_wrapped_header(year: i32) {
+fn print_wrapped_header(period: &Period) {
     let reset = ResetColor;
     let bold = SetAttribute(crossterm::style::Attribute::Bold);
 
-    println!("{bold}╭────────────────────────────────────╮{reset}");
-    println!("{bold}│        ATUIN WRAPPED {year}          │{reset}");
-    println!("{bold}│    Your Year in Shell History      │{reset}");
-    println!("{bold}╰────────────────────────────────────╯{reset}");
+    let title = format!("ATUIN WRAPPED {}", period.label());
+    let subtitle = format!("Your {} in Shell History", period.noun());
+    let width = title.chars().count().max(subtitle.chars().count()) + 8;
+    let line = "─".repeat(width);
+
+    println!("{bold}╭{line}╮{reset}");
+    println!("{bold}│{title:^width$}│{reset}");
+    println!("{bold}│{subtitle:^width$}│{reset}");
+    println!("{bold}╰{line}╯{reset}");
     println!();
 }
 
 #[allow(clippy::cast_precision_loss)]
-fn print_fun_facts(wrapped_stats: &WrappedStats, stats: &Stats, year: i32) {
+fn print_fun_facts(wrapped_stats: &WrappedStats, stats: &Stats, label: &str) {
     let reset = ResetColor;
     let bold = SetAttribute(crossterm::style::Attribute::Bold);
 
@@ -211,12 +363,12 @@ fn print_fun_facts(wrapped_stats: &WrappedStats, stats: &Stats, year: i32) {
     println!("🔍 Command Evolution:");
 
     // print stats for each half and compare
-    println!("  {bold}Top Commands{reset} in the first half of {year}:");
+    println!("  {bold}Top Commands{reset} in the first half of {label}:");
     for (cmd, count) in wrapped_stats.first_half_commands.iter().take(3) {
         println!("    {bold}{cmd}{reset} ({count} times)");
     }
 
-    println!("  {bold}Top Commands{reset} in the second half of {year}:");
+    println!("  {bold}Top Commands{reset} in the second half of {label}:");
     for (cmd, count) in wrapped_stats.second_half_commands.iter().take(3) {
         println!("    {bold}{cmd}{reset} ({count} times)");
     }
@@ -262,61 +414,432 @@ fn print_fun_facts(wrapped_stats: &WrappedStats, stats: &Stats, year: i32) {
     println!();
 }
 
-pub async fn run(
-    year: Option<i32>,
-    db: &impl Database,
-    settings: &Settings,
-    theme: &Theme,
-) -> Result<()> {
-    let now = OffsetDateTime::now_utc().to_offset(settings.timezone.0);
-    let month = now.month();
-
-    // If we're in December, then wrapped is for the current year. If not, it's for the previous year
-    let year = year.unwrap_or_else(|| {
-        if month == Month::December {
-            now.year()
-        } else {
-            now.year() - 1
-        }
-    });
+/// Where the period compares to the one before it
+fn print_comparison(comparison: &Comparison, previous: &Period) {
+    let reset = ResetColor;
+    let bold = SetAttribute(crossterm::style::Attribute::Bold);
 
-    let start = OffsetDateTime::new_in_offset(
-        Date::from_calendar_date(year, Month::January, 1).unwrap(),
-        Time::MIDNIGHT,
-        now.offset(),
-    );
-    let end = OffsetDateTime::new_in_offset(
-        Date::from_calendar_date(year, Month::December, 31).unwrap(),
-        Time::MIDNIGHT + Duration::days(1) - Duration::nanoseconds(1),
-        now.offset(),
+    let Some(growth) = comparison.growth() else {
+        return;
+    };
+
+    println!(
+        "📈 Compared to {}: {bold}{growth:+.1}%{reset} commands ({} before)",
+        previous.label(),
+        comparison.previous_commands
     );
 
-    let history = db.range(start, end).await?;
-    if history.is_empty() {
+    let tools = |tools: &[(String, usize)]| {
+        tools
+            .iter()
+            .map(|(tool, count)| format!("{bold}{tool}{reset} ({count} times)"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    if !comparison.new_tools.is_empty() {
+        println!("  {bold}New tools{reset}: {}", tools(&comparison.new_tools));
+    }
+    if !comparison.dropped_tools.is_empty() {
         println!(
-            "Your history for {year} is empty!\nMaybe 'atuin import' could help you import your previous history 🪄"
+            "  {bold}Dropped tools{reset}: {}",
+            tools(&comparison.dropped_tools)
         );
-        return Ok(());
     }
 
-    // Compute overall stats using existing functionality
-    let stats = compute(settings, &history, 10, 1).expect("Failed to compute stats");
-    let wrapped_stats = WrappedStats::new(settings, &stats, &history);
+    println!();
+}
+
+/// Print the full wrapped, for reading in the terminal
+fn print_wrapped(
+    period: &Period,
+    previous: &Period,
+    stats: &Stats,
+    wrapped_stats: &WrappedStats,
+    comparison: &Comparison,
+    theme: &Theme,
+) {
+    let label = period.label();
 
-    // Print wrapped format
-    print_wrapped_header(year);
+    print_wrapped_header(period);
 
-    println!("🎉 In {year}, you typed {} commands!", stats.total_commands);
+    println!(
+        "🎉 In {label}, you typed {} commands!",
+        stats.total_commands
+    );
     println!(
         "   That's ~{} commands every day\n",
-        stats.total_commands / 365
+        stats.total_commands / usize::try_from(period.days()).unwrap_or(1)
     );
 
+    print_comparison(comparison, previous);
+
     println!("Your Top Commands:");
     atuin_history::stats::pretty_print(stats.clone(), 1, theme);
     println!();
 
-    print_fun_facts(&wrapped_stats, &stats, year);
+    print_fun_facts(wrapped_stats, stats, &label);
+}
+
+impl Cmd {
+    pub async fn run(self, db: &impl Database, settings: &Settings, theme: &Theme) -> Result<()> {
+        let now = OffsetDateTime::now_utc().to_offset(settings.timezone.0);
+        let period = self.period(now, settings)?;
+
+        let history = period.history(db).await?;
+        if history.is_empty() {
+            println!(
+                "Your history for {} is empty!\nMaybe 'atuin import' could help you import your previous history 🪄",
+                period.label()
+            );
+            return Ok(());
+        }
 
-    Ok(())
+        let previous = period.previous();
+        let comparison = compare::compare(settings, &history, &previous.history(db).await?, 5);
+
+        // Compute overall stats using existing functionality
+        let stats = compute(settings, &history, 10, 1).expect("Failed to compute stats");
+        let wrapped_stats = WrappedStats::new(settings, &stats, &history, &period);
+
+        let card = || {
+            Card::new(
+                settings,
+                &period,
+                &stats,
+                &wrapped_stats,
+                &history,
+                &comparison,
+            )
+        };
+
+        match self.format {
+            Format::Terminal => {
+                print_wrapped(
+                    &period,
+                    &previous,
+                    &stats,
+                    &wrapped_stats,
+                    &comparison,
+                    theme,
+                );
+            }
+            Format::Svg => println!("{}", card().svg()),
+            Format::Html => {
+                let card = card();
+                println!("{}", card.html(&card.svg()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn period(&self, now: OffsetDateTime, settings: &Settings) -> Result<Period> {
+        let timezone = settings.timezone.0;
+
+        if let Some((year, month)) = self.month {
+            return Ok(Period::month(year, month, timezone));
+        }
+
+        if let Some(since) = &self.since {
+            let start = parse_date_string(since, now, settings.dialect.into())?;
+            let end = match &self.until {
+                Some(until) => parse_date_string(until, now, settings.dialect.into())?,
+                None => now,
+            };
+
+            if end <= start {
+                bail!("the period must end after it starts");
+            }
+
+            return Ok(Period {
+                span: Span::Custom,
+                start,
+                end,
+            });
+        }
+
+        // If we're in December, then wrapped is for the current year. If not, it's for the previous year
+        let year = self.year.unwrap_or_else(|| {
+            if now.month() == Month::December {
+                now.year()
+            } else {
+                now.year() - 1
+            }
+        });
+
+        Ok(Period::year(year, timezone))
+    }
+}
+
+/// The totals of a wrapped, for a card to share. It has the names of the tools used, but none of
+/// the commands themselves.
+struct Card {
+    label: String,
+    previous_label: String,
+    commands: usize,
+    per_day: usize,
+    unique_commands: usize,
+    error_rate: f64,
+    busiest_hour: Option<String>,
+    growth: Option<f64>,
+    top_tools: Vec<(String, usize)>,
+    new_tools: Vec<String>,
+    dropped_tools: Vec<String>,
+}
+
+impl Card {
+    fn new(
+        settings: &Settings,
+        period: &Period,
+        stats: &Stats,
+        wrapped_stats: &WrappedStats,
+        history: &[History],
+        comparison: &Comparison,
+    ) -> Self {
+        let mut top_tools: Vec<_> = compare::tools(settings, history).into_iter().collect();
+        top_tools.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_tools.truncate(5);
+
+        let names = |tools: &[(String, usize)]| tools.iter().map(|(t, _)| t.clone()).collect();
+
+        Self {
+            label: period.label(),
+            previous_label: period.previous().label(),
+            commands: stats.total_commands,
+            per_day: stats.total_commands / usize::try_from(period.days()).unwrap_or(1),
+            unique_commands: stats.unique_commands,
+            error_rate: wrapped_stats.error_rate,
+            busiest_hour: wrapped_stats.busiest_hour.as_ref().map(|(h, _)| h.clone()),
+            growth: comparison.growth(),
+            top_tools,
+            new_tools: names(&comparison.new_tools),
+            dropped_tools: names(&comparison.dropped_tools),
+        }
+    }
+
+    /// The card as an SVG image, drawn with nothing but text and rectangles so it renders the
+    /// same anywhere
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    fn svg(&self) -> String {
+        const WIDTH: usize = 600;
+        const FONT: &str = "ui-monospace, SFMono-Regular, Menlo, Consolas, monospace";
+
+        let mut body = String::new();
+        let mut y = 48;
+
+        let _ = write!(
+            body,
+            r##"<text x="32" y="{y}" font-size="14" letter-spacing="3" fill="#a6adc8">ATUIN WRAPPED</text>"##
+        );
+        y += 36;
+        let _ = write!(
+            body,
+            r##"<text x="32" y="{y}" font-size="28" font-weight="bold" fill="#cdd6f4">{}</text>"##,
+            escape(&self.label)
+        );
+        y += 68;
+        let _ = write!(
+            body,
+            r##"<text x="32" y="{y}" font-size="56" font-weight="bold" fill="#89b4fa">{}</text>"##,
+            self.commands
+        );
+        if let Some(growth) = self.growth {
+            let colour = if growth < 0.0 { "#f38ba8" } else { "#a6e3a1" };
+            let _ = write!(
+                body,
+                r#"<text x="{}" y="{y}" font-size="20" text-anchor="end" fill="{colour}">{growth:+.0}% on {}</text>"#,
+                WIDTH - 32,
+                escape(&self.previous_label)
+            );
+        }
+        y += 26;
+        let _ = write!(
+            body,
+            r##"<text x="32" y="{y}" font-size="14" fill="#a6adc8">commands, ~{} a day</text>"##,
+            self.per_day
+        );
+
+        y += 56;
+        let facts = [
+            (self.unique_commands.to_string(), "unique commands"),
+            (format!("{:.1}%", self.error_rate * 100.0), "failed"),
+            (
+                self.busiest_hour.clone().unwrap_or_else(|| "-".to_string()),
+                "busiest hour",
+            ),
+        ];
+        for (i, (value, caption)) in facts.iter().enumerate() {
+            let x = 32 + i * (WIDTH - 64) / facts.len();
+            let _ = write!(
+                body,
+                r##"<text x="{x}" y="{y}" font-size="22" font-weight="bold" fill="#cdd6f4">{}</text><text x="{x}" y="{}" font-size="12" fill="#a6adc8">{caption}</text>"##,
+                escape(value),
+                y + 20
+            );
+        }
+
+        if !self.top_tools.is_empty() {
+            y += 68;
+            let _ = write!(
+                body,
+                r##"<text x="32" y="{y}" font-size="14" letter-spacing="2" fill="#a6adc8">TOP TOOLS</text>"##
+            );
+
+            let max = self.top_tools[0].1.max(1);
+            for (tool, count) in &self.top_tools {
+                y += 30;
+                let bar = (320.0 * *count as f64 / max as f64).round().max(2.0) as usize;
+                let _ = write!(
+                    body,
+                    r##"<text x="32" y="{y}" font-size="16" fill="#cdd6f4">{}</text><rect x="168" y="{}" width="{bar}" height="14" rx="3" fill="#89b4fa"/><text x="{}" y="{y}" font-size="14" fill="#a6adc8">{count}</text>"##,
+                    escape(&truncate(tool, 14)),
+                    y - 12,
+                    176 + bar
+                );
+            }
+        }
+
+        for (caption, tools) in [("New", &self.new_tools), ("Dropped", &self.dropped_tools)] {
+            if tools.is_empty() {
+                continue;
+            }
+
+            y += 34;
+            let tools: Vec<String> = tools.iter().map(|t| escape(&truncate(t, 14))).collect();
+            let _ = write!(
+                body,
+                r##"<text x="32" y="{y}" font-size="14" fill="#a6adc8">{caption}: <tspan fill="#cdd6f4">{}</tspan></text>"##,
+                tools.join(", ")
+            );
+        }
+
+        y += 48;
+        let _ = write!(
+            body,
+            r##"<text x="{}" y="{y}" font-size="12" text-anchor="end" fill="#6c7086">atuin.sh</text>"##,
+            WIDTH - 32
+        );
+        let height = y + 28;
+
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{height}" viewBox="0 0 {WIDTH} {height}" font-family="{FONT}"><rect width="{WIDTH}" height="{height}" rx="16" fill="#1e1e2e"/>{body}</svg>"##
+        )
+    }
+
+    /// A self-contained HTML page showing the card
+    fn html(&self, svg: &str) -> String {
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Atuin Wrapped {}</title>
+<style>body {{ margin: 0; min-height: 100vh; display: flex; align-items: center; justify-content: center; background: #11111b; }}</style>
+</head>
+<body>
+{svg}
+</body>
+</html>"#,
+            escape(&self.label)
+        )
+    }
+}
+
+/// Text escaped to go in SVG or HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Text cut to fit a number of characters, with an ellipsis if it was cut
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else {
+        let mut cut: String = text.chars().take(width - 1).collect();
+        cut.push('…');
+        cut
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Month, UtcOffset, macros::datetime};
+
+    use super::{Card, Period, Span, escape, parse_month, parse_year};
+
+    #[test]
+    fn parse_periods() {
+        assert_eq!(parse_year("2025"), Ok(2025));
+        assert_eq!(parse_month("2025-03"), Ok((2025, Month::March)));
+
+        assert!(parse_year("2025-03").is_err());
+        assert!(parse_month("2025").is_err());
+        // too late to have a period after them
+        assert!(parse_year("10000").is_err());
+        assert!(parse_month("9999-12").is_err());
+    }
+
+    #[test]
+    fn previous_periods() {
+        let utc = UtcOffset::UTC;
+
+        assert_eq!(Period::year(2025, utc).previous(), Period::year(2024, utc));
+        assert_eq!(
+            Period::month(2025, Month::January, utc).previous(),
+            Period::month(2024, Month::December, utc)
+        );
+
+        let custom = Period {
+            span: Span::Custom,
+            start: datetime!(2025-03-10 00:00 UTC),
+            end: datetime!(2025-03-20 12:00 UTC),
+        };
+        assert_eq!(custom.days(), 11);
+        assert_eq!(custom.label(), "2025-03-10 to 2025-03-20");
+
+        let previous = custom.previous();
+        assert_eq!(previous.start, datetime!(2025-02-27 12:00 UTC));
+        assert_eq!(previous.end, custom.start);
+    }
+
+    #[test]
+    fn card_shows_totals_and_tools() {
+        let card = Card {
+            label: "March 2025".to_string(),
+            previous_label: "February 2025".to_string(),
+            commands: 1234,
+            per_day: 39,
+            unique_commands: 56,
+            error_rate: 0.1,
+            busiest_hour: Some("14:00".to_string()),
+            growth: Some(12.4),
+            top_tools: vec![("git".to_string(), 600), ("<script>".to_string(), 10)],
+            new_tools: vec!["jj".to_string()],
+            dropped_tools: Vec::new(),
+        };
+
+        let svg = card.svg();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>"));
+        assert!(svg.contains(">1234<"));
+        assert!(svg.contains("+12% on February 2025"));
+        assert!(svg.contains("&lt;script&gt;"));
+        assert!(!svg.contains("<script>"));
+        assert!(svg.contains("New: "));
+        assert!(!svg.contains("Dropped: "));
+
+        let html = card.html(&svg);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(&svg));
+        assert_eq!(escape(r#"a&"b'"#), "a&amp;&quot;b&#39;");
+    }
 }
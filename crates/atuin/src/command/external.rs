@@ -3,7 +3,10 @@ use std::process::Command;
 use std::{io, process};
 
 #[cfg(feature = "client")]
-use atuin_client::plugin::OfficialPluginRegistry;
+use atuin_client::{
+    plugin::{self, HANDSHAKE_VAR, Handshake, OfficialPluginRegistry},
+    settings::Settings,
+};
 use clap::CommandFactory;
 use clap::builder::{StyledStr, Styles};
 use eyre::Result;
@@ -13,9 +16,37 @@ use crate::Atuin;
 pub fn run(args: &[String]) -> Result<()> {
     let subcommand = &args[0];
     let bin = format!("atuin-{subcommand}");
+
+    // a plugin still runs without its manifest, as it did before it registered one
+    #[cfg(feature = "client")]
+    let manifest = match plugin::manifests(&plugin::plugins_dir()) {
+        Ok(mut manifests) => manifests.remove(subcommand),
+        Err(e) => {
+            log::warn!("could not read plugin manifests: {e}");
+            None
+        }
+    };
+
+    #[cfg(feature = "client")]
+    let mut cmd = Command::new(
+        manifest
+            .as_ref()
+            .map_or_else(|| bin.clone().into(), plugin::Manifest::bin),
+    );
+    #[cfg(not(feature = "client"))]
     let mut cmd = Command::new(&bin);
     cmd.args(&args[1..]);
 
+    // plugins that speak the protocol are told where everything is, in their environment
+    #[cfg(feature = "client")]
+    if manifest.is_some_and(|m| m.protocol.is_some()) {
+        let settings = Settings::new()?;
+        cmd.env(
+            HANDSHAKE_VAR,
+            serde_json::to_string(&Handshake::new(&settings))?,
+        );
+    }
+
     let spawn_result = match cmd.spawn() {
         Ok(child) => Ok(child),
         Err(e) => match e.kind() {
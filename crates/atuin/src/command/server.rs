@@ -1,14 +1,15 @@
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
-use atuin_server_database::DbType;
+use atuin_server_database::{Database, DbType};
 use atuin_server_postgres::Postgres;
 use atuin_server_sqlite::Sqlite;
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
 use clap::Parser;
-use eyre::{Context, Result, eyre};
+use eyre::{Context, Result, bail, eyre};
 
-use atuin_server::{Settings, example_config, launch, launch_metrics_server};
+use atuin_server::{Settings, backup, example_config, launch, launch_metrics_server};
 
 #[derive(Parser, Debug)]
 #[clap(infer_subcommands = true)]
@@ -26,6 +27,26 @@ pub enum Cmd {
 
     /// Print server example configuration
     DefaultConfig,
+
+    /// Back up all users and their encrypted record stores to a portable archive
+    Backup {
+        /// The file to write the backup to
+        path: PathBuf,
+    },
+
+    /// Restore users and records from a backup made with `atuin server backup`
+    Restore {
+        /// The backup file to restore from
+        path: PathBuf,
+
+        /// Only check the integrity of the backup, without writing to the database
+        #[clap(long)]
+        check: bool,
+
+        /// Add the backup's records to users and teams that already exist, instead of failing
+        #[clap(long)]
+        merge: bool,
+    },
 }
 
 impl Cmd {
@@ -64,6 +85,71 @@ impl Cmd {
                 println!("{}", example_config());
                 Ok(())
             }
+            Self::Backup { path } => {
+                let settings = Settings::new().wrap_err("could not load server settings")?;
+
+                match settings.db_settings.db_type() {
+                    DbType::Postgres => run_backup::<Postgres>(&settings, &path).await,
+                    DbType::Sqlite => run_backup::<Sqlite>(&settings, &path).await,
+                    DbType::Unknown => {
+                        Err(eyre!("db_uri must start with postgres:// or sqlite://"))
+                    }
+                }
+            }
+            Self::Restore { path, check, merge } => {
+                let summary = backup::verify(fs_err::File::open(&path)?)
+                    .wrap_err("backup failed integrity check")?;
+                println!("Backup is intact: {summary}");
+
+                if check {
+                    return Ok(());
+                }
+
+                let settings = Settings::new().wrap_err("could not load server settings")?;
+
+                match settings.db_settings.db_type() {
+                    DbType::Postgres => run_restore::<Postgres>(&settings, &path, merge).await,
+                    DbType::Sqlite => run_restore::<Sqlite>(&settings, &path, merge).await,
+                    DbType::Unknown => {
+                        Err(eyre!("db_uri must start with postgres:// or sqlite://"))
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn run_backup<Db: Database>(settings: &Settings, path: &Path) -> Result<()> {
+    let db = Db::new(&settings.db_settings)
+        .await
+        .wrap_err_with(|| format!("failed to connect to db: {:?}", settings.db_settings))?;
+
+    let file = fs_err::File::create_new(path)?;
+    let summary = backup::backup(&db, file).await?;
+
+    println!("Backed up {summary} to {}", path.display());
+
+    Ok(())
+}
+
+async fn run_restore<Db: Database>(settings: &Settings, path: &Path, merge: bool) -> Result<()> {
+    let db = Db::new(&settings.db_settings)
+        .await
+        .wrap_err_with(|| format!("failed to connect to db: {:?}", settings.db_settings))?;
+
+    if !merge {
+        let existing = backup::existing(&db, fs_err::File::open(path)?).await?;
+        if !existing.is_empty() {
+            bail!(
+                "{} already exist; pass --merge to add the backup's records to them",
+                existing.join(", ")
+            );
         }
     }
+
+    let summary = backup::restore(&db, fs_err::File::open(path)?, merge).await?;
+
+    println!("Restored {summary} from {}", path.display());
+
+    Ok(())
 }
@@ -619,5 +619,34 @@ if [[ $__atuin_bind_up_arrow == true ]]; then
     atuin-bind -m vi-command 'k'    atuin-up-search-vicmd
 fi
 
+#------------------------------------------------------------------------------
+# Directory jumping with `z` and `zi`, unless already defined. See `atuin dir`.
+
+__atuin_dir_jump() {
+    if (($# == 0)); then
+        builtin cd ~ || return
+    elif (($# == 1)) && [[ $1 == - || -d $1 ]]; then
+        builtin cd "$1" || return
+    else
+        local dir
+        dir=$(ATUIN_LOG=error atuin dir -- "$@") && builtin cd "$dir" || return
+    fi
+}
+
+__atuin_dir_pick() {
+    local dir
+    dir=$(ATUIN_SHELL=bash ATUIN_LOG=error atuin dir -i -- "$@" 3>&1 1>&2 2>&3)
+    [[ $dir ]] || return 0
+    builtin cd "$dir" || return
+}
+
+if ! type z &>/dev/null; then
+    z() { __atuin_dir_jump "$@"; }
+fi
+
+if ! type zi &>/dev/null; then
+    zi() { __atuin_dir_pick "$@"; }
+fi
+
 #------------------------------------------------------------------------------
 fi # (include guard) end of main content
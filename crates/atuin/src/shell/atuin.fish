@@ -52,6 +52,37 @@ function _atuin_search
     commandline -f repaint
 end
 
+# Directory jumping with `z` and `zi`, unless already defined. See `atuin dir`.
+function _atuin_dir_jump
+    if test (count $argv) -eq 0
+        cd ~
+    else if test (count $argv) -eq 1; and begin; test "$argv[1]" = -; or test -d "$argv[1]"; end
+        cd $argv[1]
+    else
+        set -l dir (ATUIN_LOG=error atuin dir -- $argv); and cd $dir
+    end
+end
+
+function _atuin_dir_pick
+    set -l dir (ATUIN_SHELL=fish ATUIN_LOG=error atuin dir -i -- $argv 3>&1 1>&2 2>&3)
+
+    if test -n "$dir"
+        cd $dir
+    end
+end
+
+if not type -q z
+    function z
+        _atuin_dir_jump $argv
+    end
+end
+
+if not type -q zi
+    function zi
+        _atuin_dir_pick $argv
+    end
+end
+
 function _atuin_bind_up
     # Fallback to fish's builtin up-or-search if we're in search or paging mode
     if commandline --search-mode; or commandline --paging-mode
@@ -146,6 +146,58 @@ New-Module -Name Atuin -ScriptBlock {
         }
     }
 
+    # Directory jumping with `z` and `zi`, unless already defined. See `atuin dir`.
+    function Invoke-AtuinDirJump {
+        if ($args.Count -eq 0) {
+            Set-Location ~
+        } elseif ($args.Count -eq 1 -and ($args[0] -eq "-" -or (Test-Path -PathType Container -LiteralPath $args[0]))) {
+            Set-Location $args[0]
+        } else {
+            $previousLog = $env:ATUIN_LOG
+            $env:ATUIN_LOG = "error"
+            try {
+                $dir = atuin dir -- @args
+                if ($LASTEXITCODE -eq 0 -and $dir) {
+                    Set-Location -LiteralPath $dir
+                }
+            }
+            finally {
+                $env:ATUIN_LOG = $previousLog
+            }
+        }
+    }
+
+    function Invoke-AtuinDirPick {
+        $resultFile = New-TemporaryFile
+        $previousShell = $env:ATUIN_SHELL
+        $previousLog = $env:ATUIN_LOG
+
+        try {
+            $env:ATUIN_SHELL = "powershell"
+            $env:ATUIN_LOG = "error"
+            $argString = (@("dir", "-i", "--result-file", """$resultFile""", "--") + ($args | ForEach-Object { """$_""" })) -join " "
+            Start-Process -PassThru -NoNewWindow -FilePath atuin -ArgumentList $argString | Wait-Process
+            $dir = (Get-Content -Raw $resultFile -Encoding UTF8 | Out-String).Trim()
+
+            if ($dir -ne "") {
+                Set-Location -LiteralPath $dir
+            }
+        }
+        finally {
+            $env:ATUIN_SHELL = $previousShell
+            $env:ATUIN_LOG = $previousLog
+            Remove-Item $resultFile
+        }
+    }
+
+    if (!(Get-Command z -ErrorAction Ignore)) {
+        Set-Alias -Name z -Value Invoke-AtuinDirJump -Scope Global
+    }
+
+    if (!(Get-Command zi -ErrorAction Ignore)) {
+        Set-Alias -Name zi -Value Invoke-AtuinDirPick -Scope Global
+    }
+
     function Enable-AtuinSearchKeys {
         param([bool]$CtrlR = $true, [bool]$UpArrow = $true)
 
@@ -174,5 +226,5 @@ New-Module -Name Atuin -ScriptBlock {
         $Function:PSConsoleHostReadLine = $script:previousPSConsoleHostReadLine
     }
 
-    Export-ModuleMember -Function @("Enable-AtuinSearchKeys", "PSConsoleHostReadLine")
+    Export-ModuleMember -Function @("Enable-AtuinSearchKeys", "PSConsoleHostReadLine", "Invoke-AtuinDirJump", "Invoke-AtuinDirPick")
 } | Import-Module -Global
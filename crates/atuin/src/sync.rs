@@ -3,8 +3,14 @@ use atuin_scripts::store::ScriptStore;
 use eyre::{Context, Result};
 
 use atuin_client::{
-    database::Database, history::store::HistoryStore, record::sqlite_store::SqliteStore,
+    database::Database,
+    history::store::HistoryStore,
+    record::{
+        keyring::{self, Keyring},
+        sqlite_store::SqliteStore,
+    },
     settings::Settings,
+    team::Team,
 };
 use atuin_common::record::RecordId;
 use atuin_kv::store::KvStore;
@@ -21,9 +27,15 @@ pub async fn build(
     db: &dyn Database,
     downloaded: Option<&[RecordId]>,
 ) -> Result<()> {
-    let encryption_key: [u8; 32] = atuin_client::encryption::load_key(settings)
-        .context("could not load encryption key")?
-        .into();
+    let keyring = Keyring::load(settings).context("could not load encryption key")?;
+
+    // Pick up any key rotations from other devices before decrypting what they sent
+    if keyring::apply(store, &keyring).await? {
+        keyring.save(settings)?;
+    }
+
+    // and move anything written with a retired key over to the current one
+    keyring::migrate(store, &keyring).await?;
 
     let host_id = Settings::host_id().expect("failed to get host_id");
 
@@ -31,11 +43,11 @@ pub async fn build(
 
     let kv_db = atuin_kv::database::Database::new(settings.kv.db_path.clone(), 1.0).await?;
 
-    let history_store = HistoryStore::new(store.clone(), host_id, encryption_key);
-    let alias_store = AliasStore::new(store.clone(), host_id, encryption_key);
-    let var_store = VarStore::new(store.clone(), host_id, encryption_key);
-    let kv_store = KvStore::new(store.clone(), kv_db, host_id, encryption_key);
-    let script_store = ScriptStore::new(store.clone(), host_id, encryption_key);
+    let history_store = HistoryStore::new(store.clone(), host_id, keyring.clone());
+    let alias_store = AliasStore::new(store.clone(), host_id, keyring.clone());
+    let var_store = VarStore::new(store.clone(), host_id, keyring.clone());
+    let kv_store = KvStore::new(store.clone(), kv_db, host_id, keyring.clone());
+    let script_store = ScriptStore::new(store.clone(), host_id, keyring.clone());
 
     history_store.incremental_build(db, downloaded).await?;
 
@@ -48,3 +60,14 @@ pub async fn build(
     script_store.build(script_db).await?;
     Ok(())
 }
+
+/// Rebuild the kv and script databases of a team from its local record store
+#[cfg(feature = "sync")]
+pub async fn build_team(team: Team, store: SqliteStore) -> Result<()> {
+    let host_id = Settings::host_id().expect("failed to get host_id");
+
+    KvStore::build_team(&team, store.clone(), host_id).await?;
+    ScriptStore::build_team(&team, store, host_id).await?;
+
+    Ok(())
+}
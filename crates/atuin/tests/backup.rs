@@ -0,0 +1,226 @@
+use atuin_common::{
+    record::{EncryptedData, Host, HostId, Record},
+    utils::uuid_v7,
+};
+use atuin_server::backup;
+use atuin_server_database::{
+    Database, DbSettings,
+    models::{NewSession, NewUser},
+};
+use atuin_server_sqlite::Sqlite;
+
+async fn open_db(dir: &tempfile::TempDir, name: &str) -> Sqlite {
+    let path = dir.path().join(name);
+    let settings = DbSettings {
+        db_uri: format!("sqlite://{}", path.display()),
+    };
+
+    Sqlite::new(&settings).await.unwrap()
+}
+
+fn records(host: HostId, tag: &str, count: u64) -> Vec<Record<EncryptedData>> {
+    (0..count)
+        .map(|idx| {
+            Record::builder()
+                .idx(idx)
+                .host(Host::new(host))
+                .version("v0".to_string())
+                .tag(tag.to_string())
+                .data(EncryptedData {
+                    data: uuid_v7().as_simple().to_string(),
+                    content_encryption_key: uuid_v7().as_simple().to_string(),
+                })
+                .build()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn backup_and_restore() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = open_db(&dir, "source.db").await;
+
+    let mut expected = Vec::new();
+
+    for (name, count) in [("alice", 1500), ("bob", 3)] {
+        let id = source
+            .add_user(&NewUser {
+                username: name.to_string(),
+                email: format!("{name}@example.com"),
+                password: format!("{name}-hash"),
+            })
+            .await
+            .unwrap();
+        source.verify_user(id).await.unwrap();
+        source
+            .add_session(&NewSession {
+                user_id: id,
+                token: format!("{name}-token"),
+            })
+            .await
+            .unwrap();
+
+        let user = source.get_user(name).await.unwrap();
+        source
+            .set_key_escrow(&user, &format!("{name}-escrow"))
+            .await
+            .unwrap();
+        let host = HostId(uuid_v7());
+        let mut recs = records(host, "history", count);
+        recs.extend(records(host, "kv", 2));
+        source.add_records(&user, &recs).await.unwrap();
+
+        expected.push((name, host, recs));
+    }
+
+    let alice = source.get_user("alice").await.unwrap();
+    let bob = source.get_user("bob").await.unwrap();
+    let team = source.create_team("ops", &alice).await.unwrap();
+    source.add_team_member(&team, &bob, false).await.unwrap();
+    let team_host = HostId(uuid_v7());
+    let team_recs = records(team_host, "kv", 4);
+    source.add_team_records(&team, &team_recs).await.unwrap();
+
+    let archive = dir.path().join("backup.gz");
+    let summary = backup::backup(&source, std::fs::File::create(&archive).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(summary.users, 2);
+    assert_eq!(summary.teams, 1);
+    assert_eq!(summary.records, 1511);
+
+    let verified = backup::verify(std::fs::File::open(&archive).unwrap()).unwrap();
+    assert_eq!(verified, summary);
+
+    let target = open_db(&dir, "target.db").await;
+    assert!(
+        backup::existing(&target, std::fs::File::open(&archive).unwrap())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    let restored = backup::restore(&target, std::fs::File::open(&archive).unwrap(), false)
+        .await
+        .unwrap();
+    assert_eq!(restored, summary);
+
+    for (name, host, recs) in expected {
+        let user = target.get_user(name).await.unwrap();
+        assert_eq!(user.password, format!("{name}-hash"));
+        assert!(user.verified.is_some());
+        assert_eq!(
+            target.get_key_escrow(&user).await.unwrap(),
+            format!("{name}-escrow")
+        );
+
+        let session = target.get_session_user(&format!("{name}-token")).await;
+        assert_eq!(session.unwrap().username, name);
+
+        let mut restored = target
+            .next_records(&user, host, "history".to_string(), None, 10000)
+            .await
+            .unwrap();
+        restored.extend(
+            target
+                .next_records(&user, host, "kv".to_string(), None, 10000)
+                .await
+                .unwrap(),
+        );
+
+        assert_eq!(restored, recs);
+    }
+
+    let team = target.get_team("ops").await.unwrap();
+    let members: Vec<_> = target
+        .list_team_members(&team)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|m| (m.username, m.admin))
+        .collect();
+    assert_eq!(
+        members,
+        vec![("alice".to_string(), true), ("bob".to_string(), false)]
+    );
+    let restored = target
+        .next_team_records(&team, team_host, "kv".to_string(), None, 100)
+        .await
+        .unwrap();
+    assert_eq!(restored, team_recs);
+
+    // restoring over existing users needs merging
+    assert_eq!(
+        backup::existing(&target, std::fs::File::open(&archive).unwrap())
+            .await
+            .unwrap(),
+        vec!["user alice", "user bob", "team ops"]
+    );
+    let err = backup::restore(&target, std::fs::File::open(&archive).unwrap(), false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("user alice already exists"));
+
+    // restoring twice merges rather than duplicating
+    backup::restore(&target, std::fs::File::open(&archive).unwrap(), true)
+        .await
+        .unwrap();
+    let user = target.get_user("bob").await.unwrap();
+    let status = target.status(&user).await.unwrap();
+    assert_eq!(status.hosts.values().next().unwrap()["history"], 2);
+}
+
+#[tokio::test]
+async fn corrupt_backup_is_rejected() {
+    use std::io::{Read, Write};
+
+    let dir = tempfile::tempdir().unwrap();
+    let source = open_db(&dir, "source.db").await;
+
+    let id = source
+        .add_user(&NewUser {
+            username: "carol".to_string(),
+            email: "carol@example.com".to_string(),
+            password: "carol-hash".to_string(),
+        })
+        .await
+        .unwrap();
+    let user = source.get_user("carol").await.unwrap();
+    assert_eq!(user.id, id);
+    source
+        .add_records(&user, &records(HostId(uuid_v7()), "history", 5))
+        .await
+        .unwrap();
+
+    let mut archive = Vec::new();
+    backup::backup(&source, &mut archive).await.unwrap();
+
+    let mut plain = String::new();
+    flate2::read::GzDecoder::new(archive.as_slice())
+        .read_to_string(&mut plain)
+        .unwrap();
+
+    // drop a record
+    let tampered: Vec<&str> = plain
+        .lines()
+        .enumerate()
+        .filter(|(i, _)| *i != 3)
+        .map(|(_, l)| l)
+        .collect();
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(tampered.join("\n").as_bytes()).unwrap();
+    let tampered = encoder.finish().unwrap();
+
+    let err = backup::verify(tampered.as_slice()).unwrap_err();
+    assert!(err.to_string().contains("integrity check failed"));
+
+    // truncate before the footer
+    let lines: Vec<&str> = plain.lines().collect();
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(lines[..lines.len() - 1].join("\n").as_bytes())
+        .unwrap();
+    let truncated = encoder.finish().unwrap();
+
+    let err = backup::verify(truncated.as_slice()).unwrap_err();
+    assert!(err.to_string().contains("truncated"));
+}
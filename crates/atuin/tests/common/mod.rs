@@ -3,8 +3,9 @@ use std::{env, time::Duration};
 use atuin_client::api_client;
 use atuin_common::utils::uuid_v7;
 use atuin_server::{Settings as ServerSettings, launch_with_tcp_listener};
-use atuin_server_database::DbSettings;
+use atuin_server_database::{DbSettings, DbType};
 use atuin_server_postgres::Postgres;
+use atuin_server_sqlite::Sqlite;
 use futures_util::TryFutureExt;
 use tokio::{net::TcpListener, sync::oneshot, task::JoinHandle};
 use tracing::{Dispatch, dispatcher};
@@ -49,13 +50,15 @@ pub async fn start_server(path: &str) -> (String, oneshot::Sender<()>, JoinHandl
     let server = tokio::spawn(async move {
         let _tracing_guard = dispatcher::set_default(&dispatch);
 
-        if let Err(e) = launch_with_tcp_listener::<Postgres>(
-            server_settings,
-            listener,
-            shutdown_rx.unwrap_or_else(|_| ()),
-        )
-        .await
-        {
+        let shutdown = shutdown_rx.unwrap_or_else(|_| ());
+        let result = match server_settings.db_settings.db_type() {
+            DbType::Sqlite => {
+                launch_with_tcp_listener::<Sqlite>(server_settings, listener, shutdown).await
+            }
+            _ => launch_with_tcp_listener::<Postgres>(server_settings, listener, shutdown).await,
+        };
+
+        if let Err(e) = result {
             tracing::error!(error=?e, "server error");
             panic!("error running server: {e:?}");
         }
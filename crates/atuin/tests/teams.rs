@@ -0,0 +1,110 @@
+use atuin_common::{
+    record::{EncryptedData, Host, HostId, Record},
+    utils::uuid_v7,
+};
+
+mod common;
+
+fn record(host: HostId) -> Record<EncryptedData> {
+    Record::builder()
+        .idx(0)
+        .host(Host::new(host))
+        .version("v0".to_string())
+        .tag("kv".to_string())
+        .data(EncryptedData {
+            data: uuid_v7().as_simple().to_string(),
+            content_encryption_key: uuid_v7().as_simple().to_string(),
+        })
+        .build()
+}
+
+#[tokio::test]
+async fn team_membership() {
+    let path = format!("/{}", uuid_v7().as_simple());
+    let (address, shutdown, server) = common::start_server(&path).await;
+
+    let names: Vec<String> = (0..3).map(|_| uuid_v7().as_simple().to_string()).collect();
+    let [alice, bob, carol] = [&names[0], &names[1], &names[2]];
+    let password = uuid_v7().as_simple().to_string();
+
+    let alice_client = common::register_inner(&address, alice, &password).await;
+    let bob_client = common::register_inner(&address, bob, &password).await;
+    let carol_client = common::register_inner(&address, carol, &password).await;
+
+    let team = format!("t{}", &uuid_v7().as_simple().to_string()[..16]);
+    let created = alice_client.create_team(&team).await.unwrap();
+    assert_eq!(created.members.len(), 1);
+    assert!(created.members[0].admin);
+
+    // names are unique
+    assert!(bob_client.create_team(&team).await.is_err());
+
+    // outsiders can't see the team, manage it, or touch its records
+    assert!(bob_client.list_teams().await.unwrap().is_empty());
+    assert!(bob_client.add_team_member(&team, bob, true).await.is_err());
+    let bob_team = common::login(&address, bob.clone(), password.clone())
+        .await
+        .with_team(&team);
+    assert!(bob_team.record_status().await.is_err());
+    assert!(
+        bob_team
+            .post_records(&[record(HostId(uuid_v7()))])
+            .await
+            .is_err()
+    );
+
+    // members can use the store, but only admins can manage members
+    alice_client
+        .add_team_member(&team, bob, false)
+        .await
+        .unwrap();
+    assert_eq!(bob_client.list_teams().await.unwrap().len(), 1);
+    bob_team
+        .post_records(&[record(HostId(uuid_v7()))])
+        .await
+        .unwrap();
+    assert_eq!(bob_team.record_status().await.unwrap().hosts.len(), 1);
+    assert!(
+        bob_client
+            .add_team_member(&team, carol, false)
+            .await
+            .is_err()
+    );
+    assert!(bob_client.remove_team_member(&team, alice).await.is_err());
+
+    // the team store is separate from each member's own
+    assert!(bob_client.record_status().await.unwrap().hosts.is_empty());
+
+    // anyone can leave
+    let left = bob_client.remove_team_member(&team, bob).await.unwrap();
+    assert_eq!(left.members.len(), 1);
+    assert!(bob_team.record_status().await.is_err());
+
+    // but the last admin can't leave other members behind, by leaving or deleting their account
+    alice_client
+        .add_team_member(&team, carol, false)
+        .await
+        .unwrap();
+    assert!(alice_client.remove_team_member(&team, alice).await.is_err());
+    assert!(
+        alice_client
+            .add_team_member(&team, alice, false)
+            .await
+            .is_err()
+    );
+    assert!(alice_client.delete().await.is_err());
+    assert_eq!(carol_client.list_teams().await.unwrap()[0].members.len(), 2);
+
+    alice_client
+        .add_team_member(&team, carol, true)
+        .await
+        .unwrap();
+    alice_client.delete().await.unwrap();
+
+    let teams = carol_client.list_teams().await.unwrap();
+    assert_eq!(teams[0].members.len(), 1);
+    assert_eq!(&teams[0].members[0].username, carol);
+
+    shutdown.send(()).unwrap();
+    server.await.unwrap();
+}
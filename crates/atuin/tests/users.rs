@@ -119,3 +119,51 @@ async fn multi_user_test() {
     shutdown.send(()).unwrap();
     server.await.unwrap();
 }
+
+#[tokio::test]
+async fn key_escrow() {
+    let path = format!("/{}", uuid_v7().as_simple());
+    let (address, shutdown, server) = common::start_server(&path).await;
+
+    let client = common::register(&address).await;
+    let other = common::register(&address).await;
+
+    // nothing is escrowed to begin with
+    assert_eq!(client.get_key_escrow().await.unwrap(), None);
+
+    client
+        .put_key_escrow("wrapped-key".to_string())
+        .await
+        .unwrap();
+    assert_eq!(
+        client.get_key_escrow().await.unwrap().as_deref(),
+        Some("wrapped-key")
+    );
+
+    // escrows are per account
+    assert_eq!(other.get_key_escrow().await.unwrap(), None);
+
+    // replacing an escrow overwrites it
+    client
+        .put_key_escrow("rotated-key".to_string())
+        .await
+        .unwrap();
+    assert_eq!(
+        client.get_key_escrow().await.unwrap().as_deref(),
+        Some("rotated-key")
+    );
+
+    // empty escrows are rejected
+    assert!(client.put_key_escrow(String::new()).await.is_err());
+
+    client.delete_key_escrow().await.unwrap();
+    assert_eq!(client.get_key_escrow().await.unwrap(), None);
+
+    // the endpoints need a session
+    let anonymous =
+        atuin_client::api_client::Client::new(&address, "not-a-session", 5, 30).unwrap();
+    assert!(anonymous.get_key_escrow().await.is_err());
+
+    shutdown.send(()).unwrap();
+    server.await.unwrap();
+}